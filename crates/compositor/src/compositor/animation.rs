@@ -0,0 +1,235 @@
+use std::{collections::BTreeMap, time::Duration};
+
+use slotmap::SecondaryMap;
+use smithay::utils::{Logical, Point, Size};
+
+use crate::compositor::api::WindowKey;
+
+/// Interval used to wake up the loop while an animation waits for a client.
+pub const ANIMATION_FRAME_INTERVAL: Duration = Duration::from_millis(16);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Easing {
+    Linear,
+    EaseIn,
+    EaseOut,
+    EaseInOut,
+    /// Same as the CSS `cubic-bezier()` timing function.
+    CubicBezier {
+        x1: f64,
+        y1: f64,
+        x2: f64,
+        y2: f64,
+    },
+}
+
+impl Easing {
+    /// Maps the linear progress `t` in `0.0..=1.0` to the eased one.
+    pub fn apply(self, t: f64) -> f64 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Easing::Linear => t,
+            Easing::EaseIn => cubic_bezier(0.42, 0.0, 1.0, 1.0, t),
+            Easing::EaseOut => cubic_bezier(0.0, 0.0, 0.58, 1.0, t),
+            Easing::EaseInOut => cubic_bezier(0.42, 0.0, 0.58, 1.0, t),
+            Easing::CubicBezier { x1, y1, x2, y2 } => {
+                cubic_bezier(x1.clamp(0.0, 1.0), y1, x2.clamp(0.0, 1.0), y2, t)
+            }
+        }
+    }
+}
+
+fn cubic_bezier(x1: f64, y1: f64, x2: f64, y2: f64, x: f64) -> f64 {
+    // Both curve coordinates are polynomials of the curve parameter
+    let bezier = |p1: f64, p2: f64, t: f64| {
+        let u = 1.0 - t;
+        3.0 * u * u * t * p1 + 3.0 * u * t * t * p2 + t * t * t
+    };
+    let derivative = |p1: f64, p2: f64, t: f64| {
+        let u = 1.0 - t;
+        3.0 * u * u * p1 + 6.0 * u * t * (p2 - p1) + 3.0 * t * t * (1.0 - p2)
+    };
+
+    // Newton's method converges quickly for most curves...
+    let mut t = x;
+    for _ in 0..8 {
+        let error = bezier(x1, x2, t) - x;
+        if error.abs() < 1e-7 {
+            return bezier(y1, y2, t);
+        }
+        let slope = derivative(x1, x2, t);
+        if slope.abs() < 1e-7 {
+            break;
+        }
+        t -= error / slope;
+    }
+
+    // ...and bisection handles the flat ones
+    let (mut low, mut high) = (0.0, 1.0);
+    t = x;
+    for _ in 0..64 {
+        let value = bezier(x1, x2, t);
+        if (value - x).abs() < 1e-7 {
+            break;
+        }
+        if value < x {
+            low = t;
+        } else {
+            high = t;
+        }
+        t = (low + high) / 2.0;
+    }
+
+    bezier(y1, y2, t)
+}
+
+/// Animated properties of a window.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WindowProperties {
+    pub location: Point<i32, Logical>,
+    pub size: Size<i32, Logical>,
+    pub opacity: f32,
+}
+
+/// Target of an animation, properties set to `None` are left alone.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct AnimationTarget {
+    pub location: Option<Point<i32, Logical>>,
+    pub size: Option<Size<i32, Logical>>,
+    pub opacity: Option<f32>,
+}
+
+/// Values of an animation at a point in time.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AnimationFrame {
+    pub window: WindowKey,
+    pub animation: u64,
+    pub location: Option<Point<i32, Logical>>,
+    pub size: Option<Size<i32, Logical>>,
+    pub opacity: Option<f32>,
+    pub finished: bool,
+}
+
+struct Animation {
+    id: u64,
+    from: WindowProperties,
+    target: AnimationTarget,
+    duration: Duration,
+    easing: Easing,
+    /// Set on the first tick, so animations start with the frame they are first shown in.
+    start: Option<Duration>,
+}
+
+impl Animation {
+    fn frame(&self, window: WindowKey, now: Duration) -> AnimationFrame {
+        let start = self.start.unwrap_or(now);
+        let elapsed = now.saturating_sub(start);
+        let finished = elapsed >= self.duration;
+        let progress = if finished {
+            1.0
+        } else {
+            self.easing
+                .apply(elapsed.as_secs_f64() / self.duration.as_secs_f64())
+        };
+
+        let lerp = |from: i32, to: i32| {
+            (f64::from(from) + (f64::from(to) - f64::from(from)) * progress).round() as i32
+        };
+
+        AnimationFrame {
+            window,
+            animation: self.id,
+            location: self.target.location.map(|to| {
+                let from = self.from.location;
+                (lerp(from.x, to.x), lerp(from.y, to.y)).into()
+            }),
+            size: self.target.size.map(|to| {
+                let from = self.from.size;
+                (lerp(from.w, to.w).max(1), lerp(from.h, to.h).max(1)).into()
+            }),
+            opacity: self.target.opacity.map(|to| {
+                let from = f64::from(self.from.opacity);
+                (from + (f64::from(to) - from) * progress).clamp(0.0, 1.0) as f32
+            }),
+            finished,
+        }
+    }
+}
+
+/// Window animations, driven by the time passed to [`Animations::tick`]
+/// so the result only depends on the clock of the caller.
+#[derive(Default)]
+pub struct Animations {
+    animations: BTreeMap<WindowKey, Animation>,
+    opacity: SecondaryMap<WindowKey, f32>,
+    next_id: u64,
+}
+
+impl Animations {
+    /// Starts an animation of `window` from its current properties.
+    ///
+    /// Returns the id of the new animation and the id of the animation it replaced.
+    pub fn start(
+        &mut self,
+        window: WindowKey,
+        from: WindowProperties,
+        target: AnimationTarget,
+        duration: Duration,
+        easing: Easing,
+    ) -> (u64, Option<u64>) {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        let replaced = self.animations.insert(
+            window,
+            Animation {
+                id,
+                from,
+                target,
+                duration,
+                easing,
+                start: None,
+            },
+        );
+
+        (id, replaced.map(|animation| animation.id))
+    }
+
+    pub fn is_active(&self) -> bool {
+        !self.animations.is_empty()
+    }
+
+    /// Current opacity of the window.
+    pub fn opacity(&self, window: WindowKey) -> f32 {
+        self.opacity.get(window).copied().unwrap_or(1.0)
+    }
+
+    /// Forgets the window, returns the id of its running animation.
+    pub fn remove_window(&mut self, window: WindowKey) -> Option<u64> {
+        self.opacity.remove(window);
+        self.animations
+            .remove(&window)
+            .map(|animation| animation.id)
+    }
+
+    /// Advances all animations to `now`, finished animations are removed.
+    pub fn tick(&mut self, now: Duration) -> Vec<AnimationFrame> {
+        let mut frames = Vec::with_capacity(self.animations.len());
+        for (window, animation) in &mut self.animations {
+            if animation.start.is_none() {
+                animation.start = Some(now);
+            }
+
+            let frame = animation.frame(*window, now);
+            if let Some(opacity) = frame.opacity {
+                self.opacity.insert(*window, opacity);
+            }
+            frames.push(frame);
+        }
+
+        self.animations
+            .retain(|_, animation| animation.start.unwrap_or(now) + animation.duration > now);
+
+        frames
+    }
+}
@@ -3,16 +3,29 @@ use plugin_engine::{
     context::ExecutionContext,
     impl_untyped_plugin_binding,
     table::CapabilityProvider,
-    wasm::{Linker, bindgen},
+    wasm::{Instance, Linker, bindgen},
 };
+use std::{sync::Arc, time::Duration};
+
 use slotmap::KeyData;
+use smithay::{
+    reexports::wayland_protocols::xdg::decoration::zv1::server::zxdg_toplevel_decoration_v1::Mode as DecorationMode,
+    utils::{Logical, Rectangle},
+};
 
-use crate::compositor::api::{
-    CompositorContext, CompositorGlobals, PluginContextType, WindowKey,
-    general::fusion::compositor::{
-        types,
-        wm_imports::{self, WindowId},
+use crate::compositor::{
+    animation::{AnimationTarget, Easing, WindowProperties},
+    api::{
+        CompositorContext, CompositorEvent, CompositorGlobals, PluginContextType, WindowKey,
+        general::fusion::compositor::{
+            types,
+            wm_imports::{self, WindowId},
+        },
     },
+    decoration::{self, DecorationConfig},
+    effect::{Shadow, StyleRules, WindowStyle},
+    gesture::GesturePhase,
+    layer,
 };
 
 bindgen!({
@@ -32,10 +45,9 @@ impl CapabilityProvider for GeneralCapabilityProvider {
     fn create_bindings(
         &self,
         store: &mut wasmtime::Store<ExecutionContext<Self::Inner>>,
-        component: &wasmtime::component::Component,
-        linker: &Linker<ExecutionContext<Self::Inner>>,
+        instance: &Instance,
     ) -> Box<dyn UntypedPluginBinding> {
-        Box::new(Compositor::instantiate(&mut *store, component, linker).unwrap())
+        Box::new(Compositor::new(&mut *store, instance).unwrap())
     }
 }
 
@@ -79,22 +91,36 @@ impl wm_imports::Host for CompositorContext {
             .unwrap();
 
         let surface = window.toplevel().unwrap();
+        let mut size = (width as i32, height as i32).into();
+        if decoration::pending_server_side(surface) {
+            let frame = smithay::utils::Rectangle::from_size(size);
+            size = compositor.decorations.content(frame).size;
+        }
+
         surface.with_pending_state(|state| {
-            state.size = Some((width as i32, height as i32).into());
+            state.size = Some(size);
         });
     }
 
-    fn set_window_pos(&mut self, window: WindowId, x: u32, y: u32) {
+    fn set_window_pos(&mut self, window_id: WindowId, x: u32, y: u32) {
         let mut compositor = self.compositor_mut();
         let window = compositor
             .mapped_windows
-            .get(WindowKey(KeyData::from_ffi(window.inner)))
+            .get(WindowKey(KeyData::from_ffi(window_id.inner)))
             .unwrap()
             .clone();
 
+        let mut location = (x as i32, y as i32).into();
+        if decoration::pending_server_side(window.toplevel().unwrap()) {
+            let frame = smithay::utils::Rectangle::new(location, (0, 0).into());
+            location = compositor.decorations.content(frame).loc;
+        }
+
+        let key = WindowKey(KeyData::from_ffi(window_id.inner));
+        let compositor = &mut *compositor;
         compositor
-            .space
-            .map_element(window, (x as i32, y as i32), true);
+            .workspaces
+            .place_window(&mut compositor.space, key, &window, location, true);
     }
 
     fn get_output_size(&mut self) -> (u32, u32) {
@@ -113,6 +139,23 @@ impl wm_imports::Host for CompositorContext {
         }
     }
 
+    fn get_usable_area(&mut self, output: Option<String>) -> Option<types::Area> {
+        let compositor = self.compositor();
+        let output = match output {
+            Some(output) => output,
+            None => compositor.workspaces.focused_output()?.to_string(),
+        };
+        let output = compositor.space.outputs().find(|o| o.name() == output)?;
+        let area = layer::usable_area(&compositor.space, output)?;
+
+        Some(types::Area {
+            x: area.loc.x,
+            y: area.loc.y,
+            width: area.size.w as u32,
+            height: area.size.h as u32,
+        })
+    }
+
     fn send_configure(&mut self, window: WindowId) {
         let mut compositor = self.compositor();
         if let Some(window) = compositor
@@ -122,6 +165,257 @@ impl wm_imports::Host for CompositorContext {
             window.toplevel().unwrap().send_pending_configure();
         }
     }
+
+    fn set_decoration_config(&mut self, config: types::DecorationConfig) {
+        let mut compositor = self.compositor_mut();
+        let generation = compositor.decorations.generation + 1;
+        compositor.decorations = decoration_config(config, generation);
+    }
+
+    fn set_window_decoration(&mut self, window: WindowId, mode: Option<types::DecorationMode>) {
+        let compositor = self.compositor();
+        if let Some(window) = compositor
+            .mapped_windows
+            .get(WindowKey(KeyData::from_ffi(window.inner)))
+        {
+            decoration::force_mode(
+                window.toplevel().unwrap(),
+                mode.map(DecorationMode::from),
+                compositor.decorations.default_mode,
+            );
+        }
+    }
+
+    fn get_workspaces(&mut self) -> Vec<types::WorkspaceInfo> {
+        let compositor = self.compositor();
+        compositor
+            .workspaces
+            .iter()
+            .map(|(key, workspace)| types::WorkspaceInfo {
+                id: key.into(),
+                name: workspace.name.clone(),
+                output: workspace.output.clone(),
+                active: compositor.workspaces.is_active(key),
+                windows: workspace
+                    .windows()
+                    .iter()
+                    .map(|key| (*key).into())
+                    .collect(),
+            })
+            .collect()
+    }
+
+    fn create_workspace(
+        &mut self,
+        name: String,
+        output: Option<String>,
+    ) -> Option<types::WorkspaceId> {
+        let mut compositor = self.compositor_mut();
+        let output = match output {
+            Some(output) => output,
+            None => compositor.workspaces.focused_output()?.to_string(),
+        };
+
+        if !compositor.space.outputs().any(|o| o.name() == output) {
+            log::warn!("Unable to create workspace {name}: output {output} does not exist");
+            return None;
+        }
+
+        Some(compositor.workspaces.create(&name, &output).into())
+    }
+
+    fn restore_window(&mut self, window: WindowId) {
+        self.compositor_mut().restore_window(window.into());
+    }
+
+    fn switch_workspace(&mut self, workspace: types::WorkspaceId) {
+        self.compositor_mut().switch_workspace(workspace.into());
+    }
+
+    fn move_window_to_workspace(&mut self, window: WindowId, workspace: types::WorkspaceId) {
+        let mut compositor = self.compositor_mut();
+        let key = WindowKey::from(window);
+        let Some(window) = compositor.mapped_windows.get(key).cloned() else {
+            return;
+        };
+
+        let compositor = &mut *compositor;
+        compositor
+            .workspaces
+            .move_window(&mut compositor.space, key, &window, workspace.into());
+    }
+
+    fn animate_window(
+        &mut self,
+        window: WindowId,
+        animation: types::WindowAnimation,
+    ) -> types::AnimationId {
+        let mut compositor = self.compositor_mut();
+        let key = WindowKey::from(window);
+        let window = compositor.mapped_windows.get(key).unwrap().clone();
+
+        // Same coordinates as set-window-pos and set-window-size
+        let decorated = decoration::pending_server_side(window.toplevel().unwrap());
+        let content = |frame: Rectangle<i32, Logical>| {
+            if decorated {
+                compositor.decorations.content(frame)
+            } else {
+                frame
+            }
+        };
+        let target = AnimationTarget {
+            location: animation.position.map(|(x, y)| {
+                content(Rectangle::new((x as i32, y as i32).into(), (0, 0).into())).loc
+            }),
+            size: animation
+                .size
+                .map(|(w, h)| content(Rectangle::from_size((w as i32, h as i32).into())).size),
+            opacity: animation.opacity,
+        };
+
+        let size = window.geometry().size;
+        let from = WindowProperties {
+            location: compositor
+                .space
+                .element_location(&window)
+                .or(target.location)
+                .unwrap_or_default(),
+            size: if size.is_empty() {
+                target.size.unwrap_or(size)
+            } else {
+                size
+            },
+            opacity: compositor.animations.opacity(key),
+        };
+
+        let (id, replaced) = compositor.animations.start(
+            key,
+            from,
+            target,
+            Duration::from_millis(u64::from(animation.duration_ms)),
+            animation.easing.into(),
+        );
+
+        if let Some(replaced) = replaced {
+            compositor.events.push(CompositorEvent::AnimationFinished {
+                window: key,
+                animation: replaced,
+                cancelled: true,
+            });
+        }
+
+        id
+    }
+
+    fn get_keyboard_layout(&mut self) -> String {
+        self.compositor().keyboard_layout.clone()
+    }
+
+    fn set_style_rules(&mut self, rules: types::StyleRules) {
+        self.compositor_mut().styles.rules = StyleRules {
+            focused: rules.focused.into(),
+            unfocused: rules.unfocused.into(),
+        };
+    }
+
+    fn set_window_style(&mut self, window: WindowId, style: Option<types::WindowStyle>) {
+        let mut compositor = self.compositor_mut();
+        let key = WindowKey::from(window);
+        if compositor.mapped_windows.contains_key(key) {
+            compositor
+                .styles
+                .set_window_style(key, style.map(WindowStyle::from));
+        }
+    }
+}
+
+impl From<types::WindowStyle> for WindowStyle {
+    fn from(style: types::WindowStyle) -> Self {
+        WindowStyle {
+            opacity: style.opacity.clamp(0.0, 1.0),
+            border_width: style.border_width as i32,
+            border_color: color(style.border_color),
+            corner_radius: style.corner_radius as i32,
+            shadow: style.shadow.map(|shadow| Shadow {
+                color: color(shadow.color),
+                blur_radius: shadow.blur_radius as i32,
+                offset: (shadow.offset_x, shadow.offset_y).into(),
+            }),
+        }
+    }
+}
+
+impl From<types::Easing> for Easing {
+    fn from(easing: types::Easing) -> Self {
+        match easing {
+            types::Easing::Linear => Easing::Linear,
+            types::Easing::EaseIn => Easing::EaseIn,
+            types::Easing::EaseOut => Easing::EaseOut,
+            types::Easing::EaseInOut => Easing::EaseInOut,
+            types::Easing::CubicBezier(curve) => Easing::CubicBezier {
+                x1: f64::from(curve.x1),
+                y1: f64::from(curve.y1),
+                x2: f64::from(curve.x2),
+                y2: f64::from(curve.y2),
+            },
+        }
+    }
+}
+
+impl From<types::DecorationMode> for DecorationMode {
+    fn from(mode: types::DecorationMode) -> Self {
+        match mode {
+            types::DecorationMode::ClientSide => DecorationMode::ClientSide,
+            types::DecorationMode::ServerSide => DecorationMode::ServerSide,
+        }
+    }
+}
+
+impl From<GesturePhase> for types::GesturePhase {
+    fn from(phase: GesturePhase) -> Self {
+        match phase {
+            GesturePhase::Begin => types::GesturePhase::Begin,
+            GesturePhase::Update => types::GesturePhase::Update,
+            GesturePhase::End => types::GesturePhase::End,
+            GesturePhase::Cancel => types::GesturePhase::Cancel,
+        }
+    }
+}
+
+fn color(color: types::Color) -> [f32; 4] {
+    [
+        f32::from(color.r) / 255.0,
+        f32::from(color.g) / 255.0,
+        f32::from(color.b) / 255.0,
+        f32::from(color.a) / 255.0,
+    ]
+}
+
+fn load_font(path: &str) -> Option<Arc<fontdue::Font>> {
+    let bytes = std::fs::read(path)
+        .map_err(|err| log::warn!("Unable to read title font {path}: {err}"))
+        .ok()?;
+    let font = fontdue::Font::from_bytes(bytes, fontdue::FontSettings::default())
+        .map_err(|err| log::warn!("Unable to parse title font {path}: {err}"))
+        .ok()?;
+    Some(Arc::new(font))
+}
+
+fn decoration_config(config: types::DecorationConfig, generation: u64) -> DecorationConfig {
+    DecorationConfig {
+        title_height: config.title_height as i32,
+        border_width: config.border_width as i32,
+        active_color: color(config.active_color),
+        inactive_color: color(config.inactive_color),
+        title_color: color(config.title_color),
+        close_color: color(config.close_color),
+        maximize_color: color(config.maximize_color),
+        minimize_color: color(config.minimize_color),
+        font: config.title_font.as_deref().and_then(load_font),
+        font_size: config.title_font_size,
+        default_mode: config.default_mode.into(),
+        generation,
+    }
 }
 
 impl types::Host for CompositorContext {}
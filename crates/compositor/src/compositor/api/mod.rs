@@ -2,6 +2,7 @@ use std::{
     collections::HashMap,
     hash::Hash,
     sync::{Arc, Mutex},
+    time::Duration,
 };
 
 use plugin_engine::{InnerContext, InnerContextFactory};
@@ -9,7 +10,13 @@ use slotmap::{KeyData, SlotMap, new_key_type};
 use smithay::desktop::{Space, Window};
 use wasmtime::component::HasData;
 
-use crate::compositor::api::general::fusion::compositor::types::WindowId;
+use crate::compositor::{
+    animation::Animations,
+    api::general::fusion::compositor::types::WindowId,
+    decoration::DecorationConfig,
+    effect::WindowStyles,
+    workspace::{WorkspaceKey, Workspaces},
+};
 
 pub mod general;
 
@@ -104,9 +111,49 @@ impl From<WindowKey> for WindowId {
     }
 }
 
+/// Events for the window manager which can't be delivered right away,
+/// e.g. because they were triggered by the window manager itself.
+pub enum CompositorEvent {
+    WorkspaceChanged {
+        output: String,
+        previous: Option<WorkspaceKey>,
+        current: WorkspaceKey,
+    },
+    AnimationFinished {
+        window: WindowKey,
+        animation: u64,
+        cancelled: bool,
+    },
+    /// Exclusive zones of the output's layer surfaces changed.
+    UsableAreaChanged {
+        output: String,
+    },
+    LayoutChanged {
+        layout: String,
+    },
+    /// The window was minimized or restored.
+    WindowMinimized {
+        window: WindowKey,
+        minimized: bool,
+    },
+}
+
 pub struct CompositorGlobals {
     pub mapped_windows: SlotMap<WindowKey, Window>,
     pub space: Space<Window>,
+    pub workspaces: Workspaces,
+    pub decorations: DecorationConfig,
+    pub animations: Animations,
+    pub styles: WindowStyles,
+    pub events: Vec<CompositorEvent>,
+    /// Name of the active keyboard layout.
+    pub keyboard_layout: String,
+}
+
+impl Default for CompositorGlobals {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl CompositorGlobals {
@@ -114,6 +161,89 @@ impl CompositorGlobals {
         Self {
             mapped_windows: SlotMap::default(),
             space: Space::default(),
+            workspaces: Workspaces::default(),
+            decorations: DecorationConfig::default(),
+            animations: Animations::default(),
+            styles: WindowStyles::default(),
+            events: Vec::new(),
+            keyboard_layout: String::new(),
+        }
+    }
+
+    pub fn switch_workspace(&mut self, workspace: WorkspaceKey) {
+        if let Some(changed) =
+            self.workspaces
+                .switch(&mut self.space, &self.mapped_windows, workspace)
+        {
+            self.events.push(CompositorEvent::WorkspaceChanged {
+                output: changed.output,
+                previous: changed.previous,
+                current: changed.current,
+            });
+        }
+    }
+
+    /// Unmaps the window until [`Self::restore_window`], `false` if it was minimized already.
+    pub fn minimize_window(&mut self, window: WindowKey) -> bool {
+        let Some(element) = self.mapped_windows.get(window) else {
+            return false;
+        };
+        if !self.workspaces.minimize(&mut self.space, window, element) {
+            return false;
+        }
+
+        self.events.push(CompositorEvent::WindowMinimized {
+            window,
+            minimized: true,
+        });
+        true
+    }
+
+    /// Maps a minimized window again where it was.
+    pub fn restore_window(&mut self, window: WindowKey) {
+        let Some(element) = self.mapped_windows.get(window) else {
+            return;
+        };
+        if self.workspaces.restore(&mut self.space, window, element) {
+            self.events.push(CompositorEvent::WindowMinimized {
+                window,
+                minimized: false,
+            });
+        }
+    }
+
+    /// Applies the state of the running animations at `now` to the windows.
+    pub fn tick_animations(&mut self, now: Duration) {
+        for frame in self.animations.tick(now) {
+            let Some(window) = self.mapped_windows.get(frame.window).cloned() else {
+                continue;
+            };
+
+            if let Some(location) = frame.location {
+                self.workspaces.place_window(
+                    &mut self.space,
+                    frame.window,
+                    &window,
+                    location,
+                    false,
+                );
+            }
+
+            if let Some(size) = frame.size {
+                let toplevel = window.toplevel().unwrap();
+                toplevel.with_pending_state(|state| {
+                    state.size = Some(size);
+                });
+                toplevel.send_pending_configure();
+            }
+
+            if frame.finished {
+                self.events.push(CompositorEvent::AnimationFinished {
+                    window: frame.window,
+                    animation: frame.animation,
+                    cancelled: false,
+                });
+            }
         }
     }
 }
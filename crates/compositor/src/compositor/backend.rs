@@ -1,6 +1,41 @@
-use smithay::output;
+use smithay::output::{self, Output};
+
+use crate::compositor::{input_device::InputConfig, output::OutputError};
 
 pub trait Backend: 'static {
     fn create_output(&self) -> output::Output;
     fn mode(&self) -> output::Mode;
+
+    /// Applies `config` to the devices whose settings live in the backend, e.g. libinput.
+    fn configure_input_devices(&mut self, _config: &InputConfig) {}
+
+    /// Modes `output` can be switched to, only the current one unless the backend can switch.
+    fn output_modes(&self, output: &Output) -> Vec<output::Mode> {
+        output.current_mode().into_iter().collect()
+    }
+
+    /// Switches `output` to one of its [`Backend::output_modes`],
+    /// with `test` the backend only checks that it could.
+    fn set_output_mode(
+        &mut self,
+        _output: &Output,
+        _mode: output::Mode,
+        _test: bool,
+    ) -> Result<(), OutputError> {
+        Ok(())
+    }
+
+    /// Turns the display of `output` off or back on,
+    /// with `test` the backend only checks that it could.
+    fn set_output_enabled(
+        &mut self,
+        _output: &Output,
+        _enabled: bool,
+        _test: bool,
+    ) -> Result<(), OutputError> {
+        Ok(())
+    }
+
+    /// Lets go of the devices on exit, e.g. restores the modes the VT had before.
+    fn shutdown(&mut self) {}
 }
@@ -0,0 +1,162 @@
+use std::{collections::HashMap, path::Path};
+
+use fusion_socket_protocol::ReloadConfigResponse;
+use plugin_engine::InnerContext;
+use serde::Deserialize;
+
+use crate::compositor::{
+    api::CompositorContext,
+    backend::Backend,
+    control::Responder,
+    cursor::CursorConfig,
+    gesture::{GestureConfig, GestureError, Gestures},
+    input_device::InputConfig,
+    keybinding::{BindingConfig, KeybindingError, Keybindings},
+    keyboard::{KeyboardConfig, KeyboardError},
+    state::App,
+};
+
+/// Used when the config file does not exist.
+const DEFAULT_CONFIG: &str = r#"
+[keybindings.default]
+"Super+Return" = { spawn = "kitty" }
+"Super+Shift+E" = { action = "quit" }
+"Super+Shift+R" = { action = "reload-config" }
+"#;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ConfigError {
+    #[error("unable to read the config: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("invalid config: {0}")]
+    Parse(#[from] toml::de::Error),
+    #[error("invalid keybinding: {0}")]
+    Keybinding(#[from] KeybindingError),
+    #[error("invalid gesture: {0}")]
+    Gesture(#[from] GestureError),
+    #[error("invalid keyboard settings: {0}")]
+    Keyboard(#[from] KeyboardError),
+}
+
+/// Contents of `compositor.toml` in the config directory.
+///
+/// ```toml
+/// [keyboard]  # see `KeyboardConfig`
+/// layout = "us,ru"
+///
+/// [cursor]  # see `CursorConfig`
+/// theme = "Adwaita"
+///
+/// [[input.device]]  # see `DeviceConfig`
+/// type = "touchpad"
+/// tap = true
+///
+/// # Every table under `keybindings` is a binding mode, `default` is active at startup
+/// [keybindings.default]
+/// "Super+Return" = { spawn = "kitty" }
+/// "Super+Shift+E" = { action = "quit" }          # quit, reload-plugins or reload-config
+/// "Super+R" = { mode = "resize" }
+/// "Super+D" = { plugin = "launcher", on = "release" }
+///
+/// [keybindings.resize]
+/// "Escape" = { mode = "default" }
+/// "Right" = { plugin = "grow-right", repeat = true }
+///
+/// [[gesture]]  # see `GestureConfig`
+/// type = "swipe"
+/// fingers = 3
+/// direction = "left"
+/// plugin = "workspace-next"
+///
+/// [debug]  # see `DebugConfig`
+/// call-plugin = true
+/// inject-input = true
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    #[serde(default)]
+    pub keyboard: KeyboardConfig,
+    #[serde(default)]
+    pub input: InputConfig,
+    #[serde(default)]
+    pub cursor: CursorConfig,
+    #[serde(default)]
+    pub keybindings: HashMap<String, HashMap<String, BindingConfig>>,
+    #[serde(default)]
+    pub gesture: Vec<GestureConfig>,
+    #[serde(default)]
+    pub debug: DebugConfig,
+}
+
+/// `[debug]` table of the config, tools meant for plugin development.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct DebugConfig {
+    /// Lets control clients call plugin exports with `CallPluginRequest`
+    pub call_plugin: bool,
+    /// Lets control clients type and move the pointer with `InjectInputRequest`
+    pub inject_input: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        toml::from_str(DEFAULT_CONFIG).unwrap()
+    }
+}
+
+impl Config {
+    pub fn default_path() -> std::path::PathBuf {
+        CompositorContext::config_path().join("compositor.toml")
+    }
+
+    /// Reads the config at `path`, falling back to the defaults when there is no file.
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        match std::fs::read_to_string(path) {
+            Ok(config) => Ok(toml::from_str(&config)?),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    pub fn keybindings(&self) -> Result<Keybindings, KeybindingError> {
+        Keybindings::parse(&self.keybindings)
+    }
+
+    pub fn gestures(&self) -> Result<Gestures, GestureError> {
+        Gestures::parse(&self.gesture)
+    }
+}
+
+impl<B: Backend> App<B> {
+    /// Re-reads the config file and applies it, keeping the current settings on errors.
+    pub fn reload_config(&mut self) -> Result<(), ConfigError> {
+        let config = Config::load(&self.config_file)?;
+        let keybindings = config.keybindings()?;
+        let gestures = config.gestures()?;
+        // Recompiling the keymap would reset the active layout
+        if config.keyboard != self.input_state.keyboard_config {
+            self.apply_keyboard_config(config.keyboard)?;
+        }
+        self.keybindings.replace_bindings(keybindings);
+        self.gestures.replace_bindings(gestures);
+        if config.input != self.input_state.input_config {
+            self.apply_input_config(config.input);
+        }
+        if config.cursor != self.input_state.cursor.config {
+            self.input_state.cursor.configure(config.cursor);
+        }
+        self.debug = config.debug;
+        log::info!("Reloaded config from {}", self.config_file.display());
+        Ok(())
+    }
+
+    pub(crate) fn reload_config_request(&mut self, responder: &mut Responder<'_>) {
+        let response = match self.reload_config() {
+            Ok(()) => ReloadConfigResponse::Ok,
+            Err(err) => ReloadConfigResponse::Error(err.to_string()),
+        };
+
+        responder.send(&response);
+    }
+}
@@ -0,0 +1,564 @@
+use std::{
+    collections::HashMap,
+    fs::Permissions,
+    io::{self, Read, Write},
+    os::unix::{
+        fs::PermissionsExt,
+        net::{UnixListener, UnixStream},
+    },
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use calloop::{
+    Interest, LoopHandle, Mode, PostAction, RegistrationToken,
+    generic::Generic,
+    timer::{TimeoutAction, Timer},
+};
+use fusion_socket_protocol::{
+    CompositorRequest, Envelope, Event, Features, FrameBuffer, Hello, HelloResponse,
+    MAX_FRAME_SIZE, PROTOCOL_VERSION, ProtocolError, Reply, SubscribeResponse, Topic,
+};
+use serde::Serialize;
+use smithay::reexports::rustix::{net::sockopt, process};
+
+use crate::compositor::{backend::Backend, data::Data, input::SpecialActions, state::App};
+
+/// Time a client has to finish a request once it started sending it.
+const FRAME_TIMEOUT: Duration = Duration::from_secs(5);
+/// Bytes waiting for a client before it is dropped, room for a frame it is still taking and the next one.
+const MAX_OUTGOING: usize = 2 * MAX_FRAME_SIZE;
+
+/// A client of the control socket, it may send any number of requests.
+struct Connection {
+    stream: UnixStream,
+    frames: FrameBuffer,
+    /// Readiness of the stream in the event loop
+    source: RegistrationToken,
+    /// Frames the client didn't take yet
+    outgoing: Vec<u8>,
+    /// Write readiness of the stream, registered while [`Self::outgoing`] isn't empty
+    writable: Option<RegistrationToken>,
+    /// When the first byte of the pending frame arrived
+    partial_since: Option<Instant>,
+    timeout: Option<RegistrationToken>,
+    /// Whether the client sent a [`Hello`] the compositor understands
+    greeted: bool,
+    /// Events streamed to the client, see [`App::notify`]
+    topics: Vec<Topic>,
+}
+
+/// Clients connected to the control socket, see [`listen`].
+#[derive(Default)]
+pub struct ControlConnections {
+    /// Path and event source of the socket, removed on [`App::shutdown`]
+    listener: Option<(PathBuf, RegistrationToken)>,
+    next_id: u64,
+    connections: HashMap<u64, Connection>,
+    /// Last reported status of every plugin, kept while somebody is subscribed to them
+    plugin_statuses: HashMap<String, String>,
+}
+
+impl ControlConnections {
+    pub fn len(&self) -> usize {
+        self.connections.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.connections.is_empty()
+    }
+
+    /// Remembers the socket [`listen`] bound, so it can be removed again.
+    pub fn set_listener(&mut self, path: PathBuf, source: RegistrationToken) {
+        self.listener = Some((path, source));
+    }
+
+    fn is_subscribed(&self, topic: Topic) -> bool {
+        self.connections
+            .values()
+            .any(|connection| connection.topics.contains(&topic))
+    }
+}
+
+/// Binds the control socket at `path` and serves its clients from the event loop.
+///
+/// Only a socket nobody listens on anymore is replaced, a running compositor keeps its own.
+pub fn listen<B: Backend + SpecialActions + 'static>(
+    handle: &LoopHandle<'static, Data<B>>,
+    path: &Path,
+) -> Result<RegistrationToken, Box<dyn std::error::Error>> {
+    if std::fs::exists(path)? {
+        if UnixStream::connect(path).is_ok() {
+            return Err(format!("{} is used by another compositor", path.display()).into());
+        }
+        std::fs::remove_file(path)?;
+    }
+
+    let listener = UnixListener::bind(path)?;
+    std::fs::set_permissions(path, Permissions::from_mode(0o600))?;
+    listener.set_nonblocking(true)?;
+    let source = Generic::new(listener, Interest::READ, Mode::Level);
+    let token = handle.insert_source(source, |_, listener, data| {
+        data.state.accept_control_connections(listener);
+        Ok(PostAction::Continue)
+    })?;
+    Ok(token)
+}
+
+/// Queues a frame for a control client, it is written by [`flush`].
+fn send(outgoing: &mut Vec<u8>, message: &impl Serialize) {
+    let frame = postcard::to_stdvec_cobs(message).expect("messages are serializable");
+    outgoing.extend_from_slice(&frame);
+}
+
+/// Writes what the client takes without blocking and waits for the stream to be writable for the rest.
+///
+/// Fails when the client went away or stopped reading.
+fn flush<B: Backend + 'static>(
+    handle: &LoopHandle<'static, Data<B>>,
+    id: u64,
+    connection: &mut Connection,
+) -> io::Result<()> {
+    while !connection.outgoing.is_empty() {
+        match connection.stream.write(&connection.outgoing) {
+            Ok(0) => return Err(io::ErrorKind::WriteZero.into()),
+            Ok(written) => drop(connection.outgoing.drain(..written)),
+            Err(error) if error.kind() == io::ErrorKind::Interrupted => {}
+            Err(error) if error.kind() == io::ErrorKind::WouldBlock => break,
+            Err(error) => return Err(error),
+        }
+    }
+
+    if connection.outgoing.len() > MAX_OUTGOING {
+        return Err(io::Error::other("the client stopped reading"));
+    }
+    if !connection.outgoing.is_empty() && connection.writable.is_none() {
+        let readiness = Generic::new(connection.stream.try_clone()?, Interest::WRITE, Mode::Level);
+        let source = handle
+            .insert_source(readiness, move |_, _, data| {
+                Ok(data.state.write_control_connection(id))
+            })
+            .map_err(|error| io::Error::other(error.error))?;
+        connection.writable = Some(source);
+    }
+    Ok(())
+}
+
+/// Where the response to a control request goes.
+pub struct Responder<'a> {
+    outgoing: &'a mut Vec<u8>,
+    id: u32,
+}
+
+impl Responder<'_> {
+    pub fn send(&mut self, response: &impl Serialize) {
+        let body = postcard::to_stdvec(response).expect("responses are serializable");
+        send(self.outgoing, &Reply::Response { id: self.id, body });
+    }
+}
+
+impl<B: Backend + SpecialActions + 'static> App<B> {
+    /// Optional parts of the protocol this compositor serves.
+    pub fn features(&self) -> Features {
+        let mut features = Features::SUBSCRIBE;
+        features.set(Features::INJECT_INPUT, self.debug.inject_input);
+        features.set(Features::CALL_PLUGIN, self.debug.call_plugin);
+        features
+    }
+
+    fn accept_control_connections(&mut self, listener: &UnixListener) {
+        loop {
+            match listener.accept() {
+                Ok((stream, _)) => {
+                    if let Err(error) = self.add_control_connection(stream) {
+                        log::warn!("Unable to serve a control client: {error}");
+                    }
+                }
+                Err(error) if error.kind() == io::ErrorKind::WouldBlock => break,
+                Err(error) => {
+                    log::warn!("Unable to accept a control client: {error}");
+                    break;
+                }
+            }
+        }
+    }
+
+    fn add_control_connection(
+        &mut self,
+        stream: UnixStream,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        // The permissions of the socket don't apply to root
+        let peer = sockopt::socket_peercred(&stream)?;
+        if peer.uid != process::getuid() {
+            return Err(format!("refused a client of user {}", peer.uid.as_raw()).into());
+        }
+
+        // A stuck client must never block the compositor, responses wait in `outgoing` instead
+        stream.set_nonblocking(true)?;
+
+        let id = self.control_connections.next_id;
+        self.control_connections.next_id += 1;
+        let readiness = Generic::new(stream.try_clone()?, Interest::READ, Mode::Level);
+        let source = self.handle.insert_source(readiness, move |_, _, data| {
+            Ok(data.state.read_control_connection(id))
+        })?;
+
+        self.control_connections.connections.insert(
+            id,
+            Connection {
+                stream,
+                frames: FrameBuffer::default(),
+                source,
+                outgoing: Vec::new(),
+                writable: None,
+                partial_since: None,
+                timeout: None,
+                greeted: false,
+                topics: Vec::new(),
+            },
+        );
+        Ok(())
+    }
+
+    fn read_control_connection(&mut self, id: u64) -> PostAction {
+        // Taken out so the requests can borrow the state
+        let Some(mut connection) = self.control_connections.connections.remove(&id) else {
+            return PostAction::Remove;
+        };
+
+        let mut bytes = [0; 4096];
+        let read = match connection.stream.read(&mut bytes) {
+            Ok(0) => {
+                self.close_control_connection(connection);
+                return PostAction::Remove;
+            }
+            Ok(read) => read,
+            Err(error)
+                if matches!(
+                    error.kind(),
+                    io::ErrorKind::Interrupted | io::ErrorKind::WouldBlock
+                ) =>
+            {
+                self.control_connections.connections.insert(id, connection);
+                return PostAction::Continue;
+            }
+            Err(error) => {
+                log::debug!("Unable to read from a control client: {error}");
+                self.close_control_connection(connection);
+                return PostAction::Remove;
+            }
+        };
+
+        connection.frames.push(&bytes[..read]);
+        let mut completed = false;
+        loop {
+            match connection.frames.next_frame() {
+                Ok(Some(mut frame)) => {
+                    completed = true;
+                    if !self.handle_control_frame(&mut connection, &mut frame) {
+                        self.close_control_connection(connection);
+                        return PostAction::Remove;
+                    }
+                }
+                Ok(None) => break,
+                Err(_) => {
+                    // Whatever follows can't be told apart from the rest of the frame
+                    let error = Reply::Error {
+                        id: None,
+                        error: ProtocolError::TooLarge,
+                    };
+                    send(&mut connection.outgoing, &error);
+                    let _ = flush(&self.handle, id, &mut connection);
+                    self.close_control_connection(connection);
+                    return PostAction::Remove;
+                }
+            }
+        }
+
+        if let Err(error) = flush(&self.handle, id, &mut connection) {
+            log::debug!("Dropping a control client: {error}");
+            self.close_control_connection(connection);
+            return PostAction::Remove;
+        }
+
+        if !connection.frames.is_partial() {
+            connection.partial_since = None;
+        } else if completed || connection.partial_since.is_none() {
+            let since = Instant::now();
+            connection.partial_since = Some(since);
+            if connection.timeout.is_none() {
+                let timer = Timer::from_deadline(since + FRAME_TIMEOUT);
+                connection.timeout = self
+                    .handle
+                    .insert_source(timer, move |_, (), data| {
+                        data.state.control_frame_timeout(id)
+                    })
+                    .ok();
+            }
+        }
+
+        self.control_connections.connections.insert(id, connection);
+        PostAction::Continue
+    }
+
+    fn control_frame_timeout(&mut self, id: u64) -> TimeoutAction {
+        let Some(connection) = self.control_connections.connections.get_mut(&id) else {
+            return TimeoutAction::Drop;
+        };
+
+        match connection.partial_since {
+            Some(since) if since.elapsed() >= FRAME_TIMEOUT => {
+                let mut connection = self.control_connections.connections.remove(&id).unwrap();
+                let error = Reply::Error {
+                    id: None,
+                    error: ProtocolError::Timeout,
+                };
+                send(&mut connection.outgoing, &error);
+                let _ = flush(&self.handle, id, &mut connection);
+                // The timer is dropped by returning
+                connection.timeout = None;
+                self.handle.remove(connection.source);
+                self.close_control_connection(connection);
+                TimeoutAction::Drop
+            }
+            Some(since) => TimeoutAction::ToInstant(since + FRAME_TIMEOUT),
+            None => {
+                connection.timeout = None;
+                TimeoutAction::Drop
+            }
+        }
+    }
+
+    /// Serves a frame of `connection`, `false` when the connection has to be closed.
+    fn handle_control_frame(&mut self, connection: &mut Connection, frame: &mut [u8]) -> bool {
+        if !connection.greeted {
+            return Self::greet(connection, frame, self.features());
+        }
+
+        let envelope = match postcard::from_bytes_cobs::<Envelope>(frame) {
+            Ok(envelope) => envelope,
+            Err(error) => {
+                let error = Reply::Error {
+                    id: None,
+                    error: ProtocolError::Malformed(error.to_string()),
+                };
+                send(&mut connection.outgoing, &error);
+                return true;
+            }
+        };
+
+        let request = match postcard::from_bytes::<CompositorRequest>(&envelope.body) {
+            Ok(request) => request,
+            Err(error) => {
+                // Requests are only appended, an index past the known ones comes from a newer client
+                let error = match postcard::take_from_bytes::<u32>(&envelope.body) {
+                    Ok((variant, _)) if variant >= CompositorRequest::VARIANTS => {
+                        ProtocolError::UnknownRequest(variant)
+                    }
+                    _ => ProtocolError::Malformed(error.to_string()),
+                };
+                let error = Reply::Error {
+                    id: Some(envelope.id),
+                    error,
+                };
+                send(&mut connection.outgoing, &error);
+                return true;
+            }
+        };
+
+        self.handle_control_request(request, envelope.id, connection);
+        true
+    }
+
+    fn greet(connection: &mut Connection, frame: &mut [u8], features: Features) -> bool {
+        let response = match postcard::from_bytes_cobs::<Hello>(frame) {
+            Ok(hello) if hello.version == PROTOCOL_VERSION => HelloResponse::Ok {
+                version: PROTOCOL_VERSION,
+                features,
+            },
+            Ok(hello) => HelloResponse::Error(format!(
+                "protocol version {} is not supported, the compositor speaks version {PROTOCOL_VERSION}",
+                hello.version
+            )),
+            Err(error) => HelloResponse::Error(format!("expected a handshake: {error}")),
+        };
+
+        connection.greeted = matches!(response, HelloResponse::Ok { .. });
+        send(&mut connection.outgoing, &response);
+        connection.greeted
+    }
+
+    fn subscribe(&mut self, connection: &mut Connection, id: u32, topics: Vec<Topic>) {
+        let mut responder = Responder {
+            outgoing: &mut connection.outgoing,
+            id,
+        };
+        responder.send(&SubscribeResponse::Ok);
+
+        connection.topics = if topics.is_empty() {
+            Topic::ALL.to_vec()
+        } else {
+            topics
+        };
+        if connection.topics.contains(&Topic::Plugin)
+            && !self.control_connections.is_subscribed(Topic::Plugin)
+        {
+            self.control_connections.plugin_statuses = self.plugin_statuses();
+        }
+    }
+
+    fn handle_control_request(
+        &mut self,
+        request: CompositorRequest,
+        id: u32,
+        connection: &mut Connection,
+    ) {
+        let stream = &mut Responder {
+            outgoing: &mut connection.outgoing,
+            id,
+        };
+        match request {
+            CompositorRequest::Exit(_) => self.exit(stream),
+            CompositorRequest::Ping(_) => Self::ping(stream),
+            CompositorRequest::GetPluginList(_) => self.get_plugin_list(stream),
+            CompositorRequest::RestartPlugin(request) => {
+                self.restart_plugin(&request.plugin_id, stream);
+            }
+            CompositorRequest::GetPluginInfo(request) => {
+                self.get_plugin_info(&request.plugin_id, stream);
+            }
+            CompositorRequest::InstallPlugin(request) => self.install_plugin(&request.path, stream),
+            CompositorRequest::UninstallPlugin(request) => {
+                self.uninstall_plugin(&request.plugin_id, stream);
+            }
+            CompositorRequest::SetPluginEnabled(request) => {
+                self.set_plugin_enabled(&request.plugin_id, request.enabled, stream);
+            }
+            CompositorRequest::CallPlugin(request) => self.call_plugin(request, stream),
+            CompositorRequest::GetWorkspaces(_) => self.get_workspaces(stream),
+            CompositorRequest::InjectInput(request) => {
+                self.inject_input_request(request.actions, stream);
+            }
+            CompositorRequest::ReloadConfig(_) => self.reload_config_request(stream),
+            CompositorRequest::GetKeyboard(_) => self.get_keyboard(stream),
+            CompositorRequest::SetKeyboard(request) => self.set_keyboard(request, stream),
+            CompositorRequest::Subscribe(request) => {
+                self.subscribe(connection, id, request.topics);
+            }
+            CompositorRequest::GetWindows(_) => self.get_windows(stream),
+            CompositorRequest::WindowAction(request) => self.window_action(request, stream),
+            CompositorRequest::GetOutputs(_) => self.get_outputs(stream),
+            CompositorRequest::SetOutput(request) => self.set_output(request, stream),
+        }
+    }
+}
+
+impl<B: Backend> App<B> {
+    /// Sends `event` to the clients subscribed to its topic.
+    pub fn notify(&mut self, event: Event) {
+        let topic = event.topic();
+        if !self.control_connections.is_subscribed(topic) {
+            return;
+        }
+
+        let body = postcard::to_stdvec(&event).expect("events are serializable");
+        let frame =
+            postcard::to_stdvec_cobs(&Reply::Event { body }).expect("events are serializable");
+        let mut dropped = Vec::new();
+        for (id, connection) in &mut self.control_connections.connections {
+            if !connection.topics.contains(&topic) {
+                continue;
+            }
+            connection.outgoing.extend_from_slice(&frame);
+            if let Err(error) = flush(&self.handle, *id, connection) {
+                log::debug!("Dropping a control subscriber: {error}");
+                dropped.push(*id);
+            }
+        }
+        for id in dropped {
+            let connection = self.control_connections.connections.remove(&id).unwrap();
+            self.handle.remove(connection.source);
+            self.close_control_connection(connection);
+        }
+    }
+
+    /// Reports the plugins whose status changed since the last call.
+    pub(crate) fn check_plugin_statuses(&mut self) {
+        if !self.control_connections.is_subscribed(Topic::Plugin) {
+            return;
+        }
+
+        let statuses = self.plugin_statuses();
+        let previous = std::mem::replace(&mut self.control_connections.plugin_statuses, statuses);
+        let changed = (self.control_connections.plugin_statuses.iter())
+            .filter(|(id, status)| previous.get(*id) != Some(*status))
+            .map(|(id, status)| Event::PluginStatusChanged {
+                id: id.clone(),
+                status: status.clone(),
+            })
+            .collect::<Vec<_>>();
+        for event in changed {
+            self.notify(event);
+        }
+    }
+
+    fn plugin_statuses(&self) -> HashMap<String, String> {
+        self.engine
+            .get_plugin_list()
+            .into_iter()
+            .filter_map(|id| {
+                let status = self.engine.get_plugin_env_by_id(&id)?.status().to_string();
+                Some((id.to_string(), status))
+            })
+            .collect()
+    }
+
+    fn write_control_connection(&mut self, id: u64) -> PostAction {
+        let Some(connection) = self.control_connections.connections.get_mut(&id) else {
+            return PostAction::Remove;
+        };
+
+        match flush(&self.handle, id, connection) {
+            Ok(()) if connection.outgoing.is_empty() => {
+                connection.writable = None;
+                PostAction::Remove
+            }
+            Ok(()) => PostAction::Continue,
+            Err(error) => {
+                log::debug!("Dropping a control client: {error}");
+                let mut connection = self.control_connections.connections.remove(&id).unwrap();
+                // Removed by returning, unlike the readiness source
+                connection.writable = None;
+                self.handle.remove(connection.source);
+                self.close_control_connection(connection);
+                PostAction::Remove
+            }
+        }
+    }
+
+    /// Drops a connection taken out of the list, its readiness source is left to the caller.
+    fn close_control_connection(&mut self, connection: Connection) {
+        if let Some(timeout) = connection.timeout {
+            self.handle.remove(timeout);
+        }
+        if let Some(writable) = connection.writable {
+            self.handle.remove(writable);
+        }
+    }
+
+    /// Stops listening, deletes the socket and hangs up on every client.
+    pub(crate) fn close_control_socket(&mut self) {
+        if let Some((path, source)) = self.control_connections.listener.take() {
+            self.handle.remove(source);
+            if let Err(error) = std::fs::remove_file(&path) {
+                log::warn!("Unable to remove {}: {error}", path.display());
+            }
+        }
+
+        let connections = std::mem::take(&mut self.control_connections.connections);
+        for connection in connections.into_values() {
+            self.handle.remove(connection.source);
+            self.close_control_connection(connection);
+        }
+    }
+}
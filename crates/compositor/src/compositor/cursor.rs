@@ -1,3 +1,4 @@
+use serde::Deserialize;
 use smithay::backend::allocator::Fourcc;
 use smithay::backend::renderer::element::Kind;
 use smithay::backend::renderer::element::memory::MemoryRenderBufferRenderElement;
@@ -17,62 +18,271 @@ use smithay::{
     input::pointer::CursorIcon,
 };
 use std::collections::HashMap;
+use std::time::Duration;
 
 use smithay::{
     input::{
         Seat,
-        keyboard::{KeyboardHandle, XkbConfig},
+        keyboard::KeyboardHandle,
         pointer::{CursorImageStatus, PointerHandle},
+        touch::TouchHandle,
     },
-    utils::{Logical, Point},
+    utils::{Logical, Point, Rectangle},
 };
 use xcursor::{
     CursorTheme,
     parser::{Image, parse_xcursor},
 };
 
-use crate::compositor::{backend::Backend, state::App, udev::TestRenderElement};
+use crate::compositor::{
+    backend::Backend,
+    config::Config,
+    input_device::{InputConfig, InputDevice},
+    keyboard::KeyboardConfig,
+    state::App,
+    udev::TestRenderElement,
+};
+
+/// Theme used when neither the config nor `XCURSOR_THEME` name one.
+pub const DEFAULT_CURSOR_THEME: &str = "default";
+/// Nominal size used when neither the config nor `XCURSOR_SIZE` set one.
+pub const DEFAULT_CURSOR_SIZE: u32 = 24;
+
+/// `[cursor]` table of the config, unset fields come from `XCURSOR_THEME` and `XCURSOR_SIZE`.
+///
+/// ```toml
+/// [cursor]
+/// theme = "Adwaita"
+/// size = 32          # logical size, scaled outputs use the larger images of the theme
+/// hardware = false   # draws the cursor into the frame instead of a cursor plane
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct CursorConfig {
+    pub theme: Option<String>,
+    pub size: Option<u32>,
+    /// Applies to outputs connected afterwards
+    pub hardware: bool,
+}
 
+impl Default for CursorConfig {
+    fn default() -> Self {
+        Self {
+            theme: None,
+            size: None,
+            hardware: true,
+        }
+    }
+}
+
+impl CursorConfig {
+    pub fn theme(&self) -> String {
+        self.theme
+            .clone()
+            .or_else(|| std::env::var("XCURSOR_THEME").ok())
+            .unwrap_or_else(|| DEFAULT_CURSOR_THEME.to_string())
+    }
+
+    pub fn size(&self) -> u32 {
+        self.size
+            .or_else(|| std::env::var("XCURSOR_SIZE").ok()?.parse().ok())
+            .filter(|size| *size > 0)
+            .unwrap_or(DEFAULT_CURSOR_SIZE)
+    }
+}
+
+/// Image of a theme cursor with the buffer it is drawn from.
+pub struct CursorFrame {
+    pub image: Image,
+    /// Ratio of the image size to the nominal size
+    pub scale: i32,
+    buffer: MemoryRenderBuffer,
+}
+
+/// Every size and animation frame of a theme cursor.
 pub struct XCursor {
-    inner: Vec<Image>,
+    frames: Vec<CursorFrame>,
+}
+
+impl XCursor {
+    /// Loads `icon` from `theme`, falling back to the alternative names of the icon.
+    pub fn load(theme: &CursorTheme, icon: CursorIcon, size: u32) -> Option<Self> {
+        let images = std::iter::once(icon.name())
+            .chain(icon.alt_names().iter().copied())
+            .find_map(|name| theme.load_icon(name))
+            .and_then(|path| std::fs::read(path).ok())
+            .and_then(|bytes| parse_xcursor(&bytes))
+            .filter(|images| !images.is_empty())?;
+
+        Some(Self::from_images(images, size))
+    }
+
+    /// Cursor for a nominal `size`, larger images are drawn with a matching buffer scale.
+    pub fn from_images(images: Vec<Image>, size: u32) -> Self {
+        let frames = images
+            .into_iter()
+            .map(|image| {
+                let scale = (f64::from(image.size) / f64::from(size)).round().max(1.0) as i32;
+                let buffer = MemoryRenderBuffer::from_slice(
+                    &image.pixels_rgba,
+                    Fourcc::Argb8888,
+                    (image.width as i32, image.height as i32),
+                    scale,
+                    Transform::Normal,
+                    None,
+                );
+                CursorFrame {
+                    image,
+                    scale,
+                    buffer,
+                }
+            })
+            .collect();
+        Self { frames }
+    }
+
+    /// Frames of the image size closest to `size`.
+    fn frames(&self, size: u32) -> impl Iterator<Item = &CursorFrame> + Clone {
+        let nearest = self
+            .frames
+            .iter()
+            .map(|frame| frame.image.size)
+            .min_by_key(|image_size| image_size.abs_diff(size))
+            .unwrap_or_default();
+        self.frames
+            .iter()
+            .filter(move |frame| frame.image.size == nearest)
+    }
+
+    /// Frame of the size closest to `size` shown at `time` into the animation.
+    pub fn frame(&self, size: u32, time: Duration) -> Option<&CursorFrame> {
+        let frames = self.frames(size);
+        let total = frames.clone().map(|frame| frame.image.delay).sum::<u32>();
+        let mut millis = if total == 0 {
+            0
+        } else {
+            (time.as_millis() % u128::from(total)) as u32
+        };
+        let mut last = None;
+        for frame in frames {
+            if millis < frame.image.delay {
+                return Some(frame);
+            }
+            millis -= frame.image.delay;
+            last = Some(frame);
+        }
+        last
+    }
+
+    pub fn is_animated(&self) -> bool {
+        self.frames
+            .first()
+            .is_some_and(|first| self.frames(first.image.size).nth(1).is_some())
+    }
 }
 
 pub struct Cursor<B: Backend> {
     pointer: PointerHandle<App<B>>,
     pub location: Point<f64, Logical>,
     status: CursorImageStatus,
+    pub config: CursorConfig,
     theme: CursorTheme,
-    cache: HashMap<CursorIcon, XCursor>,
+    /// Nominal size resolved from the config
+    size: u32,
+    /// Loaded icons, `None` for icons missing from the theme
+    cache: HashMap<CursorIcon, Option<XCursor>>,
 }
 
 impl<B: Backend> Cursor<B> {
+    pub fn new(pointer: PointerHandle<App<B>>, config: CursorConfig) -> Self {
+        let mut cursor = Self {
+            pointer,
+            location: Point::default(),
+            status: CursorImageStatus::default_named(),
+            theme: CursorTheme::load(&config.theme()),
+            size: config.size(),
+            config,
+            cache: HashMap::new(),
+        };
+        cursor.set_icon(CursorImageStatus::default_named());
+        cursor
+    }
+
     pub fn get_pointer(&self) -> PointerHandle<App<B>> {
         self.pointer.clone()
     }
 
+    pub fn status(&self) -> &CursorImageStatus {
+        &self.status
+    }
+
+    /// Nominal size of theme cursors in logical pixels.
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    /// Reloads the theme, the shown icon is loaded again from the new one.
+    pub fn configure(&mut self, config: CursorConfig) {
+        self.theme = CursorTheme::load(&config.theme());
+        self.size = config.size();
+        self.config = config;
+        self.cache.clear();
+        self.set_icon(self.status.clone());
+    }
+
     pub fn set_icon(&mut self, status: CursorImageStatus) {
         self.status = status;
         if let CursorImageStatus::Named(icon) = &self.status {
-            let icon_path = self.theme.load_icon(icon.name()).unwrap();
-            let bytes = std::fs::read(icon_path).unwrap();
-            let image = parse_xcursor(&bytes).unwrap();
-            self.cache.insert(*icon, XCursor { inner: image });
+            let (theme, size) = (&self.theme, self.size);
+            self.cache.entry(*icon).or_insert_with(|| {
+                let cursor = XCursor::load(theme, *icon, size);
+                if cursor.is_none() {
+                    log::warn!("Cursor theme has no `{}` icon", icon.name());
+                }
+                cursor
+            });
         }
     }
 
+    /// Shows `cursor` for `icon` instead of the image of the theme.
+    pub fn set_xcursor(&mut self, icon: CursorIcon, cursor: XCursor) {
+        self.cache.insert(icon, Some(cursor));
+    }
+
+    /// Loaded theme cursor of `icon`.
+    pub fn xcursor(&self, icon: CursorIcon) -> Option<&XCursor> {
+        self.cache.get(&icon).and_then(Option::as_ref)
+    }
+
+    /// Whether the shown icon needs redraws to animate.
+    pub fn is_animated(&self) -> bool {
+        match &self.status {
+            CursorImageStatus::Named(icon) => self.xcursor(*icon).is_some_and(XCursor::is_animated),
+            _ => false,
+        }
+    }
+
+    /// Cursor elements for the output at `output_geo` with `scale`, none unless the cursor is on it.
+    ///
+    /// `time` picks the frame of animated cursors.
     pub fn render_cursor<
         R: Renderer + ImportMem + ImportMemWl + ImportDmaWl + ImportEgl,
         E: RenderElement<R>,
     >(
         &self,
         renderer: &mut R,
+        output_geo: Rectangle<i32, Logical>,
         scale: Scale,
+        time: Duration,
     ) -> Vec<TestRenderElement<R, E>>
     where
         <R as smithay::backend::renderer::RendererSuper>::TextureId: Send + Clone + 'static,
     {
-        let location = self.location;
-        //let location = self.location.to_f64().to_physical(1.0);
+        if !output_geo.to_f64().contains(self.location) {
+            return vec![];
+        }
+        // Elements are placed relative to the output
+        let location = self.location - output_geo.loc.to_f64();
         match &self.status {
             CursorImageStatus::Hidden => vec![],
             CursorImageStatus::Surface(surface) => {
@@ -101,46 +311,40 @@ impl<B: Backend> Cursor<B> {
                 .map(TestRenderElement::<R, E>::from)
                 .collect()
             }
-            CursorImageStatus::Named(cursor_icon) => {
-                let cursor = self.cache.get(cursor_icon).unwrap();
-                let image = cursor.inner.first().unwrap();
+            CursorImageStatus::Named(icon) => {
+                // Scaled outputs get the larger images of the theme
+                let size = (f64::from(self.size) * scale.fractional_scale()).ceil() as u32;
+                let Some(frame) = self
+                    .xcursor(*icon)
+                    .and_then(|cursor| cursor.frame(size, time))
+                else {
+                    return vec![];
+                };
 
-                let buffer = MemoryRenderBuffer::from_slice(
-                    &image.pixels_rgba,
-                    Fourcc::Argb8888,
-                    (image.width as i32, image.height as i32),
-                    scale.integer_scale(),
-                    Transform::Normal,
-                    None,
-                );
-
-                let hotspot = Point::<i32, Logical>::new(image.xhot as i32, image.yhot as i32);
-                //let location = (location - hotspot.to_f64()).to_physical(scale.fractional_scale());
-
-                // 1. Сначала переводим логическую позицию в физическую f64
-                let location_physical = location.to_f64().to_physical(scale.fractional_scale());
-
-                // 2. Переводим hotspot в физический f64
-                let hotspot_physical = hotspot.to_f64().to_physical(scale.fractional_scale());
-
-                // 3. Вычитаем в физических координатах
-                let final_pos_f64 = location_physical - hotspot_physical;
-
-                // 4. Округляем РЕЗУЛЬТАТ до целых пикселей (важно: .to_i32_round()!)
-                let final_pos = final_pos_f64.to_i32_round();
+                let hotspot = Point::<f64, Logical>::from((
+                    f64::from(frame.image.xhot) / f64::from(frame.scale),
+                    f64::from(frame.image.yhot) / f64::from(frame.scale),
+                ));
+                // Rounded after subtracting the hotspot to stay on whole pixels
+                let location = (location - hotspot)
+                    .to_physical(scale.fractional_scale())
+                    .to_i32_round::<i32>();
 
-                let texture = MemoryRenderBufferRenderElement::from_buffer(
+                match MemoryRenderBufferRenderElement::from_buffer(
                     renderer,
-                    final_pos,
-                    &buffer,
+                    location.to_f64(),
+                    &frame.buffer,
                     None,
                     None,
                     None,
                     Kind::Cursor,
-                )
-                .unwrap();
-
-                vec![TestRenderElement::from(texture)]
+                ) {
+                    Ok(element) => vec![TestRenderElement::from(element)],
+                    Err(err) => {
+                        log::error!("Unable to upload the cursor: {err:?}");
+                        vec![]
+                    }
+                }
             }
         }
     }
@@ -149,26 +353,47 @@ impl<B: Backend> Cursor<B> {
 pub struct InputState<B: Backend> {
     pub keyboard: KeyboardHandle<App<B>>,
     pub cursor: Cursor<B>,
+    pub touch: TouchHandle<App<B>>,
+    /// Settings the keymap of `keyboard` was compiled from.
+    pub keyboard_config: KeyboardConfig,
+    pub input_config: InputConfig,
+    /// Devices seen in `DeviceAdded` events by their id.
+    pub devices: HashMap<String, InputDevice>,
 }
 
 impl<B: Backend> InputState<B> {
-    pub fn new(seat: &mut Seat<App<B>>) -> Self {
+    pub fn new(seat: &mut Seat<App<B>>, config: &Config) -> Self {
+        let mut keyboard_config = config.keyboard.clone();
         // Добавляем клавиатуру с частоток повтора и задержкой в миллисекундах.
         // Повтор - время повтора, задержка - как должно нужно ждать перез следующим повтором
-        let keyboard = seat.add_keyboard(XkbConfig::default(), 200, 25).unwrap();
+        let keyboard = seat
+            .add_keyboard(
+                keyboard_config.xkb_config(),
+                keyboard_config.repeat_delay,
+                keyboard_config.repeat_rate,
+            )
+            .unwrap_or_else(|err| {
+                log::error!("Unable to use the keyboard config, using the defaults: {err}");
+                keyboard_config = KeyboardConfig::default();
+                seat.add_keyboard(
+                    keyboard_config.xkb_config(),
+                    keyboard_config.repeat_delay,
+                    keyboard_config.repeat_rate,
+                )
+                .unwrap()
+            });
         let pointer = seat.add_pointer();
+        let touch = seat.add_touch();
 
-        let theme = CursorTheme::load("Adwaita");
-        let mut cursor = Cursor {
-            pointer,
-            location: Point::default(),
-            status: CursorImageStatus::default_named(),
-            theme,
-            cache: HashMap::default(),
-        };
-
-        cursor.set_icon(CursorImageStatus::default_named());
+        let cursor = Cursor::new(pointer, config.cursor.clone());
 
-        Self { keyboard, cursor }
+        Self {
+            keyboard,
+            cursor,
+            touch,
+            keyboard_config,
+            input_config: config.input.clone(),
+            devices: HashMap::new(),
+        }
     }
 }
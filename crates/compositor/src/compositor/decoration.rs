@@ -0,0 +1,727 @@
+use std::{cell::RefCell, sync::Arc};
+
+use smithay::{
+    backend::{
+        allocator::Fourcc,
+        renderer::{
+            ImportAll, ImportMem, Renderer,
+            element::{
+                Kind, RenderElement,
+                memory::{MemoryRenderBuffer, MemoryRenderBufferRenderElement},
+            },
+        },
+    },
+    desktop::Window,
+    input::pointer::{Focus, GrabStartData as PointerGrabStartData},
+    reexports::{
+        wayland_protocols::xdg::{
+            decoration::zv1::server::zxdg_toplevel_decoration_v1::Mode as DecorationMode,
+            shell::server::xdg_toplevel,
+        },
+        wayland_server::protocol::wl_surface::WlSurface,
+    },
+    utils::{Logical, Point, Rectangle, Scale, Serial, Size, Transform},
+    wayland::{
+        compositor::with_states,
+        shell::xdg::{ToplevelSurface, XdgToplevelSurfaceData},
+    },
+};
+
+use crate::compositor::{
+    api::{CompositorGlobals, WindowKey},
+    backend::Backend,
+    grabs::{MoveSurfaceGrab, ResizeSurfaceGrab, resize_grab::ResizeEdge},
+    layer,
+    state::App,
+    udev::{RectElement, TestRenderElement},
+};
+
+/// Appearance of server-side decorations, provided by the WM plugin.
+pub struct DecorationConfig {
+    pub title_height: i32,
+    pub border_width: i32,
+    pub active_color: [f32; 4],
+    pub inactive_color: [f32; 4],
+    pub title_color: [f32; 4],
+    pub close_color: [f32; 4],
+    pub maximize_color: [f32; 4],
+    pub minimize_color: [f32; 4],
+    pub font: Option<Arc<fontdue::Font>>,
+    pub font_size: f32,
+    pub default_mode: DecorationMode,
+    /// Bumped on every change so cached titles get re-rasterized.
+    pub generation: u64,
+}
+
+impl Default for DecorationConfig {
+    fn default() -> Self {
+        Self {
+            title_height: 24,
+            border_width: 2,
+            active_color: [0.25, 0.25, 0.3, 1.0],
+            inactive_color: [0.15, 0.15, 0.18, 1.0],
+            title_color: [0.9, 0.9, 0.9, 1.0],
+            close_color: [0.85, 0.3, 0.3, 1.0],
+            maximize_color: [0.3, 0.75, 0.35, 1.0],
+            minimize_color: [0.85, 0.7, 0.25, 1.0],
+            font: None,
+            font_size: 14.0,
+            default_mode: DecorationMode::ServerSide,
+            generation: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecorationHit {
+    Title,
+    Close,
+    Maximize,
+    Minimize,
+    Border(ResizeEdge),
+}
+
+impl DecorationConfig {
+    /// Outer frame of a window whose content occupies `content`.
+    pub fn frame(&self, content: Rectangle<i32, Logical>) -> Rectangle<i32, Logical> {
+        Rectangle::new(
+            (
+                content.loc.x - self.border_width,
+                content.loc.y - self.border_width - self.title_height,
+            )
+                .into(),
+            (
+                content.size.w + self.border_width * 2,
+                content.size.h + self.border_width * 2 + self.title_height,
+            )
+                .into(),
+        )
+    }
+
+    /// Content area left inside of `frame` once decorations are drawn around it.
+    pub fn content(&self, frame: Rectangle<i32, Logical>) -> Rectangle<i32, Logical> {
+        Rectangle::new(
+            (
+                frame.loc.x + self.border_width,
+                frame.loc.y + self.border_width + self.title_height,
+            )
+                .into(),
+            (
+                (frame.size.w - self.border_width * 2).max(1),
+                (frame.size.h - self.border_width * 2 - self.title_height).max(1),
+            )
+                .into(),
+        )
+    }
+
+    fn title_bar(&self, content: Rectangle<i32, Logical>) -> Rectangle<i32, Logical> {
+        Rectangle::new(
+            (content.loc.x, content.loc.y - self.title_height).into(),
+            (content.size.w, self.title_height).into(),
+        )
+    }
+
+    fn button_size(&self) -> i32 {
+        (self.title_height - 8).max(4)
+    }
+
+    /// Buttons from right to left: close, maximize, minimize.
+    fn buttons(
+        &self,
+        content: Rectangle<i32, Logical>,
+    ) -> [(DecorationHit, Rectangle<i32, Logical>); 3] {
+        let title_bar = self.title_bar(content);
+        let size = self.button_size();
+        let padding = (self.title_height - size) / 2;
+        let button = |index: i32| {
+            Rectangle::new(
+                (
+                    title_bar.loc.x + title_bar.size.w - (size + padding) * (index + 1),
+                    title_bar.loc.y + padding,
+                )
+                    .into(),
+                (size, size).into(),
+            )
+        };
+
+        [
+            (DecorationHit::Close, button(0)),
+            (DecorationHit::Maximize, button(1)),
+            (DecorationHit::Minimize, button(2)),
+        ]
+    }
+
+    /// Which part of the decorations of a window with the given content area is under `point`.
+    pub fn hit(
+        &self,
+        content: Rectangle<i32, Logical>,
+        point: Point<f64, Logical>,
+    ) -> Option<DecorationHit> {
+        let frame = self.frame(content).to_f64();
+        if !frame.contains(point) || content.to_f64().contains(point) {
+            return None;
+        }
+
+        if self.title_bar(content).to_f64().contains(point) {
+            let hit = self
+                .buttons(content)
+                .into_iter()
+                .find(|(_, rect)| rect.to_f64().contains(point))
+                .map_or(DecorationHit::Title, |(hit, _)| hit);
+            return Some(hit);
+        }
+
+        let border = f64::from(self.border_width);
+        let mut edges = ResizeEdge::empty();
+        if point.x < frame.loc.x + border {
+            edges |= ResizeEdge::LEFT;
+        }
+        if point.x >= frame.loc.x + frame.size.w - border {
+            edges |= ResizeEdge::RIGHT;
+        }
+        if point.y < frame.loc.y + border {
+            edges |= ResizeEdge::TOP;
+        }
+        if point.y >= frame.loc.y + frame.size.h - border {
+            edges |= ResizeEdge::BOTTOM;
+        }
+
+        Some(DecorationHit::Border(edges))
+    }
+}
+
+struct TitleCache {
+    title: String,
+    max_width: i32,
+    generation: u64,
+    buffer: Option<(MemoryRenderBuffer, Size<i32, Logical>)>,
+}
+
+/// Per-surface decoration state.
+///
+/// Stored inside of the toplevel `WlSurface`, like [`ResizeSurfaceState`](super::grabs::resize_grab).
+#[derive(Default)]
+struct DecorationState {
+    /// Mode requested by the client through xdg-decoration.
+    requested: Option<DecorationMode>,
+    /// Mode forced by the WM plugin, takes precedence over the client request.
+    forced: Option<DecorationMode>,
+    /// Content geometry to go back to when the window gets unmaximized.
+    restore: Option<Rectangle<i32, Logical>>,
+    /// Content geometry to go back to when the window leaves fullscreen.
+    restore_fullscreen: Option<Rectangle<i32, Logical>>,
+    title: Option<TitleCache>,
+}
+
+impl DecorationState {
+    fn with<F, T>(surface: &WlSurface, cb: F) -> T
+    where
+        F: FnOnce(&mut Self) -> T,
+    {
+        with_states(surface, |states| {
+            states.data_map.insert_if_missing(RefCell::<Self>::default);
+            let state = states.data_map.get::<RefCell<Self>>().unwrap();
+
+            cb(&mut state.borrow_mut())
+        })
+    }
+
+    fn mode(&self, default: DecorationMode) -> DecorationMode {
+        self.forced.or(self.requested).unwrap_or(default)
+    }
+}
+
+fn update_mode(
+    toplevel: &ToplevelSurface,
+    default: DecorationMode,
+    cb: impl FnOnce(&mut DecorationState),
+) {
+    let mode = DecorationState::with(toplevel.wl_surface(), |state| {
+        cb(state);
+        state.mode(default)
+    });
+
+    toplevel.with_pending_state(|state| {
+        state.decoration_mode = Some(mode);
+    });
+}
+
+/// Records the mode requested by the client, `None` means the client has no preference.
+pub fn request_mode(
+    toplevel: &ToplevelSurface,
+    mode: Option<DecorationMode>,
+    default: DecorationMode,
+) {
+    update_mode(toplevel, default, |state| state.requested = mode);
+}
+
+/// Overrides the mode of a window on behalf of the WM plugin, `None` removes the override.
+pub fn force_mode(
+    toplevel: &ToplevelSurface,
+    mode: Option<DecorationMode>,
+    default: DecorationMode,
+) {
+    update_mode(toplevel, default, |state| state.forced = mode);
+}
+
+/// Whether the next configure will ask the client to leave decorations to us.
+pub fn pending_server_side(toplevel: &ToplevelSurface) -> bool {
+    toplevel.with_pending_state(|state| state.decoration_mode) == Some(DecorationMode::ServerSide)
+}
+
+/// Whether the client acknowledged server-side decorations, fullscreen windows have none.
+pub fn has_server_side_decorations(window: &Window) -> bool {
+    window.toplevel().is_some_and(|toplevel| {
+        toplevel.current_state().decoration_mode == Some(DecorationMode::ServerSide)
+    }) && !is_fullscreen(window)
+}
+
+pub fn is_maximized(window: &Window) -> bool {
+    let toplevel = window.toplevel().unwrap();
+    DecorationState::with(toplevel.wl_surface(), |state| state.restore.is_some())
+}
+
+pub fn set_maximized(globals: &mut CompositorGlobals, window: &Window, maximized: bool) {
+    let toplevel = window.toplevel().unwrap();
+    if maximized == is_maximized(window) {
+        toplevel.send_pending_configure();
+        return;
+    }
+
+    let target = if maximized {
+        let Some(current) = globals.space.element_geometry(window) else {
+            return;
+        };
+        let Some(output) = globals
+            .space
+            .outputs_for_element(window)
+            .into_iter()
+            .next()
+            .or_else(|| globals.space.outputs().next().cloned())
+        else {
+            return;
+        };
+        // Panels and other exclusive zones stay visible
+        let Some(area) = layer::usable_area(&globals.space, &output) else {
+            return;
+        };
+
+        DecorationState::with(toplevel.wl_surface(), |state| state.restore = Some(current));
+        if pending_server_side(toplevel) {
+            globals.decorations.content(area)
+        } else {
+            area
+        }
+    } else {
+        let Some(restore) =
+            DecorationState::with(toplevel.wl_surface(), |state| state.restore.take())
+        else {
+            return;
+        };
+        restore
+    };
+
+    toplevel.with_pending_state(|state| {
+        if maximized {
+            state.states.set(xdg_toplevel::State::Maximized);
+        } else {
+            state.states.unset(xdg_toplevel::State::Maximized);
+        }
+        state.size = Some(target.size);
+    });
+    toplevel.send_pending_configure();
+
+    globals.space.map_element(window.clone(), target.loc, true);
+}
+
+pub fn is_fullscreen(window: &Window) -> bool {
+    let toplevel = window.toplevel().unwrap();
+    DecorationState::with(toplevel.wl_surface(), |state| {
+        state.restore_fullscreen.is_some()
+    })
+}
+
+/// Covers the whole output of the window, decorations included.
+pub fn set_fullscreen(globals: &mut CompositorGlobals, window: &Window, fullscreen: bool) {
+    let toplevel = window.toplevel().unwrap();
+    if fullscreen == is_fullscreen(window) {
+        toplevel.send_pending_configure();
+        return;
+    }
+
+    let target = if fullscreen {
+        let Some(current) = globals.space.element_geometry(window) else {
+            return;
+        };
+        let Some(output_geo) = globals
+            .space
+            .outputs_for_element(window)
+            .into_iter()
+            .next()
+            .or_else(|| globals.space.outputs().next().cloned())
+            .and_then(|output| globals.space.output_geometry(&output))
+        else {
+            return;
+        };
+
+        DecorationState::with(toplevel.wl_surface(), |state| {
+            state.restore_fullscreen = Some(current);
+        });
+        output_geo
+    } else {
+        let Some(restore) = DecorationState::with(toplevel.wl_surface(), |state| {
+            state.restore_fullscreen.take()
+        }) else {
+            return;
+        };
+        restore
+    };
+
+    toplevel.with_pending_state(|state| {
+        if fullscreen {
+            state.states.set(xdg_toplevel::State::Fullscreen);
+        } else {
+            state.states.unset(xdg_toplevel::State::Fullscreen);
+        }
+        state.size = Some(target.size);
+    });
+    toplevel.send_pending_configure();
+
+    globals.space.map_element(window.clone(), target.loc, true);
+}
+
+/// Finds the topmost window whose decorations are under `point`.
+///
+/// Returns `None` if the point hits the content of a window first.
+pub fn decoration_under(
+    globals: &CompositorGlobals,
+    point: Point<f64, Logical>,
+) -> Option<(Window, DecorationHit)> {
+    for window in globals.space.elements().rev() {
+        let Some(content) = globals.space.element_geometry(window) else {
+            continue;
+        };
+
+        if has_server_side_decorations(window)
+            && let Some(hit) = globals.decorations.hit(content, point)
+        {
+            return Some((window.clone(), hit));
+        }
+
+        if globals
+            .space
+            .element_bbox(window)
+            .is_some_and(|bbox| bbox.to_f64().contains(point))
+        {
+            return None;
+        }
+    }
+
+    None
+}
+
+fn rasterize_title(
+    font: &fontdue::Font,
+    size: f32,
+    text: &str,
+    max_width: i32,
+    color: [f32; 4],
+) -> Option<(MemoryRenderBuffer, Size<i32, Logical>)> {
+    let line = font.horizontal_line_metrics(size)?;
+    let ascent = line.ascent.ceil() as i32;
+    let height = (line.ascent - line.descent).ceil() as i32;
+
+    let mut glyphs = Vec::new();
+    let mut pen = 0.0f32;
+    for character in text.chars() {
+        let (metrics, coverage) = font.rasterize(character, size);
+        if (pen + metrics.advance_width).ceil() as i32 > max_width {
+            break;
+        }
+        glyphs.push((pen.round() as i32 + metrics.xmin, metrics, coverage));
+        pen += metrics.advance_width;
+    }
+
+    let width = pen.ceil() as i32;
+    if width <= 0 || height <= 0 {
+        return None;
+    }
+
+    // Argb8888 is stored as little-endian BGRA with premultiplied alpha
+    let mut pixels = vec![0u8; (width * height * 4) as usize];
+    for (x, metrics, coverage) in glyphs {
+        let top = ascent - (metrics.ymin + metrics.height as i32);
+        for glyph_y in 0..metrics.height {
+            for glyph_x in 0..metrics.width {
+                let px = x + glyph_x as i32;
+                let py = top + glyph_y as i32;
+                if px < 0 || py < 0 || px >= width || py >= height {
+                    continue;
+                }
+
+                let alpha =
+                    f32::from(coverage[glyph_y * metrics.width + glyph_x]) / 255.0 * color[3];
+                let index = ((py * width + px) * 4) as usize;
+                if alpha * 255.0 <= f32::from(pixels[index + 3]) {
+                    continue;
+                }
+
+                pixels[index] = (color[2] * alpha * 255.0) as u8;
+                pixels[index + 1] = (color[1] * alpha * 255.0) as u8;
+                pixels[index + 2] = (color[0] * alpha * 255.0) as u8;
+                pixels[index + 3] = (alpha * 255.0) as u8;
+            }
+        }
+    }
+
+    let buffer = MemoryRenderBuffer::from_slice(
+        &pixels,
+        Fourcc::Argb8888,
+        (width, height),
+        1,
+        Transform::Normal,
+        None,
+    );
+
+    Some((buffer, (width, height).into()))
+}
+
+fn title_buffer(
+    config: &DecorationConfig,
+    toplevel: &ToplevelSurface,
+    max_width: i32,
+) -> Option<(MemoryRenderBuffer, Size<i32, Logical>)> {
+    let font = config.font.as_ref()?;
+    let title = with_states(toplevel.wl_surface(), |states| {
+        states
+            .data_map
+            .get::<XdgToplevelSurfaceData>()
+            .unwrap()
+            .lock()
+            .unwrap()
+            .title
+            .clone()
+    })
+    .unwrap_or_default();
+
+    DecorationState::with(toplevel.wl_surface(), |state| {
+        let outdated = state.title.as_ref().is_none_or(|cache| {
+            cache.title != title
+                || cache.max_width != max_width
+                || cache.generation != config.generation
+        });
+
+        if outdated {
+            let buffer = rasterize_title(
+                font,
+                config.font_size,
+                &title,
+                max_width,
+                config.title_color,
+            );
+            state.title = Some(TitleCache {
+                title,
+                max_width,
+                generation: config.generation,
+                buffer,
+            });
+        }
+
+        state.title.as_ref().and_then(|cache| cache.buffer.clone())
+    })
+}
+
+/// Builds decoration elements of a window, `content` is relative to the output.
+pub fn render_decorations<R, E>(
+    renderer: &mut R,
+    config: &DecorationConfig,
+    window: &Window,
+    content: Rectangle<i32, Logical>,
+    scale: Scale<f64>,
+    alpha: f32,
+) -> Vec<TestRenderElement<R, E>>
+where
+    R: Renderer + ImportAll + ImportMem,
+    R::TextureId: Send + Clone + 'static,
+    E: RenderElement<R>,
+{
+    let toplevel = window.toplevel().unwrap();
+    let activated = toplevel
+        .current_state()
+        .states
+        .contains(xdg_toplevel::State::Activated);
+    let frame_color = if activated {
+        config.active_color
+    } else {
+        config.inactive_color
+    };
+
+    let rect = |rect: Rectangle<i32, Logical>, color: [f32; 4]| {
+        let rect = rect.to_physical_precise_round(scale);
+        TestRenderElement::from(RectElement::new(
+            (rect.loc.x, rect.loc.y),
+            (rect.size.w, rect.size.h),
+            color.map(|component| component * alpha),
+        ))
+    };
+
+    let mut elements = Vec::new();
+
+    let buttons = config.buttons(content);
+    let title_bar = config.title_bar(content);
+    let buttons_width = title_bar.loc.x + title_bar.size.w - buttons[2].1.loc.x;
+    let padding = (config.title_height - config.button_size()) / 2;
+    let max_width = title_bar.size.w - buttons_width - padding * 2;
+    if max_width > 0
+        && let Some((buffer, size)) = title_buffer(config, toplevel, max_width)
+    {
+        let location = Point::<i32, Logical>::from((
+            title_bar.loc.x + padding,
+            title_bar.loc.y + (config.title_height - size.h) / 2,
+        ));
+
+        match MemoryRenderBufferRenderElement::from_buffer(
+            renderer,
+            location.to_f64().to_physical(scale),
+            &buffer,
+            Some(alpha),
+            None,
+            None,
+            Kind::Unspecified,
+        ) {
+            Ok(element) => elements.push(TestRenderElement::from(element)),
+            Err(err) => log::error!("Failed to import window title: {err:?}"),
+        }
+    }
+
+    for (hit, button) in buttons {
+        let color = match hit {
+            DecorationHit::Close => config.close_color,
+            DecorationHit::Maximize => config.maximize_color,
+            _ => config.minimize_color,
+        };
+        elements.push(rect(button, color));
+    }
+
+    let frame = config.frame(content);
+    let border = config.border_width;
+    elements.extend([
+        rect(title_bar, frame_color),
+        // Top
+        rect(
+            Rectangle::new(frame.loc, (frame.size.w, border).into()),
+            frame_color,
+        ),
+        // Bottom
+        rect(
+            Rectangle::new(
+                (frame.loc.x, frame.loc.y + frame.size.h - border).into(),
+                (frame.size.w, border).into(),
+            ),
+            frame_color,
+        ),
+        // Left
+        rect(
+            Rectangle::new(
+                (frame.loc.x, frame.loc.y + border).into(),
+                (border, frame.size.h - border * 2).into(),
+            ),
+            frame_color,
+        ),
+        // Right
+        rect(
+            Rectangle::new(
+                (frame.loc.x + frame.size.w - border, frame.loc.y + border).into(),
+                (border, frame.size.h - border * 2).into(),
+            ),
+            frame_color,
+        ),
+    ]);
+
+    elements
+}
+
+impl<B: Backend> App<B> {
+    /// Reacts to a button press on the decorations of `window`.
+    pub fn handle_decoration_click(
+        &mut self,
+        globals: &mut CompositorGlobals,
+        window: &Window,
+        hit: DecorationHit,
+        button: u32,
+        serial: Serial,
+    ) {
+        // The button is a button code as defined in the
+        // Linux kernel's linux/input-event-codes.h header file, e.g. BTN_LEFT.
+        const BTN_LEFT: u32 = 0x110;
+
+        if button != BTN_LEFT {
+            return;
+        }
+
+        let pointer = self.seat.get_pointer().unwrap();
+        let start_data = PointerGrabStartData {
+            focus: None,
+            button,
+            location: self.input_state.cursor.location,
+        };
+        let toplevel = window.toplevel().unwrap();
+
+        match hit {
+            DecorationHit::Close => toplevel.send_close(),
+            DecorationHit::Maximize => {
+                let maximized = is_maximized(window);
+                set_maximized(globals, window, !maximized);
+            }
+            DecorationHit::Minimize => self.minimize_window(globals, window, serial),
+            DecorationHit::Title => {
+                let Some(initial_window_location) = globals.space.element_location(window) else {
+                    return;
+                };
+                let grab = MoveSurfaceGrab {
+                    start_data,
+                    window: window.clone(),
+                    initial_window_location,
+                };
+                pointer.set_grab(self, grab, serial, Focus::Clear);
+            }
+            DecorationHit::Border(edges) => {
+                let Some(initial_rect) = globals.space.element_geometry(window) else {
+                    return;
+                };
+
+                toplevel.with_pending_state(|state| {
+                    state.states.set(xdg_toplevel::State::Resizing);
+                });
+                toplevel.send_pending_configure();
+
+                let grab =
+                    ResizeSurfaceGrab::start(start_data, window.clone(), edges, initial_rect);
+                pointer.set_grab(self, grab, serial, Focus::Clear);
+            }
+        }
+    }
+
+    /// Minimizes `window` and takes the keyboard focus away from it.
+    pub fn minimize_window(
+        &mut self,
+        globals: &mut CompositorGlobals,
+        window: &Window,
+        serial: Serial,
+    ) {
+        let Some(key) = window.user_data().get::<WindowKey>().copied() else {
+            return;
+        };
+        if !globals.minimize_window(key) {
+            return;
+        }
+
+        let keyboard = self.seat.get_keyboard().unwrap();
+        let toplevel = window.toplevel().unwrap();
+        if keyboard.current_focus().as_ref() == Some(toplevel.wl_surface()) {
+            window.set_activated(false);
+            toplevel.send_pending_configure();
+            keyboard.set_focus(self, Option::<WlSurface>::None, serial);
+        }
+    }
+}
@@ -0,0 +1,616 @@
+use std::cell::RefCell;
+
+use slotmap::SecondaryMap;
+use smithay::{
+    backend::renderer::{
+        element::{
+            Element, Id, Kind, RenderElement, UnderlyingStorage,
+            surface::WaylandSurfaceRenderElement,
+        },
+        gles::{
+            GlesError, GlesFrame, GlesPixelProgram, GlesRenderer, GlesTexProgram, Uniform,
+            UniformName, UniformType, UniformValue, element::PixelShaderElement,
+        },
+        utils::{CommitCounter, DamageSet, OpaqueRegions},
+    },
+    desktop::Window,
+    reexports::wayland_protocols::xdg::shell::server::xdg_toplevel,
+    utils::{Buffer, Logical, Physical, Point, Rectangle, Scale, Size, Transform},
+};
+
+use crate::compositor::api::WindowKey;
+
+const ROUNDED_BOX: &str = r"
+float rounded_box(vec2 p, vec2 half_size, float radius) {
+    vec2 q = abs(p) - half_size + radius;
+    return length(max(q, 0.0)) + min(max(q.x, q.y), 0.0) - radius;
+}
+";
+
+const BORDER_SHADER: &str = r"
+precision mediump float;
+
+uniform float alpha;
+uniform vec2 size;
+varying vec2 v_coords;
+
+#if defined(DEBUG_FLAGS)
+uniform float tint;
+#endif
+
+uniform vec4 color;
+uniform float border_width;
+uniform float radius;
+
+//_ROUNDED_BOX_
+
+void main() {
+    vec2 p = v_coords * size - size / 2.0;
+    float outer = rounded_box(p, size / 2.0, radius);
+    float inner = rounded_box(p, size / 2.0 - border_width, max(radius - border_width, 0.0));
+    float coverage = clamp(0.5 - outer, 0.0, 1.0) * clamp(0.5 + inner, 0.0, 1.0);
+    vec4 result = color * coverage * alpha;
+
+#if defined(DEBUG_FLAGS)
+    if (tint == 1.0)
+        result = vec4(0.0, 0.2, 0.0, 0.2) + result * 0.8;
+#endif
+
+    gl_FragColor = result;
+}
+";
+
+const SHADOW_SHADER: &str = r"
+precision mediump float;
+
+uniform float alpha;
+uniform vec2 size;
+varying vec2 v_coords;
+
+#if defined(DEBUG_FLAGS)
+uniform float tint;
+#endif
+
+uniform vec4 color;
+uniform vec2 box_size;
+uniform vec2 window_offset;
+uniform float blur;
+uniform float radius;
+
+//_ROUNDED_BOX_
+
+void main() {
+    vec2 p = v_coords * size - size / 2.0;
+    float distance = rounded_box(p, box_size / 2.0, radius);
+    float shadow = 1.0 - smoothstep(-blur, blur, distance);
+
+    // The shadow is not visible through translucent windows
+    float window = rounded_box(p - window_offset, box_size / 2.0, radius);
+    shadow *= clamp(0.5 + window, 0.0, 1.0);
+    vec4 result = color * shadow * alpha;
+
+#if defined(DEBUG_FLAGS)
+    if (tint == 1.0)
+        result = vec4(0.0, 0.2, 0.0, 0.2) + result * 0.8;
+#endif
+
+    gl_FragColor = result;
+}
+";
+
+const CLIP_SHADER: &str = r"#version 100
+
+//_DEFINES_
+
+#if defined(EXTERNAL)
+#extension GL_OES_EGL_image_external : require
+#endif
+
+#ifdef GL_FRAGMENT_PRECISION_HIGH
+precision highp float;
+#else
+precision mediump float;
+#endif
+
+#if defined(EXTERNAL)
+uniform samplerExternalOES tex;
+#else
+uniform sampler2D tex;
+#endif
+
+uniform float alpha;
+varying vec2 v_coords;
+
+#if defined(DEBUG_FLAGS)
+uniform float tint;
+#endif
+
+uniform mat3 frag_to_geo;
+uniform vec2 geo_size;
+uniform float radius;
+
+//_ROUNDED_BOX_
+
+void main() {
+    vec4 color = texture2D(tex, v_coords);
+
+#if defined(NO_ALPHA)
+    color = vec4(color.rgb, 1.0) * alpha;
+#else
+    color = color * alpha;
+#endif
+
+    vec2 p = (frag_to_geo * vec3(gl_FragCoord.xy, 1.0)).xy;
+    color = color * clamp(0.5 - rounded_box(p - geo_size / 2.0, geo_size / 2.0, radius), 0.0, 1.0);
+
+#if defined(DEBUG_FLAGS)
+    if (tint == 1.0)
+        color = vec4(0.0, 0.2, 0.0, 0.2) + color * 0.8;
+#endif
+
+    gl_FragColor = color;
+}
+";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Shadow {
+    pub color: [f32; 4],
+    pub blur_radius: i32,
+    pub offset: Point<i32, Logical>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WindowStyle {
+    pub opacity: f32,
+    pub border_width: i32,
+    pub border_color: [f32; 4],
+    /// Ignored for windows with server-side decorations.
+    pub corner_radius: i32,
+    pub shadow: Option<Shadow>,
+}
+
+impl Default for WindowStyle {
+    fn default() -> Self {
+        Self {
+            opacity: 1.0,
+            border_width: 0,
+            border_color: [0.0; 4],
+            corner_radius: 0,
+            shadow: None,
+        }
+    }
+}
+
+/// Styles of windows without their own one, picked every frame.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct StyleRules {
+    pub focused: WindowStyle,
+    pub unfocused: WindowStyle,
+}
+
+#[derive(Default)]
+pub struct WindowStyles {
+    pub rules: StyleRules,
+    overrides: SecondaryMap<WindowKey, WindowStyle>,
+}
+
+impl WindowStyles {
+    pub fn set_window_style(&mut self, window: WindowKey, style: Option<WindowStyle>) {
+        match style {
+            Some(style) => {
+                self.overrides.insert(window, style);
+            }
+            None => {
+                self.overrides.remove(window);
+            }
+        }
+    }
+
+    pub fn remove_window(&mut self, window: WindowKey) {
+        self.overrides.remove(window);
+    }
+
+    pub fn style(&self, key: WindowKey, window: &Window) -> WindowStyle {
+        if let Some(style) = self.overrides.get(key) {
+            return *style;
+        }
+
+        let focused = window.toplevel().is_some_and(|toplevel| {
+            toplevel
+                .current_state()
+                .states
+                .contains(xdg_toplevel::State::Activated)
+        });
+
+        if focused {
+            self.rules.focused
+        } else {
+            self.rules.unfocused
+        }
+    }
+}
+
+#[derive(Clone)]
+struct Shaders {
+    border: GlesPixelProgram,
+    shadow: GlesPixelProgram,
+    clip: GlesTexProgram,
+}
+
+impl Shaders {
+    /// Shaders are compiled once per renderer, `None` if that failed.
+    fn get(renderer: &mut GlesRenderer) -> Option<Self> {
+        if let Some(shaders) = renderer.egl_context().user_data().get::<Option<Shaders>>() {
+            return shaders.clone();
+        }
+
+        let shaders = Self::compile(renderer)
+            .map_err(|err| log::error!("Failed to compile window effect shaders: {err:?}"))
+            .ok();
+        renderer
+            .egl_context()
+            .user_data()
+            .insert_if_missing(|| shaders.clone());
+        shaders
+    }
+
+    fn compile(renderer: &mut GlesRenderer) -> Result<Self, GlesError> {
+        let border = renderer.compile_custom_pixel_shader(
+            BORDER_SHADER.replace("//_ROUNDED_BOX_", ROUNDED_BOX),
+            &[
+                UniformName::new("color", UniformType::_4f),
+                UniformName::new("border_width", UniformType::_1f),
+                UniformName::new("radius", UniformType::_1f),
+            ],
+        )?;
+        let shadow = renderer.compile_custom_pixel_shader(
+            SHADOW_SHADER.replace("//_ROUNDED_BOX_", ROUNDED_BOX),
+            &[
+                UniformName::new("color", UniformType::_4f),
+                UniformName::new("box_size", UniformType::_2f),
+                UniformName::new("window_offset", UniformType::_2f),
+                UniformName::new("blur", UniformType::_1f),
+                UniformName::new("radius", UniformType::_1f),
+            ],
+        )?;
+        let clip = renderer.compile_custom_texture_shader(
+            CLIP_SHADER.replace("//_ROUNDED_BOX_", ROUNDED_BOX),
+            &[
+                UniformName::new("frag_to_geo", UniformType::Matrix3x3),
+                UniformName::new("geo_size", UniformType::_2f),
+                UniformName::new("radius", UniformType::_1f),
+            ],
+        )?;
+
+        Ok(Self {
+            border,
+            shadow,
+            clip,
+        })
+    }
+}
+
+/// Shader used by [`ClippedSurfaceElement`], `None` if effects are unavailable.
+pub fn clip_program(renderer: &mut GlesRenderer) -> Option<GlesTexProgram> {
+    Shaders::get(renderer).map(|shaders| shaders.clip)
+}
+
+#[derive(PartialEq)]
+struct BorderParams {
+    color: [f32; 4],
+    width: f32,
+    radius: f32,
+}
+
+#[derive(PartialEq)]
+struct ShadowParams {
+    color: [f32; 4],
+    box_size: (f32, f32),
+    window_offset: (f32, f32),
+    blur: f32,
+    radius: f32,
+}
+
+struct CachedElement<P> {
+    element: PixelShaderElement,
+    params: P,
+}
+
+/// Shader elements are kept between frames, so they only get damaged when they change.
+#[derive(Default)]
+struct EffectElements {
+    border: Option<CachedElement<BorderParams>>,
+    shadow: Option<CachedElement<ShadowParams>>,
+}
+
+fn cached_element<P: PartialEq>(
+    slot: &mut Option<CachedElement<P>>,
+    program: &GlesPixelProgram,
+    area: Rectangle<i32, Logical>,
+    params: P,
+    uniforms: impl Fn(&P) -> Vec<Uniform<'static>>,
+) -> PixelShaderElement {
+    match slot {
+        Some(cached) => {
+            cached.element.resize(area, None);
+            if cached.params != params {
+                cached.element.update_uniforms(uniforms(&params));
+                cached.params = params;
+            }
+        }
+        None => {
+            let element = PixelShaderElement::new(
+                program.clone(),
+                area,
+                None,
+                1.0,
+                uniforms(&params),
+                Kind::Unspecified,
+            );
+            *slot = Some(CachedElement { element, params });
+        }
+    }
+
+    slot.as_ref().unwrap().element.clone()
+}
+
+/// Border and shadow of a window around `frame`, which is relative to the output.
+///
+/// Alpha is part of the uniforms, as shader elements can't change it after creation.
+pub fn effect_elements(
+    renderer: &mut GlesRenderer,
+    window: &Window,
+    style: &WindowStyle,
+    frame: Rectangle<i32, Logical>,
+    radius: i32,
+    alpha: f32,
+) -> Vec<PixelShaderElement> {
+    let Some(shaders) = Shaders::get(renderer) else {
+        return Vec::new();
+    };
+
+    let state = window
+        .user_data()
+        .get_or_insert(|| RefCell::new(EffectElements::default()));
+    let mut state = state.borrow_mut();
+
+    let mut elements = Vec::new();
+    if style.border_width > 0 {
+        let width = style.border_width;
+        let area = Rectangle::new(
+            frame.loc - Point::from((width, width)),
+            frame.size + Size::from((width * 2, width * 2)),
+        );
+        let params = BorderParams {
+            color: style.border_color.map(|component| component * alpha),
+            width: width as f32,
+            radius: if radius > 0 {
+                (radius + width) as f32
+            } else {
+                0.0
+            },
+        };
+        elements.push(cached_element(
+            &mut state.border,
+            &shaders.border,
+            area,
+            params,
+            |params| {
+                vec![
+                    Uniform::new("color", params.color),
+                    Uniform::new("border_width", params.width),
+                    Uniform::new("radius", params.radius),
+                ]
+            },
+        ));
+    } else {
+        state.border = None;
+    }
+
+    if let Some(shadow) = style.shadow {
+        let outer = if style.border_width > 0 {
+            let width = style.border_width;
+            Rectangle::new(
+                frame.loc - Point::from((width, width)),
+                frame.size + Size::from((width * 2, width * 2)),
+            )
+        } else {
+            frame
+        };
+        let blur = shadow.blur_radius.max(1);
+        let area = Rectangle::new(
+            outer.loc + shadow.offset - Point::from((blur, blur)),
+            outer.size + Size::from((blur * 2, blur * 2)),
+        );
+        let params = ShadowParams {
+            color: shadow.color.map(|component| component * alpha),
+            box_size: (outer.size.w as f32, outer.size.h as f32),
+            window_offset: (-shadow.offset.x as f32, -shadow.offset.y as f32),
+            blur: blur as f32,
+            radius: if radius > 0 {
+                (radius + style.border_width) as f32
+            } else {
+                0.0
+            },
+        };
+        elements.push(cached_element(
+            &mut state.shadow,
+            &shaders.shadow,
+            area,
+            params,
+            |params| {
+                vec![
+                    Uniform::new("color", params.color),
+                    Uniform::new("box_size", params.box_size),
+                    Uniform::new("window_offset", params.window_offset),
+                    Uniform::new("blur", params.blur),
+                    Uniform::new("radius", params.radius),
+                ]
+            },
+        ));
+    } else {
+        state.shadow = None;
+    }
+
+    elements
+}
+
+/// Wayland surface drawn with its corners outside of the window geometry cut off.
+pub struct ClippedSurfaceElement {
+    inner: WaylandSurfaceRenderElement<GlesRenderer>,
+    program: GlesTexProgram,
+    /// Window geometry relative to the output.
+    geometry: Rectangle<i32, Physical>,
+    radius: f32,
+    /// Size of the framebuffer, needed to map `gl_FragCoord` back to the output.
+    viewport: Size<i32, Physical>,
+}
+
+impl ClippedSurfaceElement {
+    pub fn new(
+        inner: WaylandSurfaceRenderElement<GlesRenderer>,
+        program: GlesTexProgram,
+        geometry: Rectangle<i32, Physical>,
+        radius: f32,
+        viewport: Size<i32, Physical>,
+    ) -> Self {
+        Self {
+            inner,
+            program,
+            geometry,
+            radius,
+            viewport,
+        }
+    }
+
+    fn frag_to_geo(&self, projection: &[f32; 9]) -> [f32; 9] {
+        // gl_FragCoord -> normalized device coordinates
+        let ndc = [
+            [2.0 / self.viewport.w as f32, 0.0, -1.0],
+            [0.0, 2.0 / self.viewport.h as f32, -1.0],
+            [0.0, 0.0, 1.0],
+        ];
+        // Normalized device coordinates -> output, the projection is column-major
+        let projection =
+            std::array::from_fn(|row| std::array::from_fn(|column| projection[column * 3 + row]));
+        // Output -> window geometry
+        let geometry = [
+            [1.0, 0.0, -self.geometry.loc.x as f32],
+            [0.0, 1.0, -self.geometry.loc.y as f32],
+            [0.0, 0.0, 1.0],
+        ];
+
+        let matrix = multiply(&multiply(&geometry, &invert(&projection)), &ndc);
+        std::array::from_fn(|index| matrix[index % 3][index / 3])
+    }
+}
+
+fn multiply(a: &[[f32; 3]; 3], b: &[[f32; 3]; 3]) -> [[f32; 3]; 3] {
+    std::array::from_fn(|row| {
+        std::array::from_fn(|column| (0..3).map(|k| a[row][k] * b[k][column]).sum())
+    })
+}
+
+fn invert(m: &[[f32; 3]; 3]) -> [[f32; 3]; 3] {
+    let cofactor = |row: usize, column: usize| {
+        let (r0, r1) = ((row + 1) % 3, (row + 2) % 3);
+        let (c0, c1) = ((column + 1) % 3, (column + 2) % 3);
+        m[r0][c0] * m[r1][c1] - m[r0][c1] * m[r1][c0]
+    };
+    let determinant = (0..3)
+        .map(|column| m[0][column] * cofactor(0, column))
+        .sum::<f32>();
+
+    // The inverse is the transposed cofactor matrix divided by the determinant
+    std::array::from_fn(|row| std::array::from_fn(|column| cofactor(column, row) / determinant))
+}
+
+impl Element for ClippedSurfaceElement {
+    fn id(&self) -> &Id {
+        self.inner.id()
+    }
+
+    fn current_commit(&self) -> CommitCounter {
+        self.inner.current_commit()
+    }
+
+    fn location(&self, scale: Scale<f64>) -> Point<i32, Physical> {
+        self.inner.location(scale)
+    }
+
+    fn src(&self) -> Rectangle<f64, Buffer> {
+        self.inner.src()
+    }
+
+    fn transform(&self) -> Transform {
+        self.inner.transform()
+    }
+
+    fn geometry(&self, scale: Scale<f64>) -> Rectangle<i32, Physical> {
+        self.inner.geometry(scale)
+    }
+
+    fn damage_since(
+        &self,
+        scale: Scale<f64>,
+        commit: Option<CommitCounter>,
+    ) -> DamageSet<i32, Physical> {
+        self.inner.damage_since(scale, commit)
+    }
+
+    fn opaque_regions(&self, _scale: Scale<f64>) -> OpaqueRegions<i32, Physical> {
+        // The corners are transparent now
+        OpaqueRegions::default()
+    }
+
+    fn alpha(&self) -> f32 {
+        self.inner.alpha()
+    }
+
+    fn kind(&self) -> Kind {
+        self.inner.kind()
+    }
+}
+
+impl RenderElement<GlesRenderer> for ClippedSurfaceElement {
+    fn draw(
+        &self,
+        frame: &mut GlesFrame<'_, '_>,
+        src: Rectangle<f64, Buffer>,
+        dst: Rectangle<i32, Physical>,
+        damage: &[Rectangle<i32, Physical>],
+        _opaque_regions: &[Rectangle<i32, Physical>],
+    ) -> Result<(), GlesError> {
+        let uniforms = vec![
+            Uniform::new(
+                "frag_to_geo",
+                UniformValue::Matrix3x3 {
+                    matrices: vec![self.frag_to_geo(frame.projection())],
+                    transpose: false,
+                },
+            ),
+            Uniform::new(
+                "geo_size",
+                (self.geometry.size.w as f32, self.geometry.size.h as f32),
+            ),
+            Uniform::new("radius", self.radius),
+        ];
+
+        frame.override_default_tex_program(self.program.clone(), uniforms);
+        let result = self.inner.draw(frame, src, dst, damage, &[]);
+        frame.clear_tex_program_override();
+        result
+    }
+
+    fn underlying_storage(&self, _renderer: &mut GlesRenderer) -> Option<UnderlyingStorage<'_>> {
+        // Direct scanout would skip the clipping
+        None
+    }
+}
+
+smithay::backend::renderer::element::render_elements! {
+    pub WindowRenderElement<=GlesRenderer>;
+    Surface = WaylandSurfaceRenderElement<GlesRenderer>,
+    Clipped = ClippedSurfaceElement,
+    Shader = PixelShaderElement,
+}
@@ -0,0 +1,377 @@
+use serde::Deserialize;
+use smithay::{
+    input::keyboard::FilterResult,
+    utils::{Logical, Point},
+};
+
+use crate::compositor::{
+    api::general::Compositor,
+    backend::Backend,
+    keybinding::{BuiltinAction, KeyAction, KeybindingError},
+    state::App,
+};
+
+/// Swipe distance in logical pixels for a progress of 1.
+pub const SWIPE_DISTANCE: f64 = 300.0;
+/// Change of the pinch scale for a progress of 1.
+pub const PINCH_DISTANCE: f64 = 0.5;
+/// Swipe distance before the direction is decided.
+const SWIPE_THRESHOLD: f64 = 16.0;
+/// Change of the pinch scale before the direction is decided.
+const PINCH_THRESHOLD: f64 = 0.05;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum GestureKind {
+    Swipe,
+    Pinch,
+    Hold,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum GestureDirection {
+    Left,
+    Right,
+    Up,
+    Down,
+    In,
+    Out,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GesturePhase {
+    Begin,
+    Update,
+    End,
+    Cancel,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum GestureError {
+    #[error(
+        "{0} needs a direction that fits: left, right, up or down for swipes, \
+         in or out for pinches and none for holds"
+    )]
+    Direction(String),
+    #[error("{0} needs at least one finger")]
+    Fingers(String),
+    #[error(transparent)]
+    Action(#[from] KeybindingError),
+}
+
+/// Entry of the `[[gesture]]` array in the config.
+///
+/// Plugin bindings get the progress of the gesture to animate with it,
+/// other actions run when it ends after the full distance.
+///
+/// ```toml
+/// [[gesture]]
+/// type = "swipe"       # swipe, pinch or hold
+/// fingers = 3
+/// direction = "left"   # left, right, up or down for swipes, in or out for pinches
+/// plugin = "workspace-next"
+///
+/// [[gesture]]
+/// type = "hold"
+/// fingers = 4
+/// spawn = "rofi -show drun"
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GestureConfig {
+    #[serde(rename = "type")]
+    kind: GestureKind,
+    fingers: u32,
+    direction: Option<GestureDirection>,
+    spawn: Option<String>,
+    action: Option<BuiltinAction>,
+    mode: Option<String>,
+    plugin: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GestureBinding {
+    pub kind: GestureKind,
+    pub fingers: u32,
+    pub direction: Option<GestureDirection>,
+    pub action: KeyAction,
+}
+
+impl GestureBinding {
+    fn parse(config: &GestureConfig) -> Result<Self, GestureError> {
+        let name = format!("{} finger {:?}", config.fingers, config.kind);
+        let action = KeyAction::from_fields(
+            &name,
+            config.spawn.as_deref(),
+            config.action,
+            config.mode.as_deref(),
+            config.plugin.as_deref(),
+        )?;
+
+        use GestureDirection::*;
+        let fits = match config.kind {
+            GestureKind::Swipe => matches!(config.direction, Some(Left | Right | Up | Down)),
+            GestureKind::Pinch => matches!(config.direction, Some(In | Out)),
+            GestureKind::Hold => config.direction.is_none(),
+        };
+        if !fits {
+            return Err(GestureError::Direction(name));
+        }
+        if config.fingers == 0 {
+            return Err(GestureError::Fingers(name));
+        }
+
+        Ok(Self {
+            kind: config.kind,
+            fingers: config.fingers,
+            direction: config.direction,
+            action,
+        })
+    }
+}
+
+/// Change of a bound gesture for its action.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GestureUpdate {
+    pub action: KeyAction,
+    pub phase: GesturePhase,
+    /// 0 at the start of the gesture and 1 after the full distance
+    pub progress: f64,
+}
+
+/// Gesture taken from the clients because something could be bound to it.
+#[derive(Debug)]
+struct ActiveGesture {
+    kind: GestureKind,
+    fingers: u32,
+    delta: Point<f64, Logical>,
+    scale: f64,
+    /// Set once the direction is decided, `None` inside when nothing is bound to it
+    binding: Option<Option<GestureBinding>>,
+    progress: f64,
+}
+
+impl ActiveGesture {
+    fn direction(&self) -> Option<GestureDirection> {
+        match self.kind {
+            GestureKind::Swipe => {
+                let (x, y) = (self.delta.x, self.delta.y);
+                if x.abs().max(y.abs()) < SWIPE_THRESHOLD {
+                    None
+                } else if x.abs() >= y.abs() {
+                    Some(if x > 0.0 {
+                        GestureDirection::Right
+                    } else {
+                        GestureDirection::Left
+                    })
+                } else {
+                    Some(if y > 0.0 {
+                        GestureDirection::Down
+                    } else {
+                        GestureDirection::Up
+                    })
+                }
+            }
+            GestureKind::Pinch => {
+                if (self.scale - 1.0).abs() < PINCH_THRESHOLD {
+                    None
+                } else if self.scale < 1.0 {
+                    Some(GestureDirection::In)
+                } else {
+                    Some(GestureDirection::Out)
+                }
+            }
+            GestureKind::Hold => None,
+        }
+    }
+
+    fn progress(&self, direction: Option<GestureDirection>) -> f64 {
+        match direction {
+            Some(GestureDirection::Left) => -self.delta.x / SWIPE_DISTANCE,
+            Some(GestureDirection::Right) => self.delta.x / SWIPE_DISTANCE,
+            Some(GestureDirection::Up) => -self.delta.y / SWIPE_DISTANCE,
+            Some(GestureDirection::Down) => self.delta.y / SWIPE_DISTANCE,
+            Some(GestureDirection::In) => (1.0 - self.scale) / PINCH_DISTANCE,
+            Some(GestureDirection::Out) => (self.scale - 1.0) / PINCH_DISTANCE,
+            None => 0.0,
+        }
+    }
+}
+
+/// Gesture bindings and the gesture in progress.
+#[derive(Debug, Default)]
+pub struct Gestures {
+    bindings: Vec<GestureBinding>,
+    active: Option<ActiveGesture>,
+}
+
+impl Gestures {
+    /// Bindings from the `[[gesture]]` config array.
+    pub fn parse(config: &[GestureConfig]) -> Result<Self, GestureError> {
+        Ok(Self {
+            bindings: config
+                .iter()
+                .map(GestureBinding::parse)
+                .collect::<Result<_, _>>()?,
+            active: None,
+        })
+    }
+
+    /// Takes the bindings of `other`, the gesture in progress keeps its binding.
+    pub fn replace_bindings(&mut self, other: Self) {
+        self.bindings = other.bindings;
+    }
+
+    pub fn bindings(&self) -> &[GestureBinding] {
+        &self.bindings
+    }
+
+    /// Decides whether the gesture goes to the clients, gestures with bindings never do.
+    pub fn begin(
+        &mut self,
+        kind: GestureKind,
+        fingers: u32,
+    ) -> FilterResult<Option<GestureUpdate>> {
+        self.active = None;
+        let bound = self
+            .bindings
+            .iter()
+            .any(|binding| binding.kind == kind && binding.fingers == fingers);
+        if !bound {
+            return FilterResult::Forward;
+        }
+
+        let mut gesture = ActiveGesture {
+            kind,
+            fingers,
+            delta: Point::default(),
+            scale: 1.0,
+            binding: None,
+            progress: 0.0,
+        };
+        // Holds have no direction to wait for
+        let mut update = None;
+        if kind == GestureKind::Hold {
+            let binding = find_binding(&self.bindings, kind, fingers, None);
+            update = binding.as_ref().map(|binding| GestureUpdate {
+                action: binding.action.clone(),
+                phase: GesturePhase::Begin,
+                progress: 0.0,
+            });
+            gesture.binding = Some(binding);
+        }
+        self.active = Some(gesture);
+        FilterResult::Intercept(update)
+    }
+
+    /// Swipe or pinch motion, `scale` is relative to the begin of the gesture.
+    pub fn update(
+        &mut self,
+        delta: Point<f64, Logical>,
+        scale: Option<f64>,
+    ) -> FilterResult<Option<GestureUpdate>> {
+        let Some(gesture) = &mut self.active else {
+            return FilterResult::Forward;
+        };
+        gesture.delta += delta;
+        if let Some(scale) = scale {
+            gesture.scale = scale;
+        }
+
+        let phase = if gesture.binding.is_some() {
+            GesturePhase::Update
+        } else {
+            let Some(direction) = gesture.direction() else {
+                return FilterResult::Intercept(None);
+            };
+            let binding = find_binding(
+                &self.bindings,
+                gesture.kind,
+                gesture.fingers,
+                Some(direction),
+            );
+            gesture.binding = Some(binding);
+            GesturePhase::Begin
+        };
+        let Some(Some(binding)) = &gesture.binding else {
+            return FilterResult::Intercept(None);
+        };
+
+        gesture.progress = gesture.progress(binding.direction);
+        FilterResult::Intercept(Some(GestureUpdate {
+            action: binding.action.clone(),
+            phase,
+            progress: gesture.progress,
+        }))
+    }
+
+    pub fn end(&mut self, cancelled: bool) -> FilterResult<Option<GestureUpdate>> {
+        let Some(gesture) = self.active.take() else {
+            return FilterResult::Forward;
+        };
+        let Some(Some(binding)) = gesture.binding else {
+            return FilterResult::Intercept(None);
+        };
+
+        let progress = match gesture.kind {
+            GestureKind::Hold if !cancelled => 1.0,
+            _ => gesture.progress,
+        };
+        FilterResult::Intercept(Some(GestureUpdate {
+            action: binding.action,
+            phase: if cancelled {
+                GesturePhase::Cancel
+            } else {
+                GesturePhase::End
+            },
+            progress,
+        }))
+    }
+}
+
+fn find_binding(
+    bindings: &[GestureBinding],
+    kind: GestureKind,
+    fingers: u32,
+    direction: Option<GestureDirection>,
+) -> Option<GestureBinding> {
+    bindings
+        .iter()
+        .find(|binding| {
+            binding.kind == kind && binding.fingers == fingers && binding.direction == direction
+        })
+        .cloned()
+}
+
+impl<B: Backend> App<B> {
+    /// Passes the progress to plugin bindings, runs other actions once the gesture is done.
+    pub(crate) fn handle_gesture(&mut self, update: Option<GestureUpdate>) {
+        let Some(update) = update else {
+            return;
+        };
+
+        let KeyAction::Plugin(name) = &update.action else {
+            if update.phase == GesturePhase::End && update.progress >= 1.0 {
+                self.run_key_action(&update.action);
+            }
+            return;
+        };
+        let Some(mut bindings) = self
+            .engine
+            .try_get_single_write_bindings::<Compositor>("compositor.window")
+        else {
+            log::warn!("No window manager for the gesture {name}");
+            return;
+        };
+        let mut store = bindings.store();
+        if let Err(err) = bindings.fusion_compositor_wm_exports().call_on_gesture(
+            &mut store,
+            name,
+            update.phase.into(),
+            update.progress,
+        ) {
+            log::error!("Window manager failed to handle the gesture {name}: {err:?}");
+        }
+    }
+}
@@ -0,0 +1,497 @@
+use std::{
+    io::{self, Read},
+    os::unix::net::UnixStream,
+    path::PathBuf,
+    sync::Arc,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::{Duration, Instant},
+};
+
+use calloop::EventLoop;
+use fusion_socket_protocol::{
+    Client, CompositorRequest, Event, FrameBuffer, InputAction, InputScriptError,
+    parse_input_script,
+};
+use serde::de::DeserializeOwned;
+use smithay::{
+    backend::{
+        allocator::Fourcc,
+        input::{InputEvent, KeyState},
+        renderer::{
+            Bind, ExportMem, Offscreen, damage::OutputDamageTracker, pixman::PixmanRenderer,
+        },
+    },
+    input::keyboard::{FilterResult, Keysym},
+    output::{Mode, Output, PhysicalProperties, Subpixel},
+    reexports::pixman::Image,
+    utils::{Logical, Point, Rectangle, Size, Transform},
+};
+
+use crate::compositor::{
+    ClientState, InitOptions,
+    api::WindowKey,
+    backend::Backend,
+    cursor::CursorConfig,
+    data::Data,
+    init_compositor_with,
+    input::{SpecialActions, vt_switch},
+    layer,
+    render::plain_output_render_elements,
+    state::App,
+    virtual_input::{VirtualDevice, VirtualInput},
+};
+
+const DEFAULT_SIZE: (i32, i32) = (1920, 1080);
+const REFRESH: i32 = 60_000;
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Pixels read back from a headless output after rendering.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snapshot {
+    pub size: Size<i32, Logical>,
+    /// Rows of little-endian ARGB8888 pixels
+    pub pixels: Vec<u8>,
+}
+
+impl Snapshot {
+    /// Pixel at `(x, y)` as `0xAARRGGBB`.
+    pub fn pixel(&self, x: i32, y: i32) -> u32 {
+        assert!(
+            (0..self.size.w).contains(&x) && (0..self.size.h).contains(&y),
+            "pixel ({x}, {y}) is outside of the {}x{} snapshot",
+            self.size.w,
+            self.size.h
+        );
+        let offset = ((y * self.size.w + x) * 4) as usize;
+        u32::from_le_bytes(self.pixels[offset..offset + 4].try_into().unwrap())
+    }
+}
+
+struct HeadlessOutput {
+    output: Output,
+    buffer: Image<'static, 'static>,
+    damage_tracker: OutputDamageTracker,
+    snapshot: Option<Snapshot>,
+}
+
+/// Backend without display hardware, outputs are offscreen pixman images.
+pub struct HeadlessBackend {
+    renderer: PixmanRenderer,
+    outputs: Vec<HeadlessOutput>,
+    /// Virtual terminals the user asked to switch to.
+    pub vt_switches: Vec<i32>,
+}
+
+impl HeadlessBackend {
+    pub fn new() -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(Self {
+            renderer: PixmanRenderer::new()?,
+            outputs: Vec::new(),
+            vt_switches: Vec::new(),
+        })
+    }
+
+    /// Frame of the output from the last [`App::render_headless`].
+    pub fn snapshot(&self, name: &str) -> Option<&Snapshot> {
+        self.outputs
+            .iter()
+            .find(|state| state.output.name() == name)
+            .and_then(|state| state.snapshot.as_ref())
+    }
+}
+
+impl Backend for HeadlessBackend {
+    fn create_output(&self) -> Output {
+        Output::new(
+            "headless".to_string(),
+            PhysicalProperties {
+                size: (0, 0).into(),
+                subpixel: Subpixel::Unknown,
+                make: "fusion".into(),
+                model: "Headless".into(),
+            },
+        )
+    }
+
+    fn mode(&self) -> Mode {
+        Mode {
+            size: DEFAULT_SIZE.into(),
+            refresh: REFRESH,
+        }
+    }
+}
+
+impl SpecialActions for HeadlessBackend {
+    fn handle_tty_keys(&mut self, state: KeyState, keysym: Keysym) -> FilterResult<()> {
+        // There is no session to switch, the request is only recorded
+        match vt_switch(keysym).filter(|_| state == KeyState::Pressed) {
+            Some(vt) => {
+                self.vt_switches.push(vt);
+                FilterResult::Intercept(())
+            }
+            None => FilterResult::Forward,
+        }
+    }
+}
+
+impl App<HeadlessBackend> {
+    /// Adds a virtual output of `size` to the right of the existing ones.
+    pub fn add_headless_output(
+        &mut self,
+        name: &str,
+        size: impl Into<Size<i32, Logical>>,
+    ) -> Result<Output, Box<dyn std::error::Error>> {
+        let size = size.into();
+        let output = Output::new(
+            name.to_string(),
+            PhysicalProperties {
+                size: (0, 0).into(),
+                subpixel: Subpixel::Unknown,
+                make: "fusion".into(),
+                model: "Headless".into(),
+            },
+        );
+        output.create_global::<Self>(&self.display);
+
+        let mode = Mode {
+            size: (size.w, size.h).into(),
+            refresh: REFRESH,
+        };
+        output.change_current_state(Some(mode), Some(Transform::Normal), None, None);
+        output.set_preferred(mode);
+
+        let buffer = Offscreen::<Image<'static, 'static>>::create_buffer(
+            &mut self.backend.renderer,
+            Fourcc::Argb8888,
+            (size.w, size.h).into(),
+        )?;
+        self.backend.outputs.push(HeadlessOutput {
+            output: output.clone(),
+            buffer,
+            damage_tracker: OutputDamageTracker::from_output(&output),
+            snapshot: None,
+        });
+
+        self.map_output(&output);
+        self.output_state.add_output(output.clone());
+
+        Ok(output)
+    }
+
+    /// Unplugs the virtual output called `name`.
+    pub fn remove_headless_output(&mut self, name: &str) {
+        let index = (self.backend.outputs.iter())
+            .position(|headless| headless.output.name() == name)
+            .unwrap();
+        let headless = self.backend.outputs.remove(index);
+        self.unmap_output(&headless.output);
+    }
+
+    /// Renders every output and reads the frames back.
+    pub fn render_headless(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let now = self.clock.now();
+        self.globals().tick_animations(now.into());
+
+        let globals = self.globals.clone();
+        let mut globals = globals.lock().unwrap();
+        let backend = &mut self.backend;
+        for state in &mut backend.outputs {
+            let renderer = &mut backend.renderer;
+            let mut elements = plain_output_render_elements(renderer, &globals, &state.output);
+            let output_geo = globals
+                .space
+                .output_geometry(&state.output)
+                .unwrap_or_default();
+            let cursor = self.input_state.cursor.render_cursor(
+                renderer,
+                output_geo,
+                state.output.current_scale(),
+                now.into(),
+            );
+            elements.splice(0..0, cursor);
+
+            let size = state.output.current_mode().unwrap().size;
+            let mut target = renderer.bind(&mut state.buffer)?;
+            state.damage_tracker.render_output(
+                renderer,
+                &mut target,
+                0,
+                &elements,
+                [0.0, 0.0, 0.0, 1.0],
+            )?;
+
+            let region = Rectangle::from_size((size.w, size.h).into());
+            let mapping = renderer.copy_framebuffer(&target, region, Fourcc::Argb8888)?;
+            let pixels = renderer.map_texture(&mapping)?.to_vec();
+            state.snapshot = Some(Snapshot {
+                size: (size.w, size.h).into(),
+                pixels,
+            });
+
+            globals.space.elements().for_each(|window| {
+                window.send_frame(&state.output, now, Some(Duration::ZERO), |_, _| {
+                    Some(state.output.clone())
+                });
+            });
+            layer::send_frames(&state.output, now.into());
+        }
+
+        globals.space.refresh();
+        Ok(())
+    }
+}
+
+/// Drives a headless compositor from tests.
+///
+/// Nothing runs in the background, every call to [`Harness::dispatch`]
+/// processes the pending client requests and compositor events.
+pub struct Harness {
+    event_loop: EventLoop<'static, Data<HeadlessBackend>>,
+    data: Data<HeadlessBackend>,
+    control_socket: PathBuf,
+    config_file: PathBuf,
+}
+
+impl Harness {
+    /// Starts a compositor without plugins with the given outputs.
+    pub fn new(outputs: &[(&str, (i32, i32))]) -> Result<Self, Box<dyn std::error::Error>> {
+        static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+        let event_loop = EventLoop::try_new()?;
+        let name = format!(
+            "fusion-headless-{}-{}",
+            std::process::id(),
+            NEXT_ID.fetch_add(1, Ordering::Relaxed)
+        );
+        let control_socket = std::env::temp_dir().join(format!("{name}.sock"));
+        // Does not exist until a test writes it, so the default config applies
+        let config_file = std::env::temp_dir().join(format!("{name}.toml"));
+        let options = InitOptions {
+            wayland_socket: false,
+            control_socket: Some(control_socket.clone()),
+            load_plugins: false,
+            config_file: config_file.clone(),
+        };
+
+        let mut data = init_compositor_with(
+            event_loop.handle(),
+            event_loop.get_signal(),
+            HeadlessBackend::new()?,
+            &options,
+        )?;
+        // Tests drive the compositor through injected input
+        data.state.debug.inject_input = true;
+        // Snapshots must not depend on the cursor themes installed, tests set their own images
+        data.state.input_state.cursor.configure(CursorConfig {
+            theme: Some("fusion-headless".to_string()),
+            ..CursorConfig::default()
+        });
+        for (name, size) in outputs {
+            data.state.add_headless_output(name, *size)?;
+        }
+        data.state
+            .handle_input_event(InputEvent::<VirtualInput>::DeviceAdded {
+                device: VirtualDevice,
+            });
+
+        Ok(Self {
+            event_loop,
+            data,
+            control_socket,
+            config_file,
+        })
+    }
+
+    pub fn state(&mut self) -> &mut App<HeadlessBackend> {
+        &mut self.data.state
+    }
+
+    /// Path of the control socket of this compositor.
+    pub fn control_socket(&self) -> &PathBuf {
+        &self.control_socket
+    }
+
+    /// Path the compositor reads its config from, see [`App::reload_config`].
+    pub fn config_file(&self) -> &PathBuf {
+        &self.config_file
+    }
+
+    /// Connects a Wayland client, the returned stream is its end of the connection.
+    pub fn connect_client(&mut self) -> Result<UnixStream, Box<dyn std::error::Error>> {
+        let (client, server) = UnixStream::pair()?;
+        self.data
+            .display
+            .insert_client(server, Arc::new(ClientState::default()))?;
+        Ok(client)
+    }
+
+    /// Runs `f` on another thread, dispatching until it returns.
+    ///
+    /// Lets blocking clients, e.g. a control [`Client`], talk to this compositor.
+    pub fn run<T: Send>(&mut self, f: impl FnOnce() -> T + Send) -> T {
+        std::thread::scope(|scope| {
+            let handle = scope.spawn(f);
+            while !handle.is_finished() {
+                self.dispatch();
+                std::thread::sleep(Duration::from_millis(1));
+            }
+            handle.join().unwrap()
+        })
+    }
+
+    /// Opens a control connection.
+    pub fn control_client(&mut self) -> Result<Client, Box<dyn std::error::Error>> {
+        let path = self.control_socket.clone();
+        let client = self.run(move || Client::connect(path))?;
+        client.set_read_timeout(Some(RESPONSE_TIMEOUT))?;
+        Ok(client)
+    }
+
+    /// Sends `request` on a new control connection and returns the response.
+    pub fn request<T: DeserializeOwned + Send>(
+        &mut self,
+        request: impl Into<CompositorRequest>,
+    ) -> Result<T, Box<dyn std::error::Error>> {
+        let mut client = self.control_client()?;
+        self.request_on(&mut client, request)
+    }
+
+    /// Sends `request` on an open control connection and returns the response.
+    pub fn request_on<T: DeserializeOwned + Send>(
+        &mut self,
+        client: &mut Client,
+        request: impl Into<CompositorRequest>,
+    ) -> Result<T, Box<dyn std::error::Error>> {
+        let request = request.into();
+        Ok(self.run(|| client.request(request))?)
+    }
+
+    /// Waits for the next event on a subscribed control connection.
+    pub fn next_event(
+        &mut self,
+        client: &mut Client,
+    ) -> Result<Option<Event>, Box<dyn std::error::Error>> {
+        Ok(self.run(|| client.next_event())?)
+    }
+
+    /// Dispatches until the next frame arrives on a raw control `stream` and decodes it.
+    pub fn response<T: DeserializeOwned>(
+        &mut self,
+        stream: &mut UnixStream,
+    ) -> Result<T, Box<dyn std::error::Error>> {
+        stream.set_nonblocking(true)?;
+        let deadline = Instant::now() + RESPONSE_TIMEOUT;
+        let mut frames = FrameBuffer::default();
+        let mut bytes = [0; 4096];
+        loop {
+            match stream.read(&mut bytes) {
+                Ok(0) => return Err("The compositor closed the connection".into()),
+                Ok(read) => frames.push(&bytes[..read]),
+                Err(error) if error.kind() == io::ErrorKind::WouldBlock => {}
+                Err(error) => return Err(error.into()),
+            }
+            if let Some(mut frame) = frames.next_frame()? {
+                return Ok(postcard::from_bytes_cobs(&mut frame)?);
+            }
+            if Instant::now() >= deadline {
+                return Err("Timed out waiting for a response".into());
+            }
+            self.dispatch();
+        }
+    }
+
+    /// Processes whatever is pending without blocking.
+    pub fn dispatch(&mut self) {
+        self.event_loop
+            .dispatch(Some(Duration::ZERO), &mut self.data)
+            .unwrap();
+        self.data.state.dispatch_events();
+        self.data.display.flush_clients().unwrap();
+    }
+
+    /// Dispatches until `condition` holds, returns `false` on timeout.
+    pub fn dispatch_until(
+        &mut self,
+        timeout: Duration,
+        mut condition: impl FnMut(&mut App<HeadlessBackend>) -> bool,
+    ) -> bool {
+        let deadline = Instant::now() + timeout;
+        loop {
+            self.dispatch();
+            if condition(&mut self.data.state) {
+                return true;
+            }
+            if Instant::now() >= deadline {
+                return false;
+            }
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    /// Renders every output, see [`Harness::snapshot`].
+    pub fn render(&mut self) {
+        self.data.state.render_headless().unwrap();
+        self.data.display.flush_clients().unwrap();
+    }
+
+    /// Last rendered frame of the output called `name`.
+    pub fn snapshot(&self, name: &str) -> Option<&Snapshot> {
+        self.data.state.backend.snapshot(name)
+    }
+
+    /// Handles `action` as if it came from a real device.
+    pub fn input(&mut self, action: InputAction) {
+        self.data.state.inject_input(action);
+        self.dispatch();
+    }
+
+    /// Runs an input script, see [`parse_input_script`].
+    pub fn input_script(&mut self, script: &str) -> Result<(), InputScriptError> {
+        for action in parse_input_script(script)? {
+            self.input(action);
+        }
+        Ok(())
+    }
+
+    /// Moves the pointer to the global `location` with relative motion.
+    pub fn pointer_motion(&mut self, location: impl Into<Point<f64, Logical>>) {
+        let delta = location.into() - self.data.state.input_state.cursor.location;
+        self.input(InputAction::PointerMotion {
+            dx: delta.x,
+            dy: delta.y,
+        });
+    }
+
+    /// Presses or releases a pointer button, `button` is a Linux input event code.
+    pub fn pointer_button(&mut self, button: u32, pressed: bool) {
+        self.input(InputAction::PointerButton { button, pressed });
+    }
+
+    /// Presses or releases a key, `key` is a Linux input event code.
+    pub fn key(&mut self, key: u32, pressed: bool) {
+        self.input(InputAction::Key { key, pressed });
+    }
+
+    /// Places a window the way the window manager would, in global coordinates.
+    pub fn place_window(&mut self, window: WindowKey, location: impl Into<Point<i32, Logical>>) {
+        let mut globals = self.data.state.globals();
+        let Some(element) = globals.mapped_windows.get(window).cloned() else {
+            return;
+        };
+        let globals = &mut *globals;
+        globals.workspaces.place_window(
+            &mut globals.space,
+            window,
+            &element,
+            location.into(),
+            true,
+        );
+    }
+}
+
+impl Drop for Harness {
+    fn drop(&mut self) {
+        self.data.state.shutdown();
+        let _ = std::fs::remove_file(&self.config_file);
+    }
+}
@@ -1,22 +1,36 @@
 use smithay::{
     backend::{
         input::{
-            AbsolutePositionEvent, Axis, AxisSource, ButtonState, Event, InputBackend, InputEvent,
-            KeyState, KeyboardKeyEvent, Keycode, PointerAxisEvent, PointerButtonEvent,
-            PointerMotionEvent,
+            AbsolutePositionEvent, Axis, AxisSource, ButtonState, Device, Event, GestureBeginEvent,
+            GestureEndEvent, GesturePinchUpdateEvent, GestureSwipeUpdateEvent, InputBackend,
+            InputEvent, KeyState, KeyboardKeyEvent, PointerAxisEvent, PointerButtonEvent,
+            PointerMotionEvent, TouchEvent,
         },
         session::Session,
     },
     desktop::WindowSurfaceType,
     input::{
         keyboard::{FilterResult, Keysym},
-        pointer::{AxisFrame, ButtonEvent, MotionEvent, RelativeMotionEvent},
+        pointer::{self, AxisFrame, ButtonEvent, MotionEvent, RelativeMotionEvent},
+        touch::{self, DownEvent, UpEvent},
     },
-    utils::{Logical, Point, SERIAL_COUNTER},
+    utils::{Logical, Point, Rectangle, SERIAL_COUNTER, Serial},
+    wayland::shell::wlr_layer::Layer,
 };
 use wayland_server::protocol::wl_surface::WlSurface;
 
-use crate::compositor::{backend::Backend, state::App, udev::UdevData, window::WinitBackend};
+use crate::compositor::{
+    api::CompositorGlobals,
+    backend::Backend,
+    decoration,
+    gesture::GestureKind,
+    keybinding::{Binding, Modifiers},
+    layer::{self, LOWER_LAYERS, UPPER_LAYERS},
+    pointer_constraint::{self, ActiveConstraint},
+    state::App,
+    udev::UdevData,
+    window::WinitBackend,
+};
 
 impl<B: Backend + SpecialActions> App<B> {
     pub fn handle_input_event<I: InputBackend>(&mut self, input: InputEvent<I>)
@@ -25,19 +39,19 @@ impl<B: Backend + SpecialActions> App<B> {
     {
         match input {
             InputEvent::PointerMotion { event } => {
-                self.input_state.cursor.location += event.delta();
-                self.input_state.cursor.location =
-                    self.clamp_pointer_location(self.input_state.cursor.location);
-
-                let location = self.input_state.cursor.location;
-                let under = self.surface_under(location);
-
                 let pointer = self.seat.get_pointer().unwrap();
                 let serial = SERIAL_COUNTER.next_serial();
 
+                let previous = self.input_state.cursor.location;
+                let focus = self.surface_under(previous);
+                let constraint = focus.as_ref().and_then(|(surface, origin)| {
+                    pointer_constraint::active(surface, &pointer, previous - *origin)
+                });
+
+                // Clients with a locked pointer still get the relative motion
                 pointer.relative_motion(
                     self,
-                    under.clone(),
+                    focus.clone(),
                     &RelativeMotionEvent {
                         delta: event.delta(),
                         delta_unaccel: event.delta_unaccel(),
@@ -45,27 +59,44 @@ impl<B: Backend + SpecialActions> App<B> {
                     },
                 );
 
+                let location = self.clamp_pointer_location(previous + event.delta());
+                let under = self.surface_under(location);
+                let allowed = match (&constraint, &focus) {
+                    (Some(ActiveConstraint::Locked), _) => false,
+                    (Some(ActiveConstraint::Confined(region)), Some((surface, origin))) => {
+                        under.as_ref().is_some_and(|(under, _)| under == surface)
+                            && region.as_ref().is_none_or(|region| {
+                                region.contains((location - *origin).to_i32_round())
+                            })
+                    }
+                    _ => true,
+                };
+                if !allowed {
+                    pointer.frame(self);
+                    return;
+                }
+
+                self.input_state.cursor.location = location;
                 pointer.motion(
                     self,
-                    under,
+                    under.clone(),
                     &MotionEvent {
                         location,
                         serial,
                         time: event.time_msec(),
                     },
                 );
-
                 pointer.frame(self);
+
+                if let Some((surface, origin)) = under {
+                    pointer_constraint::activate(&surface, &pointer, location - origin);
+                }
             }
             InputEvent::PointerMotionAbsolute { event } => {
-                let output_geo = {
-                    let space = &self.globals.lock().unwrap().space;
-                    let output = space.outputs().next().unwrap();
-
-                    space.output_geometry(output).unwrap()
+                let Some(pos) = self.absolute_position(&event) else {
+                    return;
                 };
-
-                let pos = event.position_transformed(output_geo.size) + output_geo.loc.to_f64();
+                self.input_state.cursor.location = pos;
                 let serial = SERIAL_COUNTER.next_serial();
                 let pointer = self.seat.get_pointer().unwrap();
                 let under = self.surface_under(pos);
@@ -96,39 +127,28 @@ impl<B: Backend + SpecialActions> App<B> {
                 if ButtonState::Pressed == button_state && !pointer.is_grabbed() {
                     let location = self.input_state.cursor.location;
 
-                    // Ищем окно и ПОВЕРХНОСТЬ под курсором
-                    let under = globals.space.element_under(location).map(|(w, l)| {
-                        // важно: берем поверхность с учетом локальных координат внутри окна
-                        let surface = w
-                            .surface_under(location - l.to_f64(), WindowSurfaceType::all())
-                            .unwrap()
-                            .0;
-                        (w.clone(), surface)
-                    });
-
-                    if let Some((window, surface)) = under {
-                        // 1. Поднимаем окно в Space
-                        globals.space.raise_element(&window, true);
+                    let upper = layer::layer_under(&globals.space, location, &UPPER_LAYERS);
 
-                        // 2. Активируем окно (важно для XDG Shell)
+                    // Декорации рисуются вне окна, поэтому проверяем их первыми
+                    let decoration = upper
+                        .is_none()
+                        .then(|| decoration::decoration_under(&globals, location))
+                        .flatten();
+
+                    if let Some((window, hit)) = decoration {
+                        globals.space.raise_element(&window, true);
                         window.set_activated(true);
+                        let surface = window.toplevel().unwrap().wl_surface().clone();
+                        keyboard.set_focus(self, Some(surface), serial);
+                        window.toplevel().unwrap().send_configure();
 
-                        // 3. Устанавливаем фокус клавиатуры
-                        keyboard.set_focus(
-                            self,
-                            Some(surface), // Передаем конкретную поверхность
-                            serial,
-                        );
+                        self.handle_decoration_click(&mut globals, &window, hit, button, serial);
 
-                        // 4. Генерируем Configure события
-                        window.toplevel().unwrap().send_configure();
+                        if let Some(exclusive) = layer::exclusive_focus(&globals.space) {
+                            keyboard.set_focus(self, Some(exclusive), serial);
+                        }
                     } else {
-                        // Если кликнули мимо — снимаем фокус
-                        globals.space.elements().for_each(|window| {
-                            window.set_activated(false);
-                            window.toplevel().unwrap().send_configure();
-                        });
-                        keyboard.set_focus(self, Option::<WlSurface>::None, serial);
+                        self.focus_at(&mut globals, location, serial);
                     }
                 }
 
@@ -147,15 +167,25 @@ impl<B: Backend + SpecialActions> App<B> {
                 );
                 pointer.frame(self);
             }
+            InputEvent::DeviceAdded { device } => self.add_input_device(&device),
+            InputEvent::DeviceRemoved { device } => self.remove_input_device(&device),
             InputEvent::PointerAxis { event } => {
-                let horizontal_amount_v120 = event.amount_v120(Axis::Horizontal);
+                let multiplier = self.scroll_multiplier(&event.device().id());
+                // One wheel click scrolls 15 units, v120 counts fractions of clicks
+                let horizontal_amount_v120 = event
+                    .amount_v120(Axis::Horizontal)
+                    .map(|amt| amt * multiplier);
                 let horizontal_amount = event
                     .amount(Axis::Horizontal)
+                    .map(|amt| amt * multiplier)
                     .or_else(|| horizontal_amount_v120.map(|amt| amt * 15. / 120.))
                     .unwrap_or(0.0);
-                let vertical_amount_v120 = event.amount_v120(Axis::Vertical);
+                let vertical_amount_v120 = event
+                    .amount_v120(Axis::Vertical)
+                    .map(|amt| amt * multiplier);
                 let vertical_amount = event
                     .amount(Axis::Vertical)
+                    .map(|amt| amt * multiplier)
                     .or_else(|| vertical_amount_v120.map(|amt| amt * 15. / 120.))
                     .unwrap_or(0.0);
 
@@ -193,74 +223,383 @@ impl<B: Backend + SpecialActions> App<B> {
                 pointer.axis(self, frame);
                 pointer.frame(self);
             }
+            InputEvent::GestureSwipeBegin { event } => {
+                match self.gestures.begin(GestureKind::Swipe, event.fingers()) {
+                    FilterResult::Intercept(update) => self.handle_gesture(update),
+                    FilterResult::Forward => {
+                        let pointer = self.input_state.cursor.get_pointer();
+                        pointer.gesture_swipe_begin(
+                            self,
+                            &pointer::GestureSwipeBeginEvent {
+                                serial: SERIAL_COUNTER.next_serial(),
+                                time: event.time_msec(),
+                                fingers: event.fingers(),
+                            },
+                        );
+                    }
+                }
+            }
+            InputEvent::GestureSwipeUpdate { event } => {
+                match self.gestures.update(event.delta(), None) {
+                    FilterResult::Intercept(update) => self.handle_gesture(update),
+                    FilterResult::Forward => {
+                        let pointer = self.input_state.cursor.get_pointer();
+                        pointer.gesture_swipe_update(
+                            self,
+                            &pointer::GestureSwipeUpdateEvent {
+                                time: event.time_msec(),
+                                delta: event.delta(),
+                            },
+                        );
+                    }
+                }
+            }
+            InputEvent::GestureSwipeEnd { event } => match self.gestures.end(event.cancelled()) {
+                FilterResult::Intercept(update) => self.handle_gesture(update),
+                FilterResult::Forward => {
+                    let pointer = self.input_state.cursor.get_pointer();
+                    pointer.gesture_swipe_end(
+                        self,
+                        &pointer::GestureSwipeEndEvent {
+                            serial: SERIAL_COUNTER.next_serial(),
+                            time: event.time_msec(),
+                            cancelled: event.cancelled(),
+                        },
+                    );
+                }
+            },
+            InputEvent::GesturePinchBegin { event } => {
+                match self.gestures.begin(GestureKind::Pinch, event.fingers()) {
+                    FilterResult::Intercept(update) => self.handle_gesture(update),
+                    FilterResult::Forward => {
+                        let pointer = self.input_state.cursor.get_pointer();
+                        pointer.gesture_pinch_begin(
+                            self,
+                            &pointer::GesturePinchBeginEvent {
+                                serial: SERIAL_COUNTER.next_serial(),
+                                time: event.time_msec(),
+                                fingers: event.fingers(),
+                            },
+                        );
+                    }
+                }
+            }
+            InputEvent::GesturePinchUpdate { event } => {
+                match self.gestures.update(event.delta(), Some(event.scale())) {
+                    FilterResult::Intercept(update) => self.handle_gesture(update),
+                    FilterResult::Forward => {
+                        let pointer = self.input_state.cursor.get_pointer();
+                        pointer.gesture_pinch_update(
+                            self,
+                            &pointer::GesturePinchUpdateEvent {
+                                time: event.time_msec(),
+                                delta: event.delta(),
+                                scale: event.scale(),
+                                rotation: event.rotation(),
+                            },
+                        );
+                    }
+                }
+            }
+            InputEvent::GesturePinchEnd { event } => match self.gestures.end(event.cancelled()) {
+                FilterResult::Intercept(update) => self.handle_gesture(update),
+                FilterResult::Forward => {
+                    let pointer = self.input_state.cursor.get_pointer();
+                    pointer.gesture_pinch_end(
+                        self,
+                        &pointer::GesturePinchEndEvent {
+                            serial: SERIAL_COUNTER.next_serial(),
+                            time: event.time_msec(),
+                            cancelled: event.cancelled(),
+                        },
+                    );
+                }
+            },
+            InputEvent::GestureHoldBegin { event } => {
+                match self.gestures.begin(GestureKind::Hold, event.fingers()) {
+                    FilterResult::Intercept(update) => self.handle_gesture(update),
+                    FilterResult::Forward => {
+                        let pointer = self.input_state.cursor.get_pointer();
+                        pointer.gesture_hold_begin(
+                            self,
+                            &pointer::GestureHoldBeginEvent {
+                                serial: SERIAL_COUNTER.next_serial(),
+                                time: event.time_msec(),
+                                fingers: event.fingers(),
+                            },
+                        );
+                    }
+                }
+            }
+            InputEvent::GestureHoldEnd { event } => match self.gestures.end(event.cancelled()) {
+                FilterResult::Intercept(update) => self.handle_gesture(update),
+                FilterResult::Forward => {
+                    let pointer = self.input_state.cursor.get_pointer();
+                    pointer.gesture_hold_end(
+                        self,
+                        &pointer::GestureHoldEndEvent {
+                            serial: SERIAL_COUNTER.next_serial(),
+                            time: event.time_msec(),
+                            cancelled: event.cancelled(),
+                        },
+                    );
+                }
+            },
+            InputEvent::TouchDown { event } => {
+                let Some(location) = self.absolute_position(&event) else {
+                    return;
+                };
+                let serial = SERIAL_COUNTER.next_serial();
+                let touch = self.input_state.touch.clone();
+                if !touch.is_grabbed() {
+                    let globals = self.globals.clone();
+                    self.focus_at(&mut globals.lock().unwrap(), location, serial);
+                }
+
+                let under = self.surface_under(location);
+                touch.down(
+                    self,
+                    under,
+                    &DownEvent {
+                        slot: event.slot(),
+                        location,
+                        serial,
+                        time: event.time_msec(),
+                    },
+                );
+            }
+            InputEvent::TouchMotion { event } => {
+                let Some(location) = self.absolute_position(&event) else {
+                    return;
+                };
+                // Touch points stay with the surface they went down on
+                let under = self.surface_under(location);
+                let touch = self.input_state.touch.clone();
+                touch.motion(
+                    self,
+                    under,
+                    &touch::MotionEvent {
+                        slot: event.slot(),
+                        location,
+                        time: event.time_msec(),
+                    },
+                );
+            }
+            InputEvent::TouchUp { event } => {
+                let touch = self.input_state.touch.clone();
+                touch.up(
+                    self,
+                    &UpEvent {
+                        slot: event.slot(),
+                        serial: SERIAL_COUNTER.next_serial(),
+                        time: event.time_msec(),
+                    },
+                );
+            }
+            InputEvent::TouchFrame { .. } => {
+                let touch = self.input_state.touch.clone();
+                touch.frame(self);
+            }
+            InputEvent::TouchCancel { .. } => {
+                let touch = self.input_state.touch.clone();
+                touch.cancel(self);
+            }
+            InputEvent::TabletToolProximity { event } => self.tablet_tool_proximity(&event),
+            InputEvent::TabletToolAxis { event } => self.tablet_tool_axis(&event),
+            InputEvent::TabletToolTip { event } => self.tablet_tool_tip(&event),
+            InputEvent::TabletToolButton { event } => self.tablet_tool_button(&event),
             InputEvent::Keyboard { event } => {
                 let keyboard = self.seat.get_keyboard().unwrap();
 
                 let serial = SERIAL_COUNTER.next_serial();
-                keyboard.input::<(), _>(
+                let state = event.state();
+                let binding = keyboard.input::<Option<Binding>, _>(
                     self,
                     event.key_code(),
-                    event.state(),
+                    state,
                     serial,
                     event.time_msec(),
-                    |data, _, handle| {
+                    |data, modifiers, handle| {
                         let keysym = handle.modified_sym();
-                        data.backend.handle_tty_keys(event.state(), keysym)
+                        if let FilterResult::Intercept(()) =
+                            data.backend.handle_tty_keys(state, keysym)
+                        {
+                            return FilterResult::Intercept(None);
+                        }
+
+                        let mut keysyms = handle.raw_syms();
+                        keysyms.extend(handle.raw_latin_sym_or_raw_current_sym());
+                        data.keybindings.filter(
+                            handle.raw_code(),
+                            state,
+                            Modifiers::from(modifiers),
+                            &keysyms,
+                        )
                     },
                 );
-
-                if event.key_code() == Keycode::new(9) {
-                    std::process::exit(0);
-                }
-                if event.key_code() == Keycode::new(10) && event.state() == KeyState::Released {
-                    let mut cmd = std::process::Command::new("kitty");
-                    cmd.spawn().unwrap();
-                }
+                self.handle_keybinding(state, binding.flatten());
+                // Layouts can also be switched with xkb options such as `grp:alt_shift_toggle`
+                self.update_keyboard_layout();
             }
             _ => {}
         }
     }
+}
+
+impl<B: Backend> App<B> {
+    /// Gives the keyboard to what is at `location` and raises the window there,
+    /// clicks and touches outside of any window take the focus away.
+    pub(crate) fn focus_at(
+        &mut self,
+        globals: &mut CompositorGlobals,
+        location: Point<f64, Logical>,
+        serial: Serial,
+    ) {
+        let keyboard = self.seat.get_keyboard().unwrap();
+        let upper = layer::layer_under(&globals.space, location, &UPPER_LAYERS);
+
+        // Ищем окно и ПОВЕРХНОСТЬ под курсором
+        let under = globals.space.element_under(location).map(|(w, l)| {
+            // важно: берем поверхность с учетом локальных координат внутри окна
+            let surface = w
+                .surface_under(location - l.to_f64(), WindowSurfaceType::all())
+                .unwrap()
+                .0;
+            (w.clone(), surface)
+        });
+
+        if let Some((layer, _, _)) = upper {
+            // Windows stay activated while a panel or an overlay takes the keyboard
+            if layer.can_receive_keyboard_focus() {
+                keyboard.set_focus(self, Some(layer.wl_surface().clone()), serial);
+            }
+        } else if let Some((window, surface)) = under {
+            // 1. Поднимаем окно в Space
+            globals.space.raise_element(&window, true);
+
+            // 2. Активируем окно (важно для XDG Shell)
+            window.set_activated(true);
+
+            // 3. Устанавливаем фокус клавиатуры
+            keyboard.set_focus(
+                self,
+                Some(surface), // Передаем конкретную поверхность
+                serial,
+            );
+
+            // 4. Генерируем Configure события
+            window.toplevel().unwrap().send_configure();
+        } else {
+            // Если кликнули мимо — снимаем фокус
+            globals.space.elements().for_each(|window| {
+                window.set_activated(false);
+                window.toplevel().unwrap().send_configure();
+            });
+            let lower = layer::layer_under(&globals.space, location, &LOWER_LAYERS)
+                .filter(|(layer, _, _)| layer.can_receive_keyboard_focus())
+                .map(|(layer, _, _)| layer.wl_surface().clone());
+            keyboard.set_focus(self, lower, serial);
+        }
+
+        // Layer surfaces with exclusive interactivity keep the keyboard
+        if let Some(exclusive) = layer::exclusive_focus(&globals.space) {
+            keyboard.set_focus(self, Some(exclusive), serial);
+        }
+    }
+
+    /// Global position of an absolute event on the area the device is mapped to.
+    pub fn absolute_position<I: InputBackend>(
+        &self,
+        event: &impl AbsolutePositionEvent<I>,
+    ) -> Option<Point<f64, Logical>> {
+        let area = self.absolute_device_area(&event.device().id())?;
+        Some(self.clamp_pointer_location(event.position_transformed(area.size) + area.loc.to_f64()))
+    }
 
     pub fn surface_under(
         &self,
         pos: Point<f64, Logical>,
     ) -> Option<(WlSurface, Point<f64, Logical>)> {
-        self.globals()
-            .space
-            .element_under(pos)
-            .and_then(|(window, location)| {
-                window
-                    .surface_under(pos - location.to_f64(), WindowSurfaceType::ALL)
-                    .map(|(s, p)| (s, (p + location).to_f64()))
+        let globals = self.globals();
+        let space = &globals.space;
+        let layer_surface = |layers: &[Layer]| {
+            layer::layer_under(space, pos, layers).map(|(_, surface, location)| (surface, location))
+        };
+
+        layer_surface(&UPPER_LAYERS)
+            .or_else(|| {
+                space.element_under(pos).and_then(|(window, location)| {
+                    window
+                        .surface_under(pos - location.to_f64(), WindowSurfaceType::ALL)
+                        .map(|(s, p)| (s, (p + location).to_f64()))
+                })
             })
+            .or_else(|| layer_surface(&LOWER_LAYERS))
     }
 
-    //TODO mutli monitor setup
-    pub fn clamp_pointer_location(
-        &self,
-        mut raw_location: Point<f64, Logical>,
-    ) -> Point<f64, Logical> {
-        let output = self.output_state.outputs.keys().next().unwrap();
-        let output_location = output.current_location().to_f64();
-        let output_size = output.current_mode().unwrap().size.to_f64();
-
-        if raw_location.x < output_location.x {
-            raw_location.x = output_location.x;
-        }
-
-        if raw_location.y < output_location.y {
-            raw_location.y = output_location.y;
-        }
-
-        if raw_location.x > output_size.w {
-            raw_location.x = output_size.w;
-        }
+    /// Keeps `location` on the outputs, points outside of them move to the closest output.
+    pub fn clamp_pointer_location(&self, location: Point<f64, Logical>) -> Point<f64, Logical> {
+        let globals = self.globals();
+        let space = &globals.space;
+        space
+            .outputs()
+            .filter_map(|output| space.output_geometry(output))
+            .map(|geometry| {
+                let min = geometry.loc.to_f64();
+                // The far edges belong to the next output
+                let max = min + geometry.size.to_f64().to_point() - Point::from((1.0, 1.0));
+                Point::from((
+                    location.x.clamp(min.x, max.x),
+                    location.y.clamp(min.y, max.y),
+                ))
+            })
+            .min_by(|a, b| {
+                let distance = |point: &Point<f64, Logical>| {
+                    (point.x - location.x).powi(2) + (point.y - location.y).powi(2)
+                };
+                distance(a).total_cmp(&distance(b))
+            })
+            .unwrap_or(location)
+    }
 
-        if raw_location.y > output_size.h {
-            raw_location.y = output_size.h;
+    /// Area absolute devices such as tablets and touchscreens are mapped to,
+    /// the output set with `map-to-output` or else all outputs.
+    pub fn absolute_device_area(&self, device_id: &str) -> Option<Rectangle<i32, Logical>> {
+        let name = self
+            .input_state
+            .devices
+            .get(device_id)
+            .and_then(|device| device.settings.map_to_output.as_deref());
+        let globals = self.globals();
+        let space = &globals.space;
+        if let Some(name) = name
+            && let Some(output) = space.outputs().find(|output| output.name() == name)
+        {
+            return space.output_geometry(output);
         }
+        space
+            .outputs()
+            .filter_map(|output| space.output_geometry(output))
+            .reduce(|area, geometry| area.merge(geometry))
+    }
+}
 
-        raw_location
+/// Virtual terminal `keysym` switches to.
+pub fn vt_switch(keysym: Keysym) -> Option<i32> {
+    match keysym {
+        Keysym::XF86_Switch_VT_1 => Some(1),
+        Keysym::XF86_Switch_VT_2 => Some(2),
+        Keysym::XF86_Switch_VT_3 => Some(3),
+        Keysym::XF86_Switch_VT_4 => Some(4),
+        Keysym::XF86_Switch_VT_5 => Some(5),
+        Keysym::XF86_Switch_VT_6 => Some(6),
+        Keysym::XF86_Switch_VT_7 => Some(7),
+        Keysym::XF86_Switch_VT_8 => Some(8),
+        Keysym::XF86_Switch_VT_9 => Some(9),
+        Keysym::XF86_Switch_VT_10 => Some(10),
+        Keysym::XF86_Switch_VT_11 => Some(11),
+        Keysym::XF86_Switch_VT_12 => Some(12),
+        _ => None,
     }
 }
 
@@ -279,23 +618,7 @@ impl SpecialActions for UdevData {
             return FilterResult::Forward;
         }
 
-        let vt_num = match keysym {
-            Keysym::XF86_Switch_VT_1 => Some(1),
-            Keysym::XF86_Switch_VT_2 => Some(2),
-            Keysym::XF86_Switch_VT_3 => Some(3),
-            Keysym::XF86_Switch_VT_4 => Some(4),
-            Keysym::XF86_Switch_VT_5 => Some(5),
-            Keysym::XF86_Switch_VT_6 => Some(6),
-            Keysym::XF86_Switch_VT_7 => Some(7),
-            Keysym::XF86_Switch_VT_8 => Some(8),
-            Keysym::XF86_Switch_VT_9 => Some(9),
-            Keysym::XF86_Switch_VT_10 => Some(10),
-            Keysym::XF86_Switch_VT_11 => Some(11),
-            Keysym::XF86_Switch_VT_12 => Some(12),
-            _ => None,
-        };
-
-        if let Some(vt) = vt_num {
+        if let Some(vt) = vt_switch(keysym) {
             log::info!("Switching to VT {vt}");
             if let Err(err) = self.session.change_vt(vt) {
                 log::error!("Failed to switch session: {err}");
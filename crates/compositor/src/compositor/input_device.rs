@@ -0,0 +1,196 @@
+use serde::Deserialize;
+use smithay::{
+    backend::input::{Device, DeviceCapability},
+    wayland::tablet_manager::{TabletDescriptor, TabletSeatTrait},
+};
+
+use crate::compositor::{backend::Backend, state::App};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DeviceKind {
+    Keyboard,
+    Pointer,
+    Touchpad,
+    Touch,
+    Tablet,
+}
+
+impl DeviceKind {
+    /// Guesses the kind from the capabilities, touchpads are pointers with gestures.
+    pub fn of(device: &impl Device) -> Option<Self> {
+        if device.has_capability(DeviceCapability::Pointer) {
+            if device.has_capability(DeviceCapability::Gesture) {
+                Some(Self::Touchpad)
+            } else {
+                Some(Self::Pointer)
+            }
+        } else if device.has_capability(DeviceCapability::Keyboard) {
+            Some(Self::Keyboard)
+        } else if device.has_capability(DeviceCapability::Touch) {
+            Some(Self::Touch)
+        } else if device.has_capability(DeviceCapability::TabletTool) {
+            Some(Self::Tablet)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum AccelProfile {
+    Flat,
+    Adaptive,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ScrollMethod {
+    None,
+    TwoFinger,
+    Edge,
+    OnButtonDown,
+}
+
+/// Entry of the `[[input.device]]` array in the config.
+///
+/// Unset settings keep the libinput defaults. Every entry matching a device is applied
+/// in the order of the config, so later entries override earlier ones.
+///
+/// ```toml
+/// [[input.device]]
+/// type = "touchpad"   # keyboard, pointer, touchpad, touch or tablet
+/// tap = true
+/// natural-scroll = true
+/// scroll-method = "two-finger"   # none, two-finger, edge or on-button-down
+/// disable-while-typing = true
+///
+/// [[input.device]]
+/// name = "Logitech G502"   # as listed by `libinput list-devices`
+/// accel-profile = "flat"   # flat or adaptive
+/// accel-speed = -0.3       # from -1 to 1
+/// scroll-multiplier = 2.0
+///
+/// [[input.device]]
+/// type = "tablet"
+/// map-to-output = "DP-1"   # absolute devices span all outputs by default
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct DeviceConfig {
+    /// Matches devices with exactly this name
+    pub name: Option<String>,
+    /// Matches devices of this kind
+    #[serde(rename = "type")]
+    pub kind: Option<DeviceKind>,
+
+    pub tap: Option<bool>,
+    pub natural_scroll: Option<bool>,
+    pub accel_profile: Option<AccelProfile>,
+    pub accel_speed: Option<f64>,
+    pub left_handed: Option<bool>,
+    pub scroll_method: Option<ScrollMethod>,
+    pub disable_while_typing: Option<bool>,
+    /// Factor applied to the scroll distance
+    pub scroll_multiplier: Option<f64>,
+    /// Output absolute devices such as tablets and touchscreens are mapped to
+    pub map_to_output: Option<String>,
+}
+
+impl DeviceConfig {
+    fn matches(&self, name: &str, kind: Option<DeviceKind>) -> bool {
+        self.name.as_ref().is_none_or(|expected| expected == name)
+            && self.kind.is_none_or(|expected| Some(expected) == kind)
+    }
+
+    fn merge(&mut self, other: &Self) {
+        self.tap = other.tap.or(self.tap);
+        self.natural_scroll = other.natural_scroll.or(self.natural_scroll);
+        self.accel_profile = other.accel_profile.or(self.accel_profile);
+        self.accel_speed = other.accel_speed.or(self.accel_speed);
+        self.left_handed = other.left_handed.or(self.left_handed);
+        self.scroll_method = other.scroll_method.or(self.scroll_method);
+        self.disable_while_typing = other.disable_while_typing.or(self.disable_while_typing);
+        self.scroll_multiplier = other.scroll_multiplier.or(self.scroll_multiplier);
+        if other.map_to_output.is_some() {
+            self.map_to_output.clone_from(&other.map_to_output);
+        }
+    }
+}
+
+/// `[input]` table of the config.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct InputConfig {
+    #[serde(default)]
+    pub device: Vec<DeviceConfig>,
+}
+
+impl InputConfig {
+    /// Settings of every entry matching the device merged together.
+    pub fn settings(&self, name: &str, kind: Option<DeviceKind>) -> DeviceConfig {
+        let mut settings = DeviceConfig::default();
+        for config in self
+            .device
+            .iter()
+            .filter(|config| config.matches(name, kind))
+        {
+            settings.merge(config);
+        }
+        settings
+    }
+}
+
+/// Device seen in a `DeviceAdded` event.
+#[derive(Debug, Clone)]
+pub struct InputDevice {
+    pub name: String,
+    pub kind: Option<DeviceKind>,
+    pub settings: DeviceConfig,
+}
+
+impl<B: Backend> App<B> {
+    pub(crate) fn add_input_device(&mut self, device: &impl Device) {
+        let name = device.name();
+        let kind = DeviceKind::of(device);
+        let settings = self.input_state.input_config.settings(&name, kind);
+        log::info!("Input device added: {name} ({kind:?})");
+
+        self.input_state.devices.insert(
+            device.id(),
+            InputDevice {
+                name,
+                kind,
+                settings,
+            },
+        );
+    }
+
+    pub(crate) fn remove_input_device(&mut self, device: &impl Device) {
+        if device.has_capability(DeviceCapability::TabletTool) {
+            self.seat
+                .tablet_seat()
+                .remove_tablet(&TabletDescriptor::from(device));
+        }
+        self.input_state.devices.remove(&device.id());
+    }
+
+    /// Scroll multiplier of the device with the `id`, unknown devices scroll unchanged.
+    pub fn scroll_multiplier(&self, id: &str) -> f64 {
+        self.input_state
+            .devices
+            .get(id)
+            .and_then(|device| device.settings.scroll_multiplier)
+            .unwrap_or(1.0)
+    }
+
+    /// Replaces the `[input]` config and applies it to the known devices.
+    pub fn apply_input_config(&mut self, config: InputConfig) {
+        for device in self.input_state.devices.values_mut() {
+            device.settings = config.settings(&device.name, device.kind);
+        }
+        self.backend.configure_input_devices(&config);
+        self.input_state.input_config = config;
+    }
+}
@@ -0,0 +1,382 @@
+use std::{collections::HashMap, str::FromStr, time::Duration};
+
+use bitflags::bitflags;
+use calloop::{
+    RegistrationToken,
+    timer::{TimeoutAction, Timer},
+};
+use serde::Deserialize;
+use smithay::{
+    backend::input::KeyState,
+    input::keyboard::{FilterResult, Keycode, Keysym, ModifiersState, xkb},
+};
+
+use crate::compositor::{api::general::Compositor, backend::Backend, state::App};
+
+pub const DEFAULT_MODE: &str = "default";
+
+bitflags! {
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+    pub struct Modifiers: u8 {
+        const CTRL = 1;
+        const ALT = 1 << 1;
+        const SHIFT = 1 << 2;
+        const SUPER = 1 << 3;
+    }
+}
+
+impl From<&ModifiersState> for Modifiers {
+    fn from(state: &ModifiersState) -> Self {
+        let mut modifiers = Self::empty();
+        modifiers.set(Self::CTRL, state.ctrl);
+        modifiers.set(Self::ALT, state.alt);
+        modifiers.set(Self::SHIFT, state.shift);
+        modifiers.set(Self::SUPER, state.logo);
+        modifiers
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum KeybindingError {
+    #[error("empty key chord")]
+    Empty,
+    #[error("unknown modifier `{0}` in `{1}`")]
+    UnknownModifier(String, String),
+    #[error("unknown key `{0}` in `{1}`")]
+    UnknownKey(String, String),
+    #[error("`{0}` needs exactly one of `spawn`, `action`, `mode` or `plugin`")]
+    Action(String),
+    #[error("`{0}` switches to the unknown mode `{1}`")]
+    UnknownMode(String, String),
+}
+
+/// Key with the modifiers that have to be held, e.g. `Super+Shift+Return`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Chord {
+    pub modifiers: Modifiers,
+    /// Keysym without modifiers applied, letters are lowercase
+    pub keysym: Keysym,
+}
+
+impl FromStr for Chord {
+    type Err = KeybindingError;
+
+    fn from_str(chord: &str) -> Result<Self, Self::Err> {
+        let mut parts = chord.split('+').map(str::trim).collect::<Vec<_>>();
+        let key = parts.pop().filter(|key| !key.is_empty());
+        let Some(key) = key else {
+            return Err(KeybindingError::Empty);
+        };
+
+        let mut modifiers = Modifiers::empty();
+        for part in parts {
+            modifiers |= match part.to_ascii_lowercase().as_str() {
+                "ctrl" | "control" => Modifiers::CTRL,
+                "alt" | "mod1" => Modifiers::ALT,
+                "shift" => Modifiers::SHIFT,
+                "super" | "logo" | "mod4" => Modifiers::SUPER,
+                _ => {
+                    return Err(KeybindingError::UnknownModifier(
+                        part.to_string(),
+                        chord.to_string(),
+                    ));
+                }
+            };
+        }
+
+        // Case insensitive lookups prefer the lowercase keysym, which is what unmodified keys produce
+        let keysym = xkb::keysym_from_name(key, xkb::KEYSYM_CASE_INSENSITIVE);
+        if keysym.raw() == xkb::keysyms::KEY_NoSymbol {
+            return Err(KeybindingError::UnknownKey(
+                key.to_string(),
+                chord.to_string(),
+            ));
+        }
+
+        Ok(Self { modifiers, keysym })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Trigger {
+    #[default]
+    Press,
+    Release,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum BuiltinAction {
+    Quit,
+    ReloadPlugins,
+    ReloadConfig,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeyAction {
+    /// Shell command
+    Spawn(String),
+    Builtin(BuiltinAction),
+    EnterMode(String),
+    /// Name passed to the `on-keybinding` export of the window manager
+    Plugin(String),
+}
+
+impl KeyAction {
+    /// The action of the binding `name`, exactly one of the fields has to be set.
+    pub(crate) fn from_fields(
+        name: &str,
+        spawn: Option<&str>,
+        action: Option<BuiltinAction>,
+        mode: Option<&str>,
+        plugin: Option<&str>,
+    ) -> Result<Self, KeybindingError> {
+        let actions = [
+            spawn.map(|command| Self::Spawn(command.to_string())),
+            action.map(Self::Builtin),
+            mode.map(|mode| Self::EnterMode(mode.to_string())),
+            plugin.map(|name| Self::Plugin(name.to_string())),
+        ];
+        let mut actions = actions.into_iter().flatten();
+        match (actions.next(), actions.next()) {
+            (Some(action), None) => Ok(action),
+            _ => Err(KeybindingError::Action(name.to_string())),
+        }
+    }
+}
+
+/// Entry of a `[keybindings.<mode>]` table in the config.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BindingConfig {
+    spawn: Option<String>,
+    action: Option<BuiltinAction>,
+    mode: Option<String>,
+    plugin: Option<String>,
+    #[serde(default)]
+    on: Trigger,
+    /// Repeats the action while the key is held
+    #[serde(default)]
+    repeat: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Binding {
+    pub chord: Chord,
+    pub action: KeyAction,
+    pub trigger: Trigger,
+    pub repeat: bool,
+}
+
+impl Binding {
+    fn parse(chord: &str, config: &BindingConfig) -> Result<Self, KeybindingError> {
+        let action = KeyAction::from_fields(
+            chord,
+            config.spawn.as_deref(),
+            config.action,
+            config.mode.as_deref(),
+            config.plugin.as_deref(),
+        )?;
+
+        Ok(Self {
+            chord: chord.parse()?,
+            action,
+            trigger: config.on,
+            repeat: config.repeat,
+        })
+    }
+}
+
+/// Bindings of every mode and the state of the keys that triggered them.
+#[derive(Debug)]
+pub struct Keybindings {
+    modes: HashMap<String, Vec<Binding>>,
+    mode: String,
+    /// Keys whose press was taken by a binding, with the binding their release triggers.
+    /// Their release is never forwarded to clients.
+    held: HashMap<Keycode, Option<Binding>>,
+    repeat: Option<RegistrationToken>,
+}
+
+impl Default for Keybindings {
+    fn default() -> Self {
+        Self {
+            modes: HashMap::from([(DEFAULT_MODE.to_string(), Vec::new())]),
+            mode: DEFAULT_MODE.to_string(),
+            held: HashMap::new(),
+            repeat: None,
+        }
+    }
+}
+
+impl Keybindings {
+    /// Bindings from the `[keybindings]` config table, one table per mode.
+    pub fn parse(
+        config: &HashMap<String, HashMap<String, BindingConfig>>,
+    ) -> Result<Self, KeybindingError> {
+        let mut modes = HashMap::from([(DEFAULT_MODE.to_string(), Vec::new())]);
+        for (mode, bindings) in config {
+            let mut parsed = Vec::with_capacity(bindings.len());
+            for (chord, binding) in bindings {
+                if let Some(target) = &binding.mode
+                    && target != DEFAULT_MODE
+                    && !config.contains_key(target)
+                {
+                    return Err(KeybindingError::UnknownMode(chord.clone(), target.clone()));
+                }
+                parsed.push(Binding::parse(chord, binding)?);
+            }
+            modes.insert(mode.clone(), parsed);
+        }
+
+        Ok(Self {
+            modes,
+            ..Self::default()
+        })
+    }
+
+    /// Takes the bindings of `other`, staying in the current mode if it still exists.
+    pub fn replace_bindings(&mut self, other: Self) {
+        self.modes = other.modes;
+        if !self.modes.contains_key(&self.mode) {
+            self.mode = DEFAULT_MODE.to_string();
+        }
+    }
+
+    pub fn mode(&self) -> &str {
+        &self.mode
+    }
+
+    pub fn bindings(&self, mode: &str) -> &[Binding] {
+        self.modes.get(mode).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn enter_mode(&mut self, mode: &str) -> bool {
+        if !self.modes.contains_key(mode) {
+            return false;
+        }
+        mode.clone_into(&mut self.mode);
+        true
+    }
+
+    /// Decides whether a key event goes to the client, returns the binding to run.
+    ///
+    /// `keysyms` are the keysyms of the key without modifiers applied.
+    pub fn filter(
+        &mut self,
+        keycode: Keycode,
+        state: KeyState,
+        modifiers: Modifiers,
+        keysyms: &[Keysym],
+    ) -> FilterResult<Option<Binding>> {
+        if state == KeyState::Released {
+            return match self.held.remove(&keycode) {
+                Some(binding) => FilterResult::Intercept(binding),
+                None => FilterResult::Forward,
+            };
+        }
+
+        let binding = self.bindings(&self.mode).iter().find(|binding| {
+            binding.chord.modifiers == modifiers && keysyms.contains(&binding.chord.keysym)
+        });
+        let Some(binding) = binding.cloned() else {
+            return FilterResult::Forward;
+        };
+
+        match binding.trigger {
+            Trigger::Press => {
+                self.held.insert(keycode, None);
+                FilterResult::Intercept(Some(binding))
+            }
+            Trigger::Release => {
+                self.held.insert(keycode, Some(binding));
+                FilterResult::Intercept(None)
+            }
+        }
+    }
+}
+
+impl<B: Backend> App<B> {
+    /// Runs `binding` for the key event and starts or stops repeating it.
+    pub(crate) fn handle_keybinding(&mut self, state: KeyState, binding: Option<Binding>) {
+        // Any other key event ends the repetition
+        if let Some(token) = self.keybindings.repeat.take() {
+            self.handle.remove(token);
+        }
+
+        let Some(binding) = binding else {
+            return;
+        };
+        self.run_key_action(&binding.action);
+
+        // Follows the repeat settings of the keyboard, a zero rate disables repeating
+        let config = &self.input_state.keyboard_config;
+        if binding.repeat && state == KeyState::Pressed && config.repeat_rate > 0 {
+            let action = binding.action;
+            let timer = Timer::from_duration(Duration::from_millis(config.repeat_delay as u64));
+            let interval = Duration::from_millis(1000 / config.repeat_rate as u64);
+            self.keybindings.repeat = self
+                .handle
+                .insert_source(timer, move |_, (), data| {
+                    data.state.run_key_action(&action);
+                    TimeoutAction::ToDuration(interval)
+                })
+                .ok();
+        }
+    }
+
+    pub fn run_key_action(&mut self, action: &KeyAction) {
+        match action {
+            KeyAction::Spawn(command) => spawn(command),
+            KeyAction::Builtin(BuiltinAction::Quit) => self.loop_signal.stop(),
+            KeyAction::Builtin(BuiltinAction::ReloadPlugins) => {
+                for plugin in self.engine.get_plugin_list() {
+                    if let Err(err) = self.engine.restart_plugin(plugin.clone()) {
+                        log::error!("Unable to reload plugin {plugin}: {err}");
+                    }
+                }
+            }
+            KeyAction::Builtin(BuiltinAction::ReloadConfig) => {
+                if let Err(err) = self.reload_config() {
+                    log::error!("Unable to reload config: {err}");
+                }
+            }
+            KeyAction::EnterMode(mode) => {
+                if !self.keybindings.enter_mode(mode) {
+                    log::warn!("Unknown keybinding mode {mode}");
+                }
+            }
+            KeyAction::Plugin(name) => {
+                let Some(mut bindings) = self
+                    .engine
+                    .try_get_single_write_bindings::<Compositor>("compositor.window")
+                else {
+                    log::warn!("No window manager for the keybinding {name}");
+                    return;
+                };
+                let mut store = bindings.store();
+                if let Err(err) = bindings
+                    .fusion_compositor_wm_exports()
+                    .call_on_keybinding(&mut store, name)
+                {
+                    log::error!("Window manager failed to handle the keybinding {name}: {err:?}");
+                }
+            }
+        }
+    }
+}
+
+fn spawn(command: &str) {
+    match std::process::Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .spawn()
+    {
+        // Reaps the child once it exits
+        Ok(mut child) => {
+            std::thread::spawn(move || child.wait());
+        }
+        Err(err) => log::error!("Unable to spawn `{command}`: {err}"),
+    }
+}
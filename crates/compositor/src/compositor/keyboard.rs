@@ -0,0 +1,160 @@
+use fusion_socket_protocol::{
+    GetKeyboardResponse, Keyboard, SetKeyboardRequest, SetKeyboardResponse,
+};
+use serde::Deserialize;
+use smithay::input::keyboard::XkbConfig;
+
+use crate::compositor::{api::CompositorEvent, backend::Backend, control::Responder, state::App};
+
+/// Delay before a held key starts repeating, in milliseconds.
+pub const DEFAULT_REPEAT_DELAY: i32 = 200;
+/// Repeats per second of a held key.
+pub const DEFAULT_REPEAT_RATE: i32 = 25;
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum KeyboardError {
+    #[error("unable to compile a keymap for layout `{layout}`, variant `{variant}`")]
+    Keymap { layout: String, variant: String },
+    #[error("repeat rate and delay can't be negative")]
+    Repeat,
+}
+
+/// `[keyboard]` table of the config, empty fields use the xkb defaults.
+///
+/// ```toml
+/// [keyboard]
+/// layout = "us,ru"
+/// options = "grp:alt_shift_toggle"
+/// repeat-delay = 300
+/// repeat-rate = 30   # 0 disables the repetition
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct KeyboardConfig {
+    pub rules: String,
+    pub model: String,
+    /// Comma separated layouts, e.g. `us,ru`
+    pub layout: String,
+    /// Comma separated variants, one per layout
+    pub variant: String,
+    /// Comma separated xkb options, e.g. `grp:alt_shift_toggle,caps:escape`
+    pub options: Option<String>,
+    /// Milliseconds
+    pub repeat_delay: i32,
+    /// Repeats per second
+    pub repeat_rate: i32,
+}
+
+impl Default for KeyboardConfig {
+    fn default() -> Self {
+        Self {
+            rules: String::new(),
+            model: String::new(),
+            layout: String::new(),
+            variant: String::new(),
+            options: None,
+            repeat_delay: DEFAULT_REPEAT_DELAY,
+            repeat_rate: DEFAULT_REPEAT_RATE,
+        }
+    }
+}
+
+impl KeyboardConfig {
+    pub fn xkb_config(&self) -> XkbConfig<'_> {
+        XkbConfig {
+            rules: &self.rules,
+            model: &self.model,
+            layout: &self.layout,
+            variant: &self.variant,
+            options: self.options.clone(),
+        }
+    }
+}
+
+impl<B: Backend> App<B> {
+    /// Recompiles the keymap of the seat keyboard, the previous settings stay on errors.
+    pub fn apply_keyboard_config(&mut self, config: KeyboardConfig) -> Result<(), KeyboardError> {
+        if config.repeat_delay < 0 || config.repeat_rate < 0 {
+            return Err(KeyboardError::Repeat);
+        }
+
+        let keyboard = self.input_state.keyboard.clone();
+        keyboard
+            .set_xkb_config(self, config.xkb_config())
+            .map_err(|_| KeyboardError::Keymap {
+                layout: config.layout.clone(),
+                variant: config.variant.clone(),
+            })?;
+        keyboard.change_repeat_info(config.repeat_rate, config.repeat_delay);
+
+        self.input_state.keyboard_config = config;
+        self.update_keyboard_layout();
+        Ok(())
+    }
+
+    /// Human readable name of the active layout, e.g. `English (US)`.
+    pub fn active_keyboard_layout(&mut self) -> String {
+        let keyboard = self.input_state.keyboard.clone();
+        keyboard.with_xkb_state(self, |context| {
+            let xkb = context.xkb().lock().unwrap();
+            xkb.layout_name(xkb.active_layout()).to_string()
+        })
+    }
+
+    /// Emits `layout-changed` when the active layout differs from the last one seen.
+    pub(crate) fn update_keyboard_layout(&mut self) {
+        let layout = self.active_keyboard_layout();
+        let mut globals = self.globals();
+        if globals.keyboard_layout != layout {
+            globals.keyboard_layout.clone_from(&layout);
+            globals
+                .events
+                .push(CompositorEvent::LayoutChanged { layout });
+        }
+    }
+
+    pub(crate) fn get_keyboard(&mut self, responder: &mut Responder<'_>) {
+        let active_layout = self.active_keyboard_layout();
+        let config = &self.input_state.keyboard_config;
+        let keyboard = Keyboard {
+            layout: config.layout.clone(),
+            variant: config.variant.clone(),
+            options: config.options.clone().unwrap_or_default(),
+            repeat_delay: config.repeat_delay,
+            repeat_rate: config.repeat_rate,
+            active_layout,
+        };
+
+        let response = GetKeyboardResponse::Ok(keyboard);
+        responder.send(&response);
+    }
+
+    pub(crate) fn set_keyboard(
+        &mut self,
+        request: SetKeyboardRequest,
+        responder: &mut Responder<'_>,
+    ) {
+        let mut config = self.input_state.keyboard_config.clone();
+        if let Some(layout) = request.layout {
+            config.layout = layout;
+        }
+        if let Some(variant) = request.variant {
+            config.variant = variant;
+        }
+        if let Some(options) = request.options {
+            config.options = Some(options).filter(|options| !options.is_empty());
+        }
+        if let Some(delay) = request.repeat_delay {
+            config.repeat_delay = delay;
+        }
+        if let Some(rate) = request.repeat_rate {
+            config.repeat_rate = rate;
+        }
+
+        let response = match self.apply_keyboard_config(config) {
+            Ok(()) => SetKeyboardResponse::Ok,
+            Err(err) => SetKeyboardResponse::Error(err.to_string()),
+        };
+        responder.send(&response);
+    }
+}
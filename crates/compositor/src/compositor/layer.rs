@@ -0,0 +1,265 @@
+use std::time::Duration;
+
+use smithay::{
+    backend::renderer::{
+        ImportAll, ImportMem, Renderer,
+        element::{
+            AsRenderElements, RenderElement, RenderElementStates,
+            surface::WaylandSurfaceRenderElement,
+        },
+    },
+    delegate_layer_shell,
+    desktop::{
+        LayerSurface, Space, Window, WindowSurfaceType, layer_map_for_output,
+        utils::{
+            OutputPresentationFeedback, surface_presentation_feedback_flags_from_states,
+            surface_primary_scanout_output,
+        },
+    },
+    output::Output,
+    reexports::wayland_server::protocol::{wl_output::WlOutput, wl_surface::WlSurface},
+    utils::{Logical, Point, Rectangle, Scale},
+    wayland::{
+        compositor::with_states,
+        shell::{
+            wlr_layer::{
+                KeyboardInteractivity, Layer, LayerSurface as WlrLayerSurface, LayerSurfaceData,
+                WlrLayerShellHandler, WlrLayerShellState,
+            },
+            xdg::PopupSurface,
+        },
+    },
+};
+
+use crate::compositor::{
+    api::{CompositorEvent, CompositorGlobals},
+    backend::Backend,
+    state::App,
+    udev::TestRenderElement,
+};
+
+/// Layers drawn above the windows, topmost first.
+pub const UPPER_LAYERS: [Layer; 2] = [Layer::Overlay, Layer::Top];
+/// Layers drawn below the windows, topmost first.
+pub const LOWER_LAYERS: [Layer; 2] = [Layer::Bottom, Layer::Background];
+
+/// Part of the output not covered by exclusive zones, in global coordinates.
+pub fn usable_area(space: &Space<Window>, output: &Output) -> Option<Rectangle<i32, Logical>> {
+    let output_geo = space.output_geometry(output)?;
+    let mut zone = layer_map_for_output(output).non_exclusive_zone();
+    zone.loc += output_geo.loc;
+    Some(zone)
+}
+
+fn output_of(space: &Space<Window>, surface: &WlSurface) -> Option<Output> {
+    space
+        .outputs()
+        .find(|output| {
+            layer_map_for_output(output)
+                .layer_for_surface(surface, WindowSurfaceType::TOPLEVEL)
+                .is_some()
+        })
+        .cloned()
+}
+
+/// Arranges the layers of the output the committed layer surface is on.
+///
+/// Returns the surface if it wants exclusive keyboard focus.
+pub fn handle_commit(globals: &mut CompositorGlobals, surface: &WlSurface) -> Option<WlSurface> {
+    let output = output_of(&globals.space, surface)?;
+
+    let initial_configure_sent = with_states(surface, |states| {
+        states
+            .data_map
+            .get::<LayerSurfaceData>()
+            .unwrap()
+            .lock()
+            .unwrap()
+            .initial_configure_sent
+    });
+
+    let mut map = layer_map_for_output(&output);
+    let zone = map.non_exclusive_zone();
+    map.arrange();
+    let layer = map
+        .layer_for_surface(surface, WindowSurfaceType::TOPLEVEL)?
+        .clone();
+
+    if !initial_configure_sent {
+        layer.layer_surface().send_configure();
+    }
+
+    if map.non_exclusive_zone() != zone {
+        globals.events.push(CompositorEvent::UsableAreaChanged {
+            output: output.name(),
+        });
+    }
+
+    let exclusive = UPPER_LAYERS.contains(&layer.layer())
+        && layer.cached_state().keyboard_interactivity == KeyboardInteractivity::Exclusive;
+    exclusive.then(|| layer.wl_surface().clone())
+}
+
+/// Surface of an upper layer asking for exclusive keyboard focus.
+pub fn exclusive_focus(space: &Space<Window>) -> Option<WlSurface> {
+    space.outputs().find_map(|output| {
+        let map = layer_map_for_output(output);
+        UPPER_LAYERS.iter().find_map(|layer| {
+            map.layers_on(*layer)
+                .rev()
+                .find(|surface| {
+                    surface.cached_state().keyboard_interactivity
+                        == KeyboardInteractivity::Exclusive
+                })
+                .map(|surface| surface.wl_surface().clone())
+        })
+    })
+}
+
+/// Layer surface on `layers` under `pos`, with the surface and its global location.
+pub fn layer_under(
+    space: &Space<Window>,
+    pos: Point<f64, Logical>,
+    layers: &[Layer],
+) -> Option<(LayerSurface, WlSurface, Point<f64, Logical>)> {
+    let output = space.output_under(pos).next()?;
+    let output_geo = space.output_geometry(output)?;
+    let map = layer_map_for_output(output);
+    let local = pos - output_geo.loc.to_f64();
+
+    layers.iter().find_map(|layer| {
+        let surface = map.layer_under(*layer, local)?;
+        let location = map.layer_geometry(surface)?.loc;
+        surface
+            .surface_under(local - location.to_f64(), WindowSurfaceType::ALL)
+            .map(|(under, offset)| {
+                let global = (offset + location + output_geo.loc).to_f64();
+                (surface.clone(), under, global)
+            })
+    })
+}
+
+/// Render elements of the layer surfaces on `layers`, topmost first.
+pub fn layer_render_elements<R, E>(
+    renderer: &mut R,
+    output: &Output,
+    layers: &[Layer],
+) -> Vec<TestRenderElement<R, E>>
+where
+    R: Renderer + ImportAll + ImportMem,
+    R::TextureId: Clone + 'static,
+    E: RenderElement<R>,
+{
+    let scale = Scale::from(output.current_scale().fractional_scale());
+    let map = layer_map_for_output(output);
+
+    let mut elements = Vec::new();
+    for layer in layers {
+        for surface in map.layers_on(*layer).rev() {
+            let Some(geometry) = map.layer_geometry(surface) else {
+                continue;
+            };
+            elements.extend(
+                surface
+                    .render_elements::<WaylandSurfaceRenderElement<R>>(
+                        renderer,
+                        geometry.loc.to_physical_precise_round(scale),
+                        scale,
+                        1.0,
+                    )
+                    .into_iter()
+                    .map(TestRenderElement::from),
+            );
+        }
+    }
+
+    elements
+}
+
+pub fn send_frames(output: &Output, time: Duration) {
+    for layer in layer_map_for_output(output).layers() {
+        layer.send_frame(output, time, Some(Duration::ZERO), |_, _| {
+            Some(output.clone())
+        });
+    }
+}
+
+pub fn take_presentation_feedback(
+    output: &Output,
+    feedback: &mut OutputPresentationFeedback,
+    render_element_states: &RenderElementStates,
+) {
+    for layer in layer_map_for_output(output).layers() {
+        layer.take_presentation_feedback(feedback, surface_primary_scanout_output, |surface, _| {
+            surface_presentation_feedback_flags_from_states(surface, render_element_states)
+        });
+    }
+}
+
+delegate_layer_shell!(@<B: Backend + 'static> App<B>);
+impl<B: Backend + 'static> WlrLayerShellHandler for App<B> {
+    fn shell_state(&mut self) -> &mut WlrLayerShellState {
+        &mut self.layer_shell_state
+    }
+
+    fn new_layer_surface(
+        &mut self,
+        surface: WlrLayerSurface,
+        output: Option<WlOutput>,
+        _layer: Layer,
+        namespace: String,
+    ) {
+        let globals = self.globals();
+        let output = output.as_ref().and_then(Output::from_resource).or_else(|| {
+            let focused = globals.workspaces.focused_output();
+            let mut outputs = globals.space.outputs();
+            match focused {
+                Some(name) => outputs.find(|output| output.name() == name).cloned(),
+                None => outputs.next().cloned(),
+            }
+        });
+
+        let Some(output) = output else {
+            log::warn!("No output for layer surface {namespace}");
+            surface.send_close();
+            return;
+        };
+
+        let layer = LayerSurface::new(surface, namespace);
+        if let Err(err) = layer_map_for_output(&output).map_layer(&layer) {
+            log::warn!("Unable to map layer surface {}: {err}", layer.namespace());
+        }
+    }
+
+    fn new_popup(&mut self, _parent: WlrLayerSurface, popup: PopupSurface) {
+        if !popup.is_initial_configure_sent() {
+            // The xdg-shell handler tracks the popup, it only needs its geometry
+            popup.with_pending_state(|state| {
+                state.geometry = state.positioner.get_geometry();
+            });
+        }
+    }
+
+    fn layer_destroyed(&mut self, surface: WlrLayerSurface) {
+        let mut globals = self.globals();
+        let outputs = globals.space.outputs().cloned().collect::<Vec<_>>();
+        for output in outputs {
+            let mut map = layer_map_for_output(&output);
+            let Some(layer) = map
+                .layers()
+                .find(|layer| layer.layer_surface() == &surface)
+                .cloned()
+            else {
+                continue;
+            };
+
+            let zone = map.non_exclusive_zone();
+            map.unmap_layer(&layer);
+            if map.non_exclusive_zone() != zone {
+                globals.events.push(CompositorEvent::UsableAreaChanged {
+                    output: output.name(),
+                });
+            }
+        }
+    }
+}
@@ -1,14 +1,32 @@
+pub mod animation;
 pub mod api;
 pub mod backend;
+pub mod config;
+pub mod control;
 pub mod cursor;
 pub mod data;
+pub mod decoration;
+pub mod effect;
+pub mod gesture;
 pub mod grabs;
+#[cfg(feature = "headless")]
+pub mod headless;
 pub mod input;
+pub mod input_device;
+pub mod keybinding;
+pub mod keyboard;
+pub mod layer;
 pub mod output;
+pub mod pointer_constraint;
+pub mod render;
 pub mod state;
+pub mod tablet;
 pub mod udev;
+pub mod virtual_input;
 pub mod window;
+pub mod workspace;
 
+use std::path::PathBuf;
 use std::sync::Arc;
 
 use calloop::{LoopHandle, LoopSignal};
@@ -22,12 +40,46 @@ use smithay::wayland::compositor::{CompositorClientState, CompositorState};
 use wayland_server::backend::{ClientData, ClientId, DisconnectReason};
 
 use crate::compositor::backend::Backend;
+use crate::compositor::input::SpecialActions;
 use crate::compositor::state::App;
 
-pub fn init_compositor<B: Backend + 'static>(
+/// How the compositor is exposed to clients and plugins.
+pub struct InitOptions {
+    /// Accept Wayland clients on a `wayland-N` socket and export it as `WAYLAND_DISPLAY`.
+    pub wayland_socket: bool,
+    /// Path of the control socket used by fusionctl, by default it is named after the Wayland
+    /// display in `$XDG_RUNTIME_DIR`. With a Wayland socket it is exported as `FUSION_SOCKET`.
+    pub control_socket: Option<PathBuf>,
+    /// Load the installed plugins on startup and watch for new ones.
+    pub load_plugins: bool,
+    /// Path of `compositor.toml`, the defaults are used when it does not exist.
+    pub config_file: PathBuf,
+}
+
+impl Default for InitOptions {
+    fn default() -> Self {
+        Self {
+            wayland_socket: true,
+            control_socket: None,
+            load_plugins: true,
+            config_file: config::Config::default_path(),
+        }
+    }
+}
+
+pub fn init_compositor<B: Backend + SpecialActions + 'static>(
+    loop_handle: LoopHandle<'static, data::Data<B>>,
+    signal: LoopSignal,
+    backend: B,
+) -> Result<data::Data<B>, Box<dyn std::error::Error>> {
+    init_compositor_with(loop_handle, signal, backend, &InitOptions::default())
+}
+
+pub fn init_compositor_with<B: Backend + SpecialActions + 'static>(
     loop_handle: LoopHandle<'static, data::Data<B>>,
     signal: LoopSignal,
     backend: B,
+    options: &InitOptions,
 ) -> Result<data::Data<B>, Box<dyn std::error::Error>> {
     // Структура которая используется для хранения состояния композитора
     // и управления Бэкендом для отправки событий и получения запросов.
@@ -39,24 +91,28 @@ pub fn init_compositor<B: Backend + 'static>(
 
     // Wayland ListeningSocket который реализует calloop::EventSource и может быть использован в качестве источника в EventLoop.
     // Клиенты Wayland должны подключаться к этому сокету для получения событий и отправки запросов.
-    let socket = ListeningSocketSource::new_auto()?;
-    let socket_name = socket.socket_name().to_os_string();
-
-    println!("Socket: {}", socket_name.display());
-
-    unsafe { std::env::set_var("WAYLAND_DISPLAY", &socket_name) };
-
-    // Добавляем сокет Wayland к циклу событий
-    // Цикл событий потребляет источник (сокет), затем замыкание, которые производит событие, метаданные и клиентские данные.
-    // Событие в этом примере это UnixStream созданный сокетом,
-    // без метаданных и клиентских данных которые были определены когда создали переменную event_loop
-    loop_handle.insert_source(socket, |stream, (), data| {
-        // Вставляем нового клиента в Display вместе с данными связанными с этим клиентом.
-        // Это запустит управление клиентом через UnixStream
-        data.display
-            .insert_client(stream, Arc::new(ClientState::default()))
-            .unwrap();
-    })?;
+    let mut display_name = None;
+    if options.wayland_socket {
+        let socket = ListeningSocketSource::new_auto()?;
+        let socket_name = socket.socket_name().to_os_string();
+
+        println!("Socket: {}", socket_name.display());
+
+        unsafe { std::env::set_var("WAYLAND_DISPLAY", &socket_name) };
+        display_name = Some(socket_name.to_string_lossy().into_owned());
+
+        // Добавляем сокет Wayland к циклу событий
+        // Цикл событий потребляет источник (сокет), затем замыкание, которые производит событие, метаданные и клиентские данные.
+        // Событие в этом примере это UnixStream созданный сокетом,
+        // без метаданных и клиентских данных которые были определены когда создали переменную event_loop
+        loop_handle.insert_source(socket, |stream, (), data| {
+            // Вставляем нового клиента в Display вместе с данными связанными с этим клиентом.
+            // Это запустит управление клиентом через UnixStream
+            data.display
+                .insert_client(stream, Arc::new(ClientState::default()))
+                .unwrap();
+        })?;
+    }
 
     // Добавляем Display в цикл событий
     // Этот цикл событий может принять обобщенную структуру содержащую файловый дескриптор
@@ -83,8 +139,27 @@ pub fn init_compositor<B: Backend + 'static>(
         },
     )?;
 
+    let control_socket = match &options.control_socket {
+        Some(path) => path.clone(),
+        None => {
+            let runtime_dir =
+                std::env::var_os("XDG_RUNTIME_DIR").ok_or("XDG_RUNTIME_DIR is not set")?;
+            let display = display_name
+                .clone()
+                .unwrap_or_else(|| format!("headless-{}", std::process::id()));
+            fusion_socket_protocol::socket_path(runtime_dir, &display)
+        }
+    };
+    let listener = control::listen(&loop_handle, &control_socket)?;
+    if display_name.is_some() {
+        unsafe { std::env::set_var(fusion_socket_protocol::FUSION_SOCKET_ENV, &control_socket) };
+    }
+
     // Создаем состояние нашего композитора и передаём все глобальные объекты к которым мы будем обращаться
-    let state = App::init(&dh, backend, signal, loop_handle)?;
+    let mut state = App::init(&dh, backend, signal, loop_handle, options)?;
+    state
+        .control_connections
+        .set_listener(control_socket, listener);
 
     // Данные хранящиеся в цикле событий, мы должны получать доступ к дисплею и состоянию композитора.
     let data = data::Data {
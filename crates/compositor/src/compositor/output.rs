@@ -2,9 +2,39 @@ use std::collections::HashMap;
 
 use derive_more::Display;
 use drm::control::crtc;
-use smithay::output::Output;
+use fusion_socket_protocol::{
+    GetOutputsResponse, ModeRequest, OutputMode, SetOutputRequest, SetOutputResponse, Transform,
+};
+use smithay::{
+    desktop::layer_map_for_output,
+    output::{Mode, Output, Scale},
+    utils,
+};
 
-use crate::compositor::udev::UdevOutputState;
+use crate::compositor::{
+    api::CompositorEvent, backend::Backend, control::Responder, state::App, udev::UdevOutputState,
+};
+
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum OutputError {
+    #[error("output {0} does not exist")]
+    UnknownOutput(String),
+    #[error("output {output} has no {width}x{height} mode{refresh}")]
+    UnsupportedMode {
+        output: String,
+        width: i32,
+        height: i32,
+        refresh: String,
+    },
+    #[error("scale {0} is out of range, it has to be between 0.25 and 8")]
+    InvalidScale(f64),
+    #[error("at least one output has to stay enabled")]
+    LastOutput,
+    #[error("position {0},{1} puts the output out of range")]
+    InvalidPosition(i32, i32),
+    #[error("the backend refused the configuration: {0}")]
+    Backend(String),
+}
 
 #[derive(Default, Clone, Copy, PartialEq, Eq, Display)]
 pub enum RenderState {
@@ -17,6 +47,8 @@ pub enum RenderState {
 #[derive(Default)]
 pub struct OutputState {
     pub outputs: HashMap<Output, RenderState>,
+    /// Outputs turned off through the control socket, kept to turn them back on.
+    pub disabled: Vec<Output>,
 }
 
 impl OutputState {
@@ -54,3 +86,215 @@ impl OutputState {
         })
     }
 }
+
+impl<B: Backend> App<B> {
+    pub(crate) fn get_outputs(&self, responder: &mut Responder<'_>) {
+        let enabled = self
+            .output_state
+            .outputs
+            .keys()
+            .map(|output| (output, true));
+        let disabled = self
+            .output_state
+            .disabled
+            .iter()
+            .map(|output| (output, false));
+        let mut outputs = enabled
+            .chain(disabled)
+            .map(|(output, enabled)| {
+                let preferred = output.preferred_mode();
+                let mode = |mode: Mode| OutputMode {
+                    width: mode.size.w,
+                    height: mode.size.h,
+                    refresh: mode.refresh,
+                    preferred: preferred == Some(mode),
+                };
+                let properties = output.physical_properties();
+                let location = output.current_location();
+
+                fusion_socket_protocol::Output {
+                    name: output.name(),
+                    make: properties.make,
+                    model: properties.model,
+                    modes: self
+                        .backend
+                        .output_modes(output)
+                        .into_iter()
+                        .map(mode)
+                        .collect(),
+                    current_mode: output.current_mode().map(mode),
+                    scale: output.current_scale().fractional_scale(),
+                    transform: protocol_transform(output.current_transform()),
+                    x: location.x,
+                    y: location.y,
+                    enabled,
+                }
+            })
+            .collect::<Vec<_>>();
+        outputs.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let response = GetOutputsResponse::Ok(outputs);
+        responder.send(&response);
+    }
+
+    pub(crate) fn set_output(&mut self, request: SetOutputRequest, responder: &mut Responder<'_>) {
+        let response = match self.configure_output(request) {
+            Ok(()) => SetOutputResponse::Ok,
+            Err(error) => SetOutputResponse::Error(error.to_string()),
+        };
+
+        responder.send(&response);
+    }
+
+    /// Applies the settings of `request` the backend agrees to, nothing changes on errors.
+    pub fn configure_output(&mut self, request: SetOutputRequest) -> Result<(), OutputError> {
+        let name = &request.name;
+        let (output, enabled) = match self.output_state.outputs.keys().find(|o| o.name() == *name) {
+            Some(output) => (output.clone(), true),
+            None => match self
+                .output_state
+                .disabled
+                .iter()
+                .find(|o| o.name() == *name)
+            {
+                Some(output) => (output.clone(), false),
+                None => return Err(OutputError::UnknownOutput(name.clone())),
+            },
+        };
+
+        let mode = match request.mode {
+            Some(mode) => Some(self.find_mode(&output, mode)?),
+            None => None,
+        };
+        if let Some(scale) = request.scale
+            && !(0.25..=8.0).contains(&scale)
+        {
+            return Err(OutputError::InvalidScale(scale));
+        }
+        let enable = request.enabled.unwrap_or(enabled);
+        if enabled && !enable && self.output_state.outputs.len() == 1 {
+            return Err(OutputError::LastOutput);
+        }
+        if enabled != enable {
+            self.backend.set_output_enabled(&output, enable, true)?;
+        }
+
+        // The whole output has to fit in logical coordinates
+        let transform = request.transform.map(smithay_transform);
+        let scale = (request.scale).unwrap_or_else(|| output.current_scale().fractional_scale());
+        let location =
+            (request.position.map(Into::into)).unwrap_or_else(|| output.current_location());
+        let size = mode
+            .or(output.current_mode())
+            .map_or_else(Default::default, |mode| mode.size);
+        let size = (transform.unwrap_or_else(|| output.current_transform()))
+            .transform_size(size)
+            .to_f64()
+            .to_logical(scale)
+            .to_i32_ceil::<i32>();
+        if location.x.checked_add(size.w).is_none() || location.y.checked_add(size.h).is_none() {
+            return Err(OutputError::InvalidPosition(location.x, location.y));
+        }
+
+        let previous_mode = output.current_mode();
+        if let Some(mode) = mode {
+            self.backend.set_output_mode(&output, mode, request.test)?;
+        }
+        if request.test {
+            return Ok(());
+        }
+
+        if enabled != enable
+            && let Err(error) = self.backend.set_output_enabled(&output, enable, false)
+        {
+            if let (Some(_), Some(previous)) = (mode, previous_mode)
+                && let Err(error) = self.backend.set_output_mode(&output, previous, false)
+            {
+                log::warn!("Unable to restore the mode of {}: {error}", output.name());
+            }
+            return Err(error);
+        }
+
+        // An output turned back on returns to where it was unless it is moved
+        let mut position = request.position.map(Into::into);
+        if enabled && !enable {
+            self.unmap_output(&output);
+            self.output_state.disabled.push(output.clone());
+        } else if !enabled && enable {
+            position = position.or(Some(output.current_location()));
+            self.output_state
+                .disabled
+                .retain(|disabled| *disabled != output);
+            self.map_output(&output);
+            self.output_state.add_output(output.clone());
+        }
+
+        output.change_current_state(
+            mode,
+            transform,
+            request.scale.map(Scale::Fractional),
+            position,
+        );
+        if enable {
+            let mut globals = self.globals();
+            globals.space.map_output(&output, output.current_location());
+            layer_map_for_output(&output).arrange();
+            globals.events.push(CompositorEvent::UsableAreaChanged {
+                output: output.name(),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Mode of `output` closest to `request`, the fastest one without a refresh rate.
+    fn find_mode(&self, output: &Output, request: ModeRequest) -> Result<Mode, OutputError> {
+        let modes = self.backend.output_modes(output);
+        let sized = modes
+            .into_iter()
+            .filter(|mode| mode.size == (request.width, request.height).into());
+        let mode = match request.refresh {
+            // Refresh rates are rounded differently everywhere, a hertz of slack covers that
+            Some(refresh) => sized
+                .min_by_key(|mode| (mode.refresh - refresh).abs())
+                .filter(|mode| (mode.refresh - refresh).abs() <= 1000),
+            None => sized.max_by_key(|mode| mode.refresh),
+        };
+
+        mode.ok_or_else(|| OutputError::UnsupportedMode {
+            output: output.name(),
+            width: request.width,
+            height: request.height,
+            refresh: request
+                .refresh
+                .map(|refresh| format!(" at {:.3} Hz", f64::from(refresh) / 1000.0))
+                .unwrap_or_default(),
+        })
+    }
+}
+
+fn protocol_transform(transform: utils::Transform) -> Transform {
+    match transform {
+        utils::Transform::Normal => Transform::Normal,
+        utils::Transform::_90 => Transform::Rotate90,
+        utils::Transform::_180 => Transform::Rotate180,
+        utils::Transform::_270 => Transform::Rotate270,
+        utils::Transform::Flipped => Transform::Flipped,
+        utils::Transform::Flipped90 => Transform::Flipped90,
+        utils::Transform::Flipped180 => Transform::Flipped180,
+        utils::Transform::Flipped270 => Transform::Flipped270,
+    }
+}
+
+fn smithay_transform(transform: Transform) -> utils::Transform {
+    match transform {
+        Transform::Normal => utils::Transform::Normal,
+        Transform::Rotate90 => utils::Transform::_90,
+        Transform::Rotate180 => utils::Transform::_180,
+        Transform::Rotate270 => utils::Transform::_270,
+        Transform::Flipped => utils::Transform::Flipped,
+        Transform::Flipped90 => utils::Transform::Flipped90,
+        Transform::Flipped180 => utils::Transform::Flipped180,
+        Transform::Flipped270 => utils::Transform::Flipped270,
+    }
+}
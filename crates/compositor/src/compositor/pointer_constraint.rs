@@ -0,0 +1,102 @@
+use smithay::{
+    delegate_pointer_constraints,
+    input::pointer::PointerHandle,
+    reexports::wayland_server::protocol::wl_surface::WlSurface,
+    utils::{Logical, Point},
+    wayland::{
+        compositor::RegionAttributes,
+        pointer_constraints::{
+            PointerConstraint, PointerConstraintsHandler, with_pointer_constraint,
+        },
+    },
+};
+
+use crate::compositor::{backend::Backend, state::App};
+
+/// Constraint limiting the motion of the pointer.
+#[derive(Debug, Clone)]
+pub enum ActiveConstraint {
+    Locked,
+    /// Confined to the region or to the whole surface without one
+    Confined(Option<RegionAttributes>),
+}
+
+/// Active constraint of `surface`, `location` is relative to the surface.
+///
+/// Constraints only apply while the pointer is inside their region.
+pub fn active<B: Backend + 'static>(
+    surface: &WlSurface,
+    pointer: &PointerHandle<App<B>>,
+    location: Point<f64, Logical>,
+) -> Option<ActiveConstraint> {
+    with_pointer_constraint(surface, pointer, |constraint| {
+        let constraint = constraint.filter(|constraint| constraint.is_active())?;
+        if !constraint
+            .region()
+            .is_none_or(|region| region.contains(location.to_i32_round()))
+        {
+            return None;
+        }
+
+        Some(match &*constraint {
+            PointerConstraint::Locked(_) => ActiveConstraint::Locked,
+            PointerConstraint::Confined(confined) => {
+                ActiveConstraint::Confined(confined.region().cloned())
+            }
+        })
+    })
+}
+
+/// Activates the constraint of `surface` once the pointer is inside its region.
+///
+/// Leaving the surface deactivates it again.
+pub fn activate<B: Backend + 'static>(
+    surface: &WlSurface,
+    pointer: &PointerHandle<App<B>>,
+    location: Point<f64, Logical>,
+) {
+    with_pointer_constraint(surface, pointer, |constraint| {
+        if let Some(constraint) = constraint
+            && !constraint.is_active()
+            && constraint
+                .region()
+                .is_none_or(|region| region.contains(location.to_i32_round()))
+        {
+            constraint.activate();
+        }
+    });
+}
+
+delegate_pointer_constraints!(@<B: Backend + 'static> App<B>);
+impl<B: Backend + 'static> PointerConstraintsHandler for App<B> {
+    fn new_constraint(&mut self, surface: &WlSurface, pointer: &PointerHandle<Self>) {
+        // The pointer may already be over the surface
+        let location = self.input_state.cursor.location;
+        if let Some((under, origin)) = self.surface_under(location)
+            && &under == surface
+        {
+            activate(surface, pointer, location - origin);
+        }
+    }
+
+    fn cursor_position_hint(
+        &mut self,
+        surface: &WlSurface,
+        pointer: &PointerHandle<Self>,
+        location: Point<f64, Logical>,
+    ) {
+        if active(surface, pointer, location).is_none() {
+            return;
+        }
+        let Some((under, origin)) = self.surface_under(self.input_state.cursor.location) else {
+            return;
+        };
+        if &under != surface {
+            return;
+        }
+
+        let location = origin + location;
+        self.input_state.cursor.location = location;
+        pointer.set_location(location);
+    }
+}
@@ -0,0 +1,258 @@
+use smithay::{
+    backend::renderer::{
+        ImportAll, ImportMem, Renderer,
+        element::{
+            AsRenderElements, Kind, Wrap,
+            surface::{WaylandSurfaceRenderElement, render_elements_from_surface_tree},
+        },
+        gles::GlesRenderer,
+    },
+    desktop::{PopupManager, Window, space::SpaceRenderElements},
+    output::Output,
+    utils::{Point, Rectangle, Scale, Size},
+};
+
+use crate::compositor::{
+    api::{CompositorGlobals, WindowKey},
+    decoration::{has_server_side_decorations, render_decorations},
+    effect::{
+        ClippedSurfaceElement, WindowRenderElement, WindowStyle, clip_program, effect_elements,
+    },
+    layer::{LOWER_LAYERS, UPPER_LAYERS, layer_render_elements},
+    udev::TestRenderElement,
+};
+
+pub type OutputRenderElement = TestRenderElement<GlesRenderer, WindowRenderElement>;
+
+fn effect(element: WindowRenderElement) -> OutputRenderElement {
+    TestRenderElement::Space(SpaceRenderElements::Element(Wrap::from(element)))
+}
+
+/// Everything on `output` except the cursor, top to bottom.
+pub fn output_render_elements(
+    renderer: &mut GlesRenderer,
+    globals: &CompositorGlobals,
+    output: &Output,
+) -> Vec<OutputRenderElement> {
+    let mut elements = layer_render_elements(renderer, output, &UPPER_LAYERS);
+    elements.extend(window_render_elements(renderer, globals, output));
+    elements.extend(layer_render_elements(renderer, output, &LOWER_LAYERS));
+    elements
+}
+
+/// Same as [`output_render_elements`] for renderers without the GLES window effects,
+/// windows only keep their opacity.
+pub fn plain_output_render_elements<R>(
+    renderer: &mut R,
+    globals: &CompositorGlobals,
+    output: &Output,
+) -> Vec<TestRenderElement<R, WaylandSurfaceRenderElement<R>>>
+where
+    R: Renderer + ImportAll + ImportMem,
+    R::TextureId: Send + Clone + 'static,
+{
+    let mut elements = layer_render_elements(renderer, output, &UPPER_LAYERS);
+
+    let space = &globals.space;
+    if let Some(output_geo) = space.output_geometry(output) {
+        let scale = Scale::from(output.current_scale().fractional_scale());
+        for window in space.elements().rev() {
+            let Some(content) = space.element_geometry(window) else {
+                continue;
+            };
+
+            let decorated = has_server_side_decorations(window);
+            let bbox = if decorated {
+                globals.decorations.frame(content)
+            } else {
+                space.element_bbox(window).unwrap_or(content)
+            };
+            if !output_geo.overlaps(bbox) {
+                continue;
+            }
+
+            let alpha = window_alpha(globals, window);
+            let render_location = (content.loc - window.geometry().loc - output_geo.loc)
+                .to_physical_precise_round(scale);
+            elements.extend(
+                window
+                    .render_elements::<WaylandSurfaceRenderElement<R>>(
+                        renderer,
+                        render_location,
+                        scale,
+                        alpha,
+                    )
+                    .into_iter()
+                    .map(TestRenderElement::from),
+            );
+
+            if decorated {
+                let content = Rectangle::new(content.loc - output_geo.loc, content.size);
+                elements.extend(render_decorations(
+                    renderer,
+                    &globals.decorations,
+                    window,
+                    content,
+                    scale,
+                    alpha,
+                ));
+            }
+        }
+    }
+
+    elements.extend(layer_render_elements(renderer, output, &LOWER_LAYERS));
+    elements
+}
+
+fn window_style(globals: &CompositorGlobals, window: &Window) -> WindowStyle {
+    window
+        .user_data()
+        .get::<WindowKey>()
+        .map_or_else(WindowStyle::default, |key| {
+            globals.styles.style(*key, window)
+        })
+}
+
+/// Opacity of the window's style combined with the one of its animation.
+fn window_alpha(globals: &CompositorGlobals, window: &Window) -> f32 {
+    let animation = window
+        .user_data()
+        .get::<WindowKey>()
+        .map_or(1.0, |key| globals.animations.opacity(*key));
+    window_style(globals, window).opacity.clamp(0.0, 1.0) * animation
+}
+
+/// Window render elements for `output`, top to bottom,
+/// with server-side decorations, borders and shadows placed right under their window.
+fn window_render_elements(
+    renderer: &mut GlesRenderer,
+    globals: &CompositorGlobals,
+    output: &Output,
+) -> Vec<OutputRenderElement> {
+    let space = &globals.space;
+    let Some(output_geo) = space.output_geometry(output) else {
+        return Vec::new();
+    };
+    let scale = Scale::from(output.current_scale().fractional_scale());
+    let viewport = output
+        .current_mode()
+        .map(|mode| mode.size)
+        .unwrap_or_default();
+
+    let mut elements = Vec::new();
+    for window in space.elements().rev() {
+        let Some(content) = space.element_geometry(window) else {
+            continue;
+        };
+
+        let style = window_style(globals, window);
+
+        let decorated = has_server_side_decorations(window);
+        let frame = if decorated {
+            globals.decorations.frame(content)
+        } else {
+            content
+        };
+        // Rounded corners would cut into the title bar
+        let radius = if decorated { 0 } else { style.corner_radius };
+
+        let margin = style.border_width
+            + style.shadow.map_or(0, |shadow| {
+                shadow.blur_radius + shadow.offset.x.abs().max(shadow.offset.y.abs())
+            });
+        let bbox = if decorated {
+            frame
+        } else {
+            space.element_bbox(window).unwrap_or(content)
+        };
+        let bbox = Rectangle::new(
+            bbox.loc - Point::from((margin, margin)),
+            bbox.size + Size::from((margin * 2, margin * 2)),
+        );
+        if !output_geo.overlaps(bbox) {
+            continue;
+        }
+
+        let alpha = window_alpha(globals, window);
+        let render_location =
+            (content.loc - window.geometry().loc - output_geo.loc).to_physical_precise_round(scale);
+        let content = Rectangle::new(content.loc - output_geo.loc, content.size);
+        let clip = window
+            .toplevel()
+            .filter(|_| radius > 0)
+            .zip(clip_program(renderer));
+        match clip {
+            Some((toplevel, program)) => {
+                // Popups are allowed outside of the window geometry, only the window itself is clipped
+                let surface = toplevel.wl_surface();
+                for (popup, offset) in PopupManager::popups_for_surface(surface) {
+                    let offset = (window.geometry().loc + offset - popup.geometry().loc)
+                        .to_physical_precise_round(scale);
+                    elements.extend(
+                        render_elements_from_surface_tree::<_, WaylandSurfaceRenderElement<_>>(
+                            renderer,
+                            popup.wl_surface(),
+                            render_location + offset,
+                            scale,
+                            alpha,
+                            Kind::Unspecified,
+                        )
+                        .into_iter()
+                        .map(TestRenderElement::from),
+                    );
+                }
+
+                let geometry = content.to_physical_precise_round(scale);
+                let radius = (f64::from(radius) * scale.x) as f32;
+                let surfaces = render_elements_from_surface_tree(
+                    renderer,
+                    surface,
+                    render_location,
+                    scale,
+                    alpha,
+                    Kind::Unspecified,
+                );
+                elements.extend(surfaces.into_iter().map(|surface| {
+                    effect(WindowRenderElement::Clipped(ClippedSurfaceElement::new(
+                        surface,
+                        program.clone(),
+                        geometry,
+                        radius,
+                        viewport,
+                    )))
+                }));
+            }
+            None => elements.extend(
+                window
+                    .render_elements::<WaylandSurfaceRenderElement<GlesRenderer>>(
+                        renderer,
+                        render_location,
+                        scale,
+                        alpha,
+                    )
+                    .into_iter()
+                    .map(TestRenderElement::from),
+            ),
+        }
+
+        if decorated {
+            elements.extend(render_decorations(
+                renderer,
+                &globals.decorations,
+                window,
+                content,
+                scale,
+                alpha,
+            ));
+        }
+
+        let frame = Rectangle::new(frame.loc - output_geo.loc, frame.size);
+        elements.extend(
+            effect_elements(renderer, window, &style, frame, radius, alpha)
+                .into_iter()
+                .map(|element| effect(WindowRenderElement::Shader(element))),
+        );
+    }
+
+    elements
+}
@@ -4,16 +4,20 @@ use std::{
     cell::RefCell,
     collections::HashMap,
     io::{Read, Write},
-    os::unix::net::{UnixListener, UnixStream},
+    os::unix::net::UnixListener,
+    path::{Path, PathBuf},
     rc::Rc,
     sync::{Arc, Mutex, MutexGuard},
+    time::Duration,
 };
 
 use ::drm::control::crtc;
-use calloop::LoopHandle;
+use calloop::{LoopHandle, RegistrationToken};
 use fusion_socket_protocol::{
-    CompositorRequest, ExitResponse, FUSION_CTL_SOCKET_DEFAULT, GetPluginListResponse,
-    PingResponse, Plugin, RestartPluginResponse,
+    CallPluginRequest, CallPluginResponse, Event, ExitResponse, Geometry, GetPluginInfoResponse,
+    GetPluginListResponse, GetWindowsResponse, GetWorkspacesResponse, InstallPluginResponse,
+    PingResponse, Plugin, PluginInfo, RestartPluginResponse, SetPluginEnabledResponse,
+    UninstallPluginResponse, WindowAction, WindowActionRequest, WindowActionResponse, Workspace,
 };
 use slotmap::SlotMap;
 use smithay::{
@@ -22,7 +26,8 @@ use smithay::{
         renderer::{element::RenderElement, utils::on_commit_buffer_handler},
         session::libseat::LibSeatSession,
     },
-    delegate_compositor, delegate_data_device, delegate_dmabuf, delegate_output, delegate_seat,
+    delegate_compositor, delegate_cursor_shape, delegate_data_device, delegate_dmabuf,
+    delegate_output, delegate_pointer_gestures, delegate_relative_pointer, delegate_seat,
     delegate_shm, delegate_xdg_decoration, delegate_xdg_shell,
     desktop::{
         PopupKind, PopupManager, Space, Window, find_popup_root_surface, get_popup_toplevel_coords,
@@ -41,49 +46,64 @@ use smithay::{
         },
         x11rb::protocol::xproto::RESIZE_REQUEST_EVENT,
     },
-    utils::{Clock, Logical, Monotonic, Physical, Point, Rectangle, Serial},
+    utils::{Clock, Logical, Monotonic, Physical, Point, Rectangle, SERIAL_COUNTER, Serial},
     wayland::{
         buffer::BufferHandler,
         compositor::{
             CompositorClientState, CompositorHandler, CompositorState, get_parent,
             is_sync_subsurface, with_states,
         },
+        cursor_shape::CursorShapeManagerState,
         dmabuf::DmabufHandler,
         input_method::InputMethodHandler,
         output::{OutputHandler, OutputManagerState},
+        pointer_constraints::PointerConstraintsState,
+        pointer_gestures::PointerGesturesState,
+        relative_pointer::RelativePointerManagerState,
         selection::{
             SelectionHandler,
             data_device::{
                 ClientDndGrabHandler, DataDeviceHandler, DataDeviceState, ServerDndGrabHandler,
             },
         },
+        shell::wlr_layer::WlrLayerShellState,
         shell::xdg::{
             PopupSurface, PositionerState, ToplevelSurface, XdgShellHandler, XdgShellState,
             XdgToplevelSurfaceData,
             decoration::{XdgDecorationHandler, XdgDecorationState},
         },
         shm::{ShmHandler, ShmState},
+        tablet_manager::TabletManagerState,
     },
 };
 use smithay_drm_extras::drm_scanner;
 use wayland_server::{
     Client, DisplayHandle, Resource,
-    backend::ObjectId,
+    backend::{DisconnectReason, ObjectId},
     protocol::{wl_seat::WlSeat, wl_surface::WlSurface},
 };
 use zip::unstable::stream;
 
 use crate::compositor::{
-    ClientState,
+    ClientState, InitOptions,
     api::{
-        CompositorContext, CompositorContextFactory, CompositorGlobals, UnsafeCompositorGlobals,
-        WindowKey,
-        general::{Compositor, GeneralCapabilityProvider, fusion::compositor::types::WindowId},
+        CompositorContext, CompositorContextFactory, CompositorEvent, CompositorGlobals,
+        UnsafeCompositorGlobals, WindowKey,
+        general::{
+            Compositor, GeneralCapabilityProvider,
+            fusion::compositor::types::{WindowId, WorkspaceId},
+        },
     },
     backend::Backend,
+    config::{Config, DebugConfig},
+    control::{ControlConnections, Responder},
     cursor::InputState,
-    data,
+    data, decoration,
+    gesture::Gestures,
     grabs::{MoveSurfaceGrab, ResizeSurfaceGrab, resize_grab},
+    input::SpecialActions,
+    keybinding::Keybindings,
+    layer,
     output::OutputState,
     udev::UdevOutputState,
 };
@@ -92,13 +112,16 @@ use plugin_engine::{
     InnerContextFactory, PluginEngine, loader::LoaderConfig, table::CapabilityWriteRules,
 };
 
+/// Time a plugin gets to run its `stop` export on exit.
+const PLUGIN_STOP_TIMEOUT: Duration = Duration::from_secs(2);
+
 pub struct App<B: Backend + 'static> {
     pub loop_signal: LoopSignal,
     pub handle: LoopHandle<'static, data::Data<B>>,
     pub backend: B,
     pub display: DisplayHandle,
 
-    pub socket: UnixListener,
+    pub control_connections: ControlConnections,
     pub engine: PluginEngine<CompositorContext>,
 
     pub compositor_state: CompositorState,
@@ -109,6 +132,12 @@ pub struct App<B: Backend + 'static> {
     pub output_manager_state: OutputManagerState,
     pub xdg_shell_state: XdgShellState,
     pub xdg_decoration_state: XdgDecorationState,
+    pub layer_shell_state: WlrLayerShellState,
+    pub pointer_gestures_state: PointerGesturesState,
+    pub relative_pointer_state: RelativePointerManagerState,
+    pub pointer_constraints_state: PointerConstraintsState,
+    pub cursor_shape_state: CursorShapeManagerState,
+    pub tablet_manager_state: TabletManagerState,
 
     pub popups: PopupManager,
 
@@ -117,10 +146,17 @@ pub struct App<B: Backend + 'static> {
     pub clock: Clock<Monotonic>,
 
     pub sleep: bool,
+    /// Wakes the loop up while animations wait for clients.
+    pub animation_timer: Option<RegistrationToken>,
 
     //Input
     pub input_state: InputState<B>,
     pub output_state: OutputState,
+
+    pub config_file: PathBuf,
+    pub keybindings: Keybindings,
+    pub gestures: Gestures,
+    pub debug: DebugConfig,
 }
 
 impl<B: Backend> App<B> {
@@ -128,18 +164,40 @@ impl<B: Backend> App<B> {
         self.globals.lock().unwrap()
     }
 
-    pub fn exit(stream: &mut UnixStream) {
-        let response_data = postcard::to_stdvec_cobs(&ExitResponse).unwrap();
-        stream.write_all(&response_data).unwrap();
-        std::process::exit(0);
+    /// Stops the event loop, the caller of `run` finishes with [`App::shutdown`].
+    pub fn exit(&self, responder: &mut Responder<'_>) {
+        responder.send(&ExitResponse);
+        self.loop_signal.stop();
     }
 
-    fn ping(stream: &mut UnixStream) {
-        let response_data = postcard::to_stdvec_cobs(&PingResponse).unwrap();
-        stream.write_all(&response_data).unwrap();
+    /// Ends the session once the event loop stopped. Plugins get to run their `stop` export,
+    /// then the control socket and the Wayland clients are closed and the backend lets go of
+    /// its devices.
+    pub fn shutdown(&mut self) {
+        self.engine
+            .shutdown::<Compositor>(PLUGIN_STOP_TIMEOUT, |compositor, store| {
+                compositor.call_stop(store)
+            });
+        self.close_control_socket();
+
+        let handle = self.display.backend_handle();
+        let mut clients = Vec::new();
+        handle.with_all_clients(|client| clients.push(client));
+        for client in clients {
+            handle.kill_client(client, DisconnectReason::ConnectionClosed);
+        }
+        if let Err(error) = self.display.flush_clients() {
+            log::debug!("Unable to flush the Wayland clients: {error}");
+        }
+
+        self.backend.shutdown();
     }
 
-    fn get_plugin_list(&self, stream: &mut UnixStream) {
+    pub(crate) fn ping(responder: &mut Responder<'_>) {
+        responder.send(&PingResponse);
+    }
+
+    pub(crate) fn get_plugin_list(&self, responder: &mut Responder<'_>) {
         let mut plugins = Vec::new();
         for plugin_id in self.engine.get_plugin_list() {
             let plugin = self.engine.get_plugin_env_by_id(&plugin_id).unwrap();
@@ -152,48 +210,329 @@ impl<B: Backend> App<B> {
         }
 
         let response = GetPluginListResponse::Ok(plugins);
-        let response_data = postcard::to_stdvec_cobs(&response).unwrap();
-        stream.write_all(&response_data).unwrap();
+        responder.send(&response);
     }
 
-    fn restart_plugin(&mut self, plugin_id: &str, stream: &mut UnixStream) {
+    pub(crate) fn restart_plugin(&mut self, plugin_id: &str, responder: &mut Responder<'_>) {
         let response = match self.engine.restart_plugin(plugin_id) {
             Ok(status) => RestartPluginResponse::Ok,
-            Err(error) => match error {
-                plugin_engine::Error::PluginNotFound(message) => {
-                    RestartPluginResponse::Error(message)
+            Err(error) => RestartPluginResponse::Error(error.to_string()),
+        };
+
+        responder.send(&response);
+    }
+
+    pub(crate) fn get_plugin_info(&self, plugin_id: &str, responder: &mut Responder<'_>) {
+        let response = match self.engine.get_plugin_env_by_id(&plugin_id.into()) {
+            Some(plugin) => {
+                let manifest = plugin.manifest();
+                let authors = (manifest.authors().iter())
+                    .map(|author| match author.email() {
+                        Some(email) => format!("{} <{email}>", author.name()),
+                        None => author.name().to_string(),
+                    })
+                    .collect();
+                GetPluginInfoResponse::Ok(Box::new(PluginInfo {
+                    id: plugin_id.to_string(),
+                    name: manifest.name().to_string(),
+                    version: manifest.version().to_string(),
+                    description: manifest.description().to_string(),
+                    repository: manifest.repository().to_string(),
+                    authors,
+                    capabilities: manifest.capabilities().to_vec(),
+                    custom_capabilities: manifest.custom_capabilities().to_vec(),
+                    status: plugin.status().to_string(),
+                    failure: plugin.failure_reason().map(str::to_string),
+                    path: plugin.path().display().to_string(),
+                    log_path: PluginEngine::<CompositorContext>::log_path(manifest)
+                        .display()
+                        .to_string(),
+                }))
+            }
+            None => GetPluginInfoResponse::Error(format!("plugin {plugin_id} not found")),
+        };
+
+        responder.send(&response);
+    }
+
+    pub(crate) fn install_plugin(&mut self, path: &str, responder: &mut Responder<'_>) {
+        let response = match self.engine.install_plugin(Path::new(path)) {
+            Ok(plugin_id) => InstallPluginResponse::Ok(plugin_id.to_string()),
+            Err(error) => InstallPluginResponse::Error(error.to_string()),
+        };
+
+        responder.send(&response);
+    }
+
+    pub(crate) fn uninstall_plugin(&mut self, plugin_id: &str, responder: &mut Responder<'_>) {
+        let response = match self.engine.uninstall_plugin(plugin_id) {
+            Ok(()) => UninstallPluginResponse::Ok,
+            Err(error) => UninstallPluginResponse::Error(error.to_string()),
+        };
+
+        responder.send(&response);
+    }
+
+    pub(crate) fn call_plugin(
+        &mut self,
+        request: CallPluginRequest,
+        responder: &mut Responder<'_>,
+    ) {
+        let response = if self.debug.call_plugin {
+            match serde_json::from_str::<Vec<serde_json::Value>>(&request.args) {
+                Ok(args) => match self.engine.call_plugin(
+                    request.plugin_id,
+                    &request.capability,
+                    &request.function,
+                    &args,
+                ) {
+                    Ok(result) => CallPluginResponse::Ok(result.to_string()),
+                    Err(error) => CallPluginResponse::Error(error.to_string()),
+                },
+                Err(error) => {
+                    CallPluginResponse::Error(format!("arguments are not a JSON array: {error}"))
                 }
-            },
+            }
+        } else {
+            CallPluginResponse::Error(
+                "calling plugins is disabled, set `call-plugin = true` under `[debug]` in the config"
+                    .to_string(),
+            )
+        };
+
+        responder.send(&response);
+    }
+
+    pub(crate) fn set_plugin_enabled(
+        &mut self,
+        plugin_id: &str,
+        enabled: bool,
+        responder: &mut Responder<'_>,
+    ) {
+        let response = match self.engine.set_plugin_enabled(plugin_id, enabled) {
+            Ok(()) => SetPluginEnabledResponse::Ok,
+            Err(error) => SetPluginEnabledResponse::Error(error.to_string()),
+        };
+
+        responder.send(&response);
+    }
+
+    pub(crate) fn get_workspaces(&self, responder: &mut Responder<'_>) {
+        let globals = self.globals();
+        let workspaces = globals
+            .workspaces
+            .iter()
+            .map(|(key, workspace)| Workspace {
+                id: WorkspaceId::from(key).inner,
+                name: workspace.name.clone(),
+                output: workspace.output.clone(),
+                active: globals.workspaces.is_active(key),
+                windows: workspace.windows().len() as u32,
+            })
+            .collect();
+
+        let response = GetWorkspacesResponse::Ok(workspaces);
+        responder.send(&response);
+    }
+
+    /// Delivers the events queued in the globals to the window manager
+    /// and the control socket subscribers.
+    pub fn dispatch_events(&mut self) {
+        self.check_plugin_statuses();
+
+        let events = std::mem::take(&mut self.globals().events);
+        if events.is_empty() {
+            return;
+        }
+
+        for event in &events {
+            match event {
+                CompositorEvent::WorkspaceChanged {
+                    output,
+                    previous,
+                    current,
+                } => self.notify(Event::WorkspaceChanged {
+                    output: output.clone(),
+                    previous: previous.map(|key| WorkspaceId::from(key).inner),
+                    current: WorkspaceId::from(*current).inner,
+                }),
+                CompositorEvent::LayoutChanged { layout } => self.notify(Event::LayoutChanged {
+                    layout: layout.clone(),
+                }),
+                CompositorEvent::AnimationFinished { .. }
+                | CompositorEvent::UsableAreaChanged { .. }
+                | CompositorEvent::WindowMinimized { .. } => {}
+            }
+        }
+
+        // Without a window manager there is nobody to deliver the events to
+        let Some(mut bindings) = self
+            .engine
+            .try_get_single_write_bindings::<Compositor>("compositor.window")
+        else {
+            return;
+        };
+        let mut store = bindings.store();
+        for event in events {
+            let result = match event {
+                CompositorEvent::WorkspaceChanged {
+                    output,
+                    previous,
+                    current,
+                } => bindings
+                    .fusion_compositor_wm_exports()
+                    .call_workspace_changed(
+                        &mut store,
+                        &output,
+                        previous.map(Into::into),
+                        current.into(),
+                    ),
+                CompositorEvent::AnimationFinished {
+                    window,
+                    animation,
+                    cancelled,
+                } => bindings
+                    .fusion_compositor_wm_exports()
+                    .call_animation_finished(&mut store, window.into(), animation, cancelled),
+                CompositorEvent::UsableAreaChanged { .. } => bindings
+                    .fusion_compositor_wm_exports()
+                    .call_rearrange_windows(&mut store),
+                CompositorEvent::LayoutChanged { layout } => bindings
+                    .fusion_compositor_wm_exports()
+                    .call_layout_changed(&mut store, &layout),
+                CompositorEvent::WindowMinimized { window, minimized } => bindings
+                    .fusion_compositor_wm_exports()
+                    .call_window_minimized(&mut store, window.into(), minimized),
+            };
+            if let Err(err) = result {
+                log::error!("Window manager failed to handle an event: {err:?}");
+            }
+        }
+    }
+}
+
+impl<B: Backend + 'static> App<B> {
+    pub(crate) fn get_windows(&self, responder: &mut Responder<'_>) {
+        let globals = self.globals();
+        let focus = self.seat.get_keyboard().unwrap().current_focus();
+        let windows = globals
+            .mapped_windows
+            .iter()
+            .map(|(key, window)| {
+                let toplevel = window.toplevel().unwrap();
+                let (app_id, title) = with_states(toplevel.wl_surface(), |states| {
+                    let attributes = states
+                        .data_map
+                        .get::<XdgToplevelSurfaceData>()
+                        .unwrap()
+                        .lock()
+                        .unwrap();
+                    (
+                        attributes.app_id.clone().unwrap_or_default(),
+                        attributes.title.clone().unwrap_or_default(),
+                    )
+                });
+                let workspace = globals
+                    .workspaces
+                    .window_workspace(key)
+                    .and_then(|key| globals.workspaces.get(key));
+
+                fusion_socket_protocol::Window {
+                    id: WindowId::from(key).inner,
+                    app_id,
+                    title,
+                    workspace: workspace.map(|workspace| workspace.name.clone()),
+                    output: workspace.map(|workspace| workspace.output.clone()),
+                    geometry: globals
+                        .space
+                        .element_geometry(window)
+                        .map(|geometry| Geometry {
+                            x: geometry.loc.x,
+                            y: geometry.loc.y,
+                            width: geometry.size.w,
+                            height: geometry.size.h,
+                        }),
+                    focused: focus.as_ref() == Some(toplevel.wl_surface()),
+                    fullscreen: decoration::is_fullscreen(window),
+                }
+            })
+            .collect();
+
+        let response = GetWindowsResponse::Ok(windows);
+        responder.send(&response);
+    }
+
+    pub(crate) fn window_action(
+        &mut self,
+        request: WindowActionRequest,
+        responder: &mut Responder<'_>,
+    ) {
+        let response = match self.apply_window_action(request.id, request.action) {
+            Ok(()) => WindowActionResponse::Ok,
+            Err(error) => WindowActionResponse::Error(error),
+        };
+
+        responder.send(&response);
+    }
+
+    fn apply_window_action(&mut self, id: u64, action: WindowAction) -> Result<(), String> {
+        let globals = self.globals.clone();
+        let mut globals = globals.lock().unwrap();
+        let key = WindowKey::from(WindowId { inner: id });
+        let Some(window) = globals.mapped_windows.get(key).cloned() else {
+            return Err(format!("Window {id} does not exist"));
         };
+        let toplevel = window.toplevel().unwrap();
 
-        let response_data = postcard::to_stdvec_cobs(&response).unwrap();
-        stream.write_all(&response_data).unwrap();
-    }
-
-    pub fn handle_socket(&mut self) {
-        match self.socket.accept() {
-            Ok((mut stream, addr)) => {
-                let mut buf = Vec::new();
-                let mut byte = [0u8; 1];
-                loop {
-                    stream.read_exact(&mut byte).unwrap();
-                    if byte[0] == 0x00 {
-                        break;
-                    }
-                    buf.push(byte[0]);
+        match action {
+            WindowAction::Focus => {
+                globals.restore_window(key);
+                if let Some(workspace) = globals.workspaces.window_workspace(key) {
+                    globals.switch_workspace(workspace);
                 }
+                if globals.space.element_location(&window).is_none() {
+                    return Err(format!("Window {id} is not shown"));
+                }
+
+                let serial = SERIAL_COUNTER.next_serial();
+                let keyboard = self.seat.get_keyboard().unwrap();
+                globals.space.raise_element(&window, true);
+                window.set_activated(true);
+                keyboard.set_focus(self, Some(toplevel.wl_surface().clone()), serial);
+                toplevel.send_pending_configure();
 
-                match postcard::from_bytes_cobs::<CompositorRequest>(&mut buf).unwrap() {
-                    CompositorRequest::Exit(_) => Self::exit(&mut stream),
-                    CompositorRequest::Ping(_) => Self::ping(&mut stream),
-                    CompositorRequest::GetPluginList(_) => self.get_plugin_list(&mut stream),
-                    CompositorRequest::RestartPlugin(request) => {
-                        self.restart_plugin(&request.plugin_id, &mut stream);
-                    }
+                // Layer surfaces with exclusive interactivity keep the keyboard
+                if let Some(exclusive) = layer::exclusive_focus(&globals.space) {
+                    keyboard.set_focus(self, Some(exclusive), serial);
                 }
             }
-            Err(error) => {}
+            WindowAction::Close => toplevel.send_close(),
+            WindowAction::Move { x, y } => {
+                let globals = &mut *globals;
+                globals.workspaces.place_window(
+                    &mut globals.space,
+                    key,
+                    &window,
+                    (x, y).into(),
+                    false,
+                );
+            }
+            WindowAction::Resize { width, height } => {
+                if width <= 0 || height <= 0 {
+                    return Err(format!("Invalid size {width}x{height}"));
+                }
+                toplevel.with_pending_state(|state| {
+                    state.size = Some((width, height).into());
+                });
+                toplevel.send_pending_configure();
+            }
+            WindowAction::Fullscreen(fullscreen) => {
+                let fullscreen = fullscreen.unwrap_or(!decoration::is_fullscreen(&window));
+                decoration::set_fullscreen(&mut globals, &window, fullscreen);
+            }
         }
+
+        Ok(())
     }
 }
 
@@ -203,6 +542,7 @@ impl<B: Backend> App<B> {
         backend: B,
         loop_signal: LoopSignal,
         handle: LoopHandle<'static, data::Data<B>>,
+        options: &InitOptions,
     ) -> Result<Self, Box<dyn std::error::Error>> {
         // Композитор нашего композитора
         let compositor_state = CompositorState::new::<Self>(dh);
@@ -250,24 +590,38 @@ impl<B: Backend> App<B> {
         };
 
         // Настройка модулей
-        let mut engine = PluginEngine::new(factory, LoaderConfig::default())?;
+        let loader_config = LoaderConfig::default()
+            .enable_preload(options.load_plugins)
+            .manual_loading(!options.load_plugins);
+        let mut engine = PluginEngine::new(factory, loader_config)?;
         engine.add_capability(
             "compositor.window",
             CapabilityWriteRules::SingleWrite,
             GeneralCapabilityProvider,
         );
 
-        if std::fs::exists(FUSION_CTL_SOCKET_DEFAULT)? {
-            std::fs::remove_file(FUSION_CTL_SOCKET_DEFAULT)?;
-        }
-
-        let socket = UnixListener::bind(FUSION_CTL_SOCKET_DEFAULT)?;
-        socket.set_nonblocking(true)?;
-
-        let input_state = InputState::new(&mut seat);
+        let config = Config::load(&options.config_file).unwrap_or_else(|err| {
+            log::error!("Unable to load the config, using the defaults: {err}");
+            Config::default()
+        });
+        let input_state = InputState::new(&mut seat, &config);
+        let keybindings = config.keybindings().unwrap_or_else(|err| {
+            log::error!("Unable to load the keybindings, using the defaults: {err}");
+            Config::default().keybindings().unwrap()
+        });
+        let gestures = config.gestures().unwrap_or_else(|err| {
+            log::error!("Unable to load the gestures: {err}");
+            Gestures::default()
+        });
         let xdg_decoration_state = XdgDecorationState::new::<Self>(dh);
-
-        Ok(Self {
+        let layer_shell_state = WlrLayerShellState::new::<Self>(dh);
+        let pointer_gestures_state = PointerGesturesState::new::<Self>(dh);
+        let relative_pointer_state = RelativePointerManagerState::new::<Self>(dh);
+        let pointer_constraints_state = PointerConstraintsState::new::<Self>(dh);
+        let cursor_shape_state = CursorShapeManagerState::new::<Self>(dh);
+        let tablet_manager_state = TabletManagerState::new::<Self>(dh);
+
+        let mut state = Self {
             compositor_state,
             data_device_state,
             seat_state,
@@ -283,19 +637,69 @@ impl<B: Backend> App<B> {
 
             engine,
             globals,
-            socket,
+            control_connections: ControlConnections::default(),
             display: dh.clone(),
 
             input_state,
             output_state: OutputState::default(),
             clock: Clock::new(),
             xdg_decoration_state,
+            layer_shell_state,
+            pointer_gestures_state,
+            relative_pointer_state,
+            pointer_constraints_state,
+            cursor_shape_state,
+            tablet_manager_state,
             sleep: false,
-        })
+            animation_timer: None,
+
+            config_file: options.config_file.clone(),
+            keybindings,
+            gestures,
+            debug: config.debug.clone(),
+        };
+        state.globals().keyboard_layout = state.active_keyboard_layout();
+        Ok(state)
     }
 
+    /// Maps `output` to the right of the outputs already in the space.
     pub fn map_output(&mut self, output: &Output) {
-        self.globals().space.map_output(output, (0, 0));
+        let mut globals = self.globals();
+        let space = &globals.space;
+        let right = space
+            .outputs()
+            .filter(|mapped| *mapped != output)
+            .filter_map(|mapped| space.output_geometry(mapped))
+            .map(|geometry| geometry.loc.x + geometry.size.w)
+            .max()
+            .unwrap_or(0);
+        let location = Point::from((right, 0));
+
+        output.change_current_state(None, None, None, Some(location));
+        globals.space.map_output(output, location);
+        globals.workspaces.add_output(output);
+        drop(globals);
+
+        self.notify(Event::OutputAdded {
+            name: output.name(),
+        });
+    }
+
+    /// Takes `output` out of the space, its workspaces and windows move to one of the remaining outputs.
+    pub fn unmap_output(&mut self, output: &Output) {
+        let mut guard = self.globals();
+        let globals = &mut *guard;
+        globals.workspaces.remove_output(
+            &mut globals.space,
+            &globals.mapped_windows,
+            &output.name(),
+        );
+        globals.space.unmap_output(output);
+        drop(guard);
+        self.output_state.remove_output(output);
+        self.notify(Event::OutputRemoved {
+            name: output.name(),
+        });
     }
 
     fn unconstrain_popup(&self, popup: &PopupSurface) {
@@ -327,6 +731,9 @@ impl<B: Backend> App<B> {
 }
 
 delegate_seat!(@<B: Backend> App<B>);
+delegate_pointer_gestures!(@<B: Backend + 'static> App<B>);
+delegate_relative_pointer!(@<B: Backend + 'static> App<B>);
+delegate_cursor_shape!(@<B: Backend + 'static> App<B>);
 impl<B: Backend> SeatHandler for App<B> {
     type KeyboardFocus = WlSurface;
 
@@ -342,6 +749,18 @@ impl<B: Backend> SeatHandler for App<B> {
         self.input_state.cursor.set_icon(status);
         //TODO redraw
     }
+
+    fn focus_changed(&mut self, _seat: &Seat<Self>, focused: Option<&WlSurface>) {
+        // Called with the globals locked, the window comes from the surface instead
+        let window = focused.and_then(|surface| {
+            with_states(surface, |states| {
+                states.data_map.get::<WindowKey>().copied()
+            })
+        });
+        self.notify(Event::WindowFocused {
+            id: window.map(|window| WindowId::from(window).inner),
+        });
+    }
 }
 
 delegate_compositor!(@<B: Backend + 'static> App<B>);
@@ -356,7 +775,16 @@ impl<B: Backend + 'static> CompositorHandler for App<B> {
 
     fn commit(&mut self, surface: &WlSurface) {
         on_commit_buffer_handler::<Self>(surface);
-        let space = &mut self.globals.lock().unwrap().space;
+        let globals = self.globals.clone();
+        let mut globals = globals.lock().unwrap();
+        if let Some(exclusive) = layer::handle_commit(&mut globals, surface) {
+            let keyboard = self.seat.get_keyboard().unwrap();
+            if keyboard.current_focus().as_ref() != Some(&exclusive) {
+                keyboard.set_focus(self, Some(exclusive), SERIAL_COUNTER.next_serial());
+            }
+        }
+
+        let space = &mut globals.space;
         if !is_sync_subsurface(surface) {
             let mut root = surface.clone();
             while let Some(parent) = get_parent(&root) {
@@ -400,15 +828,26 @@ impl<B: Backend + 'static> XdgShellHandler for App<B> {
         let window = Window::new_wayland_window(surface.clone());
         let window_id = {
             let mut globals = self.globals.lock().unwrap();
-            let space = &mut globals.space;
-
-            globals.mapped_windows.insert(window.clone())
+            let window_id = globals.mapped_windows.insert(window.clone());
+            globals.workspaces.add_window(window_id);
+            window_id
         };
         window.user_data().insert_if_missing(|| window_id);
+        // Focus changes only know the surface and can't look the window up
+        with_states(surface.wl_surface(), |states| {
+            states.data_map.insert_if_missing_threadsafe(|| window_id);
+        });
+        self.notify(Event::WindowOpened {
+            id: WindowId::from(window_id).inner,
+        });
 
-        let mut bindings = self
+        let Some(mut bindings) = self
             .engine
-            .get_single_write_bindings::<Compositor>("compositor.window");
+            .try_get_single_write_bindings::<Compositor>("compositor.window")
+        else {
+            log::warn!("No window manager to place the new toplevel");
+            return;
+        };
         let mut store = bindings.store();
         let now = std::time::Instant::now();
         bindings
@@ -422,25 +861,33 @@ impl<B: Backend + 'static> XdgShellHandler for App<B> {
     fn toplevel_destroyed(&mut self, surface: ToplevelSurface) {
         let window_id = {
             let mut globals = self.globals.lock().unwrap();
-            let window = {
-                let space = &mut globals.space;
-                space
-                    .elements()
-                    .find(|w| w.toplevel().unwrap().wl_surface() == surface.wl_surface())
-                    .cloned()
-                    .unwrap()
-            };
+            // Minimized windows are not mapped into the space
+            let window = globals
+                .mapped_windows
+                .values()
+                .find(|w| w.toplevel().unwrap().wl_surface() == surface.wl_surface())
+                .cloned()
+                .unwrap();
 
             let window_id = *window.user_data().get::<WindowKey>().unwrap();
             let window = globals.mapped_windows.remove(window_id).unwrap();
+            globals.workspaces.remove_window(window_id);
+            globals.animations.remove_window(window_id);
+            globals.styles.remove_window(window_id);
             let space = &mut globals.space;
             space.unmap_elem(&window);
             window_id
         };
+        self.notify(Event::WindowClosed {
+            id: WindowId::from(window_id).inner,
+        });
 
-        let mut bindings = self
+        let Some(mut bindings) = self
             .engine
-            .get_single_write_bindings::<Compositor>("compositor.window");
+            .try_get_single_write_bindings::<Compositor>("compositor.window")
+        else {
+            return;
+        };
         let mut store = bindings.store();
 
         bindings
@@ -542,6 +989,38 @@ impl<B: Backend + 'static> XdgShellHandler for App<B> {
     fn grab(&mut self, _surface: PopupSurface, _seat: WlSeat, _serial: Serial) {
         // TODO popup grabs
     }
+
+    fn maximize_request(&mut self, surface: ToplevelSurface) {
+        let globals = self.globals.clone();
+        let mut globals = globals.lock().unwrap();
+        if let Some(window) = find_window(&globals, &surface) {
+            decoration::set_maximized(&mut globals, &window, true);
+        }
+    }
+
+    fn unmaximize_request(&mut self, surface: ToplevelSurface) {
+        let globals = self.globals.clone();
+        let mut globals = globals.lock().unwrap();
+        if let Some(window) = find_window(&globals, &surface) {
+            decoration::set_maximized(&mut globals, &window, false);
+        }
+    }
+
+    fn minimize_request(&mut self, surface: ToplevelSurface) {
+        let globals = self.globals.clone();
+        let mut globals = globals.lock().unwrap();
+        if let Some(window) = find_window(&globals, &surface) {
+            self.minimize_window(&mut globals, &window, SERIAL_COUNTER.next_serial());
+        }
+    }
+}
+
+fn find_window(globals: &CompositorGlobals, surface: &ToplevelSurface) -> Option<Window> {
+    globals
+        .mapped_windows
+        .values()
+        .find(|w| w.toplevel().unwrap().wl_surface() == surface.wl_surface())
+        .cloned()
 }
 
 impl<B: Backend + 'static> SelectionHandler for App<B> {
@@ -571,12 +1050,15 @@ pub fn handle_commit(
         .cloned()
     {
         let window_id = *window.user_data().get::<WindowKey>().unwrap();
-        let mut bindings = engine.get_single_write_bindings::<Compositor>("compositor.window");
-        let mut store = bindings.store();
-        bindings
-            .fusion_compositor_wm_exports()
-            .call_on_commit(&mut store, window_id.into())
-            .unwrap();
+        if let Some(mut bindings) =
+            engine.try_get_single_write_bindings::<Compositor>("compositor.window")
+        {
+            let mut store = bindings.store();
+            bindings
+                .fusion_compositor_wm_exports()
+                .call_on_commit(&mut store, window_id.into())
+                .unwrap();
+        }
 
         let initial_configure_sent = with_states(surface, |states| {
             states
@@ -634,28 +1116,23 @@ fn check_grab<B: Backend + 'static>(
 
 use smithay::reexports::wayland_protocols::xdg::decoration::zv1::server::zxdg_toplevel_decoration_v1::Mode as DecorationMode;
 
-//TODO plugin configuration
 delegate_xdg_decoration!(@<B: Backend + 'static> App<B>);
 impl<B: Backend> XdgDecorationHandler for App<B> {
     fn new_decoration(&mut self, toplevel: ToplevelSurface) {
-        //TODO: custom decorations;
-        toplevel.with_pending_state(|state| {
-            state.decoration_mode = Some(DecorationMode::ClientSide);
-        });
+        let default = self.globals().decorations.default_mode;
+        decoration::request_mode(&toplevel, None, default);
         toplevel.send_configure();
     }
 
     fn request_mode(&mut self, toplevel: ToplevelSurface, mode: DecorationMode) {
-        //TODO: custom decorations;
-        toplevel
-            .with_pending_state(|state| state.decoration_mode = Some(DecorationMode::ClientSide));
+        let default = self.globals().decorations.default_mode;
+        decoration::request_mode(&toplevel, Some(mode), default);
         toplevel.send_configure();
     }
 
     fn unset_mode(&mut self, toplevel: ToplevelSurface) {
-        toplevel.with_pending_state(|state| {
-            state.decoration_mode = None;
-        });
+        let default = self.globals().decorations.default_mode;
+        decoration::request_mode(&toplevel, None, default);
         toplevel.send_configure();
     }
 }
@@ -0,0 +1,147 @@
+use smithay::{
+    backend::input::{
+        InputBackend, ProximityState, TabletToolAxisEvent, TabletToolButtonEvent,
+        TabletToolDescriptor, TabletToolProximityEvent, TabletToolTipEvent, TabletToolTipState,
+    },
+    delegate_tablet_manager,
+    input::pointer::{CursorImageStatus, MotionEvent},
+    utils::{Logical, Point, SERIAL_COUNTER},
+    wayland::tablet_manager::{TabletDescriptor, TabletSeatHandler, TabletSeatTrait},
+};
+
+use crate::compositor::{backend::Backend, state::App};
+
+delegate_tablet_manager!(@<B: Backend + 'static> App<B>);
+impl<B: Backend> TabletSeatHandler for App<B> {
+    fn tablet_tool_image(&mut self, _tool: &TabletToolDescriptor, image: CursorImageStatus) {
+        // There is a single cursor and it follows the tool in proximity
+        self.input_state.cursor.set_icon(image);
+    }
+}
+
+impl<B: Backend + 'static> App<B> {
+    /// Moves the cursor with the tool so clients without tablet support can still be used.
+    fn move_cursor_to_tool(&mut self, location: Point<f64, Logical>, time: u32) {
+        self.input_state.cursor.location = location;
+        let pointer = self.seat.get_pointer().unwrap();
+        let under = self.surface_under(location);
+        pointer.motion(
+            self,
+            under,
+            &MotionEvent {
+                location,
+                serial: SERIAL_COUNTER.next_serial(),
+                time,
+            },
+        );
+        pointer.frame(self);
+    }
+
+    pub(crate) fn tablet_tool_proximity<I: InputBackend>(
+        &mut self,
+        event: &impl TabletToolProximityEvent<I>,
+    ) {
+        let Some(location) = self.absolute_position(event) else {
+            return;
+        };
+        let display = self.display.clone();
+        let tablet_seat = self.seat.tablet_seat();
+        let tablet =
+            tablet_seat.add_tablet::<Self>(&display, &TabletDescriptor::from(&event.device()));
+        let tool = tablet_seat.add_tool::<Self>(self, &display, &event.tool());
+
+        self.move_cursor_to_tool(location, event.time_msec());
+        match event.state() {
+            ProximityState::In => {
+                if let Some(under) = self.surface_under(location) {
+                    tool.proximity_in(
+                        location,
+                        under,
+                        &tablet,
+                        SERIAL_COUNTER.next_serial(),
+                        event.time_msec(),
+                    );
+                }
+            }
+            ProximityState::Out => tool.proximity_out(event.time_msec()),
+        }
+    }
+
+    pub(crate) fn tablet_tool_axis<I: InputBackend>(
+        &mut self,
+        event: &impl TabletToolAxisEvent<I>,
+    ) {
+        let Some(location) = self.absolute_position(event) else {
+            return;
+        };
+        self.move_cursor_to_tool(location, event.time_msec());
+
+        let tablet_seat = self.seat.tablet_seat();
+        let tablet = tablet_seat.get_tablet(&TabletDescriptor::from(&event.device()));
+        let tool = tablet_seat.get_tool(&event.tool());
+        let (Some(tablet), Some(tool)) = (tablet, tool) else {
+            return;
+        };
+
+        if event.pressure_has_changed() {
+            tool.pressure(event.pressure());
+        }
+        if event.distance_has_changed() {
+            tool.distance(event.distance());
+        }
+        if event.tilt_has_changed() {
+            tool.tilt(event.tilt());
+        }
+        if event.slider_has_changed() {
+            tool.slider_position(event.slider_position());
+        }
+        if event.rotation_has_changed() {
+            tool.rotation(event.rotation());
+        }
+        if event.wheel_has_changed() {
+            tool.wheel(event.wheel_delta(), event.wheel_delta_discrete());
+        }
+
+        let under = self.surface_under(location);
+        tool.motion(
+            location,
+            under,
+            &tablet,
+            SERIAL_COUNTER.next_serial(),
+            event.time_msec(),
+        );
+    }
+
+    pub(crate) fn tablet_tool_tip<I: InputBackend>(&mut self, event: &impl TabletToolTipEvent<I>) {
+        let Some(tool) = self.seat.tablet_seat().get_tool(&event.tool()) else {
+            return;
+        };
+
+        match event.tip_state() {
+            TabletToolTipState::Down => {
+                let serial = SERIAL_COUNTER.next_serial();
+                tool.tip_down(serial, event.time_msec());
+
+                // Touching the tablet focuses like a click
+                let location = self.input_state.cursor.location;
+                let globals = self.globals.clone();
+                self.focus_at(&mut globals.lock().unwrap(), location, serial);
+            }
+            TabletToolTipState::Up => tool.tip_up(event.time_msec()),
+        }
+    }
+
+    pub(crate) fn tablet_tool_button<I: InputBackend>(
+        &mut self,
+        event: &impl TabletToolButtonEvent<I>,
+    ) {
+        if let Some(tool) = self.seat.tablet_seat().get_tool(&event.tool()) {
+            tool.button(
+                event.button(),
+                event.button_state(),
+                SERIAL_COUNTER.next_serial(),
+                event.time_msec(),
+            );
+        }
+    }
+}
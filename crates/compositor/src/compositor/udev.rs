@@ -5,20 +5,20 @@
 use smithay::backend::allocator::Fourcc;
 use smithay::backend::drm::compositor::FrameFlags;
 use smithay::backend::drm::{DrmEventMetadata, DrmEventTime};
+use smithay::backend::input::InputEvent;
 use smithay::backend::libinput::{LibinputInputBackend, LibinputSessionInterface};
 use smithay::backend::renderer::element::memory::MemoryRenderBufferRenderElement;
 use smithay::backend::renderer::element::surface::WaylandSurfaceRenderElement;
 use smithay::backend::renderer::element::{Element, Id, RenderElement, RenderElementStates};
 use smithay::backend::renderer::utils::CommitCounter;
-use smithay::backend::renderer::{Frame, ImportAll, ImportMem, Renderer};
+use smithay::backend::renderer::{Frame, ImportAll, ImportEgl, ImportMem, Renderer};
 use smithay::delegate_dmabuf;
-use smithay::desktop::space::space_render_elements;
 use smithay::desktop::utils::{
     surface_presentation_feedback_flags_from_states, surface_primary_scanout_output,
 };
 use smithay::desktop::{Space, Window};
 use smithay::output::Mode;
-use smithay::reexports::input::Libinput;
+use smithay::reexports::input::{self, Libinput};
 use smithay::reexports::wayland_protocols::wp::presentation_time::server::wp_presentation_feedback;
 use smithay::utils::{Monotonic, Physical, Rectangle};
 use smithay::wayland::dmabuf::DmabufHandler;
@@ -39,6 +39,7 @@ use ::drm::{
     control::{self, ModeFlags, ModeTypeFlags, connector, crtc},
     node::NodeType,
 };
+use calloop::timer::{TimeoutAction, Timer};
 use calloop::{LoopHandle, RegistrationToken};
 use smithay::{
     backend::{
@@ -61,7 +62,11 @@ use smithay_drm_extras::{
     drm_scanner::{self, DrmScanEvent, DrmScanner},
 };
 
-use crate::compositor::output::RenderState;
+use crate::compositor::animation::ANIMATION_FRAME_INTERVAL;
+use crate::compositor::input_device::{AccelProfile, DeviceKind, InputConfig, ScrollMethod};
+use crate::compositor::layer;
+use crate::compositor::output::{OutputError, RenderState};
+use crate::compositor::render::{OutputRenderElement, output_render_elements};
 use crate::compositor::{backend::Backend, state::App};
 
 type GbmDrmCompositor = DrmCompositor<
@@ -76,6 +81,7 @@ const SUPPORTED_COLOR_FORMATS: &[Fourcc] = &[Fourcc::Argb8888, Fourcc::Abgr8888]
 #[derive(Debug)]
 struct Surface {
     info: OutputInfo,
+    connector: connector::Info,
     compositor: GbmDrmCompositor,
 }
 
@@ -104,6 +110,8 @@ pub struct UdevData {
     pub dmabuf_state: DmabufState,
     pub dmabuf_global: Option<DmabufGlobal>,
     pub device: Option<Device>,
+    /// Devices libinput reported, to apply config changes to.
+    pub input_devices: Vec<input::Device>,
 }
 
 impl Backend for UdevData {
@@ -131,9 +139,149 @@ impl Backend for UdevData {
             refresh: 60_000,
         }
     }
+
+    fn configure_input_devices(&mut self, config: &InputConfig) {
+        for device in &mut self.input_devices {
+            configure_libinput_device(device, config);
+        }
+    }
+
+    fn output_modes(&self, output: &Output) -> Vec<Mode> {
+        let Some(surface) = self.surface(output) else {
+            return Vec::new();
+        };
+        surface
+            .connector
+            .modes()
+            .iter()
+            .map(|mode| Mode::from(*mode))
+            .collect()
+    }
+
+    fn set_output_mode(
+        &mut self,
+        output: &Output,
+        mode: Mode,
+        test: bool,
+    ) -> Result<(), OutputError> {
+        let surface = self
+            .surface_mut(output)
+            .ok_or_else(|| OutputError::UnknownOutput(output.name()))?;
+        let Some(drm_mode) = (surface.connector.modes().iter())
+            .find(|drm_mode| Mode::from(**drm_mode) == mode)
+            .copied()
+        else {
+            return Err(OutputError::UnsupportedMode {
+                output: output.name(),
+                width: mode.size.w,
+                height: mode.size.h,
+                refresh: String::new(),
+            });
+        };
+
+        // The surface checks the mode against the crtc and the connector
+        let previous = surface.compositor.pending_mode();
+        (surface.compositor.use_mode(drm_mode))
+            .map_err(|error| OutputError::Backend(error.to_string()))?;
+        if test {
+            (surface.compositor.use_mode(previous))
+                .map_err(|error| OutputError::Backend(error.to_string()))?;
+        }
+        Ok(())
+    }
+
+    fn set_output_enabled(
+        &mut self,
+        output: &Output,
+        enabled: bool,
+        test: bool,
+    ) -> Result<(), OutputError> {
+        let surface = self
+            .surface_mut(output)
+            .ok_or_else(|| OutputError::UnknownOutput(output.name()))?;
+        // The next queued frame turns the crtc back on
+        if !enabled && !test {
+            (surface.compositor.clear())
+                .map_err(|error| OutputError::Backend(error.to_string()))?;
+        }
+        Ok(())
+    }
+
+    fn shutdown(&mut self) {
+        self.libinput.suspend();
+        // The DRM device restores the state it found once dropped,
+        // the session hands the VT back when `UdevData` goes
+        if let Some(mut device) = self.device.take() {
+            device.surfaces.clear();
+        }
+    }
+}
+
+/// Applies the `[[input.device]]` entries matching the device, unset settings stay as they are.
+fn configure_libinput_device(device: &mut input::Device, config: &InputConfig) {
+    let settings = config.settings(device.name(), DeviceKind::of(device));
+    let name = device.name().to_string();
+    let check = |setting: &str, result: input::DeviceConfigResult| {
+        if let Err(err) = result {
+            log::warn!("Unable to set {setting} for {name}: {err:?}");
+        }
+    };
+
+    if let Some(tap) = settings.tap {
+        check("tap", device.config_tap_set_enabled(tap));
+    }
+    if let Some(natural_scroll) = settings.natural_scroll {
+        check(
+            "natural-scroll",
+            device.config_scroll_set_natural_scroll_enabled(natural_scroll),
+        );
+    }
+    if let Some(profile) = settings.accel_profile {
+        let profile = match profile {
+            AccelProfile::Flat => input::AccelProfile::Flat,
+            AccelProfile::Adaptive => input::AccelProfile::Adaptive,
+        };
+        check("accel-profile", device.config_accel_set_profile(profile));
+    }
+    if let Some(speed) = settings.accel_speed {
+        check("accel-speed", device.config_accel_set_speed(speed));
+    }
+    if let Some(left_handed) = settings.left_handed {
+        check("left-handed", device.config_left_handed_set(left_handed));
+    }
+    if let Some(method) = settings.scroll_method {
+        let method = match method {
+            ScrollMethod::None => input::ScrollMethod::NoScroll,
+            ScrollMethod::TwoFinger => input::ScrollMethod::TwoFinger,
+            ScrollMethod::Edge => input::ScrollMethod::Edge,
+            ScrollMethod::OnButtonDown => input::ScrollMethod::OnButtonDown,
+        };
+        check("scroll-method", device.config_scroll_set_method(method));
+    }
+    if let Some(dwt) = settings.disable_while_typing {
+        check("disable-while-typing", device.config_dwt_set_enabled(dwt));
+    }
 }
 
 impl UdevData {
+    fn surface(&self, output: &Output) -> Option<&Surface> {
+        let state = output.user_data().get::<UdevOutputState>()?;
+        let device = self
+            .device
+            .as_ref()
+            .filter(|device| device.id == state.device_id)?;
+        device.surfaces.get(&state.crtc)
+    }
+
+    fn surface_mut(&mut self, output: &Output) -> Option<&mut Surface> {
+        let state = output.user_data().get::<UdevOutputState>()?;
+        let device = self
+            .device
+            .as_mut()
+            .filter(|device| device.id == state.device_id)?;
+        device.surfaces.get_mut(&state.crtc)
+    }
+
     pub fn init(handle: &LoopHandle<'_, super::data::Data<UdevData>>) -> UdevData {
         use smithay::backend::session::Event as SessionEvent;
 
@@ -147,6 +295,20 @@ impl UdevData {
         handle
             .insert_source(input_backend, |mut event, (), data| {
                 //state.handle_libinput_event(&mut event);
+                match &mut event {
+                    InputEvent::DeviceAdded { device } => {
+                        let config = &data.state.input_state.input_config;
+                        configure_libinput_device(device, config);
+                        data.state.backend.input_devices.push(device.clone());
+                    }
+                    InputEvent::DeviceRemoved { device } => {
+                        data.state
+                            .backend
+                            .input_devices
+                            .retain(|known| known != device);
+                    }
+                    _ => {}
+                }
                 data.state.handle_input_event(event);
             })
             .unwrap();
@@ -197,6 +359,7 @@ impl UdevData {
             dmabuf_global: None,
             device: None,
             libinput,
+            input_devices: Vec::new(),
         }
     }
 }
@@ -418,12 +581,18 @@ impl App<UdevData> {
             SUPPORTED_COLOR_FORMATS.iter().copied(),
             device.formats.clone(),
             device.drm.cursor_size(),
-            Some(device.gbm.clone()),
+            // Without a gbm device everything is composited into the primary plane
+            self.input_state
+                .cursor
+                .config
+                .hardware
+                .then(|| device.gbm.clone()),
         )
         .unwrap();
 
         let surface = Surface {
             info: output_info,
+            connector: connector.clone(),
             compositor,
         };
         let prev = device.surfaces.insert(crtc, surface);
@@ -442,14 +611,25 @@ impl App<UdevData> {
             return;
         }
 
-        todo!()
-
-        //let output = mayland
-        //    .workspaces
-        //    .udev_output(device.id, crtc)
-        //    .unwrap()
-        //    .clone();
-        //mayland.remove_output(&output);
+        let device_id = device.id;
+        let is_connector = |output: &Output| {
+            output
+                .user_data()
+                .get::<UdevOutputState>()
+                .is_some_and(|state| state.device_id == device_id && state.crtc == crtc)
+        };
+        // Outputs disabled through the control socket are already unmapped
+        self.output_state
+            .disabled
+            .retain(|output| !is_connector(output));
+        let output = self
+            .output_state
+            .outputs
+            .keys()
+            .find(|output| is_connector(output));
+        if let Some(output) = output.cloned() {
+            self.unmap_output(&output);
+        }
     }
 
     fn on_vblank(&mut self, crtc: crtc::Handle, meta: DrmEventMetadata) {
@@ -493,11 +673,10 @@ impl App<UdevData> {
             }
         }
 
-        let output = self
-            .output_state
-            .udev_output(crtc, device.id)
-            .unwrap()
-            .clone();
+        // Outputs disabled in the meantime don't render anymore
+        let Some(output) = self.output_state.udev_output(crtc, device.id).cloned() else {
+            return;
+        };
 
         self.output_state.queue_render(&output);
 
@@ -510,6 +689,8 @@ impl App<UdevData> {
         }
 
         let now = self.clock.now();
+        self.globals().tick_animations(now.into());
+
         let space = unsafe {
             let ptr = &raw const self.globals().space;
             &*ptr
@@ -532,53 +713,48 @@ impl App<UdevData> {
                     Some(output.clone())
                 });
             });
+            layer::send_frames(output, now.into());
+        }
+
+        // Outputs which got a frame queued are woken up by vblank,
+        // the others would wait for unrelated events while an animation waits for a client
+        let waiting =
+            (self.output_state.outputs.values()).any(|state| *state == RenderState::Queued);
+        let animating =
+            self.globals().animations.is_active() || self.input_state.cursor.is_animated();
+        if waiting && self.animation_timer.is_none() && animating {
+            let timer = Timer::from_duration(ANIMATION_FRAME_INTERVAL);
+            self.animation_timer = self
+                .handle
+                .insert_source(timer, |_, (), data| {
+                    data.state.animation_timer = None;
+                    TimeoutAction::Drop
+                })
+                .ok();
         }
     }
 
     fn render(&mut self, output: &Output) {
-        let space = unsafe {
-            let ptr = &raw const self.globals().space;
-            &*ptr
-        };
+        // Held for the whole frame, the rest of the state is borrowed next to it
+        let globals = self.globals.clone();
+        let globals = globals.lock().unwrap();
+        let space = &globals.space;
 
         let device = self.backend.device.as_mut().unwrap();
-        let elements: Vec<
-            smithay::desktop::space::SpaceRenderElements<
-                GlesRenderer,
-                WaylandSurfaceRenderElement<GlesRenderer>,
-            >,
-        > = space_render_elements(&mut device.gles, std::slice::from_ref(space), output, 1.0)
-            .unwrap();
+        let mut elements = output_render_elements(&mut device.gles, &globals, output);
 
         let udev_state = output.user_data().get::<UdevOutputState>().unwrap();
         let surface = device.surfaces.get_mut(&udev_state.crtc).unwrap();
 
-        let mut elements = elements
-            .into_iter()
-            .map(TestRenderElement::from)
-            .collect::<Vec<_>>();
-
+        let output_geo = space.output_geometry(output).unwrap_or_default();
         let output_scale = output.current_scale();
-        let mut cursor = self
-            .input_state
-            .cursor
-            .render_cursor(&mut device.gles, output_scale);
-        if !cursor.is_empty() {
-            let cursor: TestRenderElement<GlesRenderer, WaylandSurfaceRenderElement<GlesRenderer>> =
-                cursor.remove(0);
-            elements.insert(0, cursor);
-        }
-
-        let debug_cursor = {
-            let location = self.input_state.cursor.location;
-            RectElement::new(
-                (location.x as i32, location.y as i32),
-                (6, 16),
-                [0.0, 0.0, 1.0, 1.0],
-            )
-        };
-
-        elements.insert(0, TestRenderElement::from(debug_cursor));
+        let cursor: Vec<OutputRenderElement> = self.input_state.cursor.render_cursor(
+            &mut device.gles,
+            output_geo,
+            output_scale,
+            self.clock.now().into(),
+        );
+        elements.splice(0..0, cursor);
 
         let drm_compositor = &mut surface.compositor;
         match drm_compositor.render_frame(
@@ -627,6 +803,11 @@ fn presentation_feedback(
             },
         );
     }
+    layer::take_presentation_feedback(
+        output,
+        &mut output_presentation_feedback,
+        render_element_states,
+    );
 
     output_presentation_feedback
 }
@@ -0,0 +1,624 @@
+use std::{path::PathBuf, time::Duration};
+
+use fusion_socket_protocol::{InjectInputResponse, InputAction, KEY_MAX};
+use smithay::backend::input::{
+    AbsolutePositionEvent, Axis, AxisRelativeDirection, AxisSource, ButtonState, Device,
+    DeviceCapability, Event, GestureBeginEvent, GestureEndEvent, GestureHoldBeginEvent,
+    GestureHoldEndEvent, GesturePinchBeginEvent, GesturePinchEndEvent, GesturePinchUpdateEvent,
+    GestureSwipeBeginEvent, GestureSwipeEndEvent, GestureSwipeUpdateEvent, InputBackend,
+    InputEvent, KeyState, KeyboardKeyEvent, Keycode, PointerAxisEvent, PointerButtonEvent,
+    PointerMotionAbsoluteEvent, PointerMotionEvent, ProximityState, TabletToolAxisEvent,
+    TabletToolButtonEvent, TabletToolCapabilities, TabletToolDescriptor, TabletToolEvent,
+    TabletToolProximityEvent, TabletToolTipEvent, TabletToolTipState, TabletToolType,
+    TouchCancelEvent, TouchDownEvent, TouchEvent, TouchFrameEvent, TouchMotionEvent, TouchSlot,
+    TouchUpEvent, UnusedEvent,
+};
+
+use crate::compositor::{backend::Backend, control::Responder, input::SpecialActions, state::App};
+
+/// Offset between Linux input event codes and xkb keycodes.
+const EVDEV_OFFSET: u32 = 8;
+
+/// Input backend fed with [`InputAction`]s instead of real devices,
+/// for tests and automation through the control socket.
+#[derive(Debug)]
+pub struct VirtualInput;
+
+/// The only device of [`VirtualInput`], a keyboard, a touchpad, a touchscreen
+/// and a tablet at once.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct VirtualDevice;
+
+impl Device for VirtualDevice {
+    fn id(&self) -> String {
+        "virtual".to_string()
+    }
+
+    fn name(&self) -> String {
+        "Virtual input".to_string()
+    }
+
+    fn has_capability(&self, capability: DeviceCapability) -> bool {
+        matches!(
+            capability,
+            DeviceCapability::Keyboard
+                | DeviceCapability::Pointer
+                | DeviceCapability::Gesture
+                | DeviceCapability::Touch
+                | DeviceCapability::TabletTool
+        )
+    }
+
+    fn usb_id(&self) -> Option<(u32, u32)> {
+        None
+    }
+
+    fn syspath(&self) -> Option<PathBuf> {
+        None
+    }
+}
+
+macro_rules! virtual_event {
+    ($name:ident { $($field:ident: $ty:ty),* $(,)? }) => {
+        #[derive(Debug, Clone, Copy)]
+        pub struct $name {
+            /// Microseconds
+            time: u64,
+            $($field: $ty),*
+        }
+
+        impl Event<VirtualInput> for $name {
+            fn time(&self) -> u64 {
+                self.time
+            }
+
+            fn device(&self) -> VirtualDevice {
+                VirtualDevice
+            }
+        }
+    };
+}
+
+virtual_event!(VirtualKeyboardKeyEvent {
+    key: u32,
+    pressed: bool
+});
+virtual_event!(VirtualPointerMotionEvent { dx: f64, dy: f64 });
+virtual_event!(VirtualPointerMotionAbsoluteEvent { x: f64, y: f64 });
+virtual_event!(VirtualPointerButtonEvent {
+    button: u32,
+    pressed: bool
+});
+virtual_event!(VirtualPointerAxisEvent {
+    horizontal: f64,
+    vertical: f64
+});
+virtual_event!(VirtualGestureBeginEvent { fingers: u32 });
+virtual_event!(VirtualGestureEndEvent { cancelled: bool });
+virtual_event!(VirtualGestureSwipeUpdateEvent { dx: f64, dy: f64 });
+virtual_event!(VirtualGesturePinchUpdateEvent {
+    dx: f64,
+    dy: f64,
+    scale: f64,
+    rotation: f64,
+});
+virtual_event!(VirtualTouchPositionEvent {
+    slot: u32,
+    x: f64,
+    y: f64
+});
+virtual_event!(VirtualTouchEvent { slot: Option<u32> });
+// `active` is in proximity, tip down or button pressed. Tips and buttons happen
+// where the tool already is, their position is unused.
+virtual_event!(VirtualTabletToolEvent {
+    x: f64,
+    y: f64,
+    active: bool,
+    button: u32,
+});
+
+impl KeyboardKeyEvent<VirtualInput> for VirtualKeyboardKeyEvent {
+    fn key_code(&self) -> Keycode {
+        Keycode::new(self.key + EVDEV_OFFSET)
+    }
+
+    fn state(&self) -> KeyState {
+        if self.pressed {
+            KeyState::Pressed
+        } else {
+            KeyState::Released
+        }
+    }
+
+    fn count(&self) -> u32 {
+        u32::from(self.pressed)
+    }
+}
+
+impl PointerMotionEvent<VirtualInput> for VirtualPointerMotionEvent {
+    fn delta_x(&self) -> f64 {
+        self.dx
+    }
+
+    fn delta_y(&self) -> f64 {
+        self.dy
+    }
+
+    fn delta_x_unaccel(&self) -> f64 {
+        self.dx
+    }
+
+    fn delta_y_unaccel(&self) -> f64 {
+        self.dy
+    }
+}
+
+impl AbsolutePositionEvent<VirtualInput> for VirtualPointerMotionAbsoluteEvent {
+    fn x(&self) -> f64 {
+        self.x
+    }
+
+    fn y(&self) -> f64 {
+        self.y
+    }
+
+    fn x_transformed(&self, width: i32) -> f64 {
+        self.x.clamp(0.0, 1.0) * f64::from(width)
+    }
+
+    fn y_transformed(&self, height: i32) -> f64 {
+        self.y.clamp(0.0, 1.0) * f64::from(height)
+    }
+}
+
+impl PointerMotionAbsoluteEvent<VirtualInput> for VirtualPointerMotionAbsoluteEvent {}
+
+impl AbsolutePositionEvent<VirtualInput> for VirtualTouchPositionEvent {
+    fn x(&self) -> f64 {
+        self.x
+    }
+
+    fn y(&self) -> f64 {
+        self.y
+    }
+
+    fn x_transformed(&self, width: i32) -> f64 {
+        self.x.clamp(0.0, 1.0) * f64::from(width)
+    }
+
+    fn y_transformed(&self, height: i32) -> f64 {
+        self.y.clamp(0.0, 1.0) * f64::from(height)
+    }
+}
+
+impl TouchEvent<VirtualInput> for VirtualTouchPositionEvent {
+    fn slot(&self) -> TouchSlot {
+        Some(self.slot).into()
+    }
+}
+
+impl TouchEvent<VirtualInput> for VirtualTouchEvent {
+    fn slot(&self) -> TouchSlot {
+        self.slot.into()
+    }
+}
+
+impl TouchDownEvent<VirtualInput> for VirtualTouchPositionEvent {}
+impl TouchMotionEvent<VirtualInput> for VirtualTouchPositionEvent {}
+impl TouchUpEvent<VirtualInput> for VirtualTouchEvent {}
+impl TouchCancelEvent<VirtualInput> for VirtualTouchEvent {}
+impl TouchFrameEvent<VirtualInput> for VirtualTouchEvent {}
+
+impl AbsolutePositionEvent<VirtualInput> for VirtualTabletToolEvent {
+    fn x(&self) -> f64 {
+        self.x
+    }
+
+    fn y(&self) -> f64 {
+        self.y
+    }
+
+    fn x_transformed(&self, width: i32) -> f64 {
+        self.x.clamp(0.0, 1.0) * f64::from(width)
+    }
+
+    fn y_transformed(&self, height: i32) -> f64 {
+        self.y.clamp(0.0, 1.0) * f64::from(height)
+    }
+}
+
+/// A plain pen, injected actions have no axes besides the position.
+impl TabletToolEvent<VirtualInput> for VirtualTabletToolEvent {
+    fn tool(&self) -> TabletToolDescriptor {
+        TabletToolDescriptor {
+            tool_type: TabletToolType::Pen,
+            hardware_serial: 0,
+            hardware_id_wacom: 0,
+            capabilities: TabletToolCapabilities::empty(),
+        }
+    }
+
+    fn delta_x(&self) -> f64 {
+        0.0
+    }
+
+    fn delta_y(&self) -> f64 {
+        0.0
+    }
+
+    fn distance(&self) -> f64 {
+        0.0
+    }
+
+    fn distance_has_changed(&self) -> bool {
+        false
+    }
+
+    fn pressure(&self) -> f64 {
+        0.0
+    }
+
+    fn pressure_has_changed(&self) -> bool {
+        false
+    }
+
+    fn slider_position(&self) -> f64 {
+        0.0
+    }
+
+    fn slider_has_changed(&self) -> bool {
+        false
+    }
+
+    fn tilt_x(&self) -> f64 {
+        0.0
+    }
+
+    fn tilt_x_has_changed(&self) -> bool {
+        false
+    }
+
+    fn tilt_y(&self) -> f64 {
+        0.0
+    }
+
+    fn tilt_y_has_changed(&self) -> bool {
+        false
+    }
+
+    fn rotation(&self) -> f64 {
+        0.0
+    }
+
+    fn rotation_has_changed(&self) -> bool {
+        false
+    }
+
+    fn wheel_delta(&self) -> f64 {
+        0.0
+    }
+
+    fn wheel_delta_discrete(&self) -> i32 {
+        0
+    }
+
+    fn wheel_has_changed(&self) -> bool {
+        false
+    }
+}
+
+impl TabletToolAxisEvent<VirtualInput> for VirtualTabletToolEvent {}
+
+impl TabletToolProximityEvent<VirtualInput> for VirtualTabletToolEvent {
+    fn state(&self) -> ProximityState {
+        if self.active {
+            ProximityState::In
+        } else {
+            ProximityState::Out
+        }
+    }
+}
+
+impl TabletToolTipEvent<VirtualInput> for VirtualTabletToolEvent {
+    fn tip_state(&self) -> TabletToolTipState {
+        if self.active {
+            TabletToolTipState::Down
+        } else {
+            TabletToolTipState::Up
+        }
+    }
+}
+
+impl TabletToolButtonEvent<VirtualInput> for VirtualTabletToolEvent {
+    fn button(&self) -> u32 {
+        self.button
+    }
+
+    fn seat_button_count(&self) -> u32 {
+        u32::from(self.active)
+    }
+
+    fn button_state(&self) -> ButtonState {
+        if self.active {
+            ButtonState::Pressed
+        } else {
+            ButtonState::Released
+        }
+    }
+}
+
+impl PointerButtonEvent<VirtualInput> for VirtualPointerButtonEvent {
+    fn button_code(&self) -> u32 {
+        self.button
+    }
+
+    fn state(&self) -> ButtonState {
+        if self.pressed {
+            ButtonState::Pressed
+        } else {
+            ButtonState::Released
+        }
+    }
+}
+
+impl PointerAxisEvent<VirtualInput> for VirtualPointerAxisEvent {
+    fn amount(&self, axis: Axis) -> Option<f64> {
+        let amount = match axis {
+            Axis::Horizontal => self.horizontal,
+            Axis::Vertical => self.vertical,
+        };
+        (amount != 0.0).then_some(amount)
+    }
+
+    fn amount_v120(&self, _axis: Axis) -> Option<f64> {
+        None
+    }
+
+    fn source(&self) -> AxisSource {
+        AxisSource::Continuous
+    }
+
+    fn relative_direction(&self, _axis: Axis) -> AxisRelativeDirection {
+        AxisRelativeDirection::Identical
+    }
+}
+
+impl GestureBeginEvent<VirtualInput> for VirtualGestureBeginEvent {
+    fn fingers(&self) -> u32 {
+        self.fingers
+    }
+}
+
+impl GestureEndEvent<VirtualInput> for VirtualGestureEndEvent {
+    fn cancelled(&self) -> bool {
+        self.cancelled
+    }
+}
+
+impl GestureSwipeBeginEvent<VirtualInput> for VirtualGestureBeginEvent {}
+impl GestureSwipeEndEvent<VirtualInput> for VirtualGestureEndEvent {}
+impl GesturePinchBeginEvent<VirtualInput> for VirtualGestureBeginEvent {}
+impl GesturePinchEndEvent<VirtualInput> for VirtualGestureEndEvent {}
+impl GestureHoldBeginEvent<VirtualInput> for VirtualGestureBeginEvent {}
+impl GestureHoldEndEvent<VirtualInput> for VirtualGestureEndEvent {}
+
+impl GestureSwipeUpdateEvent<VirtualInput> for VirtualGestureSwipeUpdateEvent {
+    fn delta_x(&self) -> f64 {
+        self.dx
+    }
+
+    fn delta_y(&self) -> f64 {
+        self.dy
+    }
+}
+
+impl GesturePinchUpdateEvent<VirtualInput> for VirtualGesturePinchUpdateEvent {
+    fn delta_x(&self) -> f64 {
+        self.dx
+    }
+
+    fn delta_y(&self) -> f64 {
+        self.dy
+    }
+
+    fn scale(&self) -> f64 {
+        self.scale
+    }
+
+    fn rotation(&self) -> f64 {
+        self.rotation
+    }
+}
+
+impl InputBackend for VirtualInput {
+    type Device = VirtualDevice;
+    type KeyboardKeyEvent = VirtualKeyboardKeyEvent;
+    type PointerAxisEvent = VirtualPointerAxisEvent;
+    type PointerButtonEvent = VirtualPointerButtonEvent;
+    type PointerMotionEvent = VirtualPointerMotionEvent;
+    type PointerMotionAbsoluteEvent = VirtualPointerMotionAbsoluteEvent;
+    type GestureSwipeBeginEvent = VirtualGestureBeginEvent;
+    type GestureSwipeUpdateEvent = VirtualGestureSwipeUpdateEvent;
+    type GestureSwipeEndEvent = VirtualGestureEndEvent;
+    type GesturePinchBeginEvent = VirtualGestureBeginEvent;
+    type GesturePinchUpdateEvent = VirtualGesturePinchUpdateEvent;
+    type GesturePinchEndEvent = VirtualGestureEndEvent;
+    type GestureHoldBeginEvent = VirtualGestureBeginEvent;
+    type GestureHoldEndEvent = VirtualGestureEndEvent;
+    type TouchDownEvent = VirtualTouchPositionEvent;
+    type TouchUpEvent = VirtualTouchEvent;
+    type TouchMotionEvent = VirtualTouchPositionEvent;
+    type TouchCancelEvent = VirtualTouchEvent;
+    type TouchFrameEvent = VirtualTouchEvent;
+    type TabletToolAxisEvent = VirtualTabletToolEvent;
+    type TabletToolProximityEvent = VirtualTabletToolEvent;
+    type TabletToolTipEvent = VirtualTabletToolEvent;
+    type TabletToolButtonEvent = VirtualTabletToolEvent;
+    type SwitchToggleEvent = UnusedEvent;
+    type SpecialEvent = ();
+}
+
+impl VirtualInput {
+    /// Input event for `action` happening at `time`.
+    pub fn event(action: InputAction, time: Duration) -> InputEvent<Self> {
+        let time = time.as_micros() as u64;
+        match action {
+            InputAction::PointerMotion { dx, dy } => InputEvent::PointerMotion {
+                event: VirtualPointerMotionEvent { time, dx, dy },
+            },
+            InputAction::PointerMotionAbsolute { x, y } => InputEvent::PointerMotionAbsolute {
+                event: VirtualPointerMotionAbsoluteEvent { time, x, y },
+            },
+            InputAction::PointerButton { button, pressed } => InputEvent::PointerButton {
+                event: VirtualPointerButtonEvent {
+                    time,
+                    button,
+                    pressed,
+                },
+            },
+            InputAction::PointerAxis {
+                horizontal,
+                vertical,
+            } => InputEvent::PointerAxis {
+                event: VirtualPointerAxisEvent {
+                    time,
+                    horizontal,
+                    vertical,
+                },
+            },
+            InputAction::Key { key, pressed } => InputEvent::Keyboard {
+                event: VirtualKeyboardKeyEvent { time, key, pressed },
+            },
+            InputAction::GestureSwipeBegin { fingers } => InputEvent::GestureSwipeBegin {
+                event: VirtualGestureBeginEvent { time, fingers },
+            },
+            InputAction::GestureSwipeUpdate { dx, dy } => InputEvent::GestureSwipeUpdate {
+                event: VirtualGestureSwipeUpdateEvent { time, dx, dy },
+            },
+            InputAction::GestureSwipeEnd { cancelled } => InputEvent::GestureSwipeEnd {
+                event: VirtualGestureEndEvent { time, cancelled },
+            },
+            InputAction::GesturePinchBegin { fingers } => InputEvent::GesturePinchBegin {
+                event: VirtualGestureBeginEvent { time, fingers },
+            },
+            InputAction::GesturePinchUpdate {
+                dx,
+                dy,
+                scale,
+                rotation,
+            } => InputEvent::GesturePinchUpdate {
+                event: VirtualGesturePinchUpdateEvent {
+                    time,
+                    dx,
+                    dy,
+                    scale,
+                    rotation,
+                },
+            },
+            InputAction::GesturePinchEnd { cancelled } => InputEvent::GesturePinchEnd {
+                event: VirtualGestureEndEvent { time, cancelled },
+            },
+            InputAction::GestureHoldBegin { fingers } => InputEvent::GestureHoldBegin {
+                event: VirtualGestureBeginEvent { time, fingers },
+            },
+            InputAction::GestureHoldEnd { cancelled } => InputEvent::GestureHoldEnd {
+                event: VirtualGestureEndEvent { time, cancelled },
+            },
+            InputAction::TouchDown { slot, x, y } => InputEvent::TouchDown {
+                event: VirtualTouchPositionEvent { time, slot, x, y },
+            },
+            InputAction::TouchMotion { slot, x, y } => InputEvent::TouchMotion {
+                event: VirtualTouchPositionEvent { time, slot, x, y },
+            },
+            InputAction::TouchUp { slot } => InputEvent::TouchUp {
+                event: VirtualTouchEvent {
+                    time,
+                    slot: Some(slot),
+                },
+            },
+            InputAction::TouchFrame => InputEvent::TouchFrame {
+                event: VirtualTouchEvent { time, slot: None },
+            },
+            InputAction::TouchCancel => InputEvent::TouchCancel {
+                event: VirtualTouchEvent { time, slot: None },
+            },
+            InputAction::TabletProximity { x, y, in_proximity } => {
+                InputEvent::TabletToolProximity {
+                    event: VirtualTabletToolEvent {
+                        time,
+                        x,
+                        y,
+                        active: in_proximity,
+                        button: 0,
+                    },
+                }
+            }
+            InputAction::TabletMotion { x, y } => InputEvent::TabletToolAxis {
+                event: VirtualTabletToolEvent {
+                    time,
+                    x,
+                    y,
+                    active: true,
+                    button: 0,
+                },
+            },
+            InputAction::TabletTip { down } => InputEvent::TabletToolTip {
+                event: VirtualTabletToolEvent {
+                    time,
+                    x: 0.0,
+                    y: 0.0,
+                    active: down,
+                    button: 0,
+                },
+            },
+            InputAction::TabletButton { button, pressed } => InputEvent::TabletToolButton {
+                event: VirtualTabletToolEvent {
+                    time,
+                    x: 0.0,
+                    y: 0.0,
+                    active: pressed,
+                    button,
+                },
+            },
+        }
+    }
+}
+
+impl<B: Backend + SpecialActions> App<B> {
+    /// Handles `action` as if it came from a real device.
+    pub fn inject_input(&mut self, action: InputAction) {
+        let time = Duration::from(self.clock.now());
+        self.handle_input_event(VirtualInput::event(action, time));
+    }
+
+    pub(crate) fn inject_input_request(
+        &mut self,
+        actions: Vec<InputAction>,
+        responder: &mut Responder<'_>,
+    ) {
+        let pointer = actions
+            .iter()
+            .any(|action| !matches!(action, InputAction::Key { .. }));
+        let invalid_key = actions.iter().find_map(|action| match action {
+            InputAction::Key { key, .. } if *key > KEY_MAX => Some(*key),
+            _ => None,
+        });
+        let response = if !self.debug.inject_input {
+            InjectInputResponse::Error(
+                "injecting input is disabled, set `inject-input = true` under `[debug]` in the config"
+                    .to_string(),
+            )
+        } else if let Some(key) = invalid_key {
+            InjectInputResponse::Error(format!("{key} is not a key code"))
+        } else if pointer && self.output_state.outputs.is_empty() {
+            InjectInputResponse::Error("No output for the pointer".to_string())
+        } else {
+            for action in actions {
+                self.inject_input(action);
+            }
+            InjectInputResponse::Ok
+        };
+
+        responder.send(&response);
+    }
+}
@@ -2,15 +2,15 @@ use std::time::Duration;
 
 use calloop::timer::{TimeoutAction, Timer};
 use smithay::backend::renderer::damage::OutputDamageTracker;
-use smithay::backend::renderer::element::surface::WaylandSurfaceRenderElement;
 use smithay::backend::winit::{WinitEvent, WinitEventLoop, WinitGraphicsBackend};
-use smithay::desktop::space::render_output;
 use smithay::output::Mode;
 use smithay::utils::{Rectangle, Transform};
 use smithay::{backend::renderer::gles::GlesRenderer, output};
 
 use crate::compositor::backend::Backend;
 use crate::compositor::data;
+use crate::compositor::layer;
+use crate::compositor::render::output_render_elements;
 use crate::compositor::state::App;
 
 pub struct WinitBackend {
@@ -133,28 +133,26 @@ pub fn run_winit(data: &mut data::Data<WinitBackend>) {
                             state.loop_signal.stop();
                         }
                         WinitEvent::Redraw => {
-                            state.handle_socket();
+                            state.dispatch_events();
                             state.engine.load_packages();
                         }
                     });
             }
 
             {
-                let space = &state.globals.lock().unwrap().space;
+                let globals = state.globals.lock().unwrap();
                 let (renderer, mut framebuffer) = state.backend.bind();
-
-                render_output::<_, WaylandSurfaceRenderElement<GlesRenderer>, _, _>(
-                    &output,
-                    renderer,
-                    &mut framebuffer,
-                    1.0,
-                    0,
-                    [space],
-                    &[],
-                    &mut output_damage_tracker,
-                    [0.1, 0.1, 0.1, 1.0],
-                )
-                .unwrap();
+                let elements = output_render_elements(renderer, &globals, &output);
+
+                output_damage_tracker
+                    .render_output(
+                        renderer,
+                        &mut framebuffer,
+                        0,
+                        &elements,
+                        [0.1, 0.1, 0.1, 1.0],
+                    )
+                    .unwrap();
             }
 
             let size = state.backend.backend.window_size();
@@ -170,6 +168,7 @@ pub fn run_winit(data: &mut data::Data<WinitBackend>) {
                     |_, _| Some(output.clone()),
                 );
             });
+            layer::send_frames(&output, start_time.elapsed());
 
             space.refresh();
 
@@ -0,0 +1,401 @@
+use std::collections::HashMap;
+
+use slotmap::{KeyData, SlotMap, new_key_type};
+use smithay::{
+    desktop::{Space, Window},
+    output::Output,
+    utils::{Logical, Point},
+};
+
+use crate::compositor::api::{WindowKey, general::fusion::compositor::types::WorkspaceId};
+
+new_key_type! {
+    pub struct WorkspaceKey;
+}
+
+impl From<WorkspaceId> for WorkspaceKey {
+    #[inline]
+    fn from(id: WorkspaceId) -> Self {
+        Self(KeyData::from_ffi(id.inner))
+    }
+}
+
+impl From<WorkspaceKey> for WorkspaceId {
+    #[inline]
+    fn from(key: WorkspaceKey) -> Self {
+        WorkspaceId {
+            inner: key.0.as_ffi(),
+        }
+    }
+}
+
+pub struct Workspace {
+    pub name: String,
+    pub output: String,
+    /// Windows in stacking order, bottom to top.
+    windows: Vec<WindowKey>,
+    /// Locations of the windows while the workspace is hidden.
+    locations: HashMap<WindowKey, Point<i32, Logical>>,
+    /// Locations minimized windows go back to, see [`Workspaces::restore`].
+    minimized: HashMap<WindowKey, Point<i32, Logical>>,
+}
+
+impl Workspace {
+    pub fn windows(&self) -> &[WindowKey] {
+        &self.windows
+    }
+}
+
+pub struct WorkspaceChanged {
+    pub output: String,
+    pub previous: Option<WorkspaceKey>,
+    pub current: WorkspaceKey,
+}
+
+#[derive(Default)]
+pub struct Workspaces {
+    inner: SlotMap<WorkspaceKey, Workspace>,
+    active: HashMap<String, WorkspaceKey>,
+    focused_output: Option<String>,
+}
+
+impl Workspaces {
+    pub fn iter(&self) -> impl Iterator<Item = (WorkspaceKey, &Workspace)> {
+        self.inner.iter()
+    }
+
+    pub fn get(&self, key: WorkspaceKey) -> Option<&Workspace> {
+        self.inner.get(key)
+    }
+
+    pub fn is_active(&self, key: WorkspaceKey) -> bool {
+        self.inner
+            .get(key)
+            .is_some_and(|workspace| self.active.get(&workspace.output) == Some(&key))
+    }
+
+    /// Output new windows and workspaces go to when nothing else is specified.
+    pub fn focused_output(&self) -> Option<&str> {
+        self.focused_output.as_deref()
+    }
+
+    pub fn window_workspace(&self, window: WindowKey) -> Option<WorkspaceKey> {
+        self.inner
+            .iter()
+            .find(|(_, workspace)| workspace.windows.contains(&window))
+            .map(|(key, _)| key)
+    }
+
+    /// Makes sure the output has an active workspace.
+    pub fn add_output(&mut self, output: &Output) {
+        let name = output.name();
+        if self.focused_output.is_none() {
+            self.focused_output = Some(name.clone());
+        }
+
+        if !self.active.contains_key(&name) {
+            self.create("1", &name);
+        }
+    }
+
+    /// Moves the workspaces of `output` to another output of `space`, call it before `output` leaves the space.
+    ///
+    /// Workspaces merge into the target's workspace of the same name, the others stay hidden until switched to.
+    /// Without another output they are kept for when `output` comes back.
+    pub fn remove_output(
+        &mut self,
+        space: &mut Space<Window>,
+        windows: &SlotMap<WindowKey, Window>,
+        output: &str,
+    ) {
+        let target = space
+            .outputs()
+            .map(Output::name)
+            .find(|name| name != output);
+        if self.focused_output.as_deref() == Some(output) {
+            self.focused_output.clone_from(&target);
+        }
+        let Some(target) = target else {
+            return;
+        };
+
+        let offset = output_location(space, &target) - output_location(space, output);
+        let active = self.active.remove(output);
+        let keys = (self.inner.iter())
+            .filter(|(_, workspace)| workspace.output == output)
+            .map(|(key, _)| key)
+            .collect::<Vec<_>>();
+        for key in keys {
+            let workspace = &mut self.inner[key];
+            if active == Some(key) {
+                for window_key in &workspace.windows {
+                    let Some(window) = windows.get(*window_key) else {
+                        continue;
+                    };
+
+                    // Minimized windows are not mapped and stay that way
+                    if let Some(location) = space.element_location(window) {
+                        workspace.locations.insert(*window_key, location);
+                        space.unmap_elem(window);
+                    }
+                }
+            }
+            let locations = workspace.locations.values_mut();
+            for location in locations.chain(workspace.minimized.values_mut()) {
+                *location += offset;
+            }
+            workspace.output.clone_from(&target);
+
+            let name = workspace.name.clone();
+            let Some(merged) = (self.inner.iter())
+                .find(|(other, workspace)| {
+                    *other != key && workspace.output == target && workspace.name == name
+                })
+                .map(|(other, _)| other)
+            else {
+                continue;
+            };
+
+            let workspace = self.inner.remove(key).unwrap();
+            let visible = self.is_active(merged);
+            let merged = &mut self.inner[merged];
+            for window_key in workspace.windows {
+                match (
+                    workspace.locations.get(&window_key),
+                    windows.get(window_key),
+                ) {
+                    (Some(location), Some(window)) if visible => {
+                        space.map_element(window.clone(), *location, false);
+                    }
+                    (Some(location), _) => {
+                        merged.locations.insert(window_key, *location);
+                    }
+                    (None, _) => {}
+                }
+                merged.windows.push(window_key);
+            }
+            merged.minimized.extend(workspace.minimized);
+        }
+    }
+
+    pub fn create(&mut self, name: &str, output: &str) -> WorkspaceKey {
+        if let Some((key, _)) = self
+            .inner
+            .iter()
+            .find(|(_, workspace)| workspace.name == name && workspace.output == output)
+        {
+            return key;
+        }
+
+        let key = self.inner.insert(Workspace {
+            name: name.to_string(),
+            output: output.to_string(),
+            windows: Vec::new(),
+            locations: HashMap::new(),
+            minimized: HashMap::new(),
+        });
+
+        if !self.active.contains_key(output) {
+            self.active.insert(output.to_string(), key);
+        }
+
+        key
+    }
+
+    /// Adds a new window to the active workspace of the focused output.
+    pub fn add_window(&mut self, window: WindowKey) {
+        let Some(key) = self
+            .focused_output
+            .as_ref()
+            .and_then(|output| self.active.get(output))
+        else {
+            return;
+        };
+
+        self.inner[*key].windows.push(window);
+    }
+
+    pub fn remove_window(&mut self, window: WindowKey) {
+        for workspace in self.inner.values_mut() {
+            workspace.windows.retain(|key| *key != window);
+            workspace.locations.remove(&window);
+            workspace.minimized.remove(&window);
+        }
+    }
+
+    pub fn is_minimized(&self, window: WindowKey) -> bool {
+        self.inner
+            .values()
+            .any(|workspace| workspace.minimized.contains_key(&window))
+    }
+
+    /// Unmaps a window until it is restored, `false` if it was minimized already.
+    pub fn minimize(&mut self, space: &mut Space<Window>, key: WindowKey, window: &Window) -> bool {
+        let Some(workspace) = self.window_workspace(key) else {
+            return false;
+        };
+        let workspace = &mut self.inner[workspace];
+        if workspace.minimized.contains_key(&key) {
+            return false;
+        }
+        // Windows of hidden workspaces are minimized too
+        let Some(location) = space
+            .element_location(window)
+            .or_else(|| workspace.locations.remove(&key))
+        else {
+            return false;
+        };
+
+        workspace.minimized.insert(key, location);
+        space.unmap_elem(window);
+        true
+    }
+
+    /// Puts a minimized window back where it was, `false` if it wasn't minimized.
+    pub fn restore(&mut self, space: &mut Space<Window>, key: WindowKey, window: &Window) -> bool {
+        let Some(workspace) = self.window_workspace(key) else {
+            return false;
+        };
+        let Some(location) = self.inner[workspace].minimized.remove(&key) else {
+            return false;
+        };
+
+        if self.is_active(workspace) {
+            space.map_element(window.clone(), location, false);
+        } else {
+            self.inner[workspace].locations.insert(key, location);
+        }
+        true
+    }
+
+    /// Places a window, the window only gets mapped if its workspace is visible.
+    pub fn place_window(
+        &mut self,
+        space: &mut Space<Window>,
+        key: WindowKey,
+        window: &Window,
+        location: Point<i32, Logical>,
+        activate: bool,
+    ) {
+        match self.window_workspace(key) {
+            // Minimized windows only get their location for when they are restored
+            Some(workspace) if self.inner[workspace].minimized.contains_key(&key) => {
+                self.inner[workspace].minimized.insert(key, location);
+            }
+            Some(workspace) if !self.is_active(workspace) => {
+                self.inner[workspace].locations.insert(key, location);
+            }
+            _ => space.map_element(window.clone(), location, activate),
+        }
+    }
+
+    pub fn switch(
+        &mut self,
+        space: &mut Space<Window>,
+        windows: &SlotMap<WindowKey, Window>,
+        key: WorkspaceKey,
+    ) -> Option<WorkspaceChanged> {
+        let output = self.inner.get(key)?.output.clone();
+        self.focused_output = Some(output.clone());
+
+        let previous = self.active.insert(output.clone(), key);
+        if previous == Some(key) {
+            return None;
+        }
+
+        if let Some(previous) = previous.and_then(|previous| self.inner.get_mut(previous)) {
+            previous.locations.clear();
+            for window_key in &previous.windows {
+                let Some(window) = windows.get(*window_key) else {
+                    continue;
+                };
+
+                // Minimized windows are not mapped and stay that way
+                if let Some(location) = space.element_location(window) {
+                    previous.locations.insert(*window_key, location);
+                    space.unmap_elem(window);
+                }
+            }
+        }
+
+        let workspace = &mut self.inner[key];
+        for window_key in &workspace.windows {
+            if let (Some(window), Some(location)) = (
+                windows.get(*window_key),
+                workspace.locations.remove(window_key),
+            ) {
+                space.map_element(window.clone(), location, false);
+            }
+        }
+
+        Some(WorkspaceChanged {
+            output,
+            previous,
+            current: key,
+        })
+    }
+
+    pub fn move_window(
+        &mut self,
+        space: &mut Space<Window>,
+        window_key: WindowKey,
+        window: &Window,
+        target: WorkspaceKey,
+    ) {
+        let Some(source) = self.window_workspace(window_key) else {
+            return;
+        };
+        if source == target || !self.inner.contains_key(target) {
+            return;
+        }
+
+        // Hidden windows of an inactive workspace should show up when moved to an active one
+        let visible = space.element_location(window).is_some() || !self.is_active(source);
+        let location = space
+            .element_location(window)
+            .or_else(|| self.inner[source].locations.get(&window_key).copied());
+
+        let source_output = self.inner[source].output.clone();
+        let target_output = self.inner[target].output.clone();
+        let offset =
+            output_location(space, &target_output) - output_location(space, &source_output);
+
+        let workspace = &mut self.inner[source];
+        workspace.windows.retain(|key| *key != window_key);
+        workspace.locations.remove(&window_key);
+        let minimized = workspace.minimized.remove(&window_key);
+        self.inner[target].windows.push(window_key);
+
+        // Minimized windows stay minimized
+        if let Some(location) = minimized {
+            self.inner[target]
+                .minimized
+                .insert(window_key, location + offset);
+            return;
+        }
+
+        space.unmap_elem(window);
+
+        let Some(location) = location else {
+            return;
+        };
+        let location = location + offset;
+
+        if self.is_active(target) {
+            if visible {
+                space.map_element(window.clone(), location, false);
+            }
+        } else {
+            self.inner[target].locations.insert(window_key, location);
+        }
+    }
+}
+
+fn output_location(space: &Space<Window>, name: &str) -> Point<i32, Logical> {
+    space
+        .outputs()
+        .find(|output| output.name() == name)
+        .and_then(|output| space.output_geometry(output))
+        .map(|geometry| geometry.loc)
+        .unwrap_or_default()
+}
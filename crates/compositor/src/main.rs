@@ -1,10 +1,4 @@
-#![allow(clippy::cast_sign_loss)]
-#![allow(clippy::cast_possible_truncation)]
-#![allow(clippy::cast_possible_wrap)]
-#![allow(clippy::too_many_lines)]
-mod compositor;
-
-use crate::compositor::{
+use compositor::{
     udev::{UdevData, init_udev},
     window::WinitBackend,
 };
@@ -45,6 +39,7 @@ fn run_winit() -> Result<(), Box<dyn std::error::Error>> {
     let mut data =
         compositor::init_compositor(event_loop.handle(), event_loop.get_signal(), backend)?;
     event_loop.run(None, &mut data, |_| {})?;
+    data.state.shutdown();
     Ok(())
 }
 
@@ -59,9 +54,10 @@ fn run_udev() -> Result<(), Box<dyn std::error::Error>> {
 
     event_loop.run(None, &mut data, |data| {
         data.state.render_all();
-        data.state.handle_socket();
+        data.state.dispatch_events();
         data.state.engine.load_packages();
         data.display.flush_clients().unwrap();
     })?;
+    data.state.shutdown();
     Ok(())
 }
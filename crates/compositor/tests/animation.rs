@@ -0,0 +1,164 @@
+use std::time::Duration;
+
+use compositor::{
+    animation::{AnimationTarget, Animations, Easing, WindowProperties},
+    api::WindowKey,
+};
+use slotmap::SlotMap;
+
+const START: Duration = Duration::from_secs(10);
+const DURATION: Duration = Duration::from_millis(200);
+
+fn window() -> WindowKey {
+    SlotMap::<WindowKey, ()>::with_key().insert(())
+}
+
+fn from() -> WindowProperties {
+    WindowProperties {
+        location: (0, 0).into(),
+        size: (100, 100).into(),
+        opacity: 1.0,
+    }
+}
+
+fn target() -> AnimationTarget {
+    AnimationTarget {
+        location: Some((100, 50).into()),
+        size: Some((200, 300).into()),
+        opacity: Some(0.0),
+    }
+}
+
+#[test]
+fn easing_keeps_endpoints() {
+    let curves = [
+        Easing::Linear,
+        Easing::EaseIn,
+        Easing::EaseOut,
+        Easing::EaseInOut,
+        Easing::CubicBezier {
+            x1: 0.3,
+            y1: -0.5,
+            x2: 0.7,
+            y2: 1.5,
+        },
+    ];
+
+    for easing in curves {
+        assert!(easing.apply(0.0).abs() < 1e-6, "{easing:?}");
+        assert!((easing.apply(1.0) - 1.0).abs() < 1e-6, "{easing:?}");
+    }
+}
+
+#[test]
+fn easing_curves() {
+    assert!((Easing::Linear.apply(0.25) - 0.25).abs() < 1e-9);
+    assert!(Easing::EaseIn.apply(0.5) < 0.5);
+    assert!(Easing::EaseOut.apply(0.5) > 0.5);
+    assert!((Easing::EaseInOut.apply(0.5) - 0.5).abs() < 1e-6);
+
+    // Same as CSS `ease`
+    let ease = Easing::CubicBezier {
+        x1: 0.25,
+        y1: 0.1,
+        x2: 0.25,
+        y2: 1.0,
+    };
+    assert!((ease.apply(0.5) - 0.8024).abs() < 1e-3);
+}
+
+#[test]
+fn animation_interpolates() {
+    let window = window();
+    let mut animations = Animations::default();
+    animations.start(window, from(), target(), DURATION, Easing::Linear);
+    assert!(animations.is_active());
+
+    // The animation starts with the first tick
+    let frame = animations.tick(START)[0];
+    assert_eq!(frame.location, Some((0, 0).into()));
+    assert_eq!(frame.size, Some((100, 100).into()));
+    assert_eq!(frame.opacity, Some(1.0));
+    assert!(!frame.finished);
+
+    let frame = animations.tick(START + DURATION / 2)[0];
+    assert_eq!(frame.location, Some((50, 25).into()));
+    assert_eq!(frame.size, Some((150, 200).into()));
+    assert_eq!(frame.opacity, Some(0.5));
+    assert!((animations.opacity(window) - 0.5).abs() < f32::EPSILON);
+    assert!(!frame.finished);
+
+    let frame = animations.tick(START + DURATION)[0];
+    assert_eq!(frame.location, Some((100, 50).into()));
+    assert_eq!(frame.size, Some((200, 300).into()));
+    assert_eq!(frame.opacity, Some(0.0));
+    assert!(frame.finished);
+
+    assert!(!animations.is_active());
+    assert!(animations.tick(START + DURATION * 2).is_empty());
+    // The final opacity stays after the animation
+    assert!(animations.opacity(window).abs() < f32::EPSILON);
+}
+
+#[test]
+fn animation_only_touches_target() {
+    let window = window();
+    let mut animations = Animations::default();
+    let target = AnimationTarget {
+        location: Some((10, 10).into()),
+        ..AnimationTarget::default()
+    };
+    animations.start(window, from(), target, DURATION, Easing::EaseOut);
+
+    let frame = animations.tick(START)[0];
+    assert!(frame.location.is_some());
+    assert_eq!(frame.size, None);
+    assert_eq!(frame.opacity, None);
+    assert!((animations.opacity(window) - 1.0).abs() < f32::EPSILON);
+}
+
+#[test]
+fn animation_is_deterministic() {
+    let run = || {
+        let mut animations = Animations::default();
+        animations.start(window(), from(), target(), DURATION, Easing::EaseInOut);
+        (0..=10)
+            .flat_map(|step| animations.tick(START + DURATION * step / 10))
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(run(), run());
+}
+
+#[test]
+fn zero_duration_finishes_immediately() {
+    let mut animations = Animations::default();
+    animations.start(window(), from(), target(), Duration::ZERO, Easing::Linear);
+
+    let frames = animations.tick(START);
+    assert_eq!(frames.len(), 1);
+    assert!(frames[0].finished);
+    assert_eq!(frames[0].location, Some((100, 50).into()));
+    assert!(!animations.is_active());
+}
+
+#[test]
+fn new_animation_replaces_running_one() {
+    let window = window();
+    let mut animations = Animations::default();
+    let (first, replaced) = animations.start(window, from(), target(), DURATION, Easing::Linear);
+    assert_eq!(replaced, None);
+
+    animations.tick(START);
+    let (second, replaced) = animations.start(window, from(), target(), DURATION, Easing::Linear);
+    assert_ne!(first, second);
+    assert_eq!(replaced, Some(first));
+
+    // The replacement starts at its own first tick
+    let frame = animations.tick(START + DURATION)[0];
+    assert_eq!(frame.animation, second);
+    assert!(!frame.finished);
+
+    assert_eq!(animations.remove_window(window), Some(second));
+    assert!(!animations.is_active());
+}
@@ -0,0 +1,392 @@
+//! Wayland clients the headless tests connect to the compositor.
+
+#![allow(dead_code)]
+
+use std::{
+    io::Write,
+    os::unix::{io::AsFd, net::UnixStream},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread,
+    time::Duration,
+};
+
+use compositor::{api::WindowKey, headless::Harness};
+use fusion_socket_protocol::BTN_LEFT;
+use smithay_client_toolkit::reexports::{
+    client::{
+        Connection, Dispatch, QueueHandle, WEnum, delegate_noop,
+        globals::registry_queue_init,
+        protocol::{
+            wl_buffer::WlBuffer,
+            wl_compositor::WlCompositor,
+            wl_pointer::{self, WlPointer},
+            wl_seat::{self, WlSeat},
+            wl_shm::{self, WlShm},
+            wl_shm_pool::WlShmPool,
+            wl_surface::WlSurface,
+        },
+    },
+    protocols::wp::pointer_constraints::zv1::client::{
+        zwp_confined_pointer_v1::ZwpConfinedPointerV1,
+        zwp_locked_pointer_v1::ZwpLockedPointerV1,
+        zwp_pointer_constraints_v1::{Lifetime, ZwpPointerConstraintsV1},
+    },
+    protocols::xdg::{
+        decoration::zv1::client::{
+            zxdg_decoration_manager_v1::ZxdgDecorationManagerV1,
+            zxdg_toplevel_decoration_v1::{self, Mode, ZxdgToplevelDecorationV1},
+        },
+        shell::client::{
+            xdg_surface::{self, XdgSurface},
+            xdg_toplevel::{self, ResizeEdge, XdgToplevel},
+            xdg_wm_base::{self, XdgWmBase},
+        },
+    },
+};
+
+/// Used by [`client_dispatch`], test files only need their own protocols.
+pub use smithay_client_toolkit::reexports::client as wl;
+
+pub const TIMEOUT: Duration = Duration::from_secs(5);
+pub const WINDOW_SIZE: u32 = 200;
+
+pub const BLACK: u32 = 0xFF00_0000;
+pub const WHITE: u32 = 0xFFFF_FFFF;
+pub const RED: u32 = 0xFFFF_0000;
+
+/// Ignores the events of the core objects every test client binds.
+macro_rules! client_dispatch {
+    ($client:ty) => {
+        $crate::common::wl::delegate_noop!($client: ignore $crate::common::wl::protocol::wl_compositor::WlCompositor);
+        $crate::common::wl::delegate_noop!($client: ignore $crate::common::wl::protocol::wl_surface::WlSurface);
+        $crate::common::wl::delegate_noop!($client: ignore $crate::common::wl::protocol::wl_shm::WlShm);
+        $crate::common::wl::delegate_noop!($client: ignore $crate::common::wl::protocol::wl_shm_pool::WlShmPool);
+        $crate::common::wl::delegate_noop!($client: ignore $crate::common::wl::protocol::wl_buffer::WlBuffer);
+
+        impl $crate::common::wl::Dispatch<
+            $crate::common::wl::protocol::wl_registry::WlRegistry,
+            $crate::common::wl::globals::GlobalListContents,
+        > for $client
+        {
+            fn event(
+                _: &mut Self,
+                _: &$crate::common::wl::protocol::wl_registry::WlRegistry,
+                _: $crate::common::wl::protocol::wl_registry::Event,
+                _: &$crate::common::wl::globals::GlobalListContents,
+                _: &$crate::common::wl::Connection,
+                _: &$crate::common::wl::QueueHandle<Self>,
+            ) {
+            }
+        }
+    };
+}
+#[allow(unused_imports)]
+pub(crate) use client_dispatch;
+
+/// Attaches a buffer of `size` filled with `color` to `surface` and commits it.
+pub fn attach_solid_buffer<D>(
+    shm: &WlShm,
+    surface: &WlSurface,
+    qh: &QueueHandle<D>,
+    size: (u32, u32),
+    color: u32,
+) where
+    D: Dispatch<WlShmPool, ()> + Dispatch<WlBuffer, ()> + 'static,
+{
+    let (width, height) = size;
+    let stride = width * 4;
+    let mut file = tempfile::tempfile().unwrap();
+    for _ in 0..width * height {
+        file.write_all(&color.to_le_bytes()).unwrap();
+    }
+    let pool = shm.create_pool(file.as_fd(), (stride * height) as i32, qh, ());
+    let buffer = pool.create_buffer(
+        0,
+        width as i32,
+        height as i32,
+        stride as i32,
+        wl_shm::Format::Argb8888,
+        qh,
+        (),
+    );
+
+    surface.attach(Some(&buffer), 0, 0);
+    surface.damage_buffer(0, 0, width as i32, height as i32);
+    surface.commit();
+}
+
+/// What the client asks for when a button is pressed on its window.
+#[derive(Clone, Copy)]
+pub enum OnPress {
+    Nothing,
+    Move,
+    Resize,
+    /// Locks the pointer to the surface
+    Lock,
+    /// Confines the pointer to the surface
+    Confine,
+}
+
+/// How a toplevel uses xdg-decoration.
+#[derive(Clone, Copy)]
+pub enum Decorations {
+    /// Doesn't use the protocol at all
+    Unmanaged,
+    /// Leaves the choice to the compositor
+    Undecided,
+    Requested(Mode),
+}
+
+/// What a toplevel was told by the compositor.
+#[derive(Default)]
+pub struct Observed {
+    pub decoration_mode: Option<Mode>,
+    pub closed: bool,
+}
+
+pub struct ToplevelClient {
+    on_press: OnPress,
+    seat: WlSeat,
+    surface: WlSurface,
+    toplevel: XdgToplevel,
+    constraints: ZwpPointerConstraintsV1,
+    observed: Arc<Mutex<Observed>>,
+    configured: bool,
+}
+
+client_dispatch!(ToplevelClient);
+delegate_noop!(ToplevelClient: ignore ZxdgDecorationManagerV1);
+delegate_noop!(ToplevelClient: ignore ZwpPointerConstraintsV1);
+delegate_noop!(ToplevelClient: ignore ZwpLockedPointerV1);
+delegate_noop!(ToplevelClient: ignore ZwpConfinedPointerV1);
+
+impl Dispatch<XdgWmBase, ()> for ToplevelClient {
+    fn event(
+        _: &mut Self,
+        wm_base: &XdgWmBase,
+        event: xdg_wm_base::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        if let xdg_wm_base::Event::Ping { serial } = event {
+            wm_base.pong(serial);
+        }
+    }
+}
+
+impl Dispatch<XdgSurface, ()> for ToplevelClient {
+    fn event(
+        state: &mut Self,
+        surface: &XdgSurface,
+        event: xdg_surface::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        if let xdg_surface::Event::Configure { serial } = event {
+            // Committed right away, so states like activation become current
+            surface.ack_configure(serial);
+            state.surface.commit();
+            state.configured = true;
+        }
+    }
+}
+
+impl Dispatch<XdgToplevel, ()> for ToplevelClient {
+    fn event(
+        state: &mut Self,
+        _: &XdgToplevel,
+        event: xdg_toplevel::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        if let xdg_toplevel::Event::Close = event {
+            state.observed.lock().unwrap().closed = true;
+        }
+    }
+}
+
+impl Dispatch<ZxdgToplevelDecorationV1, ()> for ToplevelClient {
+    fn event(
+        state: &mut Self,
+        _: &ZxdgToplevelDecorationV1,
+        event: zxdg_toplevel_decoration_v1::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        if let zxdg_toplevel_decoration_v1::Event::Configure {
+            mode: WEnum::Value(mode),
+        } = event
+        {
+            state.observed.lock().unwrap().decoration_mode = Some(mode);
+        }
+    }
+}
+
+impl Dispatch<WlSeat, ()> for ToplevelClient {
+    fn event(
+        _: &mut Self,
+        seat: &WlSeat,
+        event: wl_seat::Event,
+        _: &(),
+        _: &Connection,
+        qh: &QueueHandle<Self>,
+    ) {
+        if let wl_seat::Event::Capabilities {
+            capabilities: WEnum::Value(capabilities),
+        } = event
+            && capabilities.contains(wl_seat::Capability::Pointer)
+        {
+            seat.get_pointer(qh, ());
+        }
+    }
+}
+
+impl Dispatch<WlPointer, ()> for ToplevelClient {
+    fn event(
+        state: &mut Self,
+        pointer: &WlPointer,
+        event: wl_pointer::Event,
+        _: &(),
+        _: &Connection,
+        qh: &QueueHandle<Self>,
+    ) {
+        let wl_pointer::Event::Button {
+            serial,
+            state: WEnum::Value(wl_pointer::ButtonState::Pressed),
+            ..
+        } = event
+        else {
+            return;
+        };
+        match state.on_press {
+            OnPress::Nothing => {}
+            OnPress::Move => state.toplevel._move(&state.seat, serial),
+            OnPress::Resize => {
+                state
+                    .toplevel
+                    .resize(&state.seat, serial, ResizeEdge::BottomRight);
+            }
+            OnPress::Lock => {
+                state.constraints.lock_pointer(
+                    &state.surface,
+                    pointer,
+                    None,
+                    Lifetime::Persistent,
+                    qh,
+                    (),
+                );
+            }
+            OnPress::Confine => {
+                state.constraints.confine_pointer(
+                    &state.surface,
+                    pointer,
+                    None,
+                    Lifetime::Persistent,
+                    qh,
+                    (),
+                );
+            }
+        }
+    }
+}
+
+/// Maps a white toplevel and keeps serving it until the compositor goes away.
+pub fn spawn_toplevel(harness: &mut Harness, on_press: OnPress) -> WindowKey {
+    spawn_decorated(harness, on_press, Decorations::Unmanaged).0
+}
+
+/// Like [`spawn_toplevel`], with what the client was told to check its decorations.
+pub fn spawn_decorated(
+    harness: &mut Harness,
+    on_press: OnPress,
+    decorations: Decorations,
+) -> (WindowKey, Arc<Mutex<Observed>>) {
+    let known = harness
+        .state()
+        .globals()
+        .mapped_windows
+        .keys()
+        .collect::<Vec<_>>();
+
+    let stream = harness.connect_client().unwrap();
+    let observed = Arc::new(Mutex::new(Observed::default()));
+    let ready = Arc::new(AtomicBool::new(false));
+    {
+        let observed = observed.clone();
+        let ready = ready.clone();
+        thread::spawn(move || run_toplevel(stream, on_press, decorations, observed, &ready));
+    }
+    assert!(harness.dispatch_until(TIMEOUT, |_| ready.load(Ordering::SeqCst)));
+
+    let globals = harness.state().globals();
+    let window = globals
+        .mapped_windows
+        .keys()
+        .find(|key| !known.contains(key))
+        .unwrap();
+    (window, observed)
+}
+
+fn run_toplevel(
+    stream: UnixStream,
+    on_press: OnPress,
+    decorations: Decorations,
+    observed: Arc<Mutex<Observed>>,
+    ready: &AtomicBool,
+) {
+    let connection = Connection::from_socket(stream).unwrap();
+    let (globals, mut queue) = registry_queue_init::<ToplevelClient>(&connection).unwrap();
+    let qh = queue.handle();
+
+    let compositor: WlCompositor = globals.bind(&qh, 1..=6, ()).unwrap();
+    let shm: WlShm = globals.bind(&qh, 1..=1, ()).unwrap();
+    let wm_base: XdgWmBase = globals.bind(&qh, 1..=6, ()).unwrap();
+    let seat: WlSeat = globals.bind(&qh, 1..=7, ()).unwrap();
+    let constraints: ZwpPointerConstraintsV1 = globals.bind(&qh, 1..=1, ()).unwrap();
+
+    let surface = compositor.create_surface(&qh, ());
+    let xdg_surface = wm_base.get_xdg_surface(&surface, &qh, ());
+    let toplevel = xdg_surface.get_toplevel(&qh, ());
+    if !matches!(decorations, Decorations::Unmanaged) {
+        let manager: ZxdgDecorationManagerV1 = globals.bind(&qh, 1..=1, ()).unwrap();
+        let decoration = manager.get_toplevel_decoration(&toplevel, &qh, ());
+        if let Decorations::Requested(mode) = decorations {
+            decoration.set_mode(mode);
+        }
+    }
+    surface.commit();
+
+    let mut client = ToplevelClient {
+        on_press,
+        seat,
+        surface: surface.clone(),
+        toplevel,
+        constraints,
+        observed,
+        configured: false,
+    };
+    while !client.configured {
+        queue.blocking_dispatch(&mut client).unwrap();
+    }
+
+    attach_solid_buffer(&shm, &surface, &qh, (WINDOW_SIZE, WINDOW_SIZE), WHITE);
+    queue.roundtrip(&mut client).unwrap();
+    ready.store(true, Ordering::SeqCst);
+
+    // Ends once the harness is dropped and the connection closes
+    while queue.blocking_dispatch(&mut client).is_ok() {}
+}
+
+/// Presses and releases the left button at `location`.
+pub fn click(harness: &mut Harness, location: (f64, f64)) {
+    harness.pointer_motion(location);
+    harness.pointer_button(BTN_LEFT, true);
+    harness.pointer_button(BTN_LEFT, false);
+}
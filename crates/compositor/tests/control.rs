@@ -0,0 +1,390 @@
+use std::{
+    io::Write,
+    os::unix::{fs::PermissionsExt, net::UnixStream},
+    time::{Duration, Instant},
+};
+
+use calloop::EventLoop;
+use compositor::{
+    control,
+    data::Data,
+    headless::{Harness, HeadlessBackend},
+};
+use fusion_socket_protocol::{
+    CallPluginRequest, CallPluginResponse, Client, CompositorRequest, Envelope, Event, ExitRequest,
+    ExitResponse, Features, GetPluginInfoRequest, GetPluginInfoResponse, GetWorkspacesRequest,
+    GetWorkspacesResponse, Hello, HelloResponse, InjectInputRequest, InjectInputResponse,
+    InputAction, InstallPluginRequest, InstallPluginResponse, KEY_MAX, MAX_FRAME_SIZE,
+    PROTOCOL_VERSION, PingRequest, PingResponse, ProtocolError, Reply, SetKeyboardRequest,
+    SetKeyboardResponse, SetPluginEnabledRequest, SetPluginEnabledResponse, SubscribeRequest,
+    SubscribeResponse, Topic, UninstallPluginRequest, UninstallPluginResponse,
+};
+
+const OUTPUT: (i32, i32) = (800, 600);
+
+fn frame(message: &impl serde::Serialize) -> Vec<u8> {
+    postcard::to_stdvec_cobs(message).unwrap()
+}
+
+fn envelope(id: u32, request: impl Into<CompositorRequest>) -> Vec<u8> {
+    let body = postcard::to_stdvec(&request.into()).unwrap();
+    frame(&Envelope { id, body })
+}
+
+/// Connects without a [`Client`], for frames a client would never send.
+fn raw_connection(harness: &mut Harness) -> UnixStream {
+    let mut stream = UnixStream::connect(harness.control_socket()).unwrap();
+    stream.write_all(&frame(&Hello::default())).unwrap();
+    let response = harness.response::<HelloResponse>(&mut stream).unwrap();
+    assert!(matches!(response, HelloResponse::Ok { .. }));
+    stream
+}
+
+#[test]
+fn handshake_reports_the_version_and_features() {
+    let mut harness = Harness::new(&[("one", OUTPUT)]).unwrap();
+    let client = harness.control_client().unwrap();
+    assert_eq!(client.version(), PROTOCOL_VERSION);
+    assert!(client.features().contains(Features::SUBSCRIBE));
+    assert!(client.features().contains(Features::INJECT_INPUT));
+}
+
+#[test]
+fn the_socket_belongs_to_its_compositor() {
+    let harness = Harness::new(&[("one", OUTPUT)]).unwrap();
+    let permissions = std::fs::metadata(harness.control_socket())
+        .unwrap()
+        .permissions();
+    assert_eq!(permissions.mode() & 0o777, 0o600);
+
+    // A second compositor must not take over a socket that is still served
+    let event_loop = EventLoop::<Data<HeadlessBackend>>::try_new().unwrap();
+    assert!(control::listen(&event_loop.handle(), harness.control_socket()).is_err());
+    assert!(UnixStream::connect(harness.control_socket()).is_ok());
+}
+
+#[test]
+fn exit_shuts_the_socket_down() {
+    let mut harness = Harness::new(&[("one", OUTPUT)]).unwrap();
+    let mut client = harness.control_client().unwrap();
+    harness
+        .request_on::<ExitResponse>(&mut client, ExitRequest)
+        .unwrap();
+
+    harness.state().shutdown();
+    assert!(!harness.control_socket().exists());
+    assert!(
+        harness
+            .request_on::<PingResponse>(&mut client, PingRequest)
+            .is_err()
+    );
+}
+
+#[test]
+fn unsupported_versions_are_refused() {
+    let mut harness = Harness::new(&[("one", OUTPUT)]).unwrap();
+    let mut stream = UnixStream::connect(harness.control_socket()).unwrap();
+
+    let hello = Hello {
+        version: PROTOCOL_VERSION + 1,
+        ..Hello::default()
+    };
+    stream.write_all(&frame(&hello)).unwrap();
+    let response = harness.response::<HelloResponse>(&mut stream).unwrap();
+    assert!(matches!(response, HelloResponse::Error(_)));
+    assert!(harness.response::<Reply>(&mut stream).is_err());
+}
+
+#[test]
+fn requests_need_a_handshake() {
+    let mut harness = Harness::new(&[("one", OUTPUT)]).unwrap();
+    let mut stream = UnixStream::connect(harness.control_socket()).unwrap();
+
+    stream.write_all(&envelope(0, PingRequest)).unwrap();
+    let response = harness.response::<HelloResponse>(&mut stream).unwrap();
+    assert!(matches!(response, HelloResponse::Error(_)));
+    assert!(harness.response::<Reply>(&mut stream).is_err());
+}
+
+#[test]
+fn connections_serve_several_requests() {
+    let mut harness = Harness::new(&[("one", OUTPUT)]).unwrap();
+    let mut first = harness.control_client().unwrap();
+    let mut second = harness.control_client().unwrap();
+
+    for _ in 0..3 {
+        harness
+            .request_on::<PingResponse>(&mut first, PingRequest)
+            .unwrap();
+        let response = harness
+            .request_on::<GetWorkspacesResponse>(&mut second, GetWorkspacesRequest)
+            .unwrap();
+        assert!(matches!(response, GetWorkspacesResponse::Ok(_)));
+    }
+    assert_eq!(harness.state().control_connections.len(), 2);
+
+    drop(first);
+    drop(second);
+    assert!(harness.dispatch_until(Duration::from_secs(5), |state| {
+        state.control_connections.is_empty()
+    }));
+}
+
+#[test]
+fn requests_may_arrive_in_pieces() {
+    let mut harness = Harness::new(&[("one", OUTPUT)]).unwrap();
+    let mut stream = raw_connection(&mut harness);
+
+    // Half a request followed by the rest and a whole second one
+    let mut bytes = envelope(1, PingRequest);
+    bytes.extend(envelope(2, PingRequest));
+    stream.write_all(&bytes[..1]).unwrap();
+    harness.dispatch();
+    harness.dispatch();
+    stream.write_all(&bytes[1..]).unwrap();
+
+    for id in [1, 2] {
+        let reply = harness.response::<Reply>(&mut stream).unwrap();
+        assert!(matches!(reply, Reply::Response { id: reply, .. } if reply == id));
+    }
+}
+
+#[test]
+fn malformed_requests_get_an_error() {
+    let mut harness = Harness::new(&[("one", OUTPUT)]).unwrap();
+    let mut stream = raw_connection(&mut harness);
+
+    stream.write_all(&[0x03, 0xff, 0xff, 0x00]).unwrap();
+    let reply = harness.response::<Reply>(&mut stream).unwrap();
+    assert!(matches!(
+        reply,
+        Reply::Error {
+            id: None,
+            error: ProtocolError::Malformed(_)
+        }
+    ));
+
+    // A readable envelope around a broken request keeps its ID
+    let body = vec![3, 0xff];
+    stream.write_all(&frame(&Envelope { id: 7, body })).unwrap();
+    let reply = harness.response::<Reply>(&mut stream).unwrap();
+    assert!(matches!(
+        reply,
+        Reply::Error {
+            id: Some(7),
+            error: ProtocolError::Malformed(_)
+        }
+    ));
+
+    // The connection is still usable
+    stream.write_all(&envelope(8, PingRequest)).unwrap();
+    let reply = harness.response::<Reply>(&mut stream).unwrap();
+    assert!(matches!(reply, Reply::Response { id: 8, .. }));
+}
+
+#[test]
+fn unknown_requests_are_reported() {
+    let mut harness = Harness::new(&[("one", OUTPUT)]).unwrap();
+    let mut stream = raw_connection(&mut harness);
+
+    // A request appended by a newer protocol
+    let body = postcard::to_stdvec(&CompositorRequest::VARIANTS).unwrap();
+    stream.write_all(&frame(&Envelope { id: 3, body })).unwrap();
+    let reply = harness.response::<Reply>(&mut stream).unwrap();
+    assert_eq!(
+        reply,
+        Reply::Error {
+            id: Some(3),
+            error: ProtocolError::UnknownRequest(CompositorRequest::VARIANTS),
+        }
+    );
+}
+
+#[test]
+fn oversized_frames_close_the_connection() {
+    let mut harness = Harness::new(&[("one", OUTPUT)]).unwrap();
+    let mut stream = raw_connection(&mut harness);
+
+    // Written from another thread, the compositor stops reading halfway
+    let mut writer = stream.try_clone().unwrap();
+    std::thread::spawn(move || {
+        let _ = writer.write_all(&vec![1; MAX_FRAME_SIZE + 4096]);
+    });
+    let reply = harness.response::<Reply>(&mut stream).unwrap();
+    assert_eq!(
+        reply,
+        Reply::Error {
+            id: None,
+            error: ProtocolError::TooLarge,
+        }
+    );
+    assert!(harness.response::<Reply>(&mut stream).is_err());
+
+    harness.request::<PingResponse>(PingRequest).unwrap();
+}
+
+#[test]
+fn clients_that_stop_reading_are_dropped() {
+    let mut harness = Harness::new(&[("one", OUTPUT)]).unwrap();
+    let mut stream = raw_connection(&mut harness);
+    stream.set_nonblocking(true).unwrap();
+
+    // Requests keep coming while none of the responses are taken
+    let requests = envelope(1, GetWorkspacesRequest).repeat(1024);
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while !harness.state().control_connections.is_empty() {
+        assert!(Instant::now() < deadline, "the client was never dropped");
+        let _ = stream.write(&requests);
+        harness.dispatch();
+    }
+
+    harness.request::<PingResponse>(PingRequest).unwrap();
+}
+
+#[test]
+fn plugin_requests_report_errors() {
+    let mut harness = Harness::new(&[("one", OUTPUT)]).unwrap();
+    let plugin_id = || "missing.plugin".to_string();
+
+    let info = GetPluginInfoRequest {
+        plugin_id: plugin_id(),
+    };
+    let response = harness.request::<GetPluginInfoResponse>(info).unwrap();
+    assert!(matches!(response, GetPluginInfoResponse::Error(_)));
+
+    let enable = SetPluginEnabledRequest {
+        plugin_id: plugin_id(),
+        enabled: false,
+    };
+    let response = harness.request::<SetPluginEnabledResponse>(enable).unwrap();
+    assert!(matches!(response, SetPluginEnabledResponse::Error(_)));
+
+    let uninstall = UninstallPluginRequest {
+        plugin_id: plugin_id(),
+    };
+    let response = harness
+        .request::<UninstallPluginResponse>(uninstall)
+        .unwrap();
+    assert!(matches!(response, UninstallPluginResponse::Error(_)));
+
+    // Only packages are accepted, before anything is read
+    let install = InstallPluginRequest {
+        path: "/missing/plugin.txt".to_string(),
+    };
+    let response = harness.request::<InstallPluginResponse>(install).unwrap();
+    assert!(matches!(response, InstallPluginResponse::Error(_)));
+}
+
+#[test]
+fn plugin_calls_need_the_debug_setting() {
+    let mut harness = Harness::new(&[("one", OUTPUT)]).unwrap();
+    let call = CallPluginRequest {
+        plugin_id: "missing.plugin".to_string(),
+        capability: "compositor".to_string(),
+        function: "init".to_string(),
+        args: "[]".to_string(),
+    };
+
+    let client = harness.control_client().unwrap();
+    assert!(!client.features().contains(Features::CALL_PLUGIN));
+    let response = harness.request::<CallPluginResponse>(call.clone()).unwrap();
+    assert!(matches!(response, CallPluginResponse::Error(error) if error.contains("disabled")));
+
+    std::fs::write(harness.config_file(), "[debug]\ncall-plugin = true\n").unwrap();
+    harness.state().reload_config().unwrap();
+    let client = harness.control_client().unwrap();
+    assert!(client.features().contains(Features::CALL_PLUGIN));
+    // The reloaded config doesn't turn it on
+    assert!(!client.features().contains(Features::INJECT_INPUT));
+    let response = harness.request::<CallPluginResponse>(call.clone()).unwrap();
+    assert!(matches!(response, CallPluginResponse::Error(error) if error.contains("not found")));
+
+    let broken = CallPluginRequest {
+        args: "{}".to_string(),
+        ..call
+    };
+    let response = harness.request::<CallPluginResponse>(broken).unwrap();
+    assert!(matches!(response, CallPluginResponse::Error(error) if error.contains("JSON")));
+}
+
+#[test]
+fn injected_keys_must_be_key_codes() {
+    let mut harness = Harness::new(&[("one", OUTPUT)]).unwrap();
+    let click = |key| {
+        vec![
+            InputAction::Key { key, pressed: true },
+            InputAction::Key {
+                key,
+                pressed: false,
+            },
+        ]
+    };
+
+    // Nothing is injected when one of the keys is out of range
+    let request = InjectInputRequest {
+        actions: [click(30), click(u32::MAX)].concat(),
+    };
+    let response = harness.request::<InjectInputResponse>(request).unwrap();
+    assert!(matches!(response, InjectInputResponse::Error(error) if error.contains("key code")));
+
+    let request = InjectInputRequest {
+        actions: click(KEY_MAX),
+    };
+    let response = harness.request::<InjectInputResponse>(request).unwrap();
+    assert_eq!(response, InjectInputResponse::Ok);
+}
+
+fn subscribe(harness: &mut Harness, topics: Vec<Topic>) -> Client {
+    let mut client = harness.control_client().unwrap();
+    let response = harness
+        .request_on::<SubscribeResponse>(&mut client, SubscribeRequest { topics })
+        .unwrap();
+    assert!(matches!(response, SubscribeResponse::Ok));
+    client
+}
+
+#[test]
+fn subscribers_get_the_events_of_their_topics() {
+    let mut harness = Harness::new(&[("one", OUTPUT)]).unwrap();
+    let mut layouts = subscribe(&mut harness, vec![Topic::Layout]);
+    let mut everything = subscribe(&mut harness, Vec::new());
+
+    let set = SetKeyboardRequest {
+        layout: Some("de".to_string()),
+        ..SetKeyboardRequest::default()
+    };
+    harness.request::<SetKeyboardResponse>(set).unwrap();
+    let changed = Event::LayoutChanged {
+        layout: "German".to_string(),
+    };
+    let event = harness.next_event(&mut layouts).unwrap();
+    assert_eq!(event, Some(changed.clone()));
+    let event = harness.next_event(&mut everything).unwrap();
+    assert_eq!(event, Some(changed));
+
+    harness.state().add_headless_output("two", OUTPUT).unwrap();
+    harness.state().remove_headless_output("one");
+    assert_eq!(
+        harness.next_event(&mut everything).unwrap(),
+        Some(Event::OutputAdded {
+            name: "two".to_string()
+        })
+    );
+    assert_eq!(
+        harness.next_event(&mut everything).unwrap(),
+        Some(Event::OutputRemoved {
+            name: "one".to_string()
+        })
+    );
+
+    // Subscribers that went away are dropped
+    drop(layouts);
+    drop(everything);
+    harness
+        .state()
+        .add_headless_output("three", OUTPUT)
+        .unwrap();
+    assert!(harness.dispatch_until(Duration::from_secs(5), |state| {
+        state.control_connections.is_empty()
+    }));
+}
@@ -0,0 +1,108 @@
+use std::time::Duration;
+
+use compositor::{
+    config::Config,
+    cursor::{CursorConfig, XCursor},
+    headless::Harness,
+};
+use smithay::input::pointer::{CursorIcon, CursorImageStatus};
+use xcursor::parser::Image;
+
+use crate::common::{BLACK, RED};
+
+mod common;
+
+const OUTPUT: (i32, i32) = (800, 600);
+
+fn image(size: u32, delay: u32) -> Image {
+    Image {
+        size,
+        width: size,
+        height: size,
+        xhot: size / 4,
+        yhot: size / 4,
+        delay,
+        pixels_rgba: vec![0; (size * size * 4) as usize],
+        pixels_argb: vec![0; (size * size * 4) as usize],
+    }
+}
+
+#[test]
+fn cursor_table_is_parsed() {
+    let config: Config = toml::from_str("[cursor]\ntheme = \"Adwaita\"\nsize = 32").unwrap();
+    assert_eq!(config.cursor.theme(), "Adwaita");
+    assert_eq!(config.cursor.size(), 32);
+    assert!(config.cursor.hardware);
+
+    assert!(toml::from_str::<Config>("[cursor]\ncolor = \"red\"").is_err());
+}
+
+#[test]
+fn frames_follow_scale_and_time() {
+    let cursor = XCursor::from_images(
+        vec![image(24, 100), image(24, 50), image(48, 100), image(48, 50)],
+        24,
+    );
+    assert!(cursor.is_animated());
+
+    // Scaled outputs use the larger images with a matching buffer scale
+    let frame = cursor.frame(48, Duration::ZERO).unwrap();
+    assert_eq!((frame.image.size, frame.scale), (48, 2));
+    let frame = cursor.frame(30, Duration::ZERO).unwrap();
+    assert_eq!((frame.image.size, frame.scale), (24, 1));
+
+    // The animation loops after the delays of all frames
+    let frame = cursor.frame(24, Duration::from_millis(120)).unwrap();
+    assert_eq!(frame.image.delay, 50);
+    let frame = cursor.frame(24, Duration::from_millis(160)).unwrap();
+    assert_eq!(frame.image.delay, 100);
+
+    let still = XCursor::from_images(vec![image(24, 0), image(48, 0)], 24);
+    assert!(!still.is_animated());
+    assert!(
+        XCursor::from_images(Vec::new(), 24)
+            .frame(24, Duration::ZERO)
+            .is_none()
+    );
+}
+
+#[test]
+fn missing_theme_icons_are_skipped() {
+    let mut harness = Harness::new(&[("one", OUTPUT)]).unwrap();
+    let cursor = &mut harness.state().input_state.cursor;
+    cursor.configure(CursorConfig {
+        theme: Some("no-such-theme".to_string()),
+        size: Some(32),
+        hardware: true,
+    });
+    assert_eq!(cursor.size(), 32);
+
+    cursor.set_icon(CursorImageStatus::Named(CursorIcon::Wait));
+    assert!(cursor.xcursor(CursorIcon::Wait).is_none());
+    assert!(!cursor.is_animated());
+}
+
+#[test]
+fn cursor_is_drawn_on_the_output_under_it() {
+    let mut harness = Harness::new(&[("one", OUTPUT), ("two", OUTPUT)]).unwrap();
+    // A red square with the hotspot in its top left corner
+    let red = Image {
+        xhot: 0,
+        yhot: 0,
+        pixels_rgba: [0, 0, 0xff, 0xff].repeat(24 * 24),
+        ..image(24, 0)
+    };
+    let cursor = &mut harness.state().input_state.cursor;
+    cursor.set_xcursor(CursorIcon::Default, XCursor::from_images(vec![red], 24));
+    cursor.set_icon(CursorImageStatus::Named(CursorIcon::Default));
+
+    harness.pointer_motion((850.0, 50.0));
+    harness.state().render_headless().unwrap();
+    assert_eq!(harness.snapshot("two").unwrap().pixel(60, 60), RED);
+    assert_eq!(harness.snapshot("one").unwrap().pixel(60, 60), BLACK);
+
+    harness.pointer_motion((50.0, 50.0));
+    harness.state().render_headless().unwrap();
+    assert_eq!(harness.snapshot("one").unwrap().pixel(60, 60), RED);
+    assert_eq!(harness.snapshot("two").unwrap().pixel(60, 60), BLACK);
+}
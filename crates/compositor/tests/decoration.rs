@@ -0,0 +1,116 @@
+use compositor::{
+    api::{CompositorGlobals, WindowKey},
+    decoration::{self, DecorationHit},
+    headless::Harness,
+};
+use smithay::{
+    reexports::wayland_protocols::xdg::decoration::zv1::server::zxdg_toplevel_decoration_v1::Mode as DecorationMode,
+    utils::Point,
+};
+use smithay_client_toolkit::reexports::protocols::xdg::decoration::zv1::client::zxdg_toplevel_decoration_v1::Mode;
+
+use crate::common::{Decorations, OnPress, TIMEOUT, click, spawn_decorated};
+
+mod common;
+
+const OUTPUT: (i32, i32) = (800, 600);
+
+fn is_decorated(globals: &CompositorGlobals, window: WindowKey) -> bool {
+    decoration::has_server_side_decorations(&globals.mapped_windows[window])
+}
+
+/// A point on the title bar of `window` that hits `button`.
+fn button(harness: &mut Harness, window: WindowKey, button: DecorationHit) -> (f64, f64) {
+    let globals = harness.state().globals();
+    let element = &globals.mapped_windows[window];
+    let content = globals.space.element_geometry(element).unwrap();
+    let config = &globals.decorations;
+    let y = f64::from(content.loc.y - config.title_height / 2);
+    (content.loc.x..content.loc.x + content.size.w)
+        .map(|x| Point::from((f64::from(x) + 0.5, y)))
+        .find(|point| config.hit(content, *point) == Some(button))
+        .map(|point| (point.x, point.y))
+        .expect("the title bar has the button")
+}
+
+#[test]
+fn decoration_mode_is_negotiated() {
+    let mut harness = Harness::new(&[("one", OUTPUT)]).unwrap();
+    let (undecided, undecided_seen) =
+        spawn_decorated(&mut harness, OnPress::Nothing, Decorations::Undecided);
+    let (client_side, client_side_seen) = spawn_decorated(
+        &mut harness,
+        OnPress::Nothing,
+        Decorations::Requested(Mode::ClientSide),
+    );
+    let (server_side, server_side_seen) = spawn_decorated(
+        &mut harness,
+        OnPress::Nothing,
+        Decorations::Requested(Mode::ServerSide),
+    );
+
+    // Clients without a preference get the default, server-side decorations
+    assert!(harness.dispatch_until(TIMEOUT, |state| {
+        let globals = state.globals();
+        is_decorated(&globals, undecided) && is_decorated(&globals, server_side)
+    }));
+    assert!(!is_decorated(&harness.state().globals(), client_side));
+    assert_eq!(
+        undecided_seen.lock().unwrap().decoration_mode,
+        Some(Mode::ServerSide)
+    );
+    assert_eq!(
+        client_side_seen.lock().unwrap().decoration_mode,
+        Some(Mode::ClientSide)
+    );
+    assert_eq!(
+        server_side_seen.lock().unwrap().decoration_mode,
+        Some(Mode::ServerSide)
+    );
+
+    // The WM overrides what the client asked for
+    {
+        let globals = harness.state().globals();
+        let toplevel = globals.mapped_windows[server_side].toplevel().unwrap();
+        let default = globals.decorations.default_mode;
+        decoration::force_mode(toplevel, Some(DecorationMode::ClientSide), default);
+        toplevel.send_configure();
+    }
+    assert!(harness.dispatch_until(TIMEOUT, |state| {
+        !is_decorated(&state.globals(), server_side)
+    }));
+    assert_eq!(
+        server_side_seen.lock().unwrap().decoration_mode,
+        Some(Mode::ClientSide)
+    );
+}
+
+#[test]
+fn decoration_buttons_are_clickable() {
+    let mut harness = Harness::new(&[("one", OUTPUT)]).unwrap();
+    let (window, observed) = spawn_decorated(
+        &mut harness,
+        OnPress::Nothing,
+        Decorations::Requested(Mode::ServerSide),
+    );
+    harness.place_window(window, (100, 100));
+    assert!(harness.dispatch_until(TIMEOUT, |state| { is_decorated(&state.globals(), window) }));
+
+    let minimize = button(&mut harness, window, DecorationHit::Minimize);
+    click(&mut harness, minimize);
+    assert!(harness.state().globals().workspaces.is_minimized(window));
+    harness.state().globals().restore_window(window);
+    assert!(!harness.state().globals().workspaces.is_minimized(window));
+
+    let maximize = button(&mut harness, window, DecorationHit::Maximize);
+    click(&mut harness, maximize);
+    let element = harness.state().globals().mapped_windows[window].clone();
+    assert!(decoration::is_maximized(&element));
+    let maximize = button(&mut harness, window, DecorationHit::Maximize);
+    click(&mut harness, maximize);
+    assert!(!decoration::is_maximized(&element));
+
+    let close = button(&mut harness, window, DecorationHit::Close);
+    click(&mut harness, close);
+    assert!(harness.dispatch_until(TIMEOUT, |_| observed.lock().unwrap().closed));
+}
@@ -0,0 +1,100 @@
+use compositor::{
+    api::WindowKey,
+    effect::{StyleRules, WindowStyle},
+    headless::Harness,
+};
+use smithay::reexports::wayland_protocols::xdg::shell::server::xdg_toplevel::State;
+
+use crate::common::{BLACK, OnPress, TIMEOUT, WHITE, click, spawn_toplevel};
+
+mod common;
+
+const OUTPUT: (i32, i32) = (800, 600);
+
+/// A white window drawn at half opacity over the black background.
+const HALF_WHITE: u32 = 0xFF80_8080;
+
+/// Compares colors per channel, blending may round either way.
+fn assert_color(actual: u32, expected: u32) {
+    let close = (0..4).all(|channel| {
+        let shift = channel * 8;
+        let a = (actual >> shift) & 0xFF;
+        let e = (expected >> shift) & 0xFF;
+        a.abs_diff(e) <= 1
+    });
+    assert!(close, "expected {expected:#010x}, got {actual:#010x}");
+}
+
+fn pixel(harness: &mut Harness, location: (i32, i32)) -> u32 {
+    harness.render();
+    let snapshot = harness.snapshot("one").unwrap();
+    snapshot.pixel(location.0, location.1)
+}
+
+/// Clicks `window` at `location` and waits for the client to take the activation.
+fn focus(harness: &mut Harness, window: WindowKey, location: (f64, f64)) {
+    click(harness, location);
+    // Out of the way of the sampled pixels, the cursor is rendered too
+    harness.pointer_motion((700.0, 500.0));
+    assert!(harness.dispatch_until(TIMEOUT, |state| {
+        let globals = state.globals();
+        let toplevel = globals.mapped_windows[window].toplevel().unwrap();
+        toplevel.current_state().states.contains(State::Activated)
+    }));
+}
+
+#[test]
+fn window_opacity_is_rendered() {
+    let mut harness = Harness::new(&[("one", OUTPUT)]).unwrap();
+    let window = spawn_toplevel(&mut harness, OnPress::Nothing);
+    harness.place_window(window, (100, 100));
+    assert_color(pixel(&mut harness, (200, 200)), WHITE);
+
+    let style = WindowStyle {
+        opacity: 0.5,
+        ..WindowStyle::default()
+    };
+    harness
+        .state()
+        .globals()
+        .styles
+        .set_window_style(window, Some(style));
+    assert_color(pixel(&mut harness, (200, 200)), HALF_WHITE);
+    assert_color(pixel(&mut harness, (50, 50)), BLACK);
+
+    // Without its own style the window follows the rules again
+    harness
+        .state()
+        .globals()
+        .styles
+        .set_window_style(window, None);
+    assert_color(pixel(&mut harness, (200, 200)), WHITE);
+}
+
+#[test]
+fn unfocused_windows_are_dimmed() {
+    let mut harness = Harness::new(&[("one", OUTPUT)]).unwrap();
+    let left = spawn_toplevel(&mut harness, OnPress::Nothing);
+    let right = spawn_toplevel(&mut harness, OnPress::Nothing);
+    harness.place_window(left, (0, 0));
+    harness.place_window(right, (300, 0));
+    focus(&mut harness, left, (100.0, 100.0));
+
+    assert_color(pixel(&mut harness, (100, 100)), WHITE);
+    assert_color(pixel(&mut harness, (400, 100)), WHITE);
+
+    // Rules changed at runtime apply on the next frame
+    harness.state().globals().styles.rules = StyleRules {
+        focused: WindowStyle::default(),
+        unfocused: WindowStyle {
+            opacity: 0.5,
+            ..WindowStyle::default()
+        },
+    };
+    assert_color(pixel(&mut harness, (100, 100)), WHITE);
+    assert_color(pixel(&mut harness, (400, 100)), HALF_WHITE);
+
+    focus(&mut harness, right, (400.0, 100.0));
+    assert_color(pixel(&mut harness, (100, 100)), HALF_WHITE);
+    assert_color(pixel(&mut harness, (400, 100)), WHITE);
+}
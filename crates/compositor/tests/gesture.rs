@@ -0,0 +1,146 @@
+use compositor::{
+    config::{Config, ConfigError},
+    gesture::{GestureError, GestureKind, GesturePhase, GestureUpdate, Gestures},
+    headless::Harness,
+    keybinding::KeyAction,
+};
+use smithay::input::keyboard::FilterResult;
+
+const OUTPUT: (i32, i32) = (800, 600);
+
+const CONFIG: &str = r#"
+[keybindings.default]
+
+[keybindings.overview]
+"Escape" = { mode = "default" }
+
+[[gesture]]
+type = "swipe"
+fingers = 3
+direction = "left"
+plugin = "workspace-next"
+
+[[gesture]]
+type = "pinch"
+fingers = 4
+direction = "in"
+mode = "overview"
+"#;
+
+fn parse(config: &str) -> Result<Gestures, ConfigError> {
+    let config: Config = toml::from_str(config)?;
+    Ok(config.gestures()?)
+}
+
+fn intercepted(result: FilterResult<Option<GestureUpdate>>) -> Option<(GesturePhase, f64)> {
+    match result {
+        FilterResult::Intercept(update) => update.map(|update| (update.phase, update.progress)),
+        FilterResult::Forward => panic!("gesture was forwarded"),
+    }
+}
+
+#[test]
+fn config_is_validated() {
+    let gestures = parse(CONFIG).unwrap();
+    assert_eq!(gestures.bindings().len(), 2);
+    assert_eq!(
+        gestures.bindings()[0].action,
+        KeyAction::Plugin("workspace-next".to_string())
+    );
+
+    let wrong_direction = r#"
+        [[gesture]]
+        type = "pinch"
+        fingers = 2
+        direction = "left"
+        action = "quit"
+    "#;
+    assert!(matches!(
+        parse(wrong_direction),
+        Err(ConfigError::Gesture(GestureError::Direction(_)))
+    ));
+
+    let no_fingers = r#"
+        [[gesture]]
+        type = "hold"
+        fingers = 0
+        action = "quit"
+    "#;
+    assert!(matches!(
+        parse(no_fingers),
+        Err(ConfigError::Gesture(GestureError::Fingers(_)))
+    ));
+
+    let two_actions = r#"
+        [[gesture]]
+        type = "hold"
+        fingers = 3
+        action = "quit"
+        spawn = "kitty"
+    "#;
+    assert!(matches!(
+        parse(two_actions),
+        Err(ConfigError::Gesture(GestureError::Action(_)))
+    ));
+}
+
+#[test]
+fn unbound_gestures_are_forwarded() {
+    let mut gestures = parse(CONFIG).unwrap();
+    assert!(matches!(
+        gestures.begin(GestureKind::Swipe, 4),
+        FilterResult::Forward
+    ));
+    assert!(matches!(
+        gestures.update((10.0, 0.0).into(), None),
+        FilterResult::Forward
+    ));
+    assert!(matches!(gestures.end(false), FilterResult::Forward));
+}
+
+#[test]
+fn swipes_report_progress() {
+    let mut gestures = parse(CONFIG).unwrap();
+    assert_eq!(intercepted(gestures.begin(GestureKind::Swipe, 3)), None);
+
+    // Nothing is reported until the direction is decided
+    assert_eq!(intercepted(gestures.update((-5.0, 0.0).into(), None)), None);
+    assert_eq!(
+        intercepted(gestures.update((-70.0, 0.0).into(), None)),
+        Some((GesturePhase::Begin, 0.25))
+    );
+    assert_eq!(
+        intercepted(gestures.update((-150.0, 10.0).into(), None)),
+        Some((GesturePhase::Update, 0.75))
+    );
+    assert_eq!(
+        intercepted(gestures.end(true)),
+        Some((GesturePhase::Cancel, 0.75))
+    );
+
+    // Swiping the other way is taken from the clients but bound to nothing
+    assert_eq!(intercepted(gestures.begin(GestureKind::Swipe, 3)), None);
+    assert_eq!(
+        intercepted(gestures.update((100.0, 0.0).into(), None)),
+        None
+    );
+    assert_eq!(intercepted(gestures.end(false)), None);
+}
+
+#[test]
+fn pinch_binding_enters_mode() {
+    let mut harness = Harness::new(&[("one", OUTPUT)]).unwrap();
+    std::fs::write(harness.config_file(), CONFIG).unwrap();
+    harness.state().reload_config().unwrap();
+
+    // Stopping halfway runs nothing
+    harness
+        .input_script("pinch begin 4\npinch update 0.8\npinch end")
+        .unwrap();
+    assert_eq!(harness.state().keybindings.mode(), "default");
+
+    harness
+        .input_script("pinch begin 4\npinch update 0.7\npinch update 0.4\npinch end")
+        .unwrap();
+    assert_eq!(harness.state().keybindings.mode(), "overview");
+}
@@ -0,0 +1,173 @@
+use std::{
+    os::unix::net::UnixStream,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread,
+};
+
+use compositor::{decoration, headless::Harness, layer};
+use smithay::utils::Rectangle;
+use smithay_client_toolkit::reexports::{
+    client::{
+        Connection, Dispatch, QueueHandle, delegate_noop,
+        globals::registry_queue_init,
+        protocol::{wl_compositor::WlCompositor, wl_shm::WlShm},
+    },
+    protocols_wlr::layer_shell::v1::client::{
+        zwlr_layer_shell_v1::{Layer, ZwlrLayerShellV1},
+        zwlr_layer_surface_v1::{self, Anchor, ZwlrLayerSurfaceV1},
+    },
+};
+
+use crate::common::{
+    BLACK, OnPress, RED, TIMEOUT, attach_solid_buffer, client_dispatch, spawn_toplevel,
+};
+
+mod common;
+
+#[derive(Default)]
+struct Client {
+    configured: Option<(u32, u32)>,
+}
+
+client_dispatch!(Client);
+delegate_noop!(Client: ignore ZwlrLayerShellV1);
+
+impl Dispatch<ZwlrLayerSurfaceV1, ()> for Client {
+    fn event(
+        state: &mut Self,
+        surface: &ZwlrLayerSurfaceV1,
+        event: zwlr_layer_surface_v1::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        if let zwlr_layer_surface_v1::Event::Configure {
+            serial,
+            width,
+            height,
+        } = event
+        {
+            surface.ack_configure(serial);
+            state.configured = Some((width, height));
+        }
+    }
+}
+
+/// Maps a layer surface of `size` filled with `color`, anchored to `anchor`
+/// and reserving `exclusive_zone` pixels.
+fn solid_layer_surface(
+    stream: UnixStream,
+    layer: Layer,
+    anchor: Anchor,
+    exclusive_zone: i32,
+    size: (u32, u32),
+    color: u32,
+) {
+    let connection = Connection::from_socket(stream).unwrap();
+    let (globals, mut queue) = registry_queue_init::<Client>(&connection).unwrap();
+    let qh = queue.handle();
+
+    let compositor: WlCompositor = globals.bind(&qh, 1..=6, ()).unwrap();
+    let shm: WlShm = globals.bind(&qh, 1..=1, ()).unwrap();
+    let layer_shell: ZwlrLayerShellV1 = globals.bind(&qh, 1..=4, ()).unwrap();
+
+    let surface = compositor.create_surface(&qh, ());
+    let layer_surface =
+        layer_shell.get_layer_surface(&surface, None, layer, "test".into(), &qh, ());
+    layer_surface.set_anchor(anchor);
+    layer_surface.set_exclusive_zone(exclusive_zone);
+    layer_surface.set_size(size.0, size.1);
+    surface.commit();
+
+    let mut client = Client::default();
+    while client.configured.is_none() {
+        queue.blocking_dispatch(&mut client).unwrap();
+    }
+
+    attach_solid_buffer(&shm, &surface, &qh, size, color);
+    queue.roundtrip(&mut client).unwrap();
+}
+
+/// Runs `client` on its own thread until it is done with its surface.
+fn run_client(harness: &mut Harness, client: impl FnOnce(UnixStream) + Send + 'static) {
+    let stream = harness.connect_client().unwrap();
+    let done = Arc::new(AtomicBool::new(false));
+    let client = {
+        let done = done.clone();
+        thread::spawn(move || {
+            client(stream);
+            done.store(true, Ordering::SeqCst);
+        })
+    };
+
+    assert!(harness.dispatch_until(TIMEOUT, |_| done.load(Ordering::SeqCst)));
+    client.join().unwrap();
+}
+
+#[test]
+fn outputs_have_configured_sizes() {
+    let mut harness = Harness::new(&[("one", (640, 480)), ("two", (320, 200))]).unwrap();
+    harness.render();
+
+    for (name, (width, height)) in [("one", (640, 480)), ("two", (320, 200))] {
+        let snapshot = harness.snapshot(name).unwrap();
+        assert_eq!(snapshot.size, (width, height).into());
+        assert_eq!(snapshot.pixel(0, 0), BLACK);
+        assert_eq!(snapshot.pixel(width - 1, height - 1), BLACK);
+    }
+
+    let space = &harness.state().globals().space;
+    let locations = space
+        .outputs()
+        .map(|output| (output.name(), space.output_geometry(output).unwrap().loc))
+        .collect::<Vec<_>>();
+    assert!(locations.contains(&("one".to_string(), (0, 0).into())));
+    assert!(locations.contains(&("two".to_string(), (640, 0).into())));
+}
+
+#[test]
+fn layer_surface_is_rendered() {
+    let mut harness = Harness::new(&[("one", (640, 480))]).unwrap();
+    run_client(&mut harness, |stream| {
+        let anchor = Anchor::Top | Anchor::Left;
+        solid_layer_surface(stream, Layer::Background, anchor, 0, (100, 100), RED);
+    });
+    harness.render();
+
+    let snapshot = harness.snapshot("one").unwrap();
+    assert_eq!(snapshot.pixel(50, 50), RED);
+    assert_eq!(snapshot.pixel(150, 150), BLACK);
+}
+
+#[test]
+fn exclusive_zones_shrink_the_usable_area() {
+    let mut harness = Harness::new(&[("one", (640, 480))]).unwrap();
+    run_client(&mut harness, |stream| {
+        let anchor = Anchor::Top | Anchor::Left | Anchor::Right;
+        solid_layer_surface(stream, Layer::Top, anchor, 30, (640, 30), RED);
+    });
+
+    let usable = {
+        let globals = harness.state().globals();
+        let output = globals.space.outputs().next().unwrap().clone();
+        layer::usable_area(&globals.space, &output).unwrap()
+    };
+    assert_eq!(usable, Rectangle::new((0, 30).into(), (640, 450).into()));
+
+    // A maximized window fills what the panel leaves
+    let window = spawn_toplevel(&mut harness, OnPress::Nothing);
+    harness.place_window(window, (100, 100));
+    let mut globals = harness.state().globals();
+    let element = globals.mapped_windows[window].clone();
+    decoration::set_maximized(&mut globals, &element, true);
+    let location = globals.space.element_location(&element).unwrap();
+    assert_eq!(location, usable.loc);
+    let size = element
+        .toplevel()
+        .unwrap()
+        .with_pending_state(|state| state.size);
+    assert_eq!(size, Some(usable.size));
+}
@@ -0,0 +1,320 @@
+use compositor::{
+    api::WindowKey,
+    headless::{Harness, HeadlessBackend},
+    state::App,
+};
+use fusion_socket_protocol::{
+    BTN_LEFT, Geometry, GetWindowsRequest, GetWindowsResponse, WindowAction, WindowActionRequest,
+    WindowActionResponse,
+};
+use slotmap::Key;
+use smithay::{
+    reexports::wayland_protocols::xdg::shell::server::xdg_toplevel::State,
+    utils::{Logical, Point},
+    wayland::pointer_constraints::with_pointer_constraint,
+};
+
+use crate::common::{OnPress, TIMEOUT, WINDOW_SIZE, click, spawn_toplevel};
+
+mod common;
+
+const OUTPUT: (i32, i32) = (800, 600);
+
+const KEY_LEFTCTRL: u32 = 29;
+const KEY_LEFTALT: u32 = 56;
+const KEY_F1: u32 = 59;
+const KEY_F3: u32 = 61;
+
+fn location(harness: &mut Harness, window: WindowKey) -> Point<i32, Logical> {
+    let globals = harness.state().globals();
+    let window = &globals.mapped_windows[window];
+    globals.space.element_location(window).unwrap()
+}
+
+fn is_focused(harness: &mut Harness, window: WindowKey) -> bool {
+    let state = harness.state();
+    let focus = state.seat.get_keyboard().unwrap().current_focus();
+    let globals = state.globals();
+    let toplevel = globals.mapped_windows[window].toplevel().unwrap();
+    let activated = toplevel.with_pending_state(|state| state.states.contains(State::Activated));
+    activated && focus.as_ref() == Some(toplevel.wl_surface())
+}
+
+fn is_constrained(state: &mut App<HeadlessBackend>, window: WindowKey) -> bool {
+    let pointer = state.seat.get_pointer().unwrap();
+    let globals = state.globals();
+    let surface = globals.mapped_windows[window]
+        .toplevel()
+        .unwrap()
+        .wl_surface();
+    with_pointer_constraint(surface, &pointer, |constraint| {
+        constraint.is_some_and(|constraint| constraint.is_active())
+    })
+}
+
+fn is_grabbed(harness: &mut Harness) -> bool {
+    harness.state().seat.get_pointer().unwrap().is_grabbed()
+}
+
+#[test]
+fn click_focuses_window() {
+    let mut harness = Harness::new(&[("one", OUTPUT)]).unwrap();
+    let left = spawn_toplevel(&mut harness, OnPress::Nothing);
+    let right = spawn_toplevel(&mut harness, OnPress::Nothing);
+    harness.place_window(left, (50, 50));
+    harness.place_window(right, (400, 50));
+
+    click(&mut harness, (100.0, 100.0));
+    assert!(is_focused(&mut harness, left));
+    assert!(!is_focused(&mut harness, right));
+
+    click(&mut harness, (500.0, 100.0));
+    assert!(is_focused(&mut harness, right));
+    assert!(!is_focused(&mut harness, left));
+
+    // Empty space takes the focus away
+    click(&mut harness, (300.0, 500.0));
+    assert!(!is_focused(&mut harness, left));
+    assert!(!is_focused(&mut harness, right));
+}
+
+#[test]
+fn touch_focuses_window() {
+    let mut harness = Harness::new(&[("one", OUTPUT)]).unwrap();
+    let window = spawn_toplevel(&mut harness, OnPress::Nothing);
+    harness.place_window(window, (200, 150));
+
+    harness
+        .input_script("touch down 0 0.375 0.375\ntouch motion 0 0.9 0.9\ntouch up 0")
+        .unwrap();
+    assert!(is_focused(&mut harness, window));
+    // The pointer doesn't follow touch points
+    assert_ne!(
+        harness.state().input_state.cursor.location,
+        (300.0, 225.0).into()
+    );
+
+    harness
+        .input_script("touch down 1 0.9 0.9\ntouch cancel")
+        .unwrap();
+    assert!(!is_focused(&mut harness, window));
+}
+
+#[test]
+fn tablet_tip_focuses_window() {
+    let mut harness = Harness::new(&[("one", OUTPUT)]).unwrap();
+    let window = spawn_toplevel(&mut harness, OnPress::Nothing);
+    harness.place_window(window, (200, 150));
+
+    // The cursor follows the tool in proximity, so clients without tablet support work too
+    harness.input_script("tablet in 0.375 0.375").unwrap();
+    assert_eq!(
+        harness.state().input_state.cursor.location,
+        (300.0, 225.0).into()
+    );
+    assert!(!is_focused(&mut harness, window));
+
+    harness
+        .input_script("tablet tip down\ntablet tip up")
+        .unwrap();
+    assert!(is_focused(&mut harness, window));
+
+    harness
+        .input_script("tablet motion 0.9 0.9\ntablet tip down\ntablet tip up\ntablet out 0.9 0.9")
+        .unwrap();
+    assert!(!is_focused(&mut harness, window));
+}
+
+#[test]
+fn move_grab_follows_pointer() {
+    let mut harness = Harness::new(&[("one", OUTPUT)]).unwrap();
+    let window = spawn_toplevel(&mut harness, OnPress::Move);
+    harness.place_window(window, (50, 50));
+
+    harness.pointer_motion((100.0, 100.0));
+    harness.pointer_button(BTN_LEFT, true);
+    assert!(harness.dispatch_until(TIMEOUT, |state| {
+        state.seat.get_pointer().unwrap().is_grabbed()
+    }));
+
+    harness.pointer_motion((160.0, 130.0));
+    assert_eq!(location(&mut harness, window), (110, 80).into());
+
+    harness.pointer_button(BTN_LEFT, false);
+    assert!(!is_grabbed(&mut harness));
+
+    // The window stays where it was dropped
+    harness.pointer_motion((300.0, 300.0));
+    assert_eq!(location(&mut harness, window), (110, 80).into());
+}
+
+#[test]
+fn resize_grab_configures_new_size() {
+    let mut harness = Harness::new(&[("one", OUTPUT)]).unwrap();
+    let window = spawn_toplevel(&mut harness, OnPress::Resize);
+    harness.place_window(window, (50, 50));
+
+    harness.pointer_motion((240.0, 240.0));
+    harness.pointer_button(BTN_LEFT, true);
+    assert!(harness.dispatch_until(TIMEOUT, |state| {
+        state.seat.get_pointer().unwrap().is_grabbed()
+    }));
+
+    harness.pointer_motion((280.0, 260.0));
+    let pending = |harness: &mut Harness| {
+        let globals = harness.state().globals();
+        let toplevel = globals.mapped_windows[window].toplevel().unwrap().clone();
+        toplevel.with_pending_state(|state| (state.size, state.states.contains(State::Resizing)))
+    };
+    assert_eq!(pending(&mut harness), (Some((240, 220).into()), true));
+
+    harness.pointer_button(BTN_LEFT, false);
+    assert!(!is_grabbed(&mut harness));
+    assert_eq!(pending(&mut harness), (Some((240, 220).into()), false));
+}
+
+#[test]
+fn locked_pointer_stays_in_place() {
+    let mut harness = Harness::new(&[("one", OUTPUT)]).unwrap();
+    let window = spawn_toplevel(&mut harness, OnPress::Lock);
+    harness.place_window(window, (50, 50));
+
+    click(&mut harness, (100.0, 100.0));
+    assert!(harness.dispatch_until(TIMEOUT, |state| is_constrained(state, window)));
+
+    harness.pointer_motion((300.0, 300.0));
+    assert_eq!(
+        harness.state().input_state.cursor.location,
+        (100.0, 100.0).into()
+    );
+}
+
+#[test]
+fn confined_pointer_stays_on_surface() {
+    let mut harness = Harness::new(&[("one", OUTPUT)]).unwrap();
+    let window = spawn_toplevel(&mut harness, OnPress::Confine);
+    harness.place_window(window, (50, 50));
+
+    click(&mut harness, (100.0, 100.0));
+    assert!(harness.dispatch_until(TIMEOUT, |state| is_constrained(state, window)));
+
+    harness.pointer_motion((200.0, 150.0));
+    assert_eq!(
+        harness.state().input_state.cursor.location,
+        (200.0, 150.0).into()
+    );
+
+    // Motion leaving the surface is dropped
+    harness.pointer_motion((400.0, 150.0));
+    assert_eq!(
+        harness.state().input_state.cursor.location,
+        (200.0, 150.0).into()
+    );
+}
+
+#[test]
+fn vt_switch_is_intercepted() {
+    let mut harness = Harness::new(&[("one", OUTPUT)]).unwrap();
+
+    harness
+        .input_script(&format!(
+            "key {KEY_LEFTCTRL} press\nkey {KEY_LEFTALT} press\nkey {KEY_F3} click\n\
+             key {KEY_LEFTALT} release\nkey {KEY_LEFTCTRL} release"
+        ))
+        .unwrap();
+    assert_eq!(harness.state().backend.vt_switches, [3]);
+
+    // Without the modifiers F1 is an ordinary key
+    harness.key(KEY_F1, true);
+    harness.key(KEY_F1, false);
+    assert_eq!(harness.state().backend.vt_switches, [3]);
+}
+
+#[test]
+fn windows_are_controlled_through_socket() {
+    let mut harness = Harness::new(&[("one", OUTPUT)]).unwrap();
+    let left = spawn_toplevel(&mut harness, OnPress::Nothing);
+    let right = spawn_toplevel(&mut harness, OnPress::Nothing);
+    harness.place_window(left, (50, 50));
+    harness.place_window(right, (400, 50));
+    let id = |window: WindowKey| window.data().as_ffi();
+    let action = |harness: &mut Harness, id: u64, action: WindowAction| {
+        harness
+            .request::<WindowActionResponse>(WindowActionRequest { id, action })
+            .unwrap()
+    };
+
+    let GetWindowsResponse::Ok(windows) = harness.request(GetWindowsRequest).unwrap() else {
+        panic!("no windows");
+    };
+    let listed = windows.iter().find(|window| window.id == id(left)).unwrap();
+    assert_eq!(windows.len(), 2);
+    assert_eq!(listed.workspace.as_deref(), Some("1"));
+    assert_eq!(listed.output.as_deref(), Some("one"));
+    assert_eq!(
+        listed.geometry,
+        Some(Geometry {
+            x: 50,
+            y: 50,
+            width: WINDOW_SIZE as i32,
+            height: WINDOW_SIZE as i32,
+        })
+    );
+
+    let response = action(&mut harness, id(left), WindowAction::Focus);
+    assert_eq!(response, WindowActionResponse::Ok);
+    assert!(is_focused(&mut harness, left));
+    assert!(!is_focused(&mut harness, right));
+
+    action(
+        &mut harness,
+        id(right),
+        WindowAction::Move { x: 300, y: 300 },
+    );
+    assert_eq!(location(&mut harness, right), (300, 300).into());
+
+    // Fullscreen toggles and goes back to where the window was
+    action(&mut harness, id(left), WindowAction::Fullscreen(None));
+    assert_eq!(location(&mut harness, left), (0, 0).into());
+    {
+        let globals = harness.state().globals();
+        let toplevel = globals.mapped_windows[left].toplevel().unwrap();
+        toplevel.with_pending_state(|state| {
+            assert!(state.states.contains(State::Fullscreen));
+            assert_eq!(state.size, Some(OUTPUT.into()));
+        });
+    }
+    action(&mut harness, id(left), WindowAction::Fullscreen(None));
+    assert_eq!(location(&mut harness, left), (50, 50).into());
+
+    let response = action(&mut harness, u64::MAX, WindowAction::Close);
+    assert!(matches!(response, WindowActionResponse::Error(_)));
+}
+
+#[test]
+fn minimized_windows_are_restored_by_focus() {
+    let mut harness = Harness::new(&[("one", OUTPUT)]).unwrap();
+    let window = spawn_toplevel(&mut harness, OnPress::Nothing);
+    harness.place_window(window, (50, 50));
+
+    assert!(harness.state().globals().minimize_window(window));
+    {
+        let globals = harness.state().globals();
+        assert!(globals.workspaces.is_minimized(window));
+        let element = &globals.mapped_windows[window];
+        assert!(globals.space.element_location(element).is_none());
+    }
+    // Placing a minimized window only moves where it comes back
+    harness.place_window(window, (100, 100));
+    assert!(!harness.state().globals().minimize_window(window));
+
+    let request = WindowActionRequest {
+        id: window.data().as_ffi(),
+        action: WindowAction::Focus,
+    };
+    let response = harness.request::<WindowActionResponse>(request).unwrap();
+    assert_eq!(response, WindowActionResponse::Ok);
+    assert!(!harness.state().globals().workspaces.is_minimized(window));
+    assert_eq!(location(&mut harness, window), (100, 100).into());
+    assert!(is_focused(&mut harness, window));
+}
@@ -0,0 +1,61 @@
+use compositor::{
+    config::Config,
+    headless::Harness,
+    input_device::{AccelProfile, DeviceKind, ScrollMethod},
+};
+
+const OUTPUT: (i32, i32) = (800, 600);
+
+const CONFIG: &str = r#"
+[[input.device]]
+type = "touchpad"
+tap = true
+natural-scroll = true
+scroll-method = "two-finger"
+
+[[input.device]]
+accel-profile = "flat"
+
+[[input.device]]
+name = "Virtual input"
+accel-profile = "adaptive"
+scroll-multiplier = 2.5
+"#;
+
+#[test]
+fn matching_entries_are_merged() {
+    let config: Config = toml::from_str(CONFIG).unwrap();
+
+    let touchpad = config
+        .input
+        .settings("Touchpad", Some(DeviceKind::Touchpad));
+    assert_eq!(touchpad.tap, Some(true));
+    assert_eq!(touchpad.natural_scroll, Some(true));
+    assert_eq!(touchpad.scroll_method, Some(ScrollMethod::TwoFinger));
+    assert_eq!(touchpad.accel_profile, Some(AccelProfile::Flat));
+    assert_eq!(touchpad.scroll_multiplier, None);
+
+    // Later entries override earlier ones
+    let virtual_input = config
+        .input
+        .settings("Virtual input", Some(DeviceKind::Pointer));
+    assert_eq!(virtual_input.tap, None);
+    assert_eq!(virtual_input.accel_profile, Some(AccelProfile::Adaptive));
+    assert_eq!(virtual_input.scroll_multiplier, Some(2.5));
+
+    let unknown_setting = "[[input.device]]\ntapping = true";
+    assert!(toml::from_str::<Config>(unknown_setting).is_err());
+}
+
+#[test]
+fn reload_updates_known_devices() {
+    let mut harness = Harness::new(&[("one", OUTPUT)]).unwrap();
+    let device = &harness.state().input_state.devices["virtual"];
+    assert_eq!(device.kind, Some(DeviceKind::Touchpad));
+    assert_eq!(harness.state().scroll_multiplier("virtual"), 1.0);
+
+    std::fs::write(harness.config_file(), CONFIG).unwrap();
+    harness.state().reload_config().unwrap();
+    assert_eq!(harness.state().scroll_multiplier("virtual"), 2.5);
+    assert_eq!(harness.state().scroll_multiplier("unknown"), 1.0);
+}
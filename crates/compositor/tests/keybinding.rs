@@ -0,0 +1,163 @@
+use compositor::{
+    config::{Config, ConfigError},
+    headless::Harness,
+    keybinding::{Chord, KeyAction, KeybindingError, Modifiers, Trigger},
+};
+use smithay::input::keyboard::Keysym;
+
+const OUTPUT: (i32, i32) = (800, 600);
+
+const KEY_ESC: u32 = 1;
+const KEY_R: u32 = 19;
+const KEY_M: u32 = 50;
+const KEY_LEFTSHIFT: u32 = 42;
+const KEY_LEFTMETA: u32 = 125;
+
+const CONFIG: &str = r#"
+[keybindings.default]
+"Super+R" = { mode = "resize" }
+"Super+M" = { mode = "resize", on = "release" }
+
+[keybindings.resize]
+"Escape" = { mode = "default" }
+"Shift+R" = { plugin = "grow", repeat = true }
+"#;
+
+fn parse(config: &str) -> Result<compositor::keybinding::Keybindings, ConfigError> {
+    let config: Config = toml::from_str(config)?;
+    Ok(config.keybindings()?)
+}
+
+fn chord(harness: &mut Harness, modifier: u32, key: u32) {
+    harness.key(modifier, true);
+    harness.key(key, true);
+    harness.key(key, false);
+    harness.key(modifier, false);
+}
+
+fn mode(harness: &mut Harness) -> String {
+    harness.state().keybindings.mode().to_string()
+}
+
+#[test]
+fn chords_are_parsed() {
+    let chord = "Super+Shift+Return".parse::<Chord>().unwrap();
+    assert_eq!(chord.modifiers, Modifiers::SUPER | Modifiers::SHIFT);
+    assert_eq!(chord.keysym, Keysym::Return);
+
+    // Modifiers are case insensitive and letters match the unshifted keysym
+    let chord = "ctrl+alt+A".parse::<Chord>().unwrap();
+    assert_eq!(chord.modifiers, Modifiers::CTRL | Modifiers::ALT);
+    assert_eq!(chord.keysym, Keysym::a);
+
+    assert_eq!("".parse::<Chord>(), Err(KeybindingError::Empty));
+    assert_eq!("Super+".parse::<Chord>(), Err(KeybindingError::Empty));
+    assert_eq!(
+        "Hyper+a".parse::<Chord>(),
+        Err(KeybindingError::UnknownModifier(
+            "Hyper".to_string(),
+            "Hyper+a".to_string()
+        ))
+    );
+    assert_eq!(
+        "Super+Nothing".parse::<Chord>(),
+        Err(KeybindingError::UnknownKey(
+            "Nothing".to_string(),
+            "Super+Nothing".to_string()
+        ))
+    );
+}
+
+#[test]
+fn config_is_validated() {
+    let keybindings = parse(CONFIG).unwrap();
+    let resize = keybindings.bindings("resize");
+    let grow = resize
+        .iter()
+        .find(|binding| binding.action == KeyAction::Plugin("grow".to_string()))
+        .unwrap();
+    assert_eq!(grow.trigger, Trigger::Press);
+    assert!(grow.repeat);
+
+    let two_actions = r#"
+        [keybindings.default]
+        "Super+Q" = { spawn = "kitty", action = "quit" }
+    "#;
+    assert!(matches!(
+        parse(two_actions),
+        Err(ConfigError::Keybinding(KeybindingError::Action(_)))
+    ));
+
+    let unknown_mode = r#"
+        [keybindings.default]
+        "Super+R" = { mode = "resize" }
+    "#;
+    assert!(matches!(
+        parse(unknown_mode),
+        Err(ConfigError::Keybinding(KeybindingError::UnknownMode(..)))
+    ));
+
+    let unknown_field = r#"
+        [keybindings.default]
+        "Super+Q" = { action = "quit", when = "always" }
+    "#;
+    assert!(matches!(parse(unknown_field), Err(ConfigError::Parse(_))));
+}
+
+#[test]
+fn bindings_switch_modes() {
+    let mut harness = Harness::new(&[("one", OUTPUT)]).unwrap();
+    std::fs::write(harness.config_file(), CONFIG).unwrap();
+    harness.state().reload_config().unwrap();
+    assert_eq!(mode(&mut harness), "default");
+
+    chord(&mut harness, KEY_LEFTMETA, KEY_R);
+    assert_eq!(mode(&mut harness), "resize");
+
+    // Bindings of other modes are inactive
+    chord(&mut harness, KEY_LEFTMETA, KEY_R);
+    assert_eq!(mode(&mut harness), "resize");
+
+    harness.key(KEY_ESC, true);
+    harness.key(KEY_ESC, false);
+    assert_eq!(mode(&mut harness), "default");
+
+    // Without the modifier the key is not bound
+    harness.key(KEY_R, true);
+    harness.key(KEY_R, false);
+    chord(&mut harness, KEY_LEFTSHIFT, KEY_R);
+    assert_eq!(mode(&mut harness), "default");
+}
+
+#[test]
+fn release_bindings_run_on_release() {
+    let mut harness = Harness::new(&[("one", OUTPUT)]).unwrap();
+    std::fs::write(harness.config_file(), CONFIG).unwrap();
+    harness.state().reload_config().unwrap();
+
+    harness.key(KEY_LEFTMETA, true);
+    harness.key(KEY_M, true);
+    assert_eq!(mode(&mut harness), "default");
+
+    // The modifier may already be gone when the key is released
+    harness.key(KEY_LEFTMETA, false);
+    harness.key(KEY_M, false);
+    assert_eq!(mode(&mut harness), "resize");
+}
+
+#[test]
+fn invalid_reload_keeps_bindings() {
+    let mut harness = Harness::new(&[("one", OUTPUT)]).unwrap();
+    std::fs::write(harness.config_file(), CONFIG).unwrap();
+    harness.state().reload_config().unwrap();
+
+    std::fs::write(
+        harness.config_file(),
+        "[keybindings.default]\n\"Super+\" = {}",
+    )
+    .unwrap();
+    assert!(harness.state().reload_config().is_err());
+
+    chord(&mut harness, KEY_LEFTMETA, KEY_R);
+    assert_eq!(mode(&mut harness), "resize");
+}
@@ -0,0 +1,106 @@
+use compositor::{
+    config::Config,
+    headless::Harness,
+    keyboard::{DEFAULT_REPEAT_RATE, KeyboardConfig, KeyboardError},
+};
+use fusion_socket_protocol::{
+    GetKeyboardRequest, GetKeyboardResponse, SetKeyboardRequest, SetKeyboardResponse,
+};
+
+const OUTPUT: (i32, i32) = (800, 600);
+
+const KEY_LEFTSHIFT: u32 = 42;
+const KEY_LEFTALT: u32 = 56;
+
+const CONFIG: &str = r#"
+[keyboard]
+layout = "us,ru"
+options = "grp:alt_shift_toggle"
+repeat-delay = 300
+"#;
+
+fn layout(harness: &mut Harness) -> String {
+    harness.state().globals().keyboard_layout.clone()
+}
+
+#[test]
+fn keyboard_table_is_parsed() {
+    let config: Config = toml::from_str(CONFIG).unwrap();
+    assert_eq!(config.keyboard.layout, "us,ru");
+    assert_eq!(
+        config.keyboard.options.as_deref(),
+        Some("grp:alt_shift_toggle")
+    );
+    assert_eq!(config.keyboard.repeat_delay, 300);
+    assert_eq!(config.keyboard.repeat_rate, DEFAULT_REPEAT_RATE);
+
+    assert!(toml::from_str::<Config>("[keyboard]\nlayouts = \"us\"").is_err());
+}
+
+#[test]
+fn config_layouts_can_be_toggled() {
+    let mut harness = Harness::new(&[("one", OUTPUT)]).unwrap();
+    std::fs::write(harness.config_file(), CONFIG).unwrap();
+    harness.state().reload_config().unwrap();
+    assert_eq!(
+        harness.state().input_state.keyboard_config.repeat_delay,
+        300
+    );
+    assert_eq!(layout(&mut harness), "English (US)");
+
+    harness.key(KEY_LEFTALT, true);
+    harness.key(KEY_LEFTSHIFT, true);
+    harness.key(KEY_LEFTSHIFT, false);
+    harness.key(KEY_LEFTALT, false);
+    assert_eq!(layout(&mut harness), "Russian");
+
+    // Reloading an unchanged config keeps the active layout
+    harness.state().reload_config().unwrap();
+    assert_eq!(layout(&mut harness), "Russian");
+}
+
+#[test]
+fn invalid_settings_are_rejected() {
+    let mut harness = Harness::new(&[("one", OUTPUT)]).unwrap();
+    let previous = harness.state().input_state.keyboard_config.clone();
+
+    let config = KeyboardConfig {
+        layout: "no-such-layout".to_string(),
+        ..KeyboardConfig::default()
+    };
+    assert!(matches!(
+        harness.state().apply_keyboard_config(config),
+        Err(KeyboardError::Keymap { .. })
+    ));
+
+    let config = KeyboardConfig {
+        repeat_rate: -1,
+        ..KeyboardConfig::default()
+    };
+    assert_eq!(
+        harness.state().apply_keyboard_config(config),
+        Err(KeyboardError::Repeat)
+    );
+    assert_eq!(harness.state().input_state.keyboard_config, previous);
+}
+
+#[test]
+fn keyboard_is_changed_through_socket() {
+    let mut harness = Harness::new(&[("one", OUTPUT)]).unwrap();
+
+    let set = SetKeyboardRequest {
+        layout: Some("de".to_string()),
+        repeat_rate: Some(40),
+        ..SetKeyboardRequest::default()
+    };
+    let response = harness.request::<SetKeyboardResponse>(set).unwrap();
+    assert!(matches!(response, SetKeyboardResponse::Ok));
+    assert_eq!(layout(&mut harness), "German");
+
+    let GetKeyboardResponse::Ok(keyboard) = harness.request(GetKeyboardRequest).unwrap() else {
+        panic!("no keyboard settings");
+    };
+    assert_eq!(keyboard.layout, "de");
+    assert_eq!(keyboard.active_layout, "German");
+    assert_eq!(keyboard.repeat_rate, 40);
+}
@@ -0,0 +1,176 @@
+use compositor::headless::Harness;
+use fusion_socket_protocol::{
+    GetOutputsRequest, GetOutputsResponse, ModeRequest, Output, SetOutputRequest,
+    SetOutputResponse, Transform,
+};
+
+const OUTPUT: (i32, i32) = (800, 600);
+
+fn outputs(harness: &mut Harness) -> Vec<Output> {
+    match harness.request(GetOutputsRequest).unwrap() {
+        GetOutputsResponse::Ok(outputs) => outputs,
+        GetOutputsResponse::Error(error) => panic!("{error}"),
+    }
+}
+
+fn set_output(harness: &mut Harness, request: SetOutputRequest) -> SetOutputResponse {
+    harness.request(request).unwrap()
+}
+
+#[test]
+fn outputs_are_listed() {
+    let mut harness = Harness::new(&[("one", OUTPUT), ("two", OUTPUT)]).unwrap();
+    let outputs = outputs(&mut harness);
+
+    let names = outputs.iter().map(|output| output.name.as_str());
+    assert_eq!(names.collect::<Vec<_>>(), ["one", "two"]);
+    let positions = outputs.iter().map(|output| (output.x, output.y));
+    assert_eq!(positions.collect::<Vec<_>>(), [(0, 0), (800, 0)]);
+    for output in outputs {
+        let mode = output.current_mode.unwrap();
+        assert_eq!((mode.width, mode.height), OUTPUT);
+        assert_eq!(output.modes, [mode]);
+        assert_eq!(output.scale, 1.0);
+        assert_eq!(output.transform, Transform::Normal);
+        assert!(output.enabled);
+    }
+}
+
+#[test]
+fn outputs_are_configured_through_socket() {
+    let mut harness = Harness::new(&[("one", OUTPUT), ("two", OUTPUT)]).unwrap();
+    let request = SetOutputRequest {
+        name: "two".to_string(),
+        mode: Some(ModeRequest {
+            width: OUTPUT.0,
+            height: OUTPUT.1,
+            refresh: None,
+        }),
+        scale: Some(2.0),
+        transform: Some(Transform::Rotate90),
+        position: Some((0, 600)),
+        ..SetOutputRequest::default()
+    };
+
+    // A test leaves everything as it was
+    let before = outputs(&mut harness);
+    let test = SetOutputRequest {
+        test: true,
+        ..request.clone()
+    };
+    assert_eq!(set_output(&mut harness, test), SetOutputResponse::Ok);
+    assert_eq!(outputs(&mut harness), before);
+
+    assert_eq!(set_output(&mut harness, request), SetOutputResponse::Ok);
+    let two = outputs(&mut harness).remove(1);
+    assert_eq!(two.scale, 2.0);
+    assert_eq!(two.transform, Transform::Rotate90);
+    assert_eq!((two.x, two.y), (0, 600));
+
+    let unknown = SetOutputRequest {
+        name: "three".to_string(),
+        ..SetOutputRequest::default()
+    };
+    let response = set_output(&mut harness, unknown);
+    assert!(matches!(response, SetOutputResponse::Error(_)));
+
+    let unsupported = SetOutputRequest {
+        name: "one".to_string(),
+        mode: Some("1024x768".parse().unwrap()),
+        test: true,
+        ..SetOutputRequest::default()
+    };
+    let response = set_output(&mut harness, unsupported);
+    assert!(matches!(response, SetOutputResponse::Error(_)));
+}
+
+#[test]
+fn outputs_are_disabled_and_enabled() {
+    let mut harness = Harness::new(&[("one", OUTPUT), ("two", OUTPUT)]).unwrap();
+    let enabled = |name: &str, enabled| SetOutputRequest {
+        name: name.to_string(),
+        enabled: Some(enabled),
+        ..SetOutputRequest::default()
+    };
+
+    let response = set_output(&mut harness, enabled("two", false));
+    assert_eq!(response, SetOutputResponse::Ok);
+    let listed = outputs(&mut harness);
+    assert!(listed[0].enabled);
+    assert!(!listed[1].enabled);
+    assert_eq!(harness.state().output_state.outputs.len(), 1);
+
+    // The last output can't go
+    let response = set_output(&mut harness, enabled("one", false));
+    assert!(matches!(response, SetOutputResponse::Error(_)));
+
+    let response = set_output(&mut harness, enabled("two", true));
+    assert_eq!(response, SetOutputResponse::Ok);
+    assert!(outputs(&mut harness).iter().all(|output| output.enabled));
+    assert_eq!(harness.state().output_state.outputs.len(), 2);
+
+    // Outputs come back where they were
+    let response = set_output(&mut harness, enabled("one", false));
+    assert_eq!(response, SetOutputResponse::Ok);
+    let response = set_output(&mut harness, enabled("one", true));
+    assert_eq!(response, SetOutputResponse::Ok);
+    let positions = outputs(&mut harness)
+        .into_iter()
+        .map(|output| (output.x, output.y));
+    assert_eq!(positions.collect::<Vec<_>>(), [(0, 0), (800, 0)]);
+}
+
+#[test]
+fn tests_check_every_field() {
+    let mut harness = Harness::new(&[("one", OUTPUT), ("two", OUTPUT)]).unwrap();
+    let before = outputs(&mut harness);
+    let test = |request: SetOutputRequest| SetOutputRequest {
+        name: "two".to_string(),
+        test: true,
+        ..request
+    };
+
+    // 800 pixels wide the output reaches past the coordinate range, rotated it fits
+    let position = Some((i32::MAX - 700, 0));
+    let response = set_output(
+        &mut harness,
+        test(SetOutputRequest {
+            position,
+            ..SetOutputRequest::default()
+        }),
+    );
+    assert!(matches!(response, SetOutputResponse::Error(error) if error.contains("out of range")));
+    let response = set_output(
+        &mut harness,
+        test(SetOutputRequest {
+            position,
+            transform: Some(Transform::Rotate90),
+            ..SetOutputRequest::default()
+        }),
+    );
+    assert_eq!(response, SetOutputResponse::Ok);
+
+    let response = set_output(
+        &mut harness,
+        test(SetOutputRequest {
+            enabled: Some(false),
+            ..SetOutputRequest::default()
+        }),
+    );
+    assert_eq!(response, SetOutputResponse::Ok);
+    assert_eq!(outputs(&mut harness), before);
+
+    // Disabling the last output is refused by a test too
+    let disable = |name: &str, test| SetOutputRequest {
+        name: name.to_string(),
+        enabled: Some(false),
+        test,
+        ..SetOutputRequest::default()
+    };
+    assert_eq!(
+        set_output(&mut harness, disable("two", false)),
+        SetOutputResponse::Ok
+    );
+    let response = set_output(&mut harness, disable("one", true));
+    assert!(matches!(response, SetOutputResponse::Error(_)));
+}
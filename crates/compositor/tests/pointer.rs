@@ -0,0 +1,71 @@
+use compositor::headless::Harness;
+use smithay::utils::{Logical, Point};
+
+const CONFIG: &str = r#"
+[[input.device]]
+name = "Virtual input"
+map-to-output = "two"
+"#;
+
+fn pointer(harness: &mut Harness) -> Point<f64, Logical> {
+    harness.state().input_state.cursor.location
+}
+
+#[test]
+fn outputs_are_placed_side_by_side() {
+    let mut harness = Harness::new(&[("one", (800, 600)), ("two", (400, 300))]).unwrap();
+    let globals = harness.state().globals();
+    let locations = globals
+        .space
+        .outputs()
+        .map(|output| {
+            (
+                output.name(),
+                globals.space.output_geometry(output).unwrap().loc,
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        locations,
+        [
+            ("one".to_string(), (0, 0).into()),
+            ("two".to_string(), (800, 0).into())
+        ]
+    );
+}
+
+#[test]
+fn pointer_moves_across_outputs() {
+    let mut harness = Harness::new(&[("one", (800, 600)), ("two", (800, 600))]).unwrap();
+    harness.pointer_motion((700.0, 300.0));
+    harness.pointer_motion((900.0, 300.0));
+    assert_eq!(pointer(&mut harness), (900.0, 300.0).into());
+
+    harness.pointer_motion((2000.0, -50.0));
+    assert_eq!(pointer(&mut harness), (1599.0, 0.0).into());
+}
+
+#[test]
+fn pointer_stays_out_of_gaps() {
+    let mut harness = Harness::new(&[("one", (800, 600)), ("two", (400, 300))]).unwrap();
+    harness.pointer_motion((700.0, 500.0));
+
+    // Below the smaller output the pointer stops at the edge of the first one
+    harness.pointer_motion((900.0, 500.0));
+    assert_eq!(pointer(&mut harness), (799.0, 500.0).into());
+
+    harness.pointer_motion((900.0, 250.0));
+    assert_eq!(pointer(&mut harness), (900.0, 250.0).into());
+}
+
+#[test]
+fn absolute_devices_map_to_outputs() {
+    let mut harness = Harness::new(&[("one", (800, 600)), ("two", (400, 300))]).unwrap();
+    harness.input_script("motion-absolute 0.5 0.5").unwrap();
+    assert_eq!(pointer(&mut harness), (600.0, 300.0).into());
+
+    std::fs::write(harness.config_file(), CONFIG).unwrap();
+    harness.state().reload_config().unwrap();
+    harness.input_script("motion-absolute 0.5 0.5").unwrap();
+    assert_eq!(pointer(&mut harness), (1000.0, 150.0).into());
+}
@@ -0,0 +1,37 @@
+use compositor::headless::Harness;
+
+use crate::common::{OnPress, spawn_toplevel};
+
+mod common;
+
+const OUTPUT: (i32, i32) = (800, 600);
+
+#[test]
+fn workspaces_move_off_removed_outputs() {
+    let mut harness = Harness::new(&[("one", OUTPUT), ("two", OUTPUT)]).unwrap();
+    {
+        let mut globals = harness.state().globals();
+        let two = globals.workspaces.create("1", "two");
+        globals.switch_workspace(two);
+    }
+    let window = spawn_toplevel(&mut harness, OnPress::Nothing);
+    harness.place_window(window, (850, 50));
+
+    harness.state().remove_headless_output("two");
+
+    let globals = harness.state().globals();
+    let workspaces = &globals.workspaces;
+    assert_eq!(workspaces.focused_output(), Some("one"));
+    // Both outputs had a workspace "1", they are merged
+    let (_, workspace) = workspaces.iter().next().unwrap();
+    assert_eq!(workspaces.iter().count(), 1);
+    assert_eq!(workspace.output, "one");
+    assert_eq!(workspace.windows(), [window]);
+
+    // Where the window was on the removed output
+    let element = &globals.mapped_windows[window];
+    assert_eq!(
+        globals.space.element_location(element),
+        Some((50, 50).into())
+    );
+}
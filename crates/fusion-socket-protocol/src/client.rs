@@ -0,0 +1,154 @@
+use std::{
+    collections::VecDeque,
+    io::{self, Read, Write},
+    os::unix::net::UnixStream,
+    path::Path,
+    time::Duration,
+};
+
+use serde::{Serialize, de::DeserializeOwned};
+
+use crate::{
+    CompositorRequest, Envelope, Event, Features, FrameBuffer, FrameError, Hello, HelloResponse,
+    ProtocolError, Reply,
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum ClientError {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error(transparent)]
+    Frame(#[from] FrameError),
+    #[error("unable to decode a message: {0}")]
+    Decode(#[from] postcard::Error),
+    #[error(transparent)]
+    Protocol(#[from] ProtocolError),
+    #[error("the compositor refused the connection: {0}")]
+    Handshake(String),
+    #[error("the compositor closed the connection")]
+    Closed,
+    #[error("got a reply to request {got} while waiting for {expected}")]
+    UnexpectedReply { expected: u32, got: u32 },
+}
+
+/// Blocking client of the control socket.
+pub struct Client {
+    stream: UnixStream,
+    frames: FrameBuffer,
+    next_id: u32,
+    version: u32,
+    features: Features,
+    /// Events that arrived while waiting for a response
+    events: VecDeque<Vec<u8>>,
+}
+
+impl Client {
+    pub fn connect(path: impl AsRef<Path>) -> Result<Self, ClientError> {
+        Self::handshake(UnixStream::connect(path)?)
+    }
+
+    /// Introduces the client on an already connected `stream`.
+    pub fn handshake(stream: UnixStream) -> Result<Self, ClientError> {
+        let mut client = Self {
+            stream,
+            frames: FrameBuffer::default(),
+            next_id: 0,
+            version: 0,
+            features: Features::empty(),
+            events: VecDeque::new(),
+        };
+
+        client.send_frame(&Hello::default())?;
+        match postcard::from_bytes_cobs(&mut client.read_frame()?)? {
+            HelloResponse::Ok { version, features } => {
+                client.version = version;
+                client.features = features;
+                Ok(client)
+            }
+            HelloResponse::Error(error) => Err(ClientError::Handshake(error)),
+        }
+    }
+
+    /// Protocol version of the compositor.
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    /// Features served by the compositor.
+    pub fn features(&self) -> Features {
+        self.features
+    }
+
+    /// Bounds how long [`Client::request`] and [`Client::next_event`] wait, `None` waits forever.
+    pub fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        self.stream.set_read_timeout(timeout)
+    }
+
+    /// Sends `request` and waits for its response.
+    pub fn request<T: DeserializeOwned>(
+        &mut self,
+        request: impl Into<CompositorRequest>,
+    ) -> Result<T, ClientError> {
+        let id = self.next_id;
+        self.next_id = self.next_id.wrapping_add(1);
+        let body = postcard::to_stdvec(&request.into())?;
+        self.send_frame(&Envelope { id, body })?;
+
+        loop {
+            match self.read_reply()? {
+                Reply::Response { id: reply, body } if reply == id => {
+                    return Ok(postcard::from_bytes(&body)?);
+                }
+                Reply::Error { id: None, error } => return Err(error.into()),
+                Reply::Error {
+                    id: Some(reply),
+                    error,
+                } if reply == id => return Err(error.into()),
+                Reply::Event { body } => self.events.push_back(body),
+                Reply::Response { id: got, .. } | Reply::Error { id: Some(got), .. } => {
+                    return Err(ClientError::UnexpectedReply { expected: id, got });
+                }
+            }
+        }
+    }
+
+    /// Waits for the next event of the subscription.
+    ///
+    /// Events this client can't decode, e.g. added in a newer compositor, are `None`.
+    pub fn next_event(&mut self) -> Result<Option<Event>, ClientError> {
+        let body = match self.events.pop_front() {
+            Some(body) => body,
+            None => loop {
+                match self.read_reply()? {
+                    Reply::Event { body } => break body,
+                    Reply::Error { error, .. } => return Err(error.into()),
+                    Reply::Response { .. } => {}
+                }
+            },
+        };
+        Ok(postcard::from_bytes(&body).ok())
+    }
+
+    fn send_frame(&mut self, message: &impl Serialize) -> Result<(), ClientError> {
+        self.stream.write_all(&postcard::to_stdvec_cobs(message)?)?;
+        Ok(())
+    }
+
+    fn read_reply(&mut self) -> Result<Reply, ClientError> {
+        Ok(postcard::from_bytes_cobs(&mut self.read_frame()?)?)
+    }
+
+    fn read_frame(&mut self) -> Result<Vec<u8>, ClientError> {
+        let mut bytes = [0; 4096];
+        loop {
+            if let Some(frame) = self.frames.next_frame()? {
+                return Ok(frame);
+            }
+            let read = self.stream.read(&mut bytes)?;
+            if read == 0 {
+                return Err(ClientError::Closed);
+            }
+            self.frames.push(&bytes[..read]);
+        }
+    }
+}
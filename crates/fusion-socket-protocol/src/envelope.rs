@@ -0,0 +1,84 @@
+use serde::{Deserialize, Serialize};
+
+use crate::MAX_FRAME_SIZE;
+
+/// Version of the wire format, only bumped for changes old peers can't cope with.
+///
+/// New requests, responses and events are appended to their enums instead,
+/// the envelopes keep them opaque so an old peer reports them as unknown.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// Optional parts of the protocol a compositor serves.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[serde(transparent)]
+pub struct Features(u64);
+
+bitflags::bitflags! {
+    impl Features: u64 {
+        /// [`crate::SubscribeRequest`] and the event stream
+        const SUBSCRIBE = 1;
+        /// [`crate::InjectInputRequest`]
+        const INJECT_INPUT = 1 << 1;
+        /// [`crate::CallPluginRequest`], only while enabled in the compositor config
+        const CALL_PLUGIN = 1 << 2;
+    }
+}
+
+/// First frame of every connection, sent by the client.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Hello {
+    pub version: u32,
+    /// Features the client knows about
+    pub features: Features,
+}
+
+impl Default for Hello {
+    fn default() -> Self {
+        Self {
+            version: PROTOCOL_VERSION,
+            features: Features::all(),
+        }
+    }
+}
+
+/// Answer to [`Hello`], the compositor closes the connection after an error.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum HelloResponse {
+    Ok { version: u32, features: Features },
+    Error(String),
+}
+
+/// Frame carrying a [`crate::CompositorRequest`].
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Envelope {
+    /// Chosen by the client and repeated in the reply
+    pub id: u32,
+    /// Postcard encoded request
+    pub body: Vec<u8>,
+}
+
+/// Frame sent by the compositor after the handshake.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum Reply {
+    /// Postcard encoded response to the request `id`
+    Response { id: u32, body: Vec<u8> },
+    /// The request `id` couldn't be served, `None` when the frame had no readable ID
+    Error {
+        id: Option<u32>,
+        error: ProtocolError,
+    },
+    /// Postcard encoded [`crate::Event`] of a subscription
+    Event { body: Vec<u8> },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ProtocolError {
+    #[error("request {0} is unknown to the compositor, it may be older than the client")]
+    UnknownRequest(u32),
+    #[error("malformed frame: {0}")]
+    Malformed(String),
+    #[error("frame is larger than {MAX_FRAME_SIZE} bytes")]
+    TooLarge,
+    #[error("timed out waiting for the rest of the frame")]
+    Timeout,
+}
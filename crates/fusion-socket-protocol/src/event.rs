@@ -0,0 +1,102 @@
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+/// Group of [`Event`]s a subscriber can ask for.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Topic {
+    Window,
+    Workspace,
+    Output,
+    Plugin,
+    Layout,
+}
+
+impl Topic {
+    pub const ALL: [Topic; 5] = [
+        Topic::Window,
+        Topic::Workspace,
+        Topic::Output,
+        Topic::Plugin,
+        Topic::Layout,
+    ];
+}
+
+impl FromStr for Topic {
+    type Err = String;
+
+    fn from_str(topic: &str) -> Result<Self, Self::Err> {
+        match topic {
+            "window" => Ok(Topic::Window),
+            "workspace" => Ok(Topic::Workspace),
+            "output" => Ok(Topic::Output),
+            "plugin" => Ok(Topic::Plugin),
+            "layout" => Ok(Topic::Layout),
+            topic => Err(format!(
+                "unknown topic `{topic}`, expected window, workspace, output, plugin or layout"
+            )),
+        }
+    }
+}
+
+/// Turns the connection into an event stream, the response is followed by
+/// an [`Event`] frame for everything that happens in the given topics.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct SubscribeRequest {
+    /// Every topic when empty
+    pub topics: Vec<Topic>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum SubscribeResponse {
+    Ok,
+    Error(String),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Event {
+    WindowOpened {
+        id: u64,
+    },
+    WindowClosed {
+        id: u64,
+    },
+    /// `None` when no window has the keyboard focus
+    WindowFocused {
+        id: Option<u64>,
+    },
+    WorkspaceChanged {
+        output: String,
+        previous: Option<u64>,
+        current: u64,
+    },
+    OutputAdded {
+        name: String,
+    },
+    OutputRemoved {
+        name: String,
+    },
+    PluginStatusChanged {
+        id: String,
+        status: String,
+    },
+    /// Active keyboard layout
+    LayoutChanged {
+        layout: String,
+    },
+}
+
+impl Event {
+    pub fn topic(&self) -> Topic {
+        match self {
+            Event::WindowOpened { .. }
+            | Event::WindowClosed { .. }
+            | Event::WindowFocused { .. } => Topic::Window,
+            Event::WorkspaceChanged { .. } => Topic::Workspace,
+            Event::OutputAdded { .. } | Event::OutputRemoved { .. } => Topic::Output,
+            Event::PluginStatusChanged { .. } => Topic::Plugin,
+            Event::LayoutChanged { .. } => Topic::Layout,
+        }
+    }
+}
@@ -0,0 +1,54 @@
+/// Largest frame accepted on the control socket, without the terminating zero.
+pub const MAX_FRAME_SIZE: usize = 1024 * 1024;
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum FrameError {
+    #[error("frame is larger than {MAX_FRAME_SIZE} bytes")]
+    TooLarge,
+}
+
+/// Collects bytes read from a socket and splits them into COBS frames.
+///
+/// Frames end with a zero byte, which is the only zero in COBS encoded data.
+#[derive(Debug, Default)]
+pub struct FrameBuffer {
+    buffer: Vec<u8>,
+    /// Bytes at the start of `buffer` known to contain no terminator.
+    scanned: usize,
+}
+
+impl FrameBuffer {
+    pub fn push(&mut self, bytes: &[u8]) {
+        self.buffer.extend_from_slice(bytes);
+    }
+
+    /// Takes the next complete frame without its terminator, ready for
+    /// `postcard::from_bytes_cobs`.
+    pub fn next_frame(&mut self) -> Result<Option<Vec<u8>>, FrameError> {
+        let Some(end) = self.buffer[self.scanned..]
+            .iter()
+            .position(|&byte| byte == 0)
+            .map(|end| self.scanned + end)
+        else {
+            self.scanned = self.buffer.len();
+            return if self.buffer.len() > MAX_FRAME_SIZE {
+                Err(FrameError::TooLarge)
+            } else {
+                Ok(None)
+            };
+        };
+
+        self.scanned = 0;
+        let mut frame = self.buffer.drain(..=end).collect::<Vec<_>>();
+        frame.pop();
+        if frame.len() > MAX_FRAME_SIZE {
+            return Err(FrameError::TooLarge);
+        }
+        Ok(Some(frame))
+    }
+
+    /// Whether part of a frame has been received.
+    pub fn is_partial(&self) -> bool {
+        !self.buffer.is_empty()
+    }
+}
@@ -0,0 +1,345 @@
+use serde::{Deserialize, Serialize};
+
+pub const BTN_LEFT: u32 = 0x110;
+pub const BTN_RIGHT: u32 = 0x111;
+pub const BTN_MIDDLE: u32 = 0x112;
+/// Largest Linux input event code, keys above it are rejected.
+pub const KEY_MAX: u32 = 0x2ff;
+
+/// Synthetic input event. Buttons and keys are Linux input event codes.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum InputAction {
+    /// Relative pointer motion in logical pixels.
+    PointerMotion {
+        dx: f64,
+        dy: f64,
+    },
+    /// Absolute pointer position, from 0.0 to 1.0 across the output.
+    PointerMotionAbsolute {
+        x: f64,
+        y: f64,
+    },
+    PointerButton {
+        button: u32,
+        pressed: bool,
+    },
+    /// Scroll amounts in logical pixels.
+    PointerAxis {
+        horizontal: f64,
+        vertical: f64,
+    },
+    Key {
+        key: u32,
+        pressed: bool,
+    },
+    GestureSwipeBegin {
+        fingers: u32,
+    },
+    /// Motion of the center of the fingers in logical pixels.
+    GestureSwipeUpdate {
+        dx: f64,
+        dy: f64,
+    },
+    GestureSwipeEnd {
+        cancelled: bool,
+    },
+    GesturePinchBegin {
+        fingers: u32,
+    },
+    /// `scale` is relative to the start of the pinch,
+    /// `rotation` is in degrees clockwise since the last update.
+    GesturePinchUpdate {
+        dx: f64,
+        dy: f64,
+        scale: f64,
+        rotation: f64,
+    },
+    GesturePinchEnd {
+        cancelled: bool,
+    },
+    GestureHoldBegin {
+        fingers: u32,
+    },
+    GestureHoldEnd {
+        cancelled: bool,
+    },
+    /// Touch point position, from 0.0 to 1.0 across the output.
+    TouchDown {
+        slot: u32,
+        x: f64,
+        y: f64,
+    },
+    TouchMotion {
+        slot: u32,
+        x: f64,
+        y: f64,
+    },
+    TouchUp {
+        slot: u32,
+    },
+    /// Ends a set of touch events happening at the same time.
+    TouchFrame,
+    TouchCancel,
+    /// Tablet tool entering or leaving proximity at an absolute position,
+    /// from 0.0 to 1.0 across the output.
+    TabletProximity {
+        x: f64,
+        y: f64,
+        in_proximity: bool,
+    },
+    TabletMotion {
+        x: f64,
+        y: f64,
+    },
+    /// The tool touching the tablet or lifting off it.
+    TabletTip {
+        down: bool,
+    },
+    TabletButton {
+        button: u32,
+        pressed: bool,
+    },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct InjectInputRequest {
+    pub actions: Vec<InputAction>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum InjectInputResponse {
+    Ok,
+    Error(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("line {line}: {message}")]
+pub struct InputScriptError {
+    pub line: usize,
+    pub message: String,
+}
+
+/// Parses an input script, one action per line:
+///
+/// ```text
+/// # comments and blank lines are skipped
+/// motion 10 -5            # relative motion
+/// motion-absolute 0.5 0.5 # absolute position from 0 to 1 on the mapped outputs
+/// button left click       # left/right/middle or a code; press, release or click
+/// axis 0 15               # horizontal and vertical scroll
+/// key 30 click            # press, release or click
+/// swipe begin 3           # touchpad gestures, begin takes the number of fingers
+/// swipe update 40 0       # motion of the fingers
+/// swipe end               # end or cancel
+/// pinch begin 2
+/// pinch update 0.5 15     # scale since the begin and optionally the rotation
+/// pinch cancel
+/// hold begin 3
+/// hold end
+/// touch down 0 0.5 0.5    # touch points, each followed by a frame
+/// touch motion 0 0.6 0.5  # slot and absolute position like motion-absolute
+/// touch up 0
+/// touch cancel
+/// tablet in 0.5 0.5       # tablet tool proximity, in or out and a position like motion-absolute
+/// tablet motion 0.6 0.5
+/// tablet tip down         # down or up
+/// tablet button 331 click # a code; press, release or click
+/// tablet out 0.6 0.5
+/// ```
+pub fn parse_input_script(script: &str) -> Result<Vec<InputAction>, InputScriptError> {
+    let mut actions = Vec::new();
+    for (index, line) in script.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or_default();
+        let words = line.split_whitespace().collect::<Vec<_>>();
+        let Some((command, args)) = words.split_first() else {
+            continue;
+        };
+
+        let error = |message: String| InputScriptError {
+            line: index + 1,
+            message,
+        };
+        let expect_args = |count: usize| {
+            if args.len() == count {
+                Ok(())
+            } else {
+                Err(error(format!(
+                    "`{command}` takes {count} arguments, got {}",
+                    args.len()
+                )))
+            }
+        };
+        let number = |arg: &str| {
+            arg.parse::<f64>()
+                .map_err(|_| error(format!("`{arg}` is not a number")))
+        };
+
+        match *command {
+            "motion" => {
+                expect_args(2)?;
+                actions.push(InputAction::PointerMotion {
+                    dx: number(args[0])?,
+                    dy: number(args[1])?,
+                });
+            }
+            "motion-absolute" => {
+                expect_args(2)?;
+                actions.push(InputAction::PointerMotionAbsolute {
+                    x: number(args[0])?,
+                    y: number(args[1])?,
+                });
+            }
+            "axis" => {
+                expect_args(2)?;
+                actions.push(InputAction::PointerAxis {
+                    horizontal: number(args[0])?,
+                    vertical: number(args[1])?,
+                });
+            }
+            "button" => {
+                expect_args(2)?;
+                let button = match args[0] {
+                    "left" => BTN_LEFT,
+                    "right" => BTN_RIGHT,
+                    "middle" => BTN_MIDDLE,
+                    code => code
+                        .parse()
+                        .map_err(|_| error(format!("unknown button `{code}`")))?,
+                };
+                for &pressed in transitions(args[1]).map_err(error)? {
+                    actions.push(InputAction::PointerButton { button, pressed });
+                }
+            }
+            "key" => {
+                expect_args(2)?;
+                let key = args[0]
+                    .parse()
+                    .ok()
+                    .filter(|key| *key <= KEY_MAX)
+                    .ok_or_else(|| error(format!("`{}` is not a key code", args[0])))?;
+                for &pressed in transitions(args[1]).map_err(error)? {
+                    actions.push(InputAction::Key { key, pressed });
+                }
+            }
+            "swipe" | "pinch" | "hold" => {
+                let Some((&phase, args)) = args.split_first() else {
+                    return Err(error(format!(
+                        "`{command}` needs begin, update, end or cancel"
+                    )));
+                };
+                let action = match (*command, phase, args) {
+                    (_, "begin", [fingers]) => {
+                        let fingers = fingers
+                            .parse()
+                            .map_err(|_| error(format!("`{fingers}` is not a finger count")))?;
+                        match *command {
+                            "swipe" => InputAction::GestureSwipeBegin { fingers },
+                            "pinch" => InputAction::GesturePinchBegin { fingers },
+                            _ => InputAction::GestureHoldBegin { fingers },
+                        }
+                    }
+                    (_, "end" | "cancel", []) => {
+                        let cancelled = phase == "cancel";
+                        match *command {
+                            "swipe" => InputAction::GestureSwipeEnd { cancelled },
+                            "pinch" => InputAction::GesturePinchEnd { cancelled },
+                            _ => InputAction::GestureHoldEnd { cancelled },
+                        }
+                    }
+                    ("swipe", "update", [dx, dy]) => InputAction::GestureSwipeUpdate {
+                        dx: number(dx)?,
+                        dy: number(dy)?,
+                    },
+                    ("pinch", "update", [scale]) => InputAction::GesturePinchUpdate {
+                        dx: 0.0,
+                        dy: 0.0,
+                        scale: number(scale)?,
+                        rotation: 0.0,
+                    },
+                    ("pinch", "update", [scale, rotation]) => InputAction::GesturePinchUpdate {
+                        dx: 0.0,
+                        dy: 0.0,
+                        scale: number(scale)?,
+                        rotation: number(rotation)?,
+                    },
+                    _ => {
+                        return Err(error(format!("invalid arguments for `{command} {phase}`")));
+                    }
+                };
+                actions.push(action);
+            }
+            "touch" => {
+                let slot = |arg: &str| {
+                    arg.parse()
+                        .map_err(|_| error(format!("`{arg}` is not a touch slot")))
+                };
+                let action = match args {
+                    ["down", id, x, y] => InputAction::TouchDown {
+                        slot: slot(id)?,
+                        x: number(x)?,
+                        y: number(y)?,
+                    },
+                    ["motion", id, x, y] => InputAction::TouchMotion {
+                        slot: slot(id)?,
+                        x: number(x)?,
+                        y: number(y)?,
+                    },
+                    ["up", id] => InputAction::TouchUp { slot: slot(id)? },
+                    ["cancel"] => InputAction::TouchCancel,
+                    _ => {
+                        return Err(error(
+                            "`touch` needs down, motion, up or cancel and their arguments"
+                                .to_string(),
+                        ));
+                    }
+                };
+                actions.push(action);
+                if action != InputAction::TouchCancel {
+                    actions.push(InputAction::TouchFrame);
+                }
+            }
+            "tablet" => match args {
+                [state @ ("in" | "out"), x, y] => actions.push(InputAction::TabletProximity {
+                    x: number(x)?,
+                    y: number(y)?,
+                    in_proximity: *state == "in",
+                }),
+                ["motion", x, y] => actions.push(InputAction::TabletMotion {
+                    x: number(x)?,
+                    y: number(y)?,
+                }),
+                ["tip", state @ ("down" | "up")] => {
+                    actions.push(InputAction::TabletTip {
+                        down: *state == "down",
+                    });
+                }
+                ["button", code, state] => {
+                    let button = code
+                        .parse()
+                        .map_err(|_| error(format!("unknown button `{code}`")))?;
+                    for &pressed in transitions(state).map_err(error)? {
+                        actions.push(InputAction::TabletButton { button, pressed });
+                    }
+                }
+                _ => {
+                    return Err(error(
+                        "`tablet` needs in, out, motion, tip or button and their arguments"
+                            .to_string(),
+                    ));
+                }
+            },
+            command => return Err(error(format!("unknown command `{command}`"))),
+        }
+    }
+
+    Ok(actions)
+}
+
+fn transitions(state: &str) -> Result<&'static [bool], String> {
+    match state {
+        "press" => Ok(&[true]),
+        "release" => Ok(&[false]),
+        "click" => Ok(&[true, false]),
+        state => Err(format!("unknown state `{state}`")),
+    }
+}
@@ -1,9 +1,48 @@
+use std::path::{Path, PathBuf};
+
 use derive_more::From;
 use serde::{Deserialize, Serialize};
 
-pub const FUSION_CTL_SOCKET_DEFAULT: &str = "/tmp/fusion-ctl.sock";
+mod client;
+mod envelope;
+mod event;
+mod frame;
+mod input;
+mod output;
+mod window;
+pub use client::*;
+pub use envelope::*;
+pub use event::*;
+pub use frame::*;
+pub use input::*;
+pub use output::*;
+pub use window::*;
+
+/// Variable the compositor exports to its children with the path of its control socket.
+pub const FUSION_SOCKET_ENV: &str = "FUSION_SOCKET";
 
-#[derive(Serialize, Deserialize)]
+/// Control socket of the compositor serving the Wayland display `display`, kept in `runtime_dir`.
+///
+/// Every display gets its own socket, so nested compositors don't take over each other's.
+pub fn socket_path(runtime_dir: impl AsRef<Path>, display: &str) -> PathBuf {
+    // `WAYLAND_DISPLAY` may also be an absolute path
+    let display = Path::new(display)
+        .file_name()
+        .map_or(display.into(), |name| name.to_string_lossy());
+    runtime_dir.as_ref().join(format!("fusion-{display}.sock"))
+}
+
+/// Control socket clients use by default: `$FUSION_SOCKET`, else the one of `$WAYLAND_DISPLAY`.
+pub fn default_socket_path() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os(FUSION_SOCKET_ENV) {
+        return Some(path.into());
+    }
+    let runtime_dir = std::env::var_os("XDG_RUNTIME_DIR")?;
+    let display = std::env::var("WAYLAND_DISPLAY").ok()?;
+    Some(socket_path(runtime_dir, &display))
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct Plugin {
     pub id: String,
     pub name: String,
@@ -11,39 +50,225 @@ pub struct Plugin {
     pub version: String,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct GetPluginListRequest;
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub enum GetPluginListResponse {
     Ok(Vec<Plugin>),
     Error(String),
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct RestartPluginRequest {
     pub plugin_id: String,
 }
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub enum RestartPluginResponse {
     Ok,
     Error(String),
 }
 
-#[derive(Serialize, Deserialize)]
+/// Everything known about a plugin, from its manifest and the engine.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PluginInfo {
+    pub id: String,
+    pub name: String,
+    pub version: String,
+    pub description: String,
+    pub repository: String,
+    /// `Name <email>` or just the name
+    pub authors: Vec<String>,
+    pub capabilities: Vec<String>,
+    pub custom_capabilities: Vec<String>,
+    pub status: String,
+    /// Why the plugin failed, only set while it is failed
+    pub failure: Option<String>,
+    /// Installed package
+    pub path: String,
+    pub log_path: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct GetPluginInfoRequest {
+    pub plugin_id: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum GetPluginInfoResponse {
+    Ok(Box<PluginInfo>),
+    Error(String),
+}
+
+/// Validates a package and copies it into the plugins directory.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct InstallPluginRequest {
+    /// Absolute path of the `.fsp` file, read by the compositor
+    pub path: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum InstallPluginResponse {
+    /// ID of the installed plugin
+    Ok(String),
+    Error(String),
+}
+
+/// Stops a plugin and deletes its package.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct UninstallPluginRequest {
+    pub plugin_id: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum UninstallPluginResponse {
+    Ok,
+    Error(String),
+}
+
+/// Starts or stops a plugin, the choice is kept across restarts.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SetPluginEnabledRequest {
+    pub plugin_id: String,
+    pub enabled: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum SetPluginEnabledResponse {
+    Ok,
+    Error(String),
+}
+
+/// Calls a plugin export without bindings, for debugging.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CallPluginRequest {
+    pub plugin_id: String,
+    /// Capability whose instance is called
+    pub capability: String,
+    /// `interface#function` for exports of an interface, e.g. `fusion:wm/layout#rearrange-windows`
+    pub function: String,
+    /// JSON array with one value per parameter
+    pub args: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum CallPluginResponse {
+    /// JSON of the result, `null` without one
+    Ok(String),
+    Error(String),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct PingRequest;
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct PingResponse;
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct ExitRequest;
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct ExitResponse;
 
-#[derive(Serialize, Deserialize, From)]
-pub enum CompositorRequest {
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Workspace {
+    pub id: u64,
+    pub name: String,
+    pub output: String,
+    pub active: bool,
+    pub windows: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct GetWorkspacesRequest;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum GetWorkspacesResponse {
+    Ok(Vec<Workspace>),
+    Error(String),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ReloadConfigRequest;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum ReloadConfigResponse {
+    Ok,
+    Error(String),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Keyboard {
+    /// Comma separated xkb layouts, variants and options, empty for the defaults
+    pub layout: String,
+    pub variant: String,
+    pub options: String,
+    /// Milliseconds
+    pub repeat_delay: i32,
+    /// Repeats per second
+    pub repeat_rate: i32,
+    /// Human readable name of the active layout, e.g. `English (US)`
+    pub active_layout: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct GetKeyboardRequest;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum GetKeyboardResponse {
+    Ok(Keyboard),
+    Error(String),
+}
+
+/// Changes the given keyboard settings and keeps the others.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct SetKeyboardRequest {
+    pub layout: Option<String>,
+    pub variant: Option<String>,
+    pub options: Option<String>,
+    pub repeat_delay: Option<i32>,
+    pub repeat_rate: Option<i32>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum SetKeyboardResponse {
+    Ok,
+    Error(String),
+}
+
+/// Declares [`CompositorRequest`] and counts its variants, so the count can't go stale.
+macro_rules! requests {
+    ($($variant:ident($request:ty)),* $(,)?) => {
+        /// Requests are only ever appended, see [`PROTOCOL_VERSION`].
+        #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, From)]
+        pub enum CompositorRequest {
+            $($variant($request)),*
+        }
+
+        impl CompositorRequest {
+            /// Number of requests, higher indices come from newer clients.
+            #[allow(clippy::cast_possible_truncation)]
+            pub const VARIANTS: u32 = [$(stringify!($variant)),*].len() as u32;
+        }
+    };
+}
+
+requests! {
     Exit(ExitRequest),
     Ping(PingRequest),
     GetPluginList(GetPluginListRequest),
     RestartPlugin(RestartPluginRequest),
+    GetWorkspaces(GetWorkspacesRequest),
+    InjectInput(InjectInputRequest),
+    ReloadConfig(ReloadConfigRequest),
+    GetKeyboard(GetKeyboardRequest),
+    SetKeyboard(SetKeyboardRequest),
+    Subscribe(SubscribeRequest),
+    GetWindows(GetWindowsRequest),
+    WindowAction(WindowActionRequest),
+    GetOutputs(GetOutputsRequest),
+    SetOutput(SetOutputRequest),
+    GetPluginInfo(GetPluginInfoRequest),
+    InstallPlugin(InstallPluginRequest),
+    UninstallPlugin(UninstallPluginRequest),
+    SetPluginEnabled(SetPluginEnabledRequest),
+    CallPlugin(CallPluginRequest),
 }
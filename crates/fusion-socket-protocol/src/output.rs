@@ -0,0 +1,154 @@
+use std::{fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OutputMode {
+    pub width: i32,
+    pub height: i32,
+    /// Millihertz
+    pub refresh: i32,
+    pub preferred: bool,
+}
+
+impl fmt::Display for OutputMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let refresh = f64::from(self.refresh) / 1000.0;
+        write!(f, "{}x{}@{refresh:.3}", self.width, self.height)
+    }
+}
+
+/// Rotation counter-clockwise and flipping around the vertical axis, as in `wl_output`.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Transform {
+    #[default]
+    Normal,
+    Rotate90,
+    Rotate180,
+    Rotate270,
+    Flipped,
+    Flipped90,
+    Flipped180,
+    Flipped270,
+}
+
+impl Transform {
+    pub const ALL: [Transform; 8] = [
+        Transform::Normal,
+        Transform::Rotate90,
+        Transform::Rotate180,
+        Transform::Rotate270,
+        Transform::Flipped,
+        Transform::Flipped90,
+        Transform::Flipped180,
+        Transform::Flipped270,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Transform::Normal => "normal",
+            Transform::Rotate90 => "90",
+            Transform::Rotate180 => "180",
+            Transform::Rotate270 => "270",
+            Transform::Flipped => "flipped",
+            Transform::Flipped90 => "flipped-90",
+            Transform::Flipped180 => "flipped-180",
+            Transform::Flipped270 => "flipped-270",
+        }
+    }
+}
+
+impl fmt::Display for Transform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Transform {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Transform::ALL
+            .into_iter()
+            .find(|transform| transform.name() == s)
+            .ok_or_else(|| {
+                format!("unknown transform {s}, expected normal, 90, 180, 270, flipped, flipped-90, flipped-180 or flipped-270")
+            })
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Output {
+    /// Connector name, e.g. `DP-1`
+    pub name: String,
+    pub make: String,
+    pub model: String,
+    pub modes: Vec<OutputMode>,
+    pub current_mode: Option<OutputMode>,
+    pub scale: f64,
+    pub transform: Transform,
+    /// Global position in logical pixels
+    pub x: i32,
+    pub y: i32,
+    pub enabled: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct GetOutputsRequest;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum GetOutputsResponse {
+    Ok(Vec<Output>),
+    Error(String),
+}
+
+/// Mode to switch to, without a refresh rate the highest one of the size is used.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ModeRequest {
+    pub width: i32,
+    pub height: i32,
+    /// Millihertz
+    pub refresh: Option<i32>,
+}
+
+impl FromStr for ModeRequest {
+    type Err = String;
+
+    /// Parses `WIDTHxHEIGHT` with an optional `@HZ`, e.g. `2560x1440@143.912`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid mode {s}, expected e.g. 1920x1080 or 1920x1080@60");
+        let (size, refresh) = match s.split_once('@') {
+            Some((size, refresh)) => {
+                let hz = refresh.parse::<f64>().map_err(|_| invalid())?;
+                (size, Some((hz * 1000.0).round() as i32))
+            }
+            None => (s, None),
+        };
+        let (width, height) = size.split_once('x').ok_or_else(invalid)?;
+
+        Ok(ModeRequest {
+            width: width.parse().map_err(|_| invalid())?,
+            height: height.parse().map_err(|_| invalid())?,
+            refresh,
+        })
+    }
+}
+
+/// Changes the given settings of an output and keeps the others.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct SetOutputRequest {
+    pub name: String,
+    pub mode: Option<ModeRequest>,
+    pub scale: Option<f64>,
+    pub transform: Option<Transform>,
+    pub position: Option<(i32, i32)>,
+    pub enabled: Option<bool>,
+    /// Only checks that the configuration would work
+    pub test: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum SetOutputResponse {
+    Ok,
+    Error(String),
+}
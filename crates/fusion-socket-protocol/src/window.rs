@@ -0,0 +1,60 @@
+use serde::{Deserialize, Serialize};
+
+/// Rectangle in global logical coordinates.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Geometry {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Window {
+    /// Same ID as in the window events
+    pub id: u64,
+    pub app_id: String,
+    pub title: String,
+    /// Name of the workspace the window belongs to
+    pub workspace: Option<String>,
+    pub output: Option<String>,
+    /// Content geometry, `None` while the window is hidden or minimized
+    pub geometry: Option<Geometry>,
+    pub focused: bool,
+    pub fullscreen: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct GetWindowsRequest;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum GetWindowsResponse {
+    Ok(Vec<Window>),
+    Error(String),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum WindowAction {
+    /// Gives the window the keyboard, switching to its workspace if needed
+    Focus,
+    /// Asks the client to close the window
+    Close,
+    /// Moves the content of the window to a global position
+    Move { x: i32, y: i32 },
+    /// Resizes the content of the window
+    Resize { width: i32, height: i32 },
+    /// `None` toggles fullscreen
+    Fullscreen(Option<bool>),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct WindowActionRequest {
+    pub id: u64,
+    pub action: WindowAction,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum WindowActionResponse {
+    Ok,
+    Error(String),
+}
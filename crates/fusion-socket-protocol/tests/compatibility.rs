@@ -0,0 +1,486 @@
+//! Pins the wire encoding, a failure here means old peers can no longer talk to new ones.
+//!
+//! Appending variants and adding tests for them is fine, changing existing bytes is not.
+
+use std::fmt::Debug;
+
+use fusion_socket_protocol::{
+    CallPluginRequest, CallPluginResponse, CompositorRequest, Envelope, Event, ExitRequest,
+    Features, Geometry, GetKeyboardRequest, GetKeyboardResponse, GetOutputsRequest,
+    GetOutputsResponse, GetPluginInfoRequest, GetPluginInfoResponse, GetPluginListRequest,
+    GetPluginListResponse, GetWindowsRequest, GetWindowsResponse, GetWorkspacesRequest,
+    GetWorkspacesResponse, Hello, HelloResponse, InjectInputRequest, InjectInputResponse,
+    InputAction, InstallPluginRequest, InstallPluginResponse, Keyboard, ModeRequest, Output,
+    OutputMode, PROTOCOL_VERSION, PingRequest, Plugin, PluginInfo, ProtocolError,
+    ReloadConfigRequest, ReloadConfigResponse, Reply, RestartPluginRequest, RestartPluginResponse,
+    SetKeyboardRequest, SetKeyboardResponse, SetOutputRequest, SetOutputResponse,
+    SetPluginEnabledRequest, SetPluginEnabledResponse, SubscribeRequest, SubscribeResponse, Topic,
+    Transform, UninstallPluginRequest, UninstallPluginResponse, Window, WindowAction,
+    WindowActionRequest, WindowActionResponse, Workspace,
+};
+use serde::{Serialize, de::DeserializeOwned};
+
+const ONE: [u8; 8] = [0, 0, 0, 0, 0, 0, 0xf0, 0x3f];
+const ZERO: [u8; 8] = [0; 8];
+
+fn assert_encoding<T>(value: T, bytes: &[u8])
+where
+    T: Serialize + DeserializeOwned + PartialEq + Debug,
+{
+    assert_eq!(postcard::to_stdvec(&value).unwrap(), bytes, "{value:?}");
+    assert_eq!(postcard::from_bytes::<T>(bytes).unwrap(), value);
+}
+
+fn concat(parts: &[&[u8]]) -> Vec<u8> {
+    parts.concat()
+}
+
+#[test]
+fn handshake() {
+    assert_eq!(PROTOCOL_VERSION, 1);
+    assert_eq!(Features::SUBSCRIBE.bits(), 1);
+    assert_eq!(Features::INJECT_INPUT.bits(), 2);
+    assert_eq!(Features::CALL_PLUGIN.bits(), 4);
+
+    assert_encoding(
+        Hello {
+            version: 1,
+            features: Features::SUBSCRIBE | Features::INJECT_INPUT,
+        },
+        &[1, 3],
+    );
+    assert_encoding(
+        HelloResponse::Ok {
+            version: 1,
+            features: Features::SUBSCRIBE,
+        },
+        &[0, 1, 1],
+    );
+    assert_encoding(HelloResponse::Error("no".to_string()), &[1, 2, b'n', b'o']);
+}
+
+#[test]
+fn envelopes() {
+    assert_encoding(
+        Envelope {
+            id: 300,
+            body: vec![1],
+        },
+        &[0xac, 0x02, 1, 1],
+    );
+    assert_encoding(
+        Reply::Response {
+            id: 1,
+            body: vec![0],
+        },
+        &[0, 1, 1, 0],
+    );
+    assert_encoding(
+        Reply::Error {
+            id: Some(2),
+            error: ProtocolError::UnknownRequest(10),
+        },
+        &[1, 1, 2, 0, 10],
+    );
+    assert_encoding(
+        Reply::Error {
+            id: None,
+            error: ProtocolError::Malformed("x".to_string()),
+        },
+        &[1, 0, 1, 1, b'x'],
+    );
+    assert_encoding(ProtocolError::TooLarge, &[2]);
+    assert_encoding(ProtocolError::Timeout, &[3]);
+    assert_encoding(Reply::Event { body: vec![5, 6] }, &[2, 2, 5, 6]);
+}
+
+#[test]
+fn requests() {
+    assert_encoding(CompositorRequest::from(ExitRequest), &[0]);
+    assert_encoding(CompositorRequest::from(PingRequest), &[1]);
+    assert_encoding(CompositorRequest::from(GetPluginListRequest), &[2]);
+    assert_encoding(
+        CompositorRequest::from(RestartPluginRequest {
+            plugin_id: "p".to_string(),
+        }),
+        &[3, 1, b'p'],
+    );
+    assert_encoding(CompositorRequest::from(GetWorkspacesRequest), &[4]);
+    assert_encoding(
+        CompositorRequest::from(InjectInputRequest {
+            actions: vec![InputAction::TouchFrame],
+        }),
+        &[5, 1, 16],
+    );
+    assert_encoding(CompositorRequest::from(ReloadConfigRequest), &[6]);
+    assert_encoding(CompositorRequest::from(GetKeyboardRequest), &[7]);
+    assert_encoding(
+        CompositorRequest::from(SetKeyboardRequest {
+            layout: Some("de".to_string()),
+            repeat_rate: Some(-1),
+            ..SetKeyboardRequest::default()
+        }),
+        &[8, 1, 2, b'd', b'e', 0, 0, 0, 1, 1],
+    );
+    assert_encoding(
+        CompositorRequest::from(SubscribeRequest {
+            topics: vec![Topic::Layout],
+        }),
+        &[9, 1, 4],
+    );
+    assert_encoding(CompositorRequest::from(GetWindowsRequest), &[10]);
+    assert_encoding(
+        CompositorRequest::from(WindowActionRequest {
+            id: 1,
+            action: WindowAction::Focus,
+        }),
+        &[11, 1, 0],
+    );
+    assert_encoding(CompositorRequest::from(GetOutputsRequest), &[12]);
+    assert_encoding(
+        CompositorRequest::from(SetOutputRequest {
+            name: "o".to_string(),
+            mode: Some(ModeRequest {
+                width: 1,
+                height: 2,
+                refresh: None,
+            }),
+            position: Some((-1, 1)),
+            enabled: Some(false),
+            test: true,
+            ..SetOutputRequest::default()
+        }),
+        &[13, 1, b'o', 1, 2, 4, 0, 0, 0, 1, 1, 2, 1, 0, 1],
+    );
+    assert_encoding(
+        CompositorRequest::from(GetPluginInfoRequest {
+            plugin_id: "p".to_string(),
+        }),
+        &[14, 1, b'p'],
+    );
+    assert_encoding(
+        CompositorRequest::from(InstallPluginRequest {
+            path: "/".to_string(),
+        }),
+        &[15, 1, b'/'],
+    );
+    assert_encoding(
+        CompositorRequest::from(UninstallPluginRequest {
+            plugin_id: "p".to_string(),
+        }),
+        &[16, 1, b'p'],
+    );
+    assert_encoding(
+        CompositorRequest::from(SetPluginEnabledRequest {
+            plugin_id: "p".to_string(),
+            enabled: true,
+        }),
+        &[17, 1, b'p', 1],
+    );
+    assert_encoding(
+        CompositorRequest::from(CallPluginRequest {
+            plugin_id: "p".to_string(),
+            capability: "c".to_string(),
+            function: "f".to_string(),
+            args: "[]".to_string(),
+        }),
+        &[18, 1, b'p', 1, b'c', 1, b'f', 2, b'[', b']'],
+    );
+    assert_eq!(CompositorRequest::VARIANTS, 19);
+}
+
+#[test]
+fn responses() {
+    assert_encoding(
+        GetPluginListResponse::Ok(vec![Plugin {
+            id: "i".to_string(),
+            name: "n".to_string(),
+            status: "s".to_string(),
+            version: "v".to_string(),
+        }]),
+        &[0, 1, 1, b'i', 1, b'n', 1, b's', 1, b'v'],
+    );
+    assert_encoding(GetPluginListResponse::Error(String::new()), &[1, 0]);
+    assert_encoding(RestartPluginResponse::Ok, &[0]);
+    assert_encoding(RestartPluginResponse::Error(String::new()), &[1, 0]);
+    assert_encoding(
+        GetWorkspacesResponse::Ok(vec![Workspace {
+            id: 1,
+            name: "w".to_string(),
+            output: "o".to_string(),
+            active: true,
+            windows: 2,
+        }]),
+        &[0, 1, 1, 1, b'w', 1, b'o', 1, 2],
+    );
+    assert_encoding(GetWorkspacesResponse::Error(String::new()), &[1, 0]);
+    assert_encoding(InjectInputResponse::Ok, &[0]);
+    assert_encoding(InjectInputResponse::Error(String::new()), &[1, 0]);
+    assert_encoding(ReloadConfigResponse::Ok, &[0]);
+    assert_encoding(ReloadConfigResponse::Error(String::new()), &[1, 0]);
+    assert_encoding(
+        GetKeyboardResponse::Ok(Keyboard {
+            layout: "us".to_string(),
+            variant: String::new(),
+            options: String::new(),
+            repeat_delay: 1,
+            repeat_rate: 2,
+            active_layout: "U".to_string(),
+        }),
+        &[0, 2, b'u', b's', 0, 0, 2, 4, 1, b'U'],
+    );
+    assert_encoding(GetKeyboardResponse::Error(String::new()), &[1, 0]);
+    assert_encoding(SetKeyboardResponse::Ok, &[0]);
+    assert_encoding(SetKeyboardResponse::Error(String::new()), &[1, 0]);
+    assert_encoding(SubscribeResponse::Ok, &[0]);
+    assert_encoding(SubscribeResponse::Error(String::new()), &[1, 0]);
+    assert_encoding(
+        GetWindowsResponse::Ok(vec![Window {
+            id: 1,
+            app_id: "a".to_string(),
+            title: "t".to_string(),
+            workspace: Some("1".to_string()),
+            output: None,
+            geometry: Some(Geometry {
+                x: -1,
+                y: 1,
+                width: 2,
+                height: 3,
+            }),
+            focused: true,
+            fullscreen: false,
+        }]),
+        &[
+            0, 1, 1, 1, b'a', 1, b't', 1, 1, b'1', 0, 1, 1, 2, 4, 6, 1, 0,
+        ],
+    );
+    assert_encoding(GetWindowsResponse::Error(String::new()), &[1, 0]);
+    assert_encoding(WindowActionResponse::Ok, &[0]);
+    assert_encoding(WindowActionResponse::Error(String::new()), &[1, 0]);
+    assert_encoding(
+        GetOutputsResponse::Ok(vec![Output {
+            name: "o".to_string(),
+            make: String::new(),
+            model: String::new(),
+            modes: Vec::new(),
+            current_mode: Some(OutputMode {
+                width: 1,
+                height: 2,
+                refresh: 3,
+                preferred: true,
+            }),
+            scale: 1.0,
+            transform: Transform::Flipped270,
+            x: 0,
+            y: -1,
+            enabled: true,
+        }]),
+        &concat(&[
+            &[0, 1, 1, b'o', 0, 0, 0, 1, 2, 4, 6, 1],
+            &ONE,
+            &[7, 0, 1, 1],
+        ]),
+    );
+    assert_encoding(GetOutputsResponse::Error(String::new()), &[1, 0]);
+    assert_encoding(SetOutputResponse::Ok, &[0]);
+    assert_encoding(SetOutputResponse::Error(String::new()), &[1, 0]);
+    assert_encoding(
+        GetPluginInfoResponse::Ok(Box::new(PluginInfo {
+            id: "i".to_string(),
+            name: "n".to_string(),
+            version: "v".to_string(),
+            description: String::new(),
+            repository: String::new(),
+            authors: vec!["a".to_string()],
+            capabilities: vec!["c".to_string()],
+            custom_capabilities: Vec::new(),
+            status: "s".to_string(),
+            failure: Some("f".to_string()),
+            path: "p".to_string(),
+            log_path: "l".to_string(),
+        })),
+        &[
+            0, 1, b'i', 1, b'n', 1, b'v', 0, 0, 1, 1, b'a', 1, 1, b'c', 0, 1, b's', 1, 1, b'f', 1,
+            b'p', 1, b'l',
+        ],
+    );
+    assert_encoding(GetPluginInfoResponse::Error(String::new()), &[1, 0]);
+    assert_encoding(InstallPluginResponse::Ok("i".to_string()), &[0, 1, b'i']);
+    assert_encoding(InstallPluginResponse::Error(String::new()), &[1, 0]);
+    assert_encoding(UninstallPluginResponse::Ok, &[0]);
+    assert_encoding(UninstallPluginResponse::Error(String::new()), &[1, 0]);
+    assert_encoding(SetPluginEnabledResponse::Ok, &[0]);
+    assert_encoding(SetPluginEnabledResponse::Error(String::new()), &[1, 0]);
+    assert_encoding(CallPluginResponse::Ok("1".to_string()), &[0, 1, b'1']);
+    assert_encoding(CallPluginResponse::Error(String::new()), &[1, 0]);
+}
+
+#[test]
+fn transforms() {
+    for (index, transform) in Transform::ALL.into_iter().enumerate() {
+        assert_encoding(transform, &[index as u8]);
+    }
+}
+
+#[test]
+fn window_actions() {
+    assert_encoding(WindowAction::Focus, &[0]);
+    assert_encoding(WindowAction::Close, &[1]);
+    assert_encoding(WindowAction::Move { x: -1, y: 2 }, &[2, 1, 4]);
+    assert_encoding(
+        WindowAction::Resize {
+            width: 1,
+            height: 2,
+        },
+        &[3, 2, 4],
+    );
+    assert_encoding(WindowAction::Fullscreen(None), &[4, 0]);
+    assert_encoding(WindowAction::Fullscreen(Some(true)), &[4, 1, 1]);
+}
+
+#[test]
+fn input_actions() {
+    let actions = [
+        (
+            InputAction::PointerMotion { dx: 1.0, dy: 0.0 },
+            concat(&[&[0], &ONE, &ZERO]),
+        ),
+        (
+            InputAction::PointerMotionAbsolute { x: 0.0, y: 1.0 },
+            concat(&[&[1], &ZERO, &ONE]),
+        ),
+        (
+            InputAction::PointerButton {
+                button: 0x110,
+                pressed: true,
+            },
+            vec![2, 0x90, 0x02, 1],
+        ),
+        (
+            InputAction::PointerAxis {
+                horizontal: 0.0,
+                vertical: 1.0,
+            },
+            concat(&[&[3], &ZERO, &ONE]),
+        ),
+        (
+            InputAction::Key {
+                key: 30,
+                pressed: false,
+            },
+            vec![4, 30, 0],
+        ),
+        (InputAction::GestureSwipeBegin { fingers: 3 }, vec![5, 3]),
+        (
+            InputAction::GestureSwipeUpdate { dx: 0.0, dy: 1.0 },
+            concat(&[&[6], &ZERO, &ONE]),
+        ),
+        (InputAction::GestureSwipeEnd { cancelled: true }, vec![7, 1]),
+        (InputAction::GesturePinchBegin { fingers: 2 }, vec![8, 2]),
+        (
+            InputAction::GesturePinchUpdate {
+                dx: 0.0,
+                dy: 0.0,
+                scale: 1.0,
+                rotation: 0.0,
+            },
+            concat(&[&[9], &ZERO, &ZERO, &ONE, &ZERO]),
+        ),
+        (
+            InputAction::GesturePinchEnd { cancelled: false },
+            vec![10, 0],
+        ),
+        (InputAction::GestureHoldBegin { fingers: 1 }, vec![11, 1]),
+        (InputAction::GestureHoldEnd { cancelled: true }, vec![12, 1]),
+        (
+            InputAction::TouchDown {
+                slot: 0,
+                x: 1.0,
+                y: 0.0,
+            },
+            concat(&[&[13, 0], &ONE, &ZERO]),
+        ),
+        (
+            InputAction::TouchMotion {
+                slot: 1,
+                x: 0.0,
+                y: 1.0,
+            },
+            concat(&[&[14, 1], &ZERO, &ONE]),
+        ),
+        (InputAction::TouchUp { slot: 2 }, vec![15, 2]),
+        (InputAction::TouchFrame, vec![16]),
+        (InputAction::TouchCancel, vec![17]),
+        (
+            InputAction::TabletProximity {
+                x: 1.0,
+                y: 0.0,
+                in_proximity: true,
+            },
+            concat(&[&[18], &ONE, &ZERO, &[1]]),
+        ),
+        (
+            InputAction::TabletMotion { x: 0.0, y: 1.0 },
+            concat(&[&[19], &ZERO, &ONE]),
+        ),
+        (InputAction::TabletTip { down: false }, vec![20, 0]),
+        (
+            InputAction::TabletButton {
+                button: 0x14b,
+                pressed: true,
+            },
+            vec![21, 0xcb, 0x02, 1],
+        ),
+    ];
+
+    for (action, bytes) in actions {
+        assert_encoding(action, &bytes);
+    }
+}
+
+#[test]
+fn events() {
+    assert_encoding(Topic::Window, &[0]);
+    assert_encoding(Topic::Workspace, &[1]);
+    assert_encoding(Topic::Output, &[2]);
+    assert_encoding(Topic::Plugin, &[3]);
+    assert_encoding(Topic::Layout, &[4]);
+
+    assert_encoding(Event::WindowOpened { id: 1 }, &[0, 1]);
+    assert_encoding(Event::WindowClosed { id: 2 }, &[1, 2]);
+    assert_encoding(Event::WindowFocused { id: None }, &[2, 0]);
+    assert_encoding(Event::WindowFocused { id: Some(3) }, &[2, 1, 3]);
+    assert_encoding(
+        Event::WorkspaceChanged {
+            output: "o".to_string(),
+            previous: Some(1),
+            current: 2,
+        },
+        &[3, 1, b'o', 1, 1, 2],
+    );
+    assert_encoding(
+        Event::OutputAdded {
+            name: "o".to_string(),
+        },
+        &[4, 1, b'o'],
+    );
+    assert_encoding(
+        Event::OutputRemoved {
+            name: "o".to_string(),
+        },
+        &[5, 1, b'o'],
+    );
+    assert_encoding(
+        Event::PluginStatusChanged {
+            id: "i".to_string(),
+            status: "s".to_string(),
+        },
+        &[6, 1, b'i', 1, b's'],
+    );
+    assert_encoding(
+        Event::LayoutChanged {
+            layout: "l".to_string(),
+        },
+        &[7, 1, b'l'],
+    );
+}
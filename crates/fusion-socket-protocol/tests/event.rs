@@ -0,0 +1,45 @@
+use fusion_socket_protocol::{Event, Topic};
+
+#[test]
+fn topics_are_parsed() {
+    assert_eq!("window".parse(), Ok(Topic::Window));
+    assert_eq!("layout".parse(), Ok(Topic::Layout));
+    assert!("windows".parse::<Topic>().is_err());
+}
+
+#[test]
+fn events_belong_to_topics() {
+    let events = [
+        (Event::WindowFocused { id: None }, Topic::Window),
+        (
+            Event::WorkspaceChanged {
+                output: "DP-1".to_string(),
+                previous: None,
+                current: 1,
+            },
+            Topic::Workspace,
+        ),
+        (
+            Event::OutputRemoved {
+                name: "DP-1".to_string(),
+            },
+            Topic::Output,
+        ),
+        (
+            Event::PluginStatusChanged {
+                id: "window-manager".to_string(),
+                status: "running".to_string(),
+            },
+            Topic::Plugin,
+        ),
+    ];
+    for (event, topic) in events {
+        assert_eq!(event.topic(), topic);
+
+        let mut frame = postcard::to_stdvec_cobs(&event).unwrap();
+        assert_eq!(
+            postcard::from_bytes_cobs::<Event>(&mut frame).unwrap(),
+            event
+        );
+    }
+}
@@ -0,0 +1,83 @@
+use fusion_socket_protocol::{
+    CompositorRequest, FrameBuffer, FrameError, MAX_FRAME_SIZE, PingRequest, RestartPluginRequest,
+};
+
+fn encode(request: impl Into<CompositorRequest>) -> Vec<u8> {
+    postcard::to_stdvec_cobs(&request.into()).unwrap()
+}
+
+#[test]
+fn frames_split_across_reads() {
+    let bytes = encode(RestartPluginRequest {
+        plugin_id: "window-manager".to_string(),
+    });
+    let (first, second) = bytes.split_at(5);
+
+    let mut frames = FrameBuffer::default();
+    frames.push(first);
+    assert_eq!(frames.next_frame(), Ok(None));
+    assert!(frames.is_partial());
+
+    frames.push(second);
+    let mut frame = frames.next_frame().unwrap().unwrap();
+    assert!(!frames.is_partial());
+    assert!(matches!(
+        postcard::from_bytes_cobs(&mut frame).unwrap(),
+        CompositorRequest::RestartPlugin(request) if request.plugin_id == "window-manager"
+    ));
+}
+
+#[test]
+fn several_frames_in_one_read() {
+    let mut bytes = encode(PingRequest);
+    bytes.extend(encode(PingRequest));
+    bytes.extend(&encode(PingRequest)[..1]);
+
+    let mut frames = FrameBuffer::default();
+    frames.push(&bytes);
+    for _ in 0..2 {
+        let mut frame = frames.next_frame().unwrap().unwrap();
+        assert!(matches!(
+            postcard::from_bytes_cobs(&mut frame).unwrap(),
+            CompositorRequest::Ping(_)
+        ));
+    }
+    assert_eq!(frames.next_frame(), Ok(None));
+    assert!(frames.is_partial());
+}
+
+#[test]
+fn oversized_frames_are_rejected() {
+    let mut frames = FrameBuffer::default();
+    frames.push(&vec![1; MAX_FRAME_SIZE]);
+    assert_eq!(frames.next_frame(), Ok(None));
+
+    frames.push(&[1]);
+    assert_eq!(frames.next_frame(), Err(FrameError::TooLarge));
+}
+
+#[test]
+fn frames_pushed_byte_by_byte() {
+    let mut bytes = encode(RestartPluginRequest {
+        plugin_id: "window-manager".to_string(),
+    });
+    bytes.extend(encode(PingRequest));
+
+    let mut frames = FrameBuffer::default();
+    let mut received = Vec::new();
+    for byte in bytes {
+        frames.push(&[byte]);
+        while let Some(mut frame) = frames.next_frame().unwrap() {
+            received.push(postcard::from_bytes_cobs(&mut frame).unwrap());
+        }
+    }
+
+    assert!(matches!(
+        received.as_slice(),
+        [
+            CompositorRequest::RestartPlugin(_),
+            CompositorRequest::Ping(_)
+        ]
+    ));
+    assert!(!frames.is_partial());
+}
@@ -0,0 +1,189 @@
+use fusion_socket_protocol::{BTN_LEFT, InputAction, parse_input_script};
+
+#[test]
+fn parses_every_command() {
+    let script = "
+        # drag something
+        motion 10 -5.5
+        motion-absolute 0.5 1
+        button left press   # grab
+        button 273 release
+        axis 0 15
+        key 30 click
+    ";
+
+    assert_eq!(
+        parse_input_script(script).unwrap(),
+        [
+            InputAction::PointerMotion { dx: 10.0, dy: -5.5 },
+            InputAction::PointerMotionAbsolute { x: 0.5, y: 1.0 },
+            InputAction::PointerButton {
+                button: BTN_LEFT,
+                pressed: true
+            },
+            InputAction::PointerButton {
+                button: 273,
+                pressed: false
+            },
+            InputAction::PointerAxis {
+                horizontal: 0.0,
+                vertical: 15.0
+            },
+            InputAction::Key {
+                key: 30,
+                pressed: true
+            },
+            InputAction::Key {
+                key: 30,
+                pressed: false
+            },
+        ]
+    );
+}
+
+#[test]
+fn empty_script_has_no_actions() {
+    assert!(parse_input_script("\n  # nothing\n").unwrap().is_empty());
+}
+
+#[test]
+fn errors_point_at_the_line() {
+    let error = parse_input_script("motion 1 1\nbutton left hold").unwrap_err();
+    assert_eq!(error.line, 2);
+    assert_eq!(error.to_string(), "line 2: unknown state `hold`");
+
+    let error = parse_input_script("motion 1").unwrap_err();
+    assert_eq!(error.line, 1);
+    assert_eq!(
+        error.to_string(),
+        "line 1: `motion` takes 2 arguments, got 1"
+    );
+
+    assert!(parse_input_script("key a press").is_err());
+    assert!(parse_input_script("key 4294967295 press").is_err());
+    assert!(parse_input_script("scroll 1 1").is_err());
+}
+
+#[test]
+fn parses_gestures() {
+    let script = "
+        swipe begin 3
+        swipe update 40 -2.5
+        swipe cancel
+        pinch begin 2
+        pinch update 0.5
+        pinch update 1.5 90
+        pinch end
+        hold begin 4
+        hold end
+    ";
+
+    assert_eq!(
+        parse_input_script(script).unwrap(),
+        [
+            InputAction::GestureSwipeBegin { fingers: 3 },
+            InputAction::GestureSwipeUpdate { dx: 40.0, dy: -2.5 },
+            InputAction::GestureSwipeEnd { cancelled: true },
+            InputAction::GesturePinchBegin { fingers: 2 },
+            InputAction::GesturePinchUpdate {
+                dx: 0.0,
+                dy: 0.0,
+                scale: 0.5,
+                rotation: 0.0
+            },
+            InputAction::GesturePinchUpdate {
+                dx: 0.0,
+                dy: 0.0,
+                scale: 1.5,
+                rotation: 90.0
+            },
+            InputAction::GesturePinchEnd { cancelled: false },
+            InputAction::GestureHoldBegin { fingers: 4 },
+            InputAction::GestureHoldEnd { cancelled: false },
+        ]
+    );
+
+    assert!(parse_input_script("swipe").is_err());
+    assert!(parse_input_script("swipe begin").is_err());
+    assert!(parse_input_script("hold update 1 1").is_err());
+    assert!(parse_input_script("pinch update 1 2 3").is_err());
+}
+
+#[test]
+fn parses_touch() {
+    let script = "
+        touch down 0 0.25 0.5
+        touch motion 0 0.5 0.5
+        touch up 0
+        touch cancel
+    ";
+
+    assert_eq!(
+        parse_input_script(script).unwrap(),
+        [
+            InputAction::TouchDown {
+                slot: 0,
+                x: 0.25,
+                y: 0.5
+            },
+            InputAction::TouchFrame,
+            InputAction::TouchMotion {
+                slot: 0,
+                x: 0.5,
+                y: 0.5
+            },
+            InputAction::TouchFrame,
+            InputAction::TouchUp { slot: 0 },
+            InputAction::TouchFrame,
+            InputAction::TouchCancel,
+        ]
+    );
+
+    assert!(parse_input_script("touch").is_err());
+    assert!(parse_input_script("touch down 0 0.5").is_err());
+    assert!(parse_input_script("touch up -1").is_err());
+}
+
+#[test]
+fn parses_tablet() {
+    let script = "
+        tablet in 0.25 0.5
+        tablet motion 0.5 0.5
+        tablet tip down
+        tablet tip up
+        tablet button 331 click
+        tablet out 0.5 0.5
+    ";
+
+    assert_eq!(
+        parse_input_script(script).unwrap(),
+        [
+            InputAction::TabletProximity {
+                x: 0.25,
+                y: 0.5,
+                in_proximity: true
+            },
+            InputAction::TabletMotion { x: 0.5, y: 0.5 },
+            InputAction::TabletTip { down: true },
+            InputAction::TabletTip { down: false },
+            InputAction::TabletButton {
+                button: 331,
+                pressed: true
+            },
+            InputAction::TabletButton {
+                button: 331,
+                pressed: false
+            },
+            InputAction::TabletProximity {
+                x: 0.5,
+                y: 0.5,
+                in_proximity: false
+            },
+        ]
+    );
+
+    assert!(parse_input_script("tablet").is_err());
+    assert!(parse_input_script("tablet in 0.5").is_err());
+    assert!(parse_input_script("tablet tip sideways").is_err());
+    assert!(parse_input_script("tablet button stylus press").is_err());
+}
@@ -0,0 +1,31 @@
+use fusion_socket_protocol::{ModeRequest, Transform};
+
+#[test]
+fn modes_are_parsed() {
+    assert_eq!(
+        "1920x1080".parse(),
+        Ok(ModeRequest {
+            width: 1920,
+            height: 1080,
+            refresh: None,
+        })
+    );
+    assert_eq!(
+        "2560x1440@143.912".parse(),
+        Ok(ModeRequest {
+            width: 2560,
+            height: 1440,
+            refresh: Some(143_912),
+        })
+    );
+    assert!("1920".parse::<ModeRequest>().is_err());
+    assert!("1920x1080@fast".parse::<ModeRequest>().is_err());
+}
+
+#[test]
+fn transforms_are_parsed() {
+    for transform in Transform::ALL {
+        assert_eq!(transform.to_string().parse(), Ok(transform));
+    }
+    assert!("45".parse::<Transform>().is_err());
+}
@@ -0,0 +1,16 @@
+use std::path::Path;
+
+use fusion_socket_protocol::socket_path;
+
+#[test]
+fn sockets_are_named_after_the_display() {
+    let runtime_dir = Path::new("/run/user/1000");
+    assert_eq!(
+        socket_path(runtime_dir, "wayland-1"),
+        runtime_dir.join("fusion-wayland-1.sock")
+    );
+    assert_eq!(
+        socket_path(runtime_dir, "/tmp/nested/wayland-2"),
+        runtime_dir.join("fusion-wayland-2.sock")
+    );
+}
@@ -0,0 +1,233 @@
+use clap::ValueEnum;
+use comfy_table::{ContentArrangement, Table, modifiers::UTF8_ROUND_CORNERS, presets::UTF8_FULL};
+use fusion_socket_protocol::{Keyboard, Output, Plugin, PluginInfo, Window, Workspace};
+use serde::Serialize;
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Format {
+    /// The protocol types as JSON
+    Json,
+    /// A table for people
+    #[default]
+    Table,
+    /// One tab separated line per row without a header
+    Plain,
+}
+
+/// Protocol types shown as a row of a table.
+pub trait Row: Serialize {
+    const HEADER: &'static [&'static str];
+
+    fn row(&self) -> Vec<String>;
+}
+
+pub fn print_list<T: Row>(format: Format, items: &[T]) -> anyhow::Result<()> {
+    match format {
+        Format::Json => println!("{}", serde_json::to_string_pretty(items)?),
+        Format::Table => {
+            let mut table = Table::new();
+            table
+                .load_preset(UTF8_FULL)
+                .apply_modifier(UTF8_ROUND_CORNERS)
+                .set_content_arrangement(ContentArrangement::Dynamic)
+                .set_header(T::HEADER.to_vec());
+            for item in items {
+                table.add_row(item.row());
+            }
+            println!("{table}");
+        }
+        Format::Plain => {
+            for item in items {
+                println!("{}", item.row().join("\t"));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Like [`print_list`], but JSON gets the object instead of a list.
+pub fn print_one<T: Row>(format: Format, item: &T) -> anyhow::Result<()> {
+    match format {
+        Format::Json => println!("{}", serde_json::to_string_pretty(item)?),
+        _ => print_list(format, std::slice::from_ref(item))?,
+    }
+
+    Ok(())
+}
+
+/// Protocol types shown as one value per line.
+pub trait Details: Serialize {
+    fn details(&self) -> Vec<(&'static str, String)>;
+}
+
+pub fn print_details<T: Details>(format: Format, item: &T) -> anyhow::Result<()> {
+    match format {
+        Format::Json => println!("{}", serde_json::to_string_pretty(item)?),
+        Format::Table => {
+            let mut table = Table::new();
+            table
+                .load_preset(UTF8_FULL)
+                .apply_modifier(UTF8_ROUND_CORNERS)
+                .set_content_arrangement(ContentArrangement::Dynamic);
+            for (name, value) in item.details() {
+                table.add_row(vec![name.to_string(), value]);
+            }
+            println!("{table}");
+        }
+        Format::Plain => {
+            for (name, value) in item.details() {
+                println!("{name}\t{value}");
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Confirms a request without a result, JSON leaves it to the exit code.
+pub fn print_message(format: Format, message: &str) {
+    if format != Format::Json {
+        println!("{message}");
+    }
+}
+
+fn mark(set: bool) -> String {
+    if set { "*" } else { "" }.to_string()
+}
+
+impl Row for Plugin {
+    const HEADER: &'static [&'static str] = &["Name", "Id", "Status", "Version"];
+
+    fn row(&self) -> Vec<String> {
+        vec![
+            self.name.clone(),
+            self.id.clone(),
+            self.status.clone(),
+            self.version.clone(),
+        ]
+    }
+}
+
+impl Row for Workspace {
+    const HEADER: &'static [&'static str] = &["Name", "Id", "Output", "Active", "Windows"];
+
+    fn row(&self) -> Vec<String> {
+        vec![
+            self.name.clone(),
+            self.id.to_string(),
+            self.output.clone(),
+            mark(self.active),
+            self.windows.to_string(),
+        ]
+    }
+}
+
+impl Row for Window {
+    const HEADER: &'static [&'static str] = &[
+        "Id",
+        "App id",
+        "Title",
+        "Workspace",
+        "Output",
+        "Geometry",
+        "Focused",
+    ];
+
+    fn row(&self) -> Vec<String> {
+        let geometry = match self.geometry {
+            Some(geometry) => format!(
+                "{}x{}+{}+{}",
+                geometry.width, geometry.height, geometry.x, geometry.y
+            ),
+            None => "hidden".to_string(),
+        };
+        vec![
+            self.id.to_string(),
+            self.app_id.clone(),
+            self.title.clone(),
+            self.workspace.clone().unwrap_or_default(),
+            self.output.clone().unwrap_or_default(),
+            geometry,
+            mark(self.focused),
+        ]
+    }
+}
+
+impl Row for Output {
+    const HEADER: &'static [&'static str] = &[
+        "Name",
+        "Make",
+        "Model",
+        "Mode",
+        "Modes",
+        "Scale",
+        "Transform",
+        "Position",
+        "Enabled",
+    ];
+
+    fn row(&self) -> Vec<String> {
+        let modes = (self.modes.iter())
+            .map(|mode| {
+                let preferred = if mode.preferred { " (preferred)" } else { "" };
+                format!("{mode}{preferred}")
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        vec![
+            self.name.clone(),
+            self.make.clone(),
+            self.model.clone(),
+            (self.current_mode)
+                .map(|mode| mode.to_string())
+                .unwrap_or_default(),
+            modes,
+            self.scale.to_string(),
+            self.transform.to_string(),
+            format!("{},{}", self.x, self.y),
+            mark(self.enabled),
+        ]
+    }
+}
+
+impl Row for Keyboard {
+    const HEADER: &'static [&'static str] = &[
+        "Active layout",
+        "Layout",
+        "Variant",
+        "Options",
+        "Repeat delay",
+        "Repeat rate",
+    ];
+
+    fn row(&self) -> Vec<String> {
+        vec![
+            self.active_layout.clone(),
+            self.layout.clone(),
+            self.variant.clone(),
+            self.options.clone(),
+            self.repeat_delay.to_string(),
+            self.repeat_rate.to_string(),
+        ]
+    }
+}
+
+impl Details for PluginInfo {
+    fn details(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("Id", self.id.clone()),
+            ("Name", self.name.clone()),
+            ("Version", self.version.clone()),
+            ("Description", self.description.clone()),
+            ("Repository", self.repository.clone()),
+            ("Authors", self.authors.join(", ")),
+            ("Capabilities", self.capabilities.join(", ")),
+            ("Custom capabilities", self.custom_capabilities.join(", ")),
+            ("Status", self.status.clone()),
+            ("Failure", self.failure.clone().unwrap_or_default()),
+            ("Path", self.path.clone()),
+            ("Log", self.log_path.clone()),
+        ]
+    }
+}
@@ -1,20 +1,34 @@
-use std::{
-    io::{Read, Write},
-    os::unix::net::UnixStream,
-};
+mod format;
 
-use clap::{Parser, Subcommand};
-use comfy_table::{
-    Cell, ContentArrangement, Table, modifiers::UTF8_ROUND_CORNERS, presets::UTF8_FULL,
-};
+use std::{io::Write, path::PathBuf};
+
+use anyhow::Context;
+use clap::{Parser, Subcommand, ValueEnum};
+use format::{Format, print_details, print_list, print_message, print_one};
 use fusion_socket_protocol::{
-    CompositorRequest, ExitRequest, ExitResponse, FUSION_CTL_SOCKET_DEFAULT, GetPluginListRequest,
-    GetPluginListResponse, PingRequest, PingResponse, Plugin, RestartPluginRequest,
-    RestartPluginResponse,
+    CallPluginRequest, CallPluginResponse, Client, CompositorRequest, ExitRequest, ExitResponse,
+    FUSION_SOCKET_ENV, Features, GetKeyboardRequest, GetKeyboardResponse, GetOutputsRequest,
+    GetOutputsResponse, GetPluginInfoRequest, GetPluginInfoResponse, GetPluginListRequest,
+    GetPluginListResponse, GetWindowsRequest, GetWindowsResponse, GetWorkspacesRequest,
+    GetWorkspacesResponse, InjectInputRequest, InjectInputResponse, InstallPluginRequest,
+    InstallPluginResponse, Keyboard, ModeRequest, Output, PingRequest, PingResponse, Plugin,
+    PluginInfo, ReloadConfigRequest, ReloadConfigResponse, RestartPluginRequest,
+    RestartPluginResponse, SetKeyboardRequest, SetKeyboardResponse, SetOutputRequest,
+    SetOutputResponse, SetPluginEnabledRequest, SetPluginEnabledResponse, SubscribeRequest,
+    SubscribeResponse, Topic, Transform, UninstallPluginRequest, UninstallPluginResponse, Window,
+    WindowAction, WindowActionRequest, WindowActionResponse, Workspace, default_socket_path,
+    parse_input_script,
 };
+use serde::de::DeserializeOwned;
 
 #[derive(Parser)]
 struct Cli {
+    /// How results are printed, events of `subscribe` are always JSON lines
+    #[arg(long, global = true, value_enum, default_value_t)]
+    output: Format,
+    /// Control socket of the compositor, by default the one of `WAYLAND_DISPLAY`
+    #[arg(long, global = true, env = FUSION_SOCKET_ENV)]
+    socket: Option<PathBuf>,
     #[command(subcommand)]
     command: Commands,
 }
@@ -25,95 +39,452 @@ enum Commands {
     Ping,
     #[command(subcommand)]
     Plugins(PluginCommands),
+    #[command(subcommand)]
+    Workspaces(WorkspaceCommands),
+    #[command(subcommand)]
+    Windows(WindowCommands),
+    #[command(subcommand)]
+    Outputs(OutputCommands),
+    /// Injects synthetic input from a script, `-` reads it from stdin
+    Input {
+        script: PathBuf,
+    },
+    /// Re-reads the compositor config
+    Reload,
+    #[command(subcommand)]
+    Keyboard(KeyboardCommands),
+    /// Prints compositor events as JSON lines until interrupted
+    Subscribe {
+        /// window, workspace, output, plugin or layout, every topic by default
+        topics: Vec<Topic>,
+    },
 }
 
 #[derive(Subcommand, Clone, Debug)]
 #[clap(rename_all = "snake_case")]
 enum PluginCommands {
     List,
-    Restart { plugin_id: String },
-}
-
-fn print_plugin_table(plugins: &[Plugin]) {
-    let mut table = Table::new();
-    table
-        .load_preset(UTF8_FULL)
-        .apply_modifier(UTF8_ROUND_CORNERS)
-        .set_content_arrangement(ContentArrangement::Dynamic)
-        .set_header(vec!["Name", "Id", "Status", "Version"]);
-
-    for plugin in plugins {
-        table.add_row(vec![
-            Cell::new(&plugin.name),
-            Cell::new(&plugin.id),
-            Cell::new(&plugin.status),
-            Cell::new(&plugin.version),
-        ]);
-    }
+    Restart {
+        plugin_id: String,
+    },
+    /// Shows the manifest, status and log file of a plugin
+    Info {
+        plugin_id: String,
+    },
+    /// Validates a `.fsp` package and copies it into the plugins directory
+    Install {
+        file: PathBuf,
+    },
+    /// Stops a plugin and deletes its package
+    Uninstall {
+        plugin_id: String,
+    },
+    /// Starts a disabled plugin, also after restarts
+    Enable {
+        plugin_id: String,
+    },
+    /// Stops a plugin, also after restarts
+    Disable {
+        plugin_id: String,
+    },
+    /// Calls an export of a plugin, needs `call-plugin = true` under `[debug]`
+    Call {
+        plugin_id: String,
+        /// Capability whose instance is called, e.g. `layout`
+        capability: String,
+        /// `interface#function` for exports of an interface
+        function: String,
+        /// One JSON value per parameter
+        #[arg(value_parser = parse_json)]
+        args: Vec<serde_json::Value>,
+    },
+}
 
-    println!("{table}");
+fn parse_json(value: &str) -> Result<serde_json::Value, String> {
+    serde_json::from_str(value).map_err(|error| format!("invalid JSON {value}: {error}"))
 }
 
-fn send_request(
-    socket: &mut UnixStream,
-    request: impl Into<CompositorRequest>,
-) -> anyhow::Result<()> {
-    let request = request.into();
-    let buf = postcard::to_stdvec_cobs(&request).unwrap();
-    socket.write_all(&buf)?;
-    Ok(())
+#[derive(Subcommand, Clone, Debug)]
+#[clap(rename_all = "snake_case")]
+enum WorkspaceCommands {
+    List,
+}
+
+#[derive(Subcommand, Clone, Debug)]
+#[clap(rename_all = "snake_case")]
+enum WindowCommands {
+    List,
+    /// Gives the window the keyboard, switching to its workspace if needed
+    Focus {
+        id: u64,
+    },
+    /// Asks the window to close
+    Close {
+        id: u64,
+    },
+    /// Moves the window content to a global position
+    Move {
+        id: u64,
+        #[arg(allow_hyphen_values = true)]
+        x: i32,
+        #[arg(allow_hyphen_values = true)]
+        y: i32,
+    },
+    Resize {
+        id: u64,
+        width: i32,
+        height: i32,
+    },
+    /// Toggles fullscreen unless `on` or `off` is given
+    Fullscreen {
+        id: u64,
+        state: Option<Toggle>,
+    },
+}
+
+#[derive(Subcommand, Clone, Debug)]
+#[clap(rename_all = "snake_case")]
+enum OutputCommands {
+    List,
+    /// Changes the given settings, the others stay as they are
+    Set {
+        name: String,
+        /// `WIDTHxHEIGHT` with an optional `@HZ`, e.g. `2560x1440@144`
+        #[arg(long)]
+        mode: Option<ModeRequest>,
+        #[arg(long)]
+        scale: Option<f64>,
+        /// normal, 90, 180, 270, flipped, flipped-90, flipped-180 or flipped-270
+        #[arg(long)]
+        transform: Option<Transform>,
+        /// Global position in logical pixels, e.g. `1920,0`
+        #[arg(long, value_parser = parse_position, allow_hyphen_values = true)]
+        pos: Option<(i32, i32)>,
+        #[arg(long, conflicts_with = "enable")]
+        disable: bool,
+        #[arg(long)]
+        enable: bool,
+        /// Only checks that the configuration would work
+        #[arg(long)]
+        test: bool,
+    },
+}
+
+fn parse_position(position: &str) -> Result<(i32, i32), String> {
+    let invalid = || format!("invalid position {position}, expected e.g. 1920,0");
+    let (x, y) = position.split_once(',').ok_or_else(invalid)?;
+    Ok((
+        x.trim().parse().map_err(|_| invalid())?,
+        y.trim().parse().map_err(|_| invalid())?,
+    ))
 }
 
-fn read_request(socket: &mut UnixStream) -> Vec<u8> {
-    let mut buf = Vec::new();
-    let mut byte = [0u8; 1];
-    loop {
-        socket.read_exact(&mut byte).unwrap();
-        if byte[0] == 0x00 {
-            break;
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum Toggle {
+    On,
+    Off,
+}
+
+#[derive(Subcommand, Clone, Debug)]
+#[clap(rename_all = "snake_case")]
+enum KeyboardCommands {
+    /// Shows the keyboard settings and the active layout
+    Get,
+    /// Changes the given settings, the others stay as they are
+    Set {
+        /// Comma separated xkb layouts, e.g. `us,ru`
+        #[arg(long)]
+        layout: Option<String>,
+        #[arg(long)]
+        variant: Option<String>,
+        /// Comma separated xkb options, e.g. `grp:alt_shift_toggle`
+        #[arg(long)]
+        options: Option<String>,
+        /// Milliseconds before a held key starts repeating
+        #[arg(long)]
+        repeat_delay: Option<i32>,
+        /// Repeats per second, 0 disables the repetition
+        #[arg(long)]
+        repeat_rate: Option<i32>,
+    },
+}
+
+/// Responses that either carry a result or an error message.
+trait Response: DeserializeOwned {
+    type Ok;
+
+    fn into_result(self) -> Result<Self::Ok, String>;
+}
+
+macro_rules! response {
+    ($($response:ident => $ok:ty),* $(,)?) => {$(
+        impl Response for $response {
+            type Ok = $ok;
+
+            fn into_result(self) -> Result<$ok, String> {
+                match self {
+                    $response::Ok(ok) => Ok(ok),
+                    $response::Error(error) => Err(error),
+                }
+            }
         }
-        buf.push(byte[0]);
-    }
+    )*};
+    ($($response:ident),* $(,)?) => {$(
+        impl Response for $response {
+            type Ok = ();
 
-    buf
+            fn into_result(self) -> Result<(), String> {
+                match self {
+                    $response::Ok => Ok(()),
+                    $response::Error(error) => Err(error),
+                }
+            }
+        }
+    )*};
+}
+
+response! {
+    GetPluginListResponse => Vec<Plugin>,
+    GetPluginInfoResponse => Box<PluginInfo>,
+    InstallPluginResponse => String,
+    CallPluginResponse => String,
+    GetWorkspacesResponse => Vec<Workspace>,
+    GetWindowsResponse => Vec<Window>,
+    GetOutputsResponse => Vec<Output>,
+    GetKeyboardResponse => Keyboard,
+}
+
+response! {
+    RestartPluginResponse,
+    UninstallPluginResponse,
+    SetPluginEnabledResponse,
+    WindowActionResponse,
+    SetOutputResponse,
+    InjectInputResponse,
+    ReloadConfigResponse,
+    SetKeyboardResponse,
+    SubscribeResponse,
+}
+
+/// Sends `request`, an `Error` from the compositor fails like any other error.
+fn request<T: Response>(
+    client: &mut Client,
+    request: impl Into<CompositorRequest>,
+) -> anyhow::Result<T::Ok> {
+    client
+        .request::<T>(request)?
+        .into_result()
+        .map_err(anyhow::Error::msg)
 }
 
 fn main() -> anyhow::Result<()> {
     let cli = Cli::parse();
 
-    let mut socket = UnixStream::connect(FUSION_CTL_SOCKET_DEFAULT)?;
+    // Parse errors should show up before anything is sent
+    let actions = match &cli.command {
+        Commands::Input { script } => {
+            let script = if script.as_os_str() == "-" {
+                std::io::read_to_string(std::io::stdin())?
+            } else {
+                std::fs::read_to_string(script)?
+            };
+            parse_input_script(&script)?
+        }
+        _ => Vec::new(),
+    };
+
+    let format = cli.output;
+    let socket = cli.socket.or_else(default_socket_path).ok_or_else(|| {
+        anyhow::anyhow!("unable to find the compositor, pass --socket or set {FUSION_SOCKET_ENV}")
+    })?;
+    let mut client = Client::connect(&socket)
+        .with_context(|| format!("unable to connect to {}", socket.display()))?;
     match cli.command {
         Commands::Exit => {
-            send_request(&mut socket, ExitRequest)?;
-            let mut bytes = read_request(&mut socket);
-            postcard::from_bytes_cobs::<ExitResponse>(&mut bytes)?;
+            client.request::<ExitResponse>(ExitRequest)?;
         }
         Commands::Ping => {
-            send_request(&mut socket, PingRequest)?;
-            let mut bytes = read_request(&mut socket);
-
-            postcard::from_bytes_cobs::<PingResponse>(&mut bytes)?;
-            println!("Pong!");
+            client.request::<PingResponse>(PingRequest)?;
+            print_message(format, "Pong!");
         }
         Commands::Plugins(command) => match command {
             PluginCommands::List => {
-                send_request(&mut socket, GetPluginListRequest)?;
-                let mut bytes = read_request(&mut socket);
-
-                match postcard::from_bytes_cobs::<GetPluginListResponse>(&mut bytes)? {
-                    GetPluginListResponse::Ok(plugins) => print_plugin_table(&plugins),
-                    GetPluginListResponse::Error(error) => println!("Error: {error}"),
-                }
+                let plugins = request::<GetPluginListResponse>(&mut client, GetPluginListRequest)?;
+                print_list(format, &plugins)?;
             }
             PluginCommands::Restart { plugin_id } => {
-                send_request(&mut socket, RestartPluginRequest { plugin_id })?;
-                let mut bytes = read_request(&mut socket);
-                match postcard::from_bytes_cobs::<RestartPluginResponse>(&mut bytes)? {
-                    RestartPluginResponse::Ok => println!("Ok"),
-                    RestartPluginResponse::Error(error) => println!("Error: {error}"),
+                request::<RestartPluginResponse>(&mut client, RestartPluginRequest { plugin_id })?;
+                print_message(format, "Ok");
+            }
+            PluginCommands::Info { plugin_id } => {
+                let info = request::<GetPluginInfoResponse>(
+                    &mut client,
+                    GetPluginInfoRequest { plugin_id },
+                )?;
+                print_details(format, &*info)?;
+            }
+            PluginCommands::Install { file } => {
+                // The compositor runs somewhere else
+                let path = std::path::absolute(file)?.display().to_string();
+                let plugin_id =
+                    request::<InstallPluginResponse>(&mut client, InstallPluginRequest { path })?;
+                match format {
+                    Format::Json => println!("{}", serde_json::to_string(&plugin_id)?),
+                    _ => println!("Installed {plugin_id}"),
                 }
             }
+            PluginCommands::Uninstall { plugin_id } => {
+                let uninstall = UninstallPluginRequest { plugin_id };
+                request::<UninstallPluginResponse>(&mut client, uninstall)?;
+                print_message(format, "Ok");
+            }
+            PluginCommands::Enable { plugin_id } => {
+                let set = SetPluginEnabledRequest {
+                    plugin_id,
+                    enabled: true,
+                };
+                request::<SetPluginEnabledResponse>(&mut client, set)?;
+                print_message(format, "Ok");
+            }
+            PluginCommands::Disable { plugin_id } => {
+                let set = SetPluginEnabledRequest {
+                    plugin_id,
+                    enabled: false,
+                };
+                request::<SetPluginEnabledResponse>(&mut client, set)?;
+                print_message(format, "Ok");
+            }
+            PluginCommands::Call {
+                plugin_id,
+                capability,
+                function,
+                args,
+            } => {
+                if !client.features().contains(Features::CALL_PLUGIN) {
+                    anyhow::bail!(
+                        "the compositor doesn't allow calling plugins, set `call-plugin = true` under `[debug]` in its config"
+                    );
+                }
+                let call = CallPluginRequest {
+                    plugin_id,
+                    capability,
+                    function,
+                    args: serde_json::to_string(&args)?,
+                };
+                let result = request::<CallPluginResponse>(&mut client, call)?;
+                let result: serde_json::Value = serde_json::from_str(&result)?;
+                match format {
+                    Format::Json => println!("{}", serde_json::to_string_pretty(&result)?),
+                    _ => println!("{result}"),
+                }
+            }
+        },
+        Commands::Workspaces(command) => match command {
+            WorkspaceCommands::List => {
+                let workspaces =
+                    request::<GetWorkspacesResponse>(&mut client, GetWorkspacesRequest)?;
+                print_list(format, &workspaces)?;
+            }
+        },
+        Commands::Windows(command) => {
+            let (id, action) = match command {
+                WindowCommands::List => {
+                    let windows = request::<GetWindowsResponse>(&mut client, GetWindowsRequest)?;
+                    return print_list(format, &windows);
+                }
+                WindowCommands::Focus { id } => (id, WindowAction::Focus),
+                WindowCommands::Close { id } => (id, WindowAction::Close),
+                WindowCommands::Move { id, x, y } => (id, WindowAction::Move { x, y }),
+                WindowCommands::Resize { id, width, height } => {
+                    (id, WindowAction::Resize { width, height })
+                }
+                WindowCommands::Fullscreen { id, state } => {
+                    let state = state.map(|state| matches!(state, Toggle::On));
+                    (id, WindowAction::Fullscreen(state))
+                }
+            };
+
+            request::<WindowActionResponse>(&mut client, WindowActionRequest { id, action })?;
+            print_message(format, "Ok");
+        }
+        Commands::Outputs(command) => match command {
+            OutputCommands::List => {
+                let outputs = request::<GetOutputsResponse>(&mut client, GetOutputsRequest)?;
+                print_list(format, &outputs)?;
+            }
+            OutputCommands::Set {
+                name,
+                mode,
+                scale,
+                transform,
+                pos,
+                disable,
+                enable,
+                test,
+            } => {
+                let set = SetOutputRequest {
+                    name,
+                    mode,
+                    scale,
+                    transform,
+                    position: pos,
+                    enabled: (disable || enable).then_some(enable),
+                    test,
+                };
+                request::<SetOutputResponse>(&mut client, set)?;
+                print_message(format, "Ok");
+            }
+        },
+        Commands::Input { .. } => {
+            if !client.features().contains(Features::INJECT_INPUT) {
+                anyhow::bail!(
+                    "the compositor doesn't accept input, set `inject-input = true` under `[debug]` in its config"
+                );
+            }
+            request::<InjectInputResponse>(&mut client, InjectInputRequest { actions })?;
+            print_message(format, "Ok");
+        }
+        Commands::Reload => {
+            request::<ReloadConfigResponse>(&mut client, ReloadConfigRequest)?;
+            print_message(format, "Ok");
+        }
+        Commands::Keyboard(command) => match command {
+            KeyboardCommands::Get => {
+                let keyboard = request::<GetKeyboardResponse>(&mut client, GetKeyboardRequest)?;
+                print_one(format, &keyboard)?;
+            }
+            KeyboardCommands::Set {
+                layout,
+                variant,
+                options,
+                repeat_delay,
+                repeat_rate,
+            } => {
+                let set = SetKeyboardRequest {
+                    layout,
+                    variant,
+                    options,
+                    repeat_delay,
+                    repeat_rate,
+                };
+                request::<SetKeyboardResponse>(&mut client, set)?;
+                print_message(format, "Ok");
+            }
         },
+        Commands::Subscribe { topics } => {
+            request::<SubscribeResponse>(&mut client, SubscribeRequest { topics })?;
+
+            let mut stdout = std::io::stdout().lock();
+            loop {
+                // Events of a newer compositor are skipped
+                let Some(event) = client.next_event()? else {
+                    continue;
+                };
+                // Flushed right away so pipes see every event as it happens
+                writeln!(stdout, "{}", serde_json::to_string(&event)?)?;
+                stdout.flush()?;
+            }
+        }
     }
 
     Ok(())
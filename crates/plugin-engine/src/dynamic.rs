@@ -0,0 +1,271 @@
+//! Calls into plugins without generated bindings, with JSON standing in for the values.
+//!
+//! | WIT                  | JSON                                            |
+//! |----------------------|-------------------------------------------------|
+//! | `bool`               | `true`                                          |
+//! | integers and floats  | numbers                                         |
+//! | `char` and `string`  | strings                                         |
+//! | `list` and `tuple`   | arrays                                          |
+//! | `record`             | objects keyed by field name                     |
+//! | `variant`            | `{"case": payload}`, `"case"` without a payload |
+//! | `enum`               | strings                                         |
+//! | `option`             | `null` or the value                             |
+//! | `result`             | `{"ok": value}` or `{"err": value}`             |
+//! | `flags`              | arrays of strings                               |
+//!
+//! Resources, futures and streams have no JSON form.
+
+use serde_json::{Map, Number, Value};
+use wasmtime::{
+    AsContextMut,
+    component::{Func, Instance, Type, Val},
+};
+
+/// Finds `name`, exports of an interface are written as `interface#function`.
+pub(crate) fn find_func(
+    mut store: impl AsContextMut,
+    instance: &Instance,
+    name: &str,
+) -> Option<Func> {
+    let index = match name.rsplit_once('#') {
+        Some((interface, name)) => {
+            let interface = instance.get_export_index(&mut store, None, interface)?;
+            instance.get_export_index(&mut store, Some(&interface), name)?
+        }
+        None => instance.get_export_index(&mut store, None, name)?,
+    };
+    instance.get_func(&mut store, index)
+}
+
+/// Calls `func` with one JSON value per parameter. No result gives `null`, several an array.
+pub(crate) fn call(
+    mut store: impl AsContextMut,
+    func: Func,
+    args: &[Value],
+) -> Result<Value, String> {
+    let ty = func.ty(&store);
+    if ty.params().len() != args.len() {
+        return Err(format!(
+            "expected {} arguments, got {}",
+            ty.params().len(),
+            args.len()
+        ));
+    }
+    let params = (ty.params().zip(args))
+        .map(|((name, ty), arg)| to_val(&ty, arg).map_err(|error| format!("{name}: {error}")))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut results = vec![Val::Bool(false); ty.results().len()];
+    func.call(&mut store, &params, &mut results)
+        .map_err(|error| error.to_string())?;
+    func.post_return(&mut store)
+        .map_err(|error| error.to_string())?;
+
+    match results.as_slice() {
+        [] => Ok(Value::Null),
+        [result] => to_json(result),
+        results => results.iter().map(to_json).collect(),
+    }
+}
+
+fn type_name(ty: &Type) -> &'static str {
+    match ty {
+        Type::Bool => "bool",
+        Type::S8 => "s8",
+        Type::U8 => "u8",
+        Type::S16 => "s16",
+        Type::U16 => "u16",
+        Type::S32 => "s32",
+        Type::U32 => "u32",
+        Type::S64 => "s64",
+        Type::U64 => "u64",
+        Type::Float32 => "f32",
+        Type::Float64 => "f64",
+        Type::Char => "char",
+        Type::String => "string",
+        Type::List(_) => "list",
+        Type::Record(_) => "record",
+        Type::Tuple(_) => "tuple",
+        Type::Variant(_) => "variant",
+        Type::Enum(_) => "enum",
+        Type::Option(_) => "option",
+        Type::Result(_) => "result",
+        Type::Flags(_) => "flags",
+        Type::Own(_) | Type::Borrow(_) => "resource",
+        Type::Future(_) => "future",
+        Type::Stream(_) => "stream",
+        Type::ErrorContext => "error-context",
+    }
+}
+
+fn signed<T: TryFrom<i64>>(json: &Value) -> Option<T> {
+    json.as_i64()?.try_into().ok()
+}
+
+fn unsigned<T: TryFrom<u64>>(json: &Value) -> Option<T> {
+    json.as_u64()?.try_into().ok()
+}
+
+/// The only key of `json` and its value, how variants and results are written.
+fn single_entry(json: &Value) -> Option<(&str, &Value)> {
+    match json {
+        Value::String(name) => Some((name, &Value::Null)),
+        Value::Object(object) if object.len() == 1 => object
+            .iter()
+            .next()
+            .map(|(name, value)| (name.as_str(), value)),
+        _ => None,
+    }
+}
+
+fn payload(ty: Option<Type>, json: &Value) -> Result<Option<Box<Val>>, String> {
+    match ty {
+        Some(ty) => Ok(Some(Box::new(to_val(&ty, json)?))),
+        None if json.is_null() => Ok(None),
+        None => Err(format!("expected no payload, got {json}")),
+    }
+}
+
+pub(crate) fn to_val(ty: &Type, json: &Value) -> Result<Val, String> {
+    let mismatch = || format!("expected {}, got {json}", type_name(ty));
+    let val = match ty {
+        Type::Bool => json.as_bool().map(Val::Bool),
+        Type::S8 => signed(json).map(Val::S8),
+        Type::U8 => unsigned(json).map(Val::U8),
+        Type::S16 => signed(json).map(Val::S16),
+        Type::U16 => unsigned(json).map(Val::U16),
+        Type::S32 => signed(json).map(Val::S32),
+        Type::U32 => unsigned(json).map(Val::U32),
+        Type::S64 => signed(json).map(Val::S64),
+        Type::U64 => unsigned(json).map(Val::U64),
+        #[allow(clippy::cast_possible_truncation)]
+        Type::Float32 => json.as_f64().map(|value| Val::Float32(value as f32)),
+        Type::Float64 => json.as_f64().map(Val::Float64),
+        Type::Char => {
+            let mut chars = json.as_str().ok_or_else(mismatch)?.chars();
+            match (chars.next(), chars.next()) {
+                (Some(char), None) => Some(Val::Char(char)),
+                _ => None,
+            }
+        }
+        Type::String => json.as_str().map(|value| Val::String(value.to_string())),
+        Type::List(list) => {
+            let items = json.as_array().ok_or_else(mismatch)?;
+            let items = (items.iter())
+                .map(|item| to_val(&list.ty(), item))
+                .collect::<Result<_, _>>()?;
+            Some(Val::List(items))
+        }
+        Type::Tuple(tuple) => {
+            let items = json.as_array().ok_or_else(mismatch)?;
+            if items.len() != tuple.types().len() {
+                return Err(mismatch());
+            }
+            let items = (tuple.types().zip(items))
+                .map(|(ty, item)| to_val(&ty, item))
+                .collect::<Result<_, _>>()?;
+            Some(Val::Tuple(items))
+        }
+        Type::Record(record) => {
+            let object = json.as_object().ok_or_else(mismatch)?;
+            if let Some(unknown) =
+                (object.keys()).find(|key| !record.fields().any(|f| f.name == *key))
+            {
+                return Err(format!("unknown field {unknown}"));
+            }
+            let fields = (record.fields())
+                .map(|field| {
+                    // Missing fields are fine for options
+                    let value = object.get(field.name).unwrap_or(&Value::Null);
+                    let value =
+                        to_val(&field.ty, value).map_err(|e| format!("{}: {e}", field.name))?;
+                    Ok((field.name.to_string(), value))
+                })
+                .collect::<Result<_, String>>()?;
+            Some(Val::Record(fields))
+        }
+        Type::Variant(variant) => {
+            let (name, value) = single_entry(json).ok_or_else(mismatch)?;
+            let case = (variant.cases())
+                .find(|case| case.name == name)
+                .ok_or_else(|| format!("unknown case {name}"))?;
+            Some(Val::Variant(name.to_string(), payload(case.ty, value)?))
+        }
+        Type::Enum(enumeration) => json
+            .as_str()
+            .filter(|name| enumeration.names().any(|known| known == *name))
+            .map(|name| Val::Enum(name.to_string())),
+        Type::Option(option) => match json {
+            Value::Null => Some(Val::Option(None)),
+            json => Some(Val::Option(Some(Box::new(to_val(&option.ty(), json)?)))),
+        },
+        Type::Result(result) => match single_entry(json).ok_or_else(mismatch)? {
+            ("ok", value) => Some(Val::Result(Ok(payload(result.ok(), value)?))),
+            ("err", value) => Some(Val::Result(Err(payload(result.err(), value)?))),
+            _ => None,
+        },
+        Type::Flags(flags) => {
+            let names = json.as_array().ok_or_else(mismatch)?;
+            let names = (names.iter())
+                .map(|name| {
+                    name.as_str()
+                        .filter(|name| flags.names().any(|known| known == *name))
+                        .map(str::to_string)
+                        .ok_or_else(|| format!("unknown flag {name}"))
+                })
+                .collect::<Result<_, _>>()?;
+            Some(Val::Flags(names))
+        }
+        Type::Own(_) | Type::Borrow(_) | Type::Future(_) | Type::Stream(_) | Type::ErrorContext => {
+            return Err(format!("{} can't be written as JSON", type_name(ty)));
+        }
+    };
+
+    val.ok_or_else(mismatch)
+}
+
+pub(crate) fn to_json(val: &Val) -> Result<Value, String> {
+    let payload = |payload: &Option<Box<Val>>| match payload {
+        Some(value) => to_json(value),
+        None => Ok(Value::Null),
+    };
+    let entry =
+        |name: &str, value: Value| Value::Object(Map::from_iter([(name.to_string(), value)]));
+
+    Ok(match val {
+        Val::Bool(value) => Value::Bool(*value),
+        Val::S8(value) => Value::from(*value),
+        Val::U8(value) => Value::from(*value),
+        Val::S16(value) => Value::from(*value),
+        Val::U16(value) => Value::from(*value),
+        Val::S32(value) => Value::from(*value),
+        Val::U32(value) => Value::from(*value),
+        Val::S64(value) => Value::from(*value),
+        Val::U64(value) => Value::from(*value),
+        // NaN and infinities have no JSON form
+        Val::Float32(value) => {
+            Number::from_f64(f64::from(*value)).map_or(Value::Null, Value::Number)
+        }
+        Val::Float64(value) => Number::from_f64(*value).map_or(Value::Null, Value::Number),
+        Val::Char(value) => Value::String(value.to_string()),
+        Val::String(value) => Value::String(value.clone()),
+        Val::List(items) | Val::Tuple(items) => {
+            items.iter().map(to_json).collect::<Result<_, _>>()?
+        }
+        Val::Record(fields) => Value::Object(
+            (fields.iter())
+                .map(|(name, value)| Ok((name.clone(), to_json(value)?)))
+                .collect::<Result<_, String>>()?,
+        ),
+        Val::Variant(name, None) | Val::Enum(name) => Value::String(name.clone()),
+        Val::Variant(name, value) => entry(name, payload(value)?),
+        Val::Option(value) => payload(value)?,
+        Val::Result(Ok(value)) => entry("ok", payload(value)?),
+        Val::Result(Err(value)) => entry("err", payload(value)?),
+        Val::Flags(names) => names.iter().cloned().map(Value::String).collect(),
+        Val::Resource(_) => return Err("resources can't be written as JSON".to_string()),
+        Val::Future(_) | Val::Stream(_) | Val::ErrorContext(_) => {
+            return Err("futures and streams can't be written as JSON".to_string());
+        }
+    })
+}
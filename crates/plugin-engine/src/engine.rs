@@ -1,24 +1,29 @@
 use crate::{
+    FILE_EXTENSION,
     config::Config,
     context::ExecutionContext,
+    dynamic,
     env::PluginEnvironment,
     general::General,
     loader::{FusionPackage, LoaderConfig, PluginLoader},
     manifest::Manifest,
+    settings::PluginSettings,
     table::{CapabilityProvider, CapabilityTable, CapabilityWriteRules},
 };
 use derive_more::Display;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use std::{
     any::{Any, TypeId},
     collections::HashMap,
     fmt::Display,
     path::{Path, PathBuf},
+    sync::mpsc::{self, RecvTimeoutError},
+    time::Duration,
 };
 use wasmtime::{Engine, InstanceAllocationStrategy, Store};
 use wasmtime::{
     StoreContextMut,
-    component::{Component, Linker},
+    component::{Component, Instance, Linker},
 };
 
 pub trait InnerContextFactory<I: InnerContext> {
@@ -32,7 +37,8 @@ pub trait InnerContext: Send + Sync + Sized + 'static {
     fn plugins_path() -> PathBuf;
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
 pub struct PluginID(String);
 
 impl Display for PluginID {
@@ -58,6 +64,7 @@ pub struct PluginEngine<I: InnerContext> {
     loader: PluginLoader,
     captable: CapabilityTable<I>,
     plugins: HashMap<PluginID, Plugin<I>>,
+    settings: PluginSettings,
     factory: I::Factory,
 }
 
@@ -126,6 +133,9 @@ impl<I: InnerContext> PluginEngine<I> {
         config.compiler_inlining(true);
         config.wasm_simd(true);
         config.allocation_strategy(InstanceAllocationStrategy::pooling());
+        // Only advanced to interrupt plugins that don't stop in time, see `Self::shutdown`.
+        // Every store has a deadline one epoch ahead, so other calls are never interrupted.
+        config.epoch_interruption(true);
         let engine = Engine::new(&config)?;
         let loader = PluginLoader::new::<I>(loader_config)?;
 
@@ -134,6 +144,7 @@ impl<I: InnerContext> PluginEngine<I> {
             loader,
             captable: CapabilityTable::default(),
             plugins: HashMap::default(),
+            settings: PluginSettings::load(&I::config_path()),
             factory,
         })
     }
@@ -165,14 +176,37 @@ impl<I: InnerContext> PluginEngine<I> {
                     binding,
                 }
             }
-            Plugin::Failed(_) => {
-                panic!("Failed plugin cannot provide bindings");
+            Plugin::Failed(_) | Plugin::Disabled(_) => {
+                panic!("Plugin that is not running cannot provide bindings");
             }
         }
     }
 
+    /// Same as [`Self::get_single_write_bindings`], but `None` while no running plugin
+    /// provides the capability.
+    pub fn try_get_single_write_bindings<B: UntypedPluginBinding>(
+        &mut self,
+        capability: &str,
+    ) -> Option<BindingContext<'_, I, B>> {
+        let capability = self.captable.get_capability_by_name(capability);
+        let plugin_id = capability.writers().iter().next()?;
+        match self.plugins.get_mut(plugin_id)? {
+            Plugin::Running(env) => {
+                let binding_id = TypeId::of::<B>();
+                let bindings = env.bindings_mut();
+                let binding = bindings.inner.get(&binding_id)?;
+                let binding = binding.as_any().downcast_ref::<B>()?;
+                Some(BindingContext {
+                    store: &mut bindings.store,
+                    binding,
+                })
+            }
+            Plugin::Failed(_) | Plugin::Disabled(_) => None,
+        }
+    }
+
     fn create_context(&self, manifest: &Manifest, config: Config) -> ExecutionContext<I> {
-        let log_file = I::logs_path().join(manifest.name());
+        let log_file = Self::log_path(manifest);
         let inner_context = self.factory.generate(manifest.capabilities());
         ExecutionContext::new(config, log_file, inner_context)
     }
@@ -200,7 +234,8 @@ impl<I: InnerContext> PluginEngine<I> {
         )?;
 
         let context = self.create_context(&package.manifest, package.config);
-        let store = Store::new(&self.engine, context);
+        let mut store = Store::new(&self.engine, context);
+        store.set_epoch_deadline(1);
         let component = Component::from_binary(&self.engine, &package.module)?;
         let _ = linker.define_unknown_imports_as_traps(&component);
 
@@ -233,7 +268,11 @@ impl<I: InnerContext> PluginEngine<I> {
             log::error!("[{}] Unable to initialize plugin: {err}", manifest.name(),);
             self.captable
                 .remove_observing(manifest.capabilities(), &plugin_id);
-            Plugin::Failed(FailedPlugin { path, manifest })
+            Plugin::Failed(FailedPlugin {
+                path,
+                manifest,
+                reason: format!("Unable to initialize plugin: {err}"),
+            })
         } else {
             Plugin::Running(env)
         };
@@ -250,6 +289,15 @@ impl<I: InnerContext> PluginEngine<I> {
         for package in packages {
             let id = package.manifest.id().clone();
             let name = package.manifest.name().to_string();
+            if self.settings.is_disabled(&id) {
+                log::debug!("[Engine] Skipping disabled plugin: {name}");
+                let disabled = DisabledPlugin {
+                    path: package.path,
+                    manifest: package.manifest,
+                };
+                self.plugins.insert(id, Plugin::Disabled(disabled));
+                continue;
+            }
             let silent_link = self.plugins.contains_key(&id);
 
             log::debug!(
@@ -279,6 +327,7 @@ impl<I: InnerContext> PluginEngine<I> {
                             Plugin::Failed(FailedPlugin {
                                 path: package.path,
                                 manifest: package.manifest,
+                                reason: format!("Unable to prepare plugin: {err}"),
                             }),
                         );
                     }
@@ -291,8 +340,11 @@ impl<I: InnerContext> PluginEngine<I> {
         let plugin_id = plugin_id.into();
         if let Some(plugin) = self.plugins.remove(&plugin_id) {
             log::info!("[Engine] Restart plugin: {}", plugin.manifest().name());
-            self.captable
-                .remove_observing(plugin.manifest().capabilities(), &plugin_id);
+            // Disabled plugins were never linked
+            if !matches!(plugin, Plugin::Disabled(_)) {
+                self.captable
+                    .remove_observing(plugin.manifest().capabilities(), &plugin_id);
+            }
             self.loader.load_plugin(plugin.path()).unwrap();
             Ok(())
         } else {
@@ -311,7 +363,7 @@ impl<I: InnerContext> PluginEngine<I> {
             .filter(|(_, v)| matches!(v, Plugin::Failed(_)))
             .map(|(_, v)| match v {
                 Plugin::Failed(failed) => failed,
-                Plugin::Running(_) => unreachable!(),
+                Plugin::Running(_) | Plugin::Disabled(_) => unreachable!(),
             })
             .collect()
     }
@@ -323,6 +375,175 @@ impl<I: InnerContext> PluginEngine<I> {
     pub fn get_plugin_env_by_id(&self, plugin_id: &PluginID) -> Option<&Plugin<I>> {
         self.plugins.get(plugin_id)
     }
+
+    /// Calls an export of the instance behind `capability` without bindings, for debugging.
+    ///
+    /// `function` is `interface#name` for exports of an interface, see [`crate::dynamic`] for
+    /// how `args` and the result are written.
+    pub fn call_plugin(
+        &mut self,
+        plugin_id: impl Into<PluginID>,
+        capability: &str,
+        function: &str,
+        args: &[serde_json::Value],
+    ) -> Result<serde_json::Value, Error> {
+        let plugin_id = plugin_id.into();
+        let env = match self.plugins.get_mut(&plugin_id) {
+            Some(Plugin::Running(env)) => env,
+            Some(_) => return Err(Error::NotRunning(plugin_id.to_string())),
+            None => return Err(Error::PluginNotFound(plugin_id.to_string())),
+        };
+
+        let bindings = env.bindings_mut();
+        let instance = *bindings
+            .instances
+            .get(capability)
+            .ok_or_else(|| Error::Call(format!("plugin {plugin_id} does not use {capability}")))?;
+        let func = dynamic::find_func(&mut bindings.store, &instance, function)
+            .ok_or_else(|| Error::Call(format!("{function} is not exported")))?;
+        dynamic::call(&mut bindings.store, func, args).map_err(Error::Call)
+    }
+
+    /// File the plugin writes its log to.
+    #[must_use]
+    pub fn log_path(manifest: &Manifest) -> PathBuf {
+        I::logs_path().join(manifest.name())
+    }
+
+    /// Validates the package at `path` and copies it into the plugins directory.
+    pub fn install_plugin(&mut self, path: &Path) -> Result<PluginID, Error> {
+        let invalid = |error: &dyn Display| Error::InvalidPackage(error.to_string());
+        let file_name = (path.file_name())
+            .filter(|_| path.extension().is_some_and(|ext| ext == FILE_EXTENSION))
+            .ok_or_else(|| {
+                Error::InvalidPackage(format!(
+                    "{} is not a .{FILE_EXTENSION} file",
+                    path.display()
+                ))
+            })?;
+
+        let bytes = std::fs::read(path)?;
+        let destination = I::plugins_path().join(file_name);
+        let package =
+            FusionPackage::create(&bytes, destination.clone()).map_err(|e| invalid(&e))?;
+        Component::from_binary(&self.engine, &package.module).map_err(|e| invalid(&e))?;
+
+        let plugin_id = package.manifest.id().clone();
+        if self.plugins.contains_key(&plugin_id) || destination.exists() {
+            return Err(Error::AlreadyInstalled(plugin_id.to_string()));
+        }
+
+        log::info!("[Engine] Install plugin: {}", package.manifest.name());
+        // Moved into place once complete, the watcher skips files that aren't packages
+        let partial = destination.with_extension(format!("{FILE_EXTENSION}.part"));
+        if let Err(error) =
+            std::fs::write(&partial, bytes).and_then(|()| std::fs::rename(&partial, &destination))
+        {
+            let _ = std::fs::remove_file(&partial);
+            return Err(error.into());
+        }
+        self.loader.load_plugin(destination).unwrap();
+        Ok(plugin_id)
+    }
+
+    /// Stops the plugin and deletes its package.
+    pub fn uninstall_plugin(&mut self, plugin_id: impl Into<PluginID>) -> Result<(), Error> {
+        let plugin_id = plugin_id.into();
+        let Some(plugin) = self.plugins.get(&plugin_id) else {
+            return Err(Error::PluginNotFound(plugin_id.to_string()));
+        };
+
+        log::info!("[Engine] Uninstall plugin: {}", plugin.manifest().name());
+        std::fs::remove_file(plugin.path())?;
+        if let Some(Plugin::Running(env)) = self.plugins.remove(&plugin_id) {
+            self.captable
+                .remove_observing(env.manifest().capabilities(), &plugin_id);
+        }
+        if self.settings.set_disabled(&plugin_id, false) {
+            self.settings.save(&I::config_path())?;
+        }
+        Ok(())
+    }
+
+    /// Starts or stops the plugin, the choice is kept across restarts.
+    pub fn set_plugin_enabled(
+        &mut self,
+        plugin_id: impl Into<PluginID>,
+        enabled: bool,
+    ) -> Result<(), Error> {
+        let plugin_id = plugin_id.into();
+        let Some(plugin) = self.plugins.remove(&plugin_id) else {
+            return Err(Error::PluginNotFound(plugin_id.to_string()));
+        };
+
+        let plugin = match (plugin, enabled) {
+            (Plugin::Disabled(disabled), true) => {
+                log::info!("[Engine] Enable plugin: {}", disabled.manifest.name());
+                self.loader.load_plugin(disabled.path).unwrap();
+                None
+            }
+            (plugin @ (Plugin::Running(_) | Plugin::Failed(_)), false) => {
+                log::info!("[Engine] Disable plugin: {}", plugin.manifest().name());
+                if let Plugin::Running(env) = &plugin {
+                    self.captable
+                        .remove_observing(env.manifest().capabilities(), &plugin_id);
+                }
+                Some(Plugin::Disabled(DisabledPlugin {
+                    path: plugin.path(),
+                    manifest: plugin.manifest().clone(),
+                }))
+            }
+            (plugin, _) => Some(plugin),
+        };
+        if let Some(plugin) = plugin {
+            self.plugins.insert(plugin_id.clone(), plugin);
+        }
+
+        if self.settings.set_disabled(&plugin_id, !enabled) {
+            self.settings.save(&I::config_path())?;
+        }
+        Ok(())
+    }
+
+    /// Runs `stop` on every running plugin with bindings `B` and unloads all plugins.
+    ///
+    /// A plugin gets `timeout` to return, after that it traps wherever it is.
+    /// The epoch only advances here, once per plugin that runs out of time.
+    pub fn shutdown<B: UntypedPluginBinding>(
+        &mut self,
+        timeout: Duration,
+        mut stop: impl FnMut(&B, &mut Store<ExecutionContext<I>>) -> wasmtime::Result<()>,
+    ) {
+        for (plugin_id, plugin) in self.plugins.drain() {
+            let Plugin::Running(mut env) = plugin else {
+                continue;
+            };
+            self.captable
+                .remove_observing(env.manifest().capabilities(), &plugin_id);
+            let name = env.manifest().name().to_string();
+            let Bindings { store, inner, .. } = env.bindings_mut();
+            let Some(binding) = (inner.get(&TypeId::of::<B>()))
+                .and_then(|binding| binding.as_any().downcast_ref::<B>())
+            else {
+                continue;
+            };
+
+            log::debug!("[{name}] Stopping plugin");
+            store.set_epoch_deadline(1);
+            let (finished, watched) = mpsc::channel::<()>();
+            let engine = self.engine.clone();
+            let watchdog = std::thread::spawn(move || {
+                if watched.recv_timeout(timeout) == Err(RecvTimeoutError::Timeout) {
+                    engine.increment_epoch();
+                }
+            });
+            if let Err(error) = stop(binding, store) {
+                log::error!("[{name}] Unable to stop plugin: {error}");
+            }
+            drop(finished);
+            let _ = watchdog.join();
+        }
+    }
 }
 
 pub trait UntypedPluginBinding: 'static {
@@ -334,6 +555,8 @@ pub trait UntypedPluginBinding: 'static {
 pub(crate) struct Bindings<I: InnerContext> {
     store: Store<ExecutionContext<I>>,
     inner: HashMap<TypeId, Box<dyn UntypedPluginBinding>>,
+    /// Instance behind the bindings of each capability
+    instances: HashMap<String, Instance>,
 }
 
 impl<I: InnerContext> Bindings<I> {
@@ -341,6 +564,7 @@ impl<I: InnerContext> Bindings<I> {
         Self {
             store,
             inner: HashMap::new(),
+            instances: HashMap::new(),
         }
     }
 
@@ -348,6 +572,10 @@ impl<I: InnerContext> Bindings<I> {
         self.inner.insert((*bindings).type_id(), bindings);
     }
 
+    pub fn add_instance(&mut self, capability: String, instance: Instance) {
+        self.instances.insert(capability, instance);
+    }
+
     pub const fn store_mut(&mut self) -> &mut Store<ExecutionContext<I>> {
         &mut self.store
     }
@@ -356,6 +584,7 @@ impl<I: InnerContext> Bindings<I> {
 pub struct FailedPlugin {
     path: PathBuf,
     manifest: Manifest,
+    reason: String,
 }
 
 impl FailedPlugin {
@@ -368,6 +597,29 @@ impl FailedPlugin {
     pub const fn manifest(&self) -> &Manifest {
         &self.manifest
     }
+
+    #[must_use]
+    pub const fn reason(&self) -> &str {
+        self.reason.as_str()
+    }
+}
+
+/// Plugin the user turned off, its package is known but never instantiated.
+pub struct DisabledPlugin {
+    path: PathBuf,
+    manifest: Manifest,
+}
+
+impl DisabledPlugin {
+    #[must_use]
+    pub const fn path(&self) -> &PathBuf {
+        &self.path
+    }
+
+    #[must_use]
+    pub const fn manifest(&self) -> &Manifest {
+        &self.manifest
+    }
 }
 
 #[derive(Default, Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Display)]
@@ -375,11 +627,13 @@ pub enum PluginStatus {
     #[default]
     Running,
     Failed,
+    Disabled,
 }
 
 pub enum Plugin<I: InnerContext> {
     Running(PluginEnvironment<I>),
     Failed(FailedPlugin),
+    Disabled(DisabledPlugin),
 }
 
 impl<I: InnerContext> Plugin<I> {
@@ -388,6 +642,7 @@ impl<I: InnerContext> Plugin<I> {
         match self {
             Plugin::Running(env) => env.manifest(),
             Plugin::Failed(failed) => failed.manifest(),
+            Plugin::Disabled(disabled) => disabled.manifest(),
         }
     }
 
@@ -396,6 +651,7 @@ impl<I: InnerContext> Plugin<I> {
         match self {
             Plugin::Running(env) => env.path().clone(),
             Plugin::Failed(failed) => failed.path().clone(),
+            Plugin::Disabled(disabled) => disabled.path().clone(),
         }
     }
 
@@ -404,6 +660,7 @@ impl<I: InnerContext> Plugin<I> {
         match self {
             Plugin::Running(env) => env.manifest().id(),
             Plugin::Failed(failed) => failed.manifest().id(),
+            Plugin::Disabled(disabled) => disabled.manifest().id(),
         }
     }
 
@@ -412,10 +669,32 @@ impl<I: InnerContext> Plugin<I> {
         match self {
             Plugin::Running(_) => PluginStatus::Running,
             Plugin::Failed(_) => PluginStatus::Failed,
+            Plugin::Disabled(_) => PluginStatus::Disabled,
+        }
+    }
+
+    /// Why the plugin failed, `None` unless it did.
+    #[must_use]
+    pub fn failure_reason(&self) -> Option<&str> {
+        match self {
+            Plugin::Failed(failed) => Some(failed.reason()),
+            Plugin::Running(_) | Plugin::Disabled(_) => None,
         }
     }
 }
 
+#[derive(Debug, thiserror::Error)]
 pub enum Error {
+    #[error("plugin {0} not found")]
     PluginNotFound(String),
+    #[error("plugin {0} is already installed")]
+    AlreadyInstalled(String),
+    #[error("invalid plugin package: {0}")]
+    InvalidPackage(String),
+    #[error("plugin {0} is not running")]
+    NotRunning(String),
+    #[error("call failed: {0}")]
+    Call(String),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
 }
@@ -9,13 +9,15 @@ pub mod loader;
 pub mod manifest;
 pub mod table;
 
+mod dynamic;
 mod engine;
+mod settings;
 pub use engine::*;
 
 pub mod wasm {
     pub use wasmtime::{
         Store,
-        component::{Component, Linker, bindgen},
+        component::{Component, Instance, Linker, bindgen},
     };
 }
 
@@ -202,7 +202,7 @@ impl InnerPluginLoader {
                 Ok(entry) => {
                     let path = entry.path();
 
-                    if path.is_file() && path.extension().is_some_and(|ext| ext == FILE_EXTENSION) {
+                    if path.is_file() && is_package(&path) {
                         self.load_package(path);
                     }
                 }
@@ -231,7 +231,7 @@ impl InnerPluginLoader {
         match event.kind {
             notify::EventKind::Create(_) => {
                 log::debug!("[Watcher] Detected file creation");
-                for path in event.paths {
+                for path in event.paths.into_iter().filter(|path| is_package(path)) {
                     let metadata = std::fs::metadata(&path)
                         .map_err(|error| {
                             log::error!("[Loader] {error}");
@@ -263,7 +263,7 @@ impl InnerPluginLoader {
                         RenameMode::From => {
                             self.renamed = self.loaded.remove(&path);
                         }
-                        RenameMode::To => {
+                        RenameMode::To if is_package(&path) => {
                             log::info!("[Loader] Rename module file: {}", path.display());
                             if let Some(renamed) = self.renamed.take() {
                                 self.loaded.insert(path, renamed);
@@ -321,6 +321,11 @@ impl InnerPluginLoader {
     }
 }
 
+/// Whether `path` names a plugin package, other files in the plugins directory are ignored.
+fn is_package(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == FILE_EXTENSION)
+}
+
 #[derive(Clone)]
 pub struct FusionPackage {
     pub path: PathBuf,
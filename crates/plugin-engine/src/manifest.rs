@@ -11,6 +11,18 @@ pub struct Author {
     email: Option<String>,
 }
 
+impl Author {
+    #[must_use]
+    pub const fn name(&self) -> &str {
+        self.name.as_str()
+    }
+
+    #[must_use]
+    pub fn email(&self) -> Option<&str> {
+        self.email.as_deref()
+    }
+}
+
 #[derive(Debug, Clone, Deserialize)]
 pub struct ModuleError {
     name: String,
@@ -0,0 +1,54 @@
+use std::{
+    collections::BTreeSet,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::PluginID;
+
+/// Plugin choices that survive restarts, stored in the config directory.
+#[derive(Default, Serialize, Deserialize)]
+pub(crate) struct PluginSettings {
+    #[serde(default)]
+    disabled: BTreeSet<PluginID>,
+}
+
+impl PluginSettings {
+    const FILE_NAME: &str = "plugins.toml";
+
+    fn file(config_path: &Path) -> PathBuf {
+        config_path.join(Self::FILE_NAME)
+    }
+
+    /// Falls back to the defaults when the file is missing or broken.
+    pub fn load(config_path: &Path) -> Self {
+        let file = Self::file(config_path);
+        let Ok(content) = std::fs::read_to_string(&file) else {
+            return Self::default();
+        };
+
+        toml::from_str(&content).unwrap_or_else(|error| {
+            log::error!("[Engine] Ignoring {}: {error}", file.display());
+            Self::default()
+        })
+    }
+
+    pub fn save(&self, config_path: &Path) -> std::io::Result<()> {
+        let content = toml::to_string(self).map_err(std::io::Error::other)?;
+        std::fs::write(Self::file(config_path), content)
+    }
+
+    pub fn is_disabled(&self, plugin_id: &PluginID) -> bool {
+        self.disabled.contains(plugin_id)
+    }
+
+    /// Returns whether anything changed.
+    pub fn set_disabled(&mut self, plugin_id: &PluginID, disabled: bool) -> bool {
+        if disabled {
+            self.disabled.insert(plugin_id.clone())
+        } else {
+            self.disabled.remove(plugin_id)
+        }
+    }
+}
@@ -3,7 +3,7 @@
 use crate::{
     engine::{Bindings, PluginID, UntypedPluginBinding},
     general::General,
-    wasm::{Component, Linker, Store},
+    wasm::{Component, Instance, Linker, Store},
 };
 use bitflags::bitflags;
 use log::info;
@@ -14,11 +14,11 @@ use crate::{context::ExecutionContext, engine::InnerContext};
 pub trait CapabilityProvider: 'static {
     type Inner: InnerContext;
     fn link_functions(&self, linker: &mut Linker<ExecutionContext<Self::Inner>>);
+    /// Wraps the instance the engine created for this capability, see `X::new` of `bindgen!`.
     fn create_bindings(
         &self,
         store: &mut Store<ExecutionContext<Self::Inner>>,
-        component: &Component,
-        linker: &Linker<ExecutionContext<Self::Inner>>,
+        instance: &Instance,
     ) -> Box<dyn UntypedPluginBinding>;
 }
 
@@ -189,12 +189,13 @@ impl<I: InnerContext> CapabilityTable<I> {
         for requested in capabilities {
             // SAFETY: We have already checked that the capability exists
             let capability = unsafe { self.inner.get_mut(requested).unwrap_unchecked() };
-            let binding =
-                capability
-                    .provider
-                    .create_bindings(bindings.store_mut(), component, linker);
+            let instance = linker.instantiate(bindings.store_mut(), component).unwrap();
+            let binding = capability
+                .provider
+                .create_bindings(bindings.store_mut(), &instance);
 
             bindings.add(binding);
+            bindings.add_instance(requested.clone(), instance);
         }
     }
 
@@ -0,0 +1,57 @@
+mod common;
+mod context;
+
+use plugin_engine::{Error, PluginEngine, loader::LoaderConfig, table::CapabilityWriteRules};
+use serde_json::json;
+
+use crate::{
+    common::{PLUGINS_PATH, initialize, wait_one_second},
+    context::call_api::{
+        CallApi, CallApiCapProvider, CallApiFactory, PLUGIN, PLUGIN_FILE, TestsApi,
+    },
+};
+
+#[test]
+fn call_plugin() -> Result<(), Box<dyn std::error::Error>> {
+    initialize(&[PLUGIN]);
+
+    let mut engine = PluginEngine::<CallApi>::new(
+        CallApiFactory,
+        LoaderConfig::default()
+            .enable_preload(false)
+            .manual_loading(true),
+    )?;
+    engine.add_capability(
+        "tests-api",
+        CapabilityWriteRules::SingleWrite,
+        CallApiCapProvider,
+    );
+
+    engine.load_package(PLUGINS_PATH.path().join(PLUGIN_FILE));
+    wait_one_second(&mut engine);
+    let plugin_id = engine.get_plugin_list().first().unwrap().clone();
+
+    let result = engine.call_plugin(plugin_id.clone(), "tests-api", "add-value", &[json!(42)])?;
+    assert_eq!(result, json!(null));
+    let result = engine.call_plugin(plugin_id.clone(), "tests-api", "get-value", &[])?;
+    assert_eq!(result, json!(42));
+
+    // The bindings share the instance
+    let mut api = engine.get_single_write_bindings::<TestsApi>("tests-api");
+    let mut store = api.store();
+    assert_eq!(api.call_get_value(&mut store)?, 42);
+
+    for (function, args) in [
+        ("add-value", vec![json!(256)]),
+        ("add-value", vec![json!("42")]),
+        ("add-value", Vec::new()),
+        ("missing", Vec::new()),
+    ] {
+        let result = engine.call_plugin(plugin_id.clone(), "tests-api", function, &args);
+        assert!(matches!(result, Err(Error::Call(_))));
+    }
+    let result = engine.call_plugin("missing", "tests-api", "get-value", &[]);
+    assert!(matches!(result, Err(Error::PluginNotFound(_))));
+
+    Ok(())
+}
@@ -8,7 +8,7 @@ use plugin_engine::{
     impl_untyped_plugin_binding,
     loader::LoaderConfig,
     table::{CapabilityProvider, CapabilityWriteRules},
-    wasm::{Component, Linker, Store},
+    wasm::{Instance, Linker, Store},
 };
 
 use crate::common::{CONFIG_PATH, LOGS_PATH, PLUGINS_PATH};
@@ -55,10 +55,9 @@ impl CapabilityProvider for CallApiCapProvider {
     fn create_bindings(
         &self,
         store: &mut Store<ExecutionContext<Self::Inner>>,
-        component: &Component,
-        linker: &Linker<ExecutionContext<Self::Inner>>,
+        instance: &Instance,
     ) -> Box<dyn UntypedPluginBinding> {
-        Box::new(TestsApi::instantiate(store, component, linker).unwrap())
+        Box::new(TestsApi::new(store, instance).unwrap())
     }
 }
 
@@ -0,0 +1,88 @@
+#![allow(clippy::non_std_lazy_statics)]
+
+use std::path::PathBuf;
+
+use lazy_static::lazy_static;
+use plugin_engine::{Error, PluginEngine, PluginStatus, loader::LoaderConfig};
+use tempfile::TempDir;
+
+use crate::{
+    common::{LOGS_PATH, PLUGINS_PATH, initialize, wait_one_second},
+    context::empty::{Empty, EmptyFactory, Paths},
+};
+
+mod common;
+mod context;
+
+lazy_static! {
+    static ref INSTALLED_PATH: TempDir = TempDir::new().unwrap();
+    static ref SETTINGS_PATH: TempDir = TempDir::new().unwrap();
+}
+
+struct InstallPaths;
+impl Paths for InstallPaths {
+    fn config_path() -> PathBuf {
+        SETTINGS_PATH.path().to_path_buf()
+    }
+
+    fn logs_path() -> PathBuf {
+        LOGS_PATH.path().to_path_buf()
+    }
+
+    fn plugins_path() -> PathBuf {
+        INSTALLED_PATH.path().to_path_buf()
+    }
+}
+
+type Engine = PluginEngine<Empty<InstallPaths>>;
+
+fn status(engine: &Engine) -> Option<PluginStatus> {
+    let plugin_id = "test.fusion.empty".into();
+    Some(engine.get_plugin_env_by_id(&plugin_id)?.status())
+}
+
+#[test]
+fn manage_plugins() -> Result<(), Box<dyn std::error::Error>> {
+    const PLUGIN: &str = "empty_plugin";
+    const PLUGIN_FILE: &str = "empty_plugin_1.0.fsp";
+
+    initialize(&[PLUGIN]);
+
+    let loader_config = LoaderConfig::default()
+        .enable_preload(true)
+        .manual_loading(true);
+    let mut engine = Engine::new(EmptyFactory, loader_config)?;
+
+    let not_a_package = PLUGINS_PATH.path().join("empty_plugin.txt");
+    std::fs::write(&not_a_package, "")?;
+    let result = engine.install_plugin(&not_a_package);
+    assert!(matches!(result, Err(Error::InvalidPackage(_))));
+
+    let plugin_id = engine.install_plugin(&PLUGINS_PATH.path().join(PLUGIN_FILE))?;
+    assert!(INSTALLED_PATH.path().join(PLUGIN_FILE).is_file());
+    // Only the complete package is left behind
+    assert_eq!(std::fs::read_dir(INSTALLED_PATH.path())?.count(), 1);
+    wait_one_second(&mut engine);
+    assert_eq!(status(&engine), Some(PluginStatus::Running));
+
+    let result = engine.install_plugin(&PLUGINS_PATH.path().join(PLUGIN_FILE));
+    assert!(matches!(result, Err(Error::AlreadyInstalled(_))));
+
+    // Disabled plugins stay disabled after a restart
+    engine.set_plugin_enabled(plugin_id.clone(), false)?;
+    assert_eq!(status(&engine), Some(PluginStatus::Disabled));
+    drop(engine);
+    let mut engine = Engine::new(EmptyFactory, loader_config)?;
+    wait_one_second(&mut engine);
+    assert_eq!(status(&engine), Some(PluginStatus::Disabled));
+
+    engine.set_plugin_enabled(plugin_id.clone(), true)?;
+    wait_one_second(&mut engine);
+    assert_eq!(status(&engine), Some(PluginStatus::Running));
+
+    engine.uninstall_plugin(plugin_id)?;
+    assert_eq!(status(&engine), None);
+    assert!(!INSTALLED_PATH.path().join(PLUGIN_FILE).exists());
+
+    Ok(())
+}
@@ -17,6 +17,12 @@ impl Guest for Example {
     fn get_value() -> u8 {
         GLOBAL_VALUE.load(Ordering::SeqCst)
     }
+
+    fn spin() {
+        loop {
+            std::hint::spin_loop();
+        }
+    }
 }
 
 export!(Example);
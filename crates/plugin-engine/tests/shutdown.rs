@@ -0,0 +1,77 @@
+use std::time::{Duration, Instant};
+
+use plugin_engine::{PluginEngine, loader::LoaderConfig, table::CapabilityWriteRules};
+
+use crate::{
+    common::{PLUGINS_PATH, initialize, wait_one_second},
+    context::call_api::{
+        CallApi, CallApiCapProvider, CallApiFactory, PLUGIN, PLUGIN_FILE, TestsApi,
+    },
+};
+
+mod common;
+mod context;
+
+fn load_engine() -> Result<PluginEngine<CallApi>, Box<dyn std::error::Error>> {
+    initialize(&[PLUGIN]);
+
+    let mut engine = PluginEngine::<CallApi>::new(
+        CallApiFactory,
+        LoaderConfig::default()
+            .enable_preload(false)
+            .manual_loading(true),
+    )?;
+    engine.add_capability(
+        "tests-api",
+        CapabilityWriteRules::SingleWrite,
+        CallApiCapProvider,
+    );
+
+    engine.load_package(PLUGINS_PATH.path().join(PLUGIN_FILE));
+    wait_one_second(&mut engine);
+    assert_eq!(engine.get_plugin_list().len(), 1);
+    Ok(engine)
+}
+
+#[test]
+fn shutdown() -> Result<(), Box<dyn std::error::Error>> {
+    let mut engine = load_engine()?;
+
+    let mut stopped = 0;
+    engine.shutdown(Duration::from_secs(1), |api: &TestsApi, store| {
+        stopped += 1;
+        api.call_add_value(&mut *store, 1)
+    });
+    assert_eq!(stopped, 1);
+    assert!(engine.get_plugin_list().is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn stuck_plugins_are_interrupted() -> Result<(), Box<dyn std::error::Error>> {
+    let mut engine = load_engine()?;
+
+    let timeout = Duration::from_millis(200);
+    let start = Instant::now();
+    let mut trapped = false;
+    engine.shutdown(timeout, |api: &TestsApi, store| {
+        let result = api.call_spin(&mut *store);
+        trapped = result.is_err();
+        result
+    });
+    assert!(trapped);
+    assert!(start.elapsed() >= timeout);
+    assert!(start.elapsed() < Duration::from_secs(5));
+    assert!(engine.get_plugin_list().is_empty());
+
+    // The advanced epoch doesn't cut short the calls of plugins loaded afterwards
+    engine.load_package(PLUGINS_PATH.path().join(PLUGIN_FILE));
+    wait_one_second(&mut engine);
+    let mut api = engine.get_single_write_bindings::<TestsApi>("tests-api");
+    let mut store = api.store();
+    api.call_add_value(&mut store, 42)?;
+    assert_eq!(api.call_get_value(&mut store)?, 42);
+
+    Ok(())
+}
@@ -5,8 +5,13 @@ use std::sync::Mutex;
 use crate::{
     WindowManager,
     fusion::fusion::compositor::{
-        types::WindowId,
-        wm_imports::{get_output_size, send_configure, set_window_pos, set_window_size},
+        types::{
+            Area, Color, GesturePhase, Shadow, StyleRules, WindowId, WindowStyle, WorkspaceId,
+        },
+        wm_imports::{
+            create_workspace, get_output_size, get_usable_area, get_workspaces, send_configure,
+            set_style_rules, set_window_pos, set_window_size, switch_workspace,
+        },
     },
 };
 
@@ -27,24 +32,83 @@ impl GlobalState {
     }
 
     pub fn rearrange_windows(&mut self) {
-        if self.windows.is_empty() {
+        let visible = get_workspaces()
+            .into_iter()
+            .filter(|workspace| workspace.active)
+            .flat_map(|workspace| workspace.windows)
+            .collect::<Vec<_>>();
+        let windows = self
+            .windows
+            .iter()
+            .filter(|window| visible.iter().any(|w| w.inner == window.inner))
+            .collect::<Vec<_>>();
+
+        if windows.is_empty() {
             return;
         }
-        let (screen_width, screen_height) = get_output_size();
-        let width_per_window = screen_width / self.windows.len() as u32;
+        let area = get_usable_area(None).unwrap_or_else(|| {
+            let (width, height) = get_output_size();
+            Area {
+                x: 0,
+                y: 0,
+                width,
+                height,
+            }
+        });
+        let width_per_window = area.width / windows.len() as u32;
 
-        for (i, window) in self.windows.iter().enumerate() {
+        for (i, window) in windows.into_iter().enumerate() {
             let window = *window;
-            let x_pos = i as u32 * width_per_window;
-            let y_pos = 0;
+            let x_pos = area.x.max(0) as u32 + i as u32 * width_per_window;
+            let y_pos = area.y.max(0) as u32;
 
-            set_window_size(window, width_per_window, screen_height);
+            set_window_size(window, width_per_window, area.height);
             send_configure(window);
             set_window_pos(window, x_pos, y_pos);
         }
     }
 }
 
+/// Rounded windows with a shadow, unfocused ones are dimmed.
+pub fn set_styles() {
+    let style = WindowStyle {
+        opacity: 1.0,
+        border_width: 2,
+        border_color: Color {
+            r: 90,
+            g: 140,
+            b: 220,
+            a: 255,
+        },
+        corner_radius: 8,
+        shadow: Some(Shadow {
+            color: Color {
+                r: 0,
+                g: 0,
+                b: 0,
+                a: 128,
+            },
+            blur_radius: 12,
+            offset_x: 0,
+            offset_y: 4,
+        }),
+    };
+
+    set_style_rules(StyleRules {
+        focused: style,
+        unfocused: WindowStyle {
+            opacity: 0.85,
+            border_color: Color {
+                r: 60,
+                g: 60,
+                b: 60,
+                a: 255,
+            },
+            ..style
+        },
+    });
+}
+
 static STATE: Mutex<GlobalState> = Mutex::new(GlobalState::new());
 
 fn state<R>(f: impl FnOnce(&mut GlobalState) -> R) -> R {
@@ -72,6 +136,41 @@ impl exports::fusion::compositor::wm_exports::Guest for crate::WindowManager {
     }
 
     fn on_commit(_: WindowId) {}
+
+    fn workspace_changed(_: String, _: Option<WorkspaceId>, _: WorkspaceId) {
+        state(GlobalState::rearrange_windows);
+    }
+
+    fn animation_finished(_: WindowId, _: u64, _: bool) {}
+
+    fn on_keybinding(name: String) {
+        // `workspace-<name>` bindings switch to that workspace on the focused output
+        if let Some(workspace) = name.strip_prefix("workspace-")
+            && let Some(workspace) = create_workspace(workspace, None)
+        {
+            switch_workspace(workspace);
+        }
+    }
+
+    fn on_gesture(name: String, phase: GesturePhase, progress: f64) {
+        // Gestures behave like keybindings once they are swiped all the way
+        if matches!(phase, GesturePhase::End) && progress >= 1.0 {
+            Self::on_keybinding(name);
+        }
+    }
+
+    fn layout_changed(_: String) {}
+
+    fn window_minimized(window: WindowId, minimized: bool) {
+        // Minimized windows leave their place to the others
+        state(|wm| {
+            wm.windows.retain(|&w| w.inner != window.inner);
+            if !minimized {
+                wm.windows.push(window);
+            }
+            wm.rearrange_windows();
+        });
+    }
 }
 
 impl Guest for crate::WindowManager {
@@ -11,6 +11,7 @@ pub struct WindowManager;
 impl Guest for WindowManager {
     fn init() {
         info("Plugin initialized");
+        fusion::set_styles();
     }
 }
 